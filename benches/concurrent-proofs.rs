@@ -0,0 +1,48 @@
+//! Measures the throughput of proofs served from a frozen tree by a growing number of threads.
+//! Reads of a `SyncMerkleTree` take no locks, so throughput should grow with the number of threads
+//! up to the number of cores.
+//!
+//! ```text
+//! cargo bench --bench concurrent-proofs -- [<leaves>] [<proofs per thread>]
+//! ```
+
+use merkle_tree::{HashFunction, Keccak256, MerkleTree};
+use std::hint::black_box;
+use std::thread;
+use std::time::Instant;
+
+fn main() {
+    // Arguments which are not numbers, such as the `--bench` passed by cargo, are skipped
+    let mut args = std::env::args()
+        .skip(1)
+        .filter_map(|a| a.parse::<usize>().ok());
+    let leaves = args.next().unwrap_or(1 << 16);
+    let proofs = args.next().unwrap_or(20_000);
+
+    let values: Vec<[u8; 8]> = (0..leaves as u64).map(|i| i.to_be_bytes()).collect();
+    let inputs: Vec<&[u8]> = values.iter().map(|v| &v[..]).collect();
+    let tree = MerkleTree::<Keccak256>::new(&inputs).freeze();
+    let hashes: Vec<[u8; 32]> = values.iter().map(|v| Keccak256::hash(v)).collect();
+
+    let cores = thread::available_parallelism().map_or(4, |n| n.get());
+    let mut single = None;
+    println!("{leaves} leaves, {proofs} proofs per thread");
+    for threads in (0..).map(|i| 1 << i).take_while(|t| *t <= cores * 2) {
+        let start = Instant::now();
+        thread::scope(|scope| {
+            for worker in 0..threads {
+                let tree = tree.clone();
+                let hashes = &hashes;
+                scope.spawn(move || {
+                    for i in 0..proofs {
+                        let leaf = hashes[(i * threads + worker) % hashes.len()];
+                        black_box(tree.proof(leaf));
+                    }
+                });
+            }
+        });
+        let rate = (threads * proofs) as f64 / start.elapsed().as_secs_f64();
+        let speedup = rate / *single.get_or_insert(rate);
+        println!("{threads:>3} threads: {rate:>12.0} proofs/s ({speedup:.2}x)");
+    }
+}
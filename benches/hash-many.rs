@@ -0,0 +1,51 @@
+//! Compares hashing leaves one at a time against hashing them in batches, and the time taken to
+//! build a tree. Batches are only hashed in parallel with the `keccak-simd` feature, so comparing
+//! runs with and without it shows the speedup of the accelerated backend, which requires a target
+//! with AVX2.
+//!
+//! ```text
+//! RUSTFLAGS="-C target-cpu=native" cargo bench --bench hash-many --features keccak-simd -- [<leaves>]
+//! ```
+
+use merkle_tree::{HashFunction, Keccak256, MerkleTree};
+use std::hint::black_box;
+use std::time::Instant;
+
+fn main() {
+    // Arguments which are not numbers, such as the `--bench` passed by cargo, are skipped
+    let leaves = std::env::args()
+        .skip(1)
+        .find_map(|a| a.parse::<u64>().ok())
+        .unwrap_or(1 << 20);
+    let values: Vec<[u8; 32]> = (0..leaves)
+        .map(|i| Keccak256::hash(&i.to_be_bytes()))
+        .collect();
+    let inputs: Vec<&[u8]> = values.iter().map(|v| &v[..]).collect();
+    println!(
+        "{leaves} leaves, keccak-simd {}",
+        cfg!(feature = "keccak-simd")
+    );
+
+    let start = Instant::now();
+    for input in &inputs {
+        black_box(Keccak256::hash(input));
+    }
+    report("hash", start);
+
+    let start = Instant::now();
+    for batch in inputs.chunks(256) {
+        black_box(Keccak256::hash_many(batch));
+    }
+    report("hash_many", start);
+
+    let start = Instant::now();
+    black_box(MerkleTree::<Keccak256>::new(&inputs).root());
+    report("MerkleTree::new", start);
+}
+
+fn report(name: &str, start: Instant) {
+    println!(
+        "{name:>16}: {:>8.1} ms",
+        start.elapsed().as_secs_f64() * 1000.0
+    );
+}
@@ -0,0 +1,64 @@
+//! The content addressed store of chunks shared by every snapshot of the backup.
+
+use merkle_tree::{HashFunction, Keccak256, SparseMerkleProof, SparseMerkleTree};
+
+/// Every chunk stored by the backup, keyed by its leaf hash within the tree of the file it came
+/// from. Chunks shared by several files or snapshots are stored once, and the root of the map
+/// commits to exactly the chunks held, so a proof shows either that a chunk is stored or that it
+/// must be uploaded.
+pub struct ChunkIndex {
+    chunks: SparseMerkleTree<Keccak256>,
+    stored_bytes: u64,
+}
+
+impl ChunkIndex {
+    pub fn new() -> Self {
+        Self {
+            chunks: SparseMerkleTree::new(),
+            stored_bytes: 0,
+        }
+    }
+
+    /// Stores a chunk unless already present, returning whether it was new.
+    pub fn store(&mut self, key: <Keccak256 as HashFunction>::Hash, chunk: &[u8]) -> bool {
+        if self.chunks.get(key).is_some() {
+            return false;
+        }
+        self.chunks.insert(key, chunk);
+        self.stored_bytes += chunk.len() as u64;
+        true
+    }
+
+    pub fn get(&self, key: <Keccak256 as HashFunction>::Hash) -> Option<&[u8]> {
+        self.chunks.get(key)
+    }
+
+    pub fn root(&self) -> <Keccak256 as HashFunction>::Hash {
+        self.chunks.root()
+    }
+
+    /// The number of distinct chunks stored.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// The number of bytes held, counting each distinct chunk once.
+    pub fn stored_bytes(&self) -> u64 {
+        self.stored_bytes
+    }
+
+    pub fn proof(&self, key: <Keccak256 as HashFunction>::Hash) -> SparseMerkleProof<Keccak256> {
+        self.chunks.proof(key)
+    }
+
+    /// Verifies that the index with the supplied root holds `chunk` under `key`.
+    pub fn verify(
+        &self,
+        proof: &SparseMerkleProof<Keccak256>,
+        key: <Keccak256 as HashFunction>::Hash,
+        chunk: &[u8],
+        root: <Keccak256 as HashFunction>::Hash,
+    ) -> bool {
+        self.chunks.verify(proof, key, Some(chunk), root)
+    }
+}
@@ -0,0 +1,104 @@
+//! A deduplicating backup index, built from the chunked file trees, the sparse map and the
+//! memory-mapped store.
+//!
+//! Each snapshot of a directory splits its files into chunks, storing each distinct chunk once in
+//! an index committed to by a sparse Merkle tree, and records a manifest tree over the roots of
+//! its files. Snapshots are compared by diffing their manifests, and restored files are verified
+//! chunk by chunk against the manifest.
+//!
+//! ```text
+//! cargo run --example backup-index --features mmap -- <directory> [<later directory>]
+//! ```
+
+mod index;
+mod snapshot;
+
+use index::ChunkIndex;
+use snapshot::Snapshot;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+fn main() -> std::io::Result<()> {
+    let directories: Vec<PathBuf> = std::env::args().skip(1).map(PathBuf::from).collect();
+    if directories.is_empty() || directories.len() > 2 {
+        eprintln!("usage: backup-index <directory> [<later directory>]");
+        std::process::exit(2);
+    }
+
+    let stores = std::env::temp_dir().join(format!("backup-index-{}", std::process::id()));
+    let mut index = ChunkIndex::new();
+    let mut snapshots = vec![];
+    for (i, directory) in directories.iter().enumerate() {
+        let (snapshot, upload) = Snapshot::take(
+            directory,
+            &stores.join(format!("snapshot-{}", i)),
+            &mut index,
+        )?;
+        println!(
+            "snapshot {} of {}: {} files, root 0x{}",
+            i,
+            directory.display(),
+            snapshot.files().len(),
+            hex::encode(snapshot.root())
+        );
+        println!(
+            "  {} of {} chunks new, {} bytes uploaded",
+            upload.new_chunks, upload.chunks, upload.bytes
+        );
+        snapshots.push(snapshot);
+    }
+    println!(
+        "index: {} distinct chunks, {} bytes, root 0x{}",
+        index.len(),
+        index.stored_bytes(),
+        hex::encode(index.root())
+    );
+
+    // Every file restores from the index and verifies against its snapshot
+    for snapshot in &snapshots {
+        for path in snapshot.files().keys() {
+            if snapshot.restore(path, &index).is_none() {
+                eprintln!("failed to restore {}", path);
+                std::process::exit(1);
+            }
+        }
+    }
+    println!("all files restored and verified");
+
+    if let [earlier, later] = &snapshots[..] {
+        report(earlier, later);
+    }
+    std::fs::remove_dir_all(stores)
+}
+
+/// Prints the files added, removed and modified between two snapshots.
+fn report(earlier: &Snapshot, later: &Snapshot) {
+    let diff = earlier.diff(later);
+    let paths = |snapshot: &Snapshot, entries: &[[u8; 32]]| -> BTreeSet<String> {
+        snapshot
+            .files()
+            .keys()
+            .filter(|path| {
+                snapshot
+                    .entry_hash(path)
+                    .is_some_and(|hash| entries.contains(&hash))
+            })
+            .cloned()
+            .collect()
+    };
+    let removed = paths(earlier, &diff.removed);
+    let added = paths(later, &diff.added);
+    for path in added.iter() {
+        let change = match removed.contains(path) {
+            true => "modified",
+            false => "added",
+        };
+        println!("{}: {}", change, path);
+    }
+    for path in removed.iter().filter(|path| !added.contains(*path)) {
+        println!("removed: {}", path);
+    }
+    if diff.is_empty() {
+        println!("snapshots are identical");
+    }
+}
@@ -0,0 +1,166 @@
+//! Snapshots of a directory, each a tree over the roots of its chunked files.
+
+use crate::index::ChunkIndex;
+use merkle_tree::chunker::verify_chunk;
+use merkle_tree::{HashFunction, Keccak256, MerkleTree, MerkleTreeBuilder, MmapStore, TreeDiff};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Files are split into chunks of this many bytes, so an edit within a large file only uploads
+/// the chunks it touches.
+pub const CHUNK_SIZE: usize = 4096;
+
+type Hash = <Keccak256 as HashFunction>::Hash;
+
+/// A snapshot of a directory. The manifest has one leaf per file, committing to its relative path
+/// and the root of the tree over its chunks, and is held in memory-mapped files so snapshots of
+/// large directories need not be resident.
+pub struct Snapshot {
+    manifest: MerkleTree<Keccak256, MmapStore<Hash>>,
+    /// The root of the chunk tree of each file, by relative path.
+    files: BTreeMap<String, Hash>,
+    /// The leaf hashes of the chunks of each file, in order.
+    chunks: BTreeMap<String, Vec<Hash>>,
+}
+
+/// What taking a snapshot added to the index.
+#[derive(Debug, Default)]
+pub struct Upload {
+    pub chunks: usize,
+    pub new_chunks: usize,
+    pub bytes: u64,
+}
+
+impl Snapshot {
+    /// Chunks every file below `directory`, storing chunks not already held by `index`, and
+    /// writes the manifest into `store_path`.
+    pub fn take(
+        directory: &Path,
+        store_path: &Path,
+        index: &mut ChunkIndex,
+    ) -> std::io::Result<(Self, Upload)> {
+        let mut paths = vec![];
+        collect(directory, "", &mut paths)?;
+
+        let mut upload = Upload::default();
+        let (mut files, mut chunks) = (BTreeMap::new(), BTreeMap::new());
+        for path in paths {
+            let mut contents = vec![];
+            File::open(directory.join(&path))?.read_to_end(&mut contents)?;
+            let tree = MerkleTree::<Keccak256>::from_reader(&contents[..], CHUNK_SIZE)?;
+            let leaves = tree.tree().leaves().to_vec();
+            for (leaf, chunk) in leaves.iter().zip(chunks_of(&contents)) {
+                upload.chunks += 1;
+                if index.store(*leaf, chunk) {
+                    upload.new_chunks += 1;
+                    upload.bytes += chunk.len() as u64;
+                }
+            }
+            files.insert(path.clone(), tree.root());
+            chunks.insert(path, leaves);
+        }
+
+        let entries: Vec<Vec<u8>> = files.iter().map(|(p, r)| entry(p, r)).collect();
+        let manifest = builder().build_in(MmapStore::create(store_path)?, &entries)?;
+        Ok((
+            Self {
+                manifest,
+                files,
+                chunks,
+            },
+            upload,
+        ))
+    }
+
+    pub fn root(&self) -> Hash {
+        self.manifest.root()
+    }
+
+    pub fn files(&self) -> &BTreeMap<String, Hash> {
+        &self.files
+    }
+
+    /// The leaf hash of the manifest entry of a file.
+    pub fn entry_hash(&self, path: &str) -> Option<Hash> {
+        let root = self.files.get(path)?;
+        Some(builder().hash_leaf(&entry(path, root)))
+    }
+
+    /// The manifest entries present in only one of two snapshots.
+    pub fn diff(&self, other: &Snapshot) -> TreeDiff<Keccak256> {
+        self.manifest.diff(&other.manifest)
+    }
+
+    /// Restores a file from the chunks of the index, verifying each chunk against the root of the
+    /// file as recorded in the manifest and each file against the manifest root.
+    pub fn restore(&self, path: &str, index: &ChunkIndex) -> Option<Vec<u8>> {
+        let entry = self.entry_hash(path)?;
+        let manifest_proof = self.manifest.proof(entry);
+        if !self.manifest.verify(manifest_proof, entry, self.root()) {
+            return None;
+        }
+
+        // The chunk tree is rebuilt from the stored chunks, so a substituted chunk changes its root
+        let root = self.files[path];
+        let mut contents = vec![];
+        for leaf in &self.chunks[path] {
+            let chunk = index.get(*leaf)?;
+            if !index.verify(&index.proof(*leaf), *leaf, chunk, index.root()) {
+                return None;
+            }
+            contents.extend_from_slice(chunk);
+        }
+        let rebuilt = MerkleTree::<Keccak256>::from_reader(&contents[..], CHUNK_SIZE).ok()?;
+        let verified = chunks_of(&contents).enumerate().all(|(i, chunk)| {
+            rebuilt
+                .proof(i)
+                .is_some_and(|p| verify_chunk(chunk, p, root))
+        });
+        (verified && rebuilt.root() == root).then_some(contents)
+    }
+}
+
+/// Manifest entries are in path order, with leaf and node hashes separated.
+fn builder() -> MerkleTreeBuilder<Keccak256> {
+    MerkleTreeBuilder::new()
+        .sort_leaves(false)
+        .sort_pairs(false)
+        .domain_separation(true)
+}
+
+/// The length of the path, the path and the root of the file, so no path can be given the root of
+/// another.
+fn entry(path: &str, root: &Hash) -> Vec<u8> {
+    let mut entry = (path.len() as u64).to_le_bytes().to_vec();
+    entry.extend_from_slice(path.as_bytes());
+    entry.extend_from_slice(root);
+    entry
+}
+
+/// The chunks of a file, as split by `from_reader`. An empty file is a single empty chunk.
+fn chunks_of(contents: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let empty: &[u8] = &[];
+    let mut chunks: Vec<&[u8]> = contents.chunks(CHUNK_SIZE).collect();
+    if chunks.is_empty() {
+        chunks.push(empty);
+    }
+    chunks.into_iter()
+}
+
+/// The relative paths of the files below `directory`, separated by `/`. Links are skipped.
+fn collect(directory: &Path, prefix: &str, paths: &mut Vec<String>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = format!("{}{}", prefix, name);
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            collect(&entry.path(), &format!("{}/", path), paths)?;
+        } else if kind.is_file() {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
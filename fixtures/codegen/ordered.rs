@@ -0,0 +1,39 @@
+// Generated by merkle-tree for a tree with spec `keccak256/ordered/promote-odd/arity2/leaf-prefix00/node-prefix01`.
+// Do not edit: regenerate this file whenever the tree changes.
+
+/// Root of the tree.
+pub const ROOT: [u8; 32] = [
+    0x2a, 0x3d, 0xd7, 0x73, 0x3a, 0x12, 0xc3, 0x65,
+    0xe2, 0x0b, 0xb0, 0x69, 0x21, 0xe3, 0xad, 0x01,
+    0xb9, 0x20, 0x09, 0xa0, 0x08, 0x2a, 0xbe, 0xa5,
+    0xc4, 0x62, 0xc0, 0xdc, 0x36, 0x27, 0x59, 0xed,
+];
+/// Maximum number of siblings in a proof.
+pub const DEPTH: usize = 3;
+
+const LEAF_PREFIX: &[u8] = &[0x00];
+const NODE_PREFIX: &[u8] = &[0x01];
+
+/// Verifies that `proof` recomputes `ROOT` from the leaf `value`, where `hash` computes the keccak256
+/// hash of the concatenation of its parts.
+///
+/// Bit `i` of `left` is set when the `i`th sibling is on the left.
+pub fn verify(
+    value: &[u8],
+    proof: &[[u8; 32]],
+    left: u64,
+    hash: fn(&[&[u8]]) -> [u8; 32],
+) -> bool {
+    if proof.len() > DEPTH {
+        return false;
+    }
+    let mut node = hash(&[LEAF_PREFIX, value]);
+    for (i, sibling) in proof.iter().enumerate() {
+        node = if (left >> i) & 1 == 1 {
+            hash(&[NODE_PREFIX, sibling, &node])
+        } else {
+            hash(&[NODE_PREFIX, &node, sibling])
+        };
+    }
+    node == ROOT
+}
@@ -0,0 +1,36 @@
+// Generated by merkle-tree for a tree with spec `keccak256/sorted/promote-odd/arity2/leaf-prefix00/node-prefix01`.
+// Do not edit: regenerate this file whenever the tree changes.
+
+/// Root of the tree.
+pub const ROOT: [u8; 32] = [
+    0x0d, 0x2a, 0x10, 0xa4, 0x6d, 0xe4, 0xfb, 0x47,
+    0x4e, 0x78, 0x16, 0x68, 0x2e, 0x63, 0x67, 0x33,
+    0x81, 0xc5, 0x6a, 0xeb, 0x04, 0x22, 0xee, 0x28,
+    0xc8, 0xfa, 0xdb, 0xe6, 0x7f, 0x97, 0x14, 0x09,
+];
+/// Maximum number of siblings in a proof.
+pub const DEPTH: usize = 3;
+
+const LEAF_PREFIX: &[u8] = &[0x00];
+const NODE_PREFIX: &[u8] = &[0x01];
+
+/// Verifies that `proof` recomputes `ROOT` from the leaf `value`, where `hash` computes the keccak256
+/// hash of the concatenation of its parts.
+pub fn verify(
+    value: &[u8],
+    proof: &[[u8; 32]],
+    hash: fn(&[&[u8]]) -> [u8; 32],
+) -> bool {
+    if proof.len() > DEPTH {
+        return false;
+    }
+    let mut node = hash(&[LEAF_PREFIX, value]);
+    for sibling in proof {
+        node = if *sibling < node {
+            hash(&[NODE_PREFIX, sibling, &node])
+        } else {
+            hash(&[NODE_PREFIX, &node, sibling])
+        };
+    }
+    node == ROOT
+}
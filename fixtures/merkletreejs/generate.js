@@ -0,0 +1,36 @@
+// Regenerates fixtures.json from merkletreejs across its option matrix.
+//
+//   npm install merkletreejs keccak256
+//   node generate.js > fixtures.json
+const { MerkleTree } = require('merkletreejs')
+const keccak256 = require('keccak256')
+
+const hex = (buffer) => '0x' + buffer.toString('hex')
+const address = (i) => Buffer.from(i.toString(16).padStart(40, '0'), 'hex')
+
+const cases = [
+  ['letters', ['a', 'b', 'c'].map((x) => Buffer.from(x))],
+  ['five letters', ['a', 'b', 'c', 'd', 'e'].map((x) => Buffer.from(x))],
+  ['numbers', [1, 2, 3, 4, 5, 6, 7].map((x) => Buffer.from([x]))],
+  ['addresses', [1, 2, 3, 4].map(address)],
+]
+
+const output = cases.map(([name, values]) => {
+  const trees = []
+  for (const sortLeaves of [false, true]) {
+    for (const sortPairs of [false, true]) {
+      for (const duplicateOdd of [false, true]) {
+        const leaves = values.map((v) => keccak256(v))
+        const tree = new MerkleTree(leaves, keccak256, { sortLeaves, sortPairs, duplicateOdd })
+        const entry = { sortLeaves, sortPairs, duplicateOdd, root: tree.getHexRoot() }
+        if (!duplicateOdd) {
+          entry.proof = tree.getHexProof(leaves[0])
+        }
+        trees.push(entry)
+      }
+    }
+  }
+  return { name, leaves: values.map(hex), trees }
+})
+
+console.log(JSON.stringify({ cases: output }, null, 2))
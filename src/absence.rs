@@ -0,0 +1,276 @@
+use crate::{
+    verify_proof, Direction, HashFunction, MerkleError, MerkleProof, MerkleTree, OddStrategy,
+    TreeSpec,
+};
+
+/// A proof that a leaf is not in a tree with sorted leaves, being the proofs of the adjacent
+/// leaves either side of where it would be. A leaf below the first leaf or above the last has a
+/// neighbour on one side only.
+///
+/// The position of each neighbour is bound by the directions of its proof, so absence can only be
+/// proven by trees with sorted leaves and positional pairs. The directions of a proof depend on the
+/// number of leaves in the tree, which the root does not commit to, so the verifier must know the
+/// number of leaves from a trusted source rather than from the prover.
+pub struct AbsenceProof<T: HashFunction> {
+    pub left: Option<Neighbour<T>>,
+    pub right: Option<Neighbour<T>>,
+}
+
+/// A leaf adjacent to an absent leaf, along with its proof.
+pub struct Neighbour<T: HashFunction> {
+    pub leaf: T::Hash,
+    pub index: usize,
+    pub proof: MerkleProof<T>,
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Generates a proof that a leaf is not in the tree, failing if the leaf is present or if the
+    /// tree cannot prove absence.
+    pub fn proof_of_absence(
+        &self,
+        leaf: impl Into<T::Hash>,
+    ) -> Result<AbsenceProof<T>, MerkleError> {
+        if !proves_absence(&self.spec) {
+            return Err(MerkleError::AbsenceUnsupported);
+        }
+        let leaves = self.leaves();
+        if leaves.is_empty() {
+            return Err(MerkleError::EmptyTree);
+        }
+
+        let leaf = leaf.into();
+        let index = leaves.partition_point(|l| *l < leaf);
+        if leaves.get(index) == Some(&leaf) {
+            return Err(MerkleError::LeafFound);
+        }
+        let neighbour = |index: usize| Neighbour {
+            leaf: leaves[index],
+            index,
+            proof: self.proof_by_index(index),
+        };
+        Ok(AbsenceProof {
+            left: index.checked_sub(1).map(neighbour),
+            right: (index < leaves.len()).then(|| neighbour(index)),
+        })
+    }
+
+    /// Verifies a proof that `leaf` is not in the tree with `root`, which must be the root of a tree
+    /// with as many leaves as this one.
+    pub fn verify_absence(
+        &self,
+        proof: &AbsenceProof<T>,
+        leaf: impl Into<T::Hash>,
+        root: impl Into<T::Hash>,
+    ) -> bool {
+        let leaf_count = self.leaves().len();
+        verify_absence(&self.spec, leaf_count, proof, leaf.into(), root.into())
+    }
+}
+
+/// Verifies that `leaf` is not in a tree with the supplied spec, number of leaves and root, without
+/// the tree itself. Both neighbours must be in the tree at adjacent positions and bracket the leaf,
+/// or a single neighbour must be the first or last leaf.
+///
+/// The number of leaves must come from a trusted source: a tree padded by duplicating or zeroing
+/// odd nodes has leaves whose proofs are also valid proofs of the last leaf of a smaller tree, so a
+/// prover supplying a smaller number could prove a present leaf absent.
+pub fn verify_absence<T: HashFunction>(
+    spec: &TreeSpec,
+    leaf_count: usize,
+    proof: &AbsenceProof<T>,
+    leaf: T::Hash,
+    root: T::Hash,
+) -> bool {
+    if !proves_absence(spec) || leaf_count == 0 {
+        return false;
+    }
+    let bracketed = match (&proof.left, &proof.right) {
+        (Some(left), Some(right)) => {
+            left.leaf < leaf && leaf < right.leaf && left.index.checked_add(1) == Some(right.index)
+        }
+        (Some(left), None) => left.leaf < leaf && left.index == leaf_count - 1,
+        (None, Some(right)) => leaf < right.leaf && right.index == 0,
+        (None, None) => false,
+    };
+
+    bracketed
+        && [&proof.left, &proof.right]
+            .into_iter()
+            .flatten()
+            .all(|neighbour| {
+                neighbour.index < leaf_count
+                    && has_path(spec, leaf_count, neighbour)
+                    && verify_proof(spec, &neighbour.proof, neighbour.leaf, root)
+            })
+}
+
+/// Whether the leaves of trees with the spec are sorted, and the positions of proven leaves bound
+/// by their proofs. Padding leaves follow the sorted leaves, so padded trees are not sorted.
+fn proves_absence(spec: &TreeSpec) -> bool {
+    spec.sort_leaves && !spec.sort_pairs && spec.padding.is_none()
+}
+
+/// Whether the directions and sibling counts of a proof are those of the leaf at its index within
+/// a tree of `len` leaves.
+fn has_path<T: HashFunction>(spec: &TreeSpec, len: usize, neighbour: &Neighbour<T>) -> bool {
+    let arity = spec.arity;
+    let mut directions = vec![];
+    let mut counts = vec![];
+    let (mut index, mut len) = (neighbour.index, len);
+    while len > 1 {
+        let start = index - index % arity;
+        let end = len.min(start + arity);
+        if end - start > 1 || spec.odd_strategy != OddStrategy::Promote {
+            directions.extend(
+                (start..end)
+                    .filter(|i| *i != index)
+                    .map(|i| match i < index {
+                        true => Direction::Left,
+                        false => Direction::Right,
+                    }),
+            );
+            if spec.odd_strategy != OddStrategy::Promote {
+                directions.extend((end..start + arity).map(|_| Direction::Right));
+            }
+            counts.push(match spec.odd_strategy {
+                OddStrategy::Promote => end - start - 1,
+                _ => arity - 1,
+            });
+        }
+        index /= arity;
+        len = len.div_ceil(arity);
+    }
+
+    let proof = &neighbour.proof;
+    proof.directions() == Some(&directions[..])
+        && match arity {
+            2 => proof.sibling_counts().is_none(),
+            _ => proof.sibling_counts() == Some(&counts[..]),
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_absence;
+    use crate::{Bytes, Keccak256, MerkleError, MerkleTree, MerkleTreeBuilder, TreeSpec};
+
+    fn tree(spec: &str, count: u8) -> MerkleTree<Keccak256> {
+        let values: Vec<[u8; 1]> = (0..count).map(|i| [i * 2]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        MerkleTreeBuilder::from_spec(&TreeSpec::parse(spec).unwrap())
+            .unwrap()
+            .build(&leaves)
+    }
+
+    #[test]
+    fn proves_absence() {
+        for spec in [
+            "keccak256/sorted-leaves/promote-odd/arity2",
+            "keccak256/sorted-leaves/dup-odd/arity2",
+            "keccak256/sorted-leaves/promote-odd/arity3",
+            "keccak256/sorted-leaves/zero-odd/arity4",
+        ] {
+            for count in 1..=9 {
+                let tree = tree(spec, count);
+                let root = tree.root();
+                let leaves = tree.leaves().clone();
+
+                // Absent leaves below, between and above the leaves of the tree
+                let mut absent = vec![[0; 32], [0xff; 32]];
+                absent.extend(leaves.windows(2).filter_map(|pair| {
+                    let mut between = pair[0];
+                    between[31] = between[31].wrapping_add(1);
+                    (between < pair[1]).then_some(between)
+                }));
+                for leaf in absent {
+                    let proof = tree.proof_of_absence(leaf).unwrap();
+                    assert!(
+                        tree.verify_absence(&proof, leaf, root),
+                        "{} {}",
+                        spec,
+                        count
+                    );
+                    assert!(!tree.verify_absence(&proof, leaf, [0; 32]));
+                }
+
+                for leaf in &leaves {
+                    assert_eq!(
+                        tree.proof_of_absence(*leaf).err(),
+                        Some(MerkleError::LeafFound)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_non_adjacent_neighbours() {
+        let tree = tree("keccak256/sorted-leaves/promote-odd/arity2", 5);
+        let root = tree.root();
+        let leaves = tree.leaves().clone();
+        let mut leaf = leaves[1];
+        leaf[31] = leaf[31].wrapping_add(1);
+        assert!(leaves[1] < leaf && leaf < leaves[2]);
+
+        // Skipping a leaf, or claiming other positions or tree sizes, fails verification
+        let mut proof = tree.proof_of_absence(leaf).unwrap();
+        proof.right = tree.proof_of_absence([0xff; 32]).unwrap().left;
+        assert!(!tree.verify_absence(&proof, leaf, root));
+
+        let mut proof = tree.proof_of_absence(leaf).unwrap();
+        proof.right.as_mut().unwrap().index = 3;
+        proof.left.as_mut().unwrap().index = 2;
+        assert!(!tree.verify_absence(&proof, leaf, root));
+
+        let mut proof = tree.proof_of_absence([0xff; 32]).unwrap();
+        proof.left.as_mut().unwrap().index = 3;
+        assert!(!tree.verify_absence(&proof, [0xff; 32], root));
+
+        let mut proof = tree.proof_of_absence(leaf).unwrap();
+        proof.right = None;
+        assert!(!tree.verify_absence(&proof, leaf, root));
+    }
+
+    #[test]
+    fn rejects_forged_leaf_counts() {
+        for spec in [
+            "keccak256/sorted-leaves/dup-odd/arity2",
+            "keccak256/sorted-leaves/zero-odd/arity2",
+        ] {
+            let tree = tree(spec, 8);
+            let root = tree.root();
+            let leaves = tree.leaves().clone();
+
+            // The proof of the fifth leaf is also that of the last leaf of a tree of five leaves,
+            // which would prove the sixth leaf absent
+            let mut between = leaves[4];
+            between[31] = between[31].wrapping_add(1);
+            assert!(leaves[4] < between && between < leaves[5]);
+            let mut proof = tree.proof_of_absence(between).unwrap();
+            proof.right = None;
+            assert!(verify_absence(tree.spec(), 5, &proof, leaves[5], root));
+            assert!(!verify_absence(tree.spec(), 8, &proof, leaves[5], root));
+            assert!(!tree.verify_absence(&proof, leaves[5], root));
+        }
+    }
+
+    #[test]
+    fn requires_positional_sorted_trees() {
+        for spec in [
+            "keccak256/sorted/promote-odd/arity2",
+            "keccak256/ordered/promote-odd/arity2",
+            "keccak256/sorted-leaves/promote-odd/arity2/pad-zero",
+        ] {
+            assert_eq!(
+                tree(spec, 3).proof_of_absence([0; 32]).err(),
+                Some(MerkleError::AbsenceUnsupported)
+            );
+        }
+        assert_eq!(
+            tree("keccak256/sorted-leaves/promote-odd/arity2", 0)
+                .proof_of_absence([0; 32])
+                .err(),
+            Some(MerkleError::EmptyTree)
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{Clock, HashFunction, MerkleTree, Store, SystemClock};
+use std::time::{Duration, SystemTime};
+
+/// When an `Anchorer` publishes the root of a tree. Policies combine, publishing once any of
+/// them is due, and roots may always be published on demand.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AnchorPolicy {
+    appends: Option<usize>,
+    interval: Option<Duration>,
+}
+
+impl AnchorPolicy {
+    /// A policy publishing only on demand.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publishes once `appends` leaves have been appended since the last publication.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `appends` is zero.
+    pub fn every_appends(mut self, appends: usize) -> Self {
+        assert!(appends > 0, "appends must be at least one");
+        self.appends = Some(appends);
+        self
+    }
+
+    /// Publishes once `interval` has elapsed since the last publication.
+    pub fn every(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+}
+
+/// Publishes the roots of a growing tree to an anchor, such as a blockchain or a transparency
+/// log, as its policy falls due. A root is never published twice in a row, so calls made while
+/// the tree is unchanged publish nothing.
+///
+/// The anchorer holds no reference to the tree, so is told of appends and polled for elapsed time
+/// by the code appending to the tree.
+pub struct Anchorer<H, F> {
+    policy: AnchorPolicy,
+    publish: F,
+    clock: Box<dyn Clock>,
+    appends: usize,
+    published_at: SystemTime,
+    published: Option<H>,
+}
+
+impl<H: Copy + PartialEq, E, F: FnMut(H) -> Result<(), E>> Anchorer<H, F> {
+    /// Creates an anchorer calling `publish` with each root to anchor. Roots which fail to publish
+    /// are retried when next due.
+    pub fn new(policy: AnchorPolicy, publish: F) -> Self {
+        Self::with_clock(policy, publish, Box::new(SystemClock))
+    }
+
+    /// Creates an anchorer timing intervals by the supplied clock.
+    pub fn with_clock(policy: AnchorPolicy, publish: F, clock: Box<dyn Clock>) -> Self {
+        let published_at = clock.now();
+        Self {
+            policy,
+            publish,
+            clock,
+            appends: 0,
+            published_at,
+            published: None,
+        }
+    }
+
+    /// Records that `count` leaves were appended to the tree, publishing its root if due.
+    pub fn appended<T, S>(&mut self, tree: &MerkleTree<T, S>, count: usize) -> Result<bool, E>
+    where
+        T: HashFunction<Hash = H>,
+        S: Store<H>,
+    {
+        self.appends = self.appends.saturating_add(count);
+        self.tick(tree)
+    }
+
+    /// Publishes the root of the tree if any policy is due, returning whether it was published.
+    pub fn tick<T, S>(&mut self, tree: &MerkleTree<T, S>) -> Result<bool, E>
+    where
+        T: HashFunction<Hash = H>,
+        S: Store<H>,
+    {
+        let appends = self.policy.appends.is_some_and(|n| self.appends >= n);
+        let elapsed = self.policy.interval.is_some_and(|interval| {
+            let elapsed = self.clock.now().duration_since(self.published_at);
+            elapsed.is_ok_and(|elapsed| elapsed >= interval)
+        });
+        match appends || elapsed {
+            true => self.publish(tree),
+            false => Ok(false),
+        }
+    }
+
+    /// Publishes the root of the tree now, unless it was the last root published.
+    pub fn publish<T, S>(&mut self, tree: &MerkleTree<T, S>) -> Result<bool, E>
+    where
+        T: HashFunction<Hash = H>,
+        S: Store<H>,
+    {
+        let root = tree.root();
+        if self.published == Some(root) {
+            return Ok(false);
+        }
+        (self.publish)(root)?;
+        self.published = Some(root);
+        self.published_at = self.clock.now();
+        self.appends = 0;
+        Ok(true)
+    }
+
+    /// The last root published.
+    pub fn published(&self) -> Option<H> {
+        self.published
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnchorPolicy, Anchorer};
+    use crate::{Bytes, Clock, Keccak256, MerkleTree};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    /// A clock advanced by the test.
+    struct TestClock(Arc<AtomicU64>);
+
+    impl Clock for TestClock {
+        fn now(&self) -> SystemTime {
+            UNIX_EPOCH + Duration::from_secs(self.0.load(Ordering::SeqCst))
+        }
+    }
+
+    #[test]
+    fn publishes_when_due() {
+        let leaves: Vec<&Bytes> = ["a"].iter().map(|x| x.as_bytes()).collect();
+        let mut tree = MerkleTree::<Keccak256>::new(&leaves);
+        let time = Arc::new(AtomicU64::new(0));
+        let mut roots = vec![];
+        let policy = AnchorPolicy::new()
+            .every_appends(3)
+            .every(Duration::from_secs(60));
+        let mut anchorer = Anchorer::with_clock(
+            policy,
+            |root| {
+                roots.push(root);
+                Ok::<_, ()>(())
+            },
+            Box::new(TestClock(time.clone())),
+        );
+
+        // Published after three appends
+        for (i, leaf) in ["b", "c", "d"].iter().enumerate() {
+            tree.push(leaf.as_bytes());
+            assert_eq!(anchorer.appended(&tree, 1), Ok(i == 2));
+        }
+        assert_eq!(anchorer.published(), Some(tree.root()));
+
+        // Published once the interval elapses, but not again while the root is unchanged
+        tree.push(b"e");
+        assert_eq!(anchorer.appended(&tree, 1), Ok(false));
+        time.store(61, Ordering::SeqCst);
+        assert_eq!(anchorer.tick(&tree), Ok(true));
+        time.store(200, Ordering::SeqCst);
+        assert_eq!(anchorer.tick(&tree), Ok(false));
+        assert_eq!(anchorer.publish(&tree), Ok(false));
+
+        tree.push(b"f");
+        assert_eq!(anchorer.publish(&tree), Ok(true));
+        drop(anchorer);
+        assert_eq!(roots.len(), 3);
+        assert_eq!(roots.last(), Some(&tree.root()));
+    }
+
+    #[test]
+    fn retries_failed_publications() {
+        let leaves: Vec<&Bytes> = ["a"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Keccak256>::new(&leaves);
+        let mut attempts = 0;
+        let mut anchorer = Anchorer::new(AnchorPolicy::new().every_appends(1), |_| {
+            attempts += 1;
+            match attempts {
+                1 => Err("unavailable"),
+                _ => Ok(()),
+            }
+        });
+        assert_eq!(anchorer.appended(&tree, 1), Err("unavailable"));
+        assert_eq!(anchorer.published(), None);
+        assert_eq!(anchorer.tick(&tree), Ok(true));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{HashFunction, MerkleTree};
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+
+/// The default minimum number of distinct leaves, below which the members of a tree can be
+/// recovered almost instantly by hashing candidate values against the published root.
+pub const MIN_ANONYMITY_SET: usize = 1024;
+
+/// How a tree with too few distinct leaves is reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnonymityPolicy {
+    /// The tree is accepted along with a warning.
+    Warn,
+    /// The tree is rejected.
+    Strict,
+}
+
+/// A tree whose leaves are too few for its members to stay private once the root and the leaf
+/// encoding are published, as when leaves are derived from emails or national IDs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnonymityWarning {
+    /// The number of distinct leaves, excluding padding.
+    pub leaves: usize,
+    pub minimum: usize,
+}
+
+impl Display for AnonymityWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "tree has {} distinct leaves, fewer than the minimum anonymity set of {}, so its \
+             members can be brute-forced from the root; salt each leaf with a random value held \
+             by its member",
+            self.leaves, self.minimum
+        )
+    }
+}
+
+impl std::error::Error for AnonymityWarning {}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// The number of distinct leaves excluding padding, being the number of candidates a leaf
+    /// could be among.
+    pub fn anonymity_set(&self) -> usize {
+        let padding = MerkleTree::<T>::padding_leaf(&self.spec);
+        self.leaves()
+            .iter()
+            .filter(|leaf| Some(**leaf) != padding)
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    /// Checks that the tree has at least `minimum` distinct leaves, warning or failing according
+    /// to `policy` when it does not. Trees with salted leaves pass, as their members cannot be
+    /// brute-forced without the salt.
+    pub fn check_anonymity(
+        &self,
+        minimum: usize,
+        policy: AnonymityPolicy,
+    ) -> Result<Option<AnonymityWarning>, AnonymityWarning> {
+        let leaves = self.anonymity_set();
+        if leaves >= minimum || self.spec.leaf_key.is_some() {
+            return Ok(None);
+        }
+        let warning = AnonymityWarning { leaves, minimum };
+        match policy {
+            AnonymityPolicy::Warn => Ok(Some(warning)),
+            AnonymityPolicy::Strict => Err(warning),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        AnonymityPolicy, AnonymityWarning, Bytes, Keccak256, MerkleTree, MerkleTreeBuilder,
+        PaddingLeaf, MIN_ANONYMITY_SET,
+    };
+
+    #[test]
+    fn checks_anonymity_sets() {
+        let leaves: Vec<&Bytes> = ["a", "b", "a", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .pad_to_pow2(PaddingLeaf::Zero)
+            .build(&leaves);
+        assert_eq!(tree.anonymity_set(), 3);
+
+        let warning = AnonymityWarning {
+            leaves: 3,
+            minimum: MIN_ANONYMITY_SET,
+        };
+        assert_eq!(
+            tree.check_anonymity(MIN_ANONYMITY_SET, AnonymityPolicy::Warn),
+            Ok(Some(warning.clone()))
+        );
+        assert_eq!(
+            tree.check_anonymity(MIN_ANONYMITY_SET, AnonymityPolicy::Strict),
+            Err(warning.clone())
+        );
+        assert!(warning.to_string().contains("salt"));
+        assert_eq!(tree.check_anonymity(3, AnonymityPolicy::Strict), Ok(None));
+        let salted = MerkleTreeBuilder::<Keccak256>::new()
+            .salt(b"salt")
+            .build(&leaves);
+        assert_eq!(
+            salted.check_anonymity(MIN_ANONYMITY_SET, AnonymityPolicy::Strict),
+            Ok(None)
+        );
+
+        let values: Vec<[u8; 2]> = (0..MIN_ANONYMITY_SET as u16)
+            .map(|i| i.to_le_bytes())
+            .collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let tree = MerkleTree::<Keccak256>::new(&leaves);
+        assert_eq!(
+            tree.check_anonymity(MIN_ANONYMITY_SET, AnonymityPolicy::Strict),
+            Ok(None)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use crate::store::out_of_bounds;
+use crate::Store;
+use std::io::{Error, ErrorKind};
+
+/// The most layers an arena store can hold, enough for trees of any number of leaves.
+const MAX_LAYERS: usize = usize::BITS as usize + 1;
+
+/// A store holding nodes within a buffer supplied by the caller, such as one allocated from an
+/// arena or a bump allocator, so the memory held by the nodes of a tree is bounded and accounted
+/// for by the caller. The buffer is divided between the layers of a tree of at most a given
+/// number of leaves, and no other memory is allocated for nodes.
+///
+/// Only the nodes are held in the buffer. Other allocations of the tree, such as its proofs, the
+/// leaves collected to be sorted by `build_in`, the batches in which leaves are hashed and any
+/// index built by `index_leaves`, are made by the global allocator, which environments with a
+/// custom allocator bound by registering it as the `#[global_allocator]`.
+///
+/// Writing beyond the capacity of a layer fails with `ErrorKind::OutOfMemory`.
+pub struct ArenaStore<'a, H> {
+    buffer: &'a mut [H],
+    /// The offset of each layer within the buffer, followed by the end of the last layer.
+    offsets: [usize; MAX_LAYERS + 1],
+    lens: [usize; MAX_LAYERS],
+    layers: usize,
+    regions: usize,
+}
+
+impl<'a, H: Copy> ArenaStore<'a, H> {
+    /// Creates an empty store for a tree of at most `leaves` leaves, including any padding
+    /// leaves, with the given arity. Fails if the buffer is smaller than `required`.
+    pub fn new(buffer: &'a mut [H], leaves: usize, arity: usize) -> std::io::Result<Self> {
+        let mut offsets = [0; MAX_LAYERS + 1];
+        let mut regions = 0;
+        for len in layer_lens(leaves, arity) {
+            offsets[regions + 1] = offsets[regions] + len;
+            regions += 1;
+        }
+        if buffer.len() < offsets[regions] {
+            return Err(Error::new(
+                ErrorKind::OutOfMemory,
+                format!("arena store requires {} nodes", offsets[regions]),
+            ));
+        }
+        Ok(Self {
+            buffer,
+            offsets,
+            lens: [0; MAX_LAYERS],
+            layers: 0,
+            regions,
+        })
+    }
+
+    /// The number of nodes a buffer must hold for a tree of at most `leaves` leaves.
+    pub fn required(leaves: usize, arity: usize) -> usize {
+        layer_lens(leaves, arity).sum()
+    }
+
+    /// The number of nodes the store can hold.
+    pub fn capacity(&self) -> usize {
+        self.offsets[self.regions]
+    }
+
+    /// The number of nodes held.
+    pub fn used(&self) -> usize {
+        self.lens[..self.layers].iter().sum()
+    }
+}
+
+impl<H: Copy> Store<H> for ArenaStore<'_, H> {
+    fn layers(&self) -> usize {
+        self.layers
+    }
+
+    fn layer_len(&self, layer: usize) -> usize {
+        match layer < self.layers {
+            true => self.lens[layer],
+            false => 0,
+        }
+    }
+
+    fn get(&self, layer: usize, index: usize) -> Option<H> {
+        match index < self.layer_len(layer) {
+            true => Some(self.buffer[self.offsets[layer] + index]),
+            false => None,
+        }
+    }
+
+    fn put(&mut self, layer: usize, index: usize, hash: H) -> std::io::Result<()> {
+        if layer > self.layers || index > self.layer_len(layer) {
+            return Err(out_of_bounds(layer, index));
+        }
+        let offset = self.offsets[layer] + index;
+        if layer >= self.regions || offset >= self.offsets[layer + 1] {
+            return Err(Error::new(ErrorKind::OutOfMemory, "arena store is full"));
+        }
+        if layer == self.layers {
+            self.layers += 1;
+        }
+        if index == self.lens[layer] {
+            self.lens[layer] += 1;
+        }
+        self.buffer[offset] = hash;
+        Ok(())
+    }
+}
+
+/// The number of nodes in each layer of a tree of `leaves` leaves, from the leaves to the root.
+fn layer_lens(leaves: usize, arity: usize) -> impl Iterator<Item = usize> {
+    let next = move |len: &usize| (*len > 1).then(|| len.div_ceil(arity.max(2)));
+    std::iter::successors(Some(leaves), next).take_while(|len| *len > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArenaStore, Bytes, Keccak256, MerkleTreeBuilder, OddStrategy};
+    use std::io::ErrorKind;
+
+    #[test]
+    fn builds_within_arena() {
+        let values: Vec<[u8; 2]> = (0..100u16).map(|i| i.to_be_bytes()).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for builder in [
+            MerkleTreeBuilder::<Keccak256>::new().sort_leaves(false),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .sort_pairs(false)
+                .odd_strategy(OddStrategy::Duplicate)
+                .arity(3),
+        ] {
+            let arity = builder.spec().arity;
+            let expected = builder.build(&leaves);
+            let mut buffer = vec![[0; 32]; ArenaStore::<[u8; 32]>::required(120, arity)];
+            let store = ArenaStore::new(&mut buffer, 120, arity).unwrap();
+            let mut tree = builder.build_in(store, &leaves[..90]).unwrap();
+            for leaf in &leaves[90..] {
+                tree.append(leaf).unwrap();
+            }
+            assert_eq!(tree.root(), expected.root());
+            let leaf = expected.leaves()[42];
+            assert!(tree.verify(tree.proof(leaf), leaf, tree.root()));
+            assert!(tree.store().used() <= tree.store().capacity());
+
+            // Trees outgrowing the arena fail rather than allocating
+            let mut buffer = vec![[0; 32]; ArenaStore::<[u8; 32]>::required(90, arity)];
+            let store = ArenaStore::new(&mut buffer, 90, arity).unwrap();
+            let mut tree = builder.build_in(store, &leaves[..90]).unwrap();
+            assert_eq!(
+                tree.append(leaves[90]).unwrap_err().kind(),
+                ErrorKind::OutOfMemory
+            );
+        }
+        let mut buffer = [[0u8; 32]; 4];
+        assert!(ArenaStore::new(&mut buffer, 4, 2).is_err());
+    }
+}
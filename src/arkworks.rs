@@ -0,0 +1,179 @@
+//! An R1CS gadget verifying proofs of binary trees with sorted pairs hashed by `Poseidon` over BN254, so
+//! membership can be proven within a SNARK without revealing the leaf or its path.
+
+use crate::poseidon;
+use crate::{
+    FieldElement, HashFunction, MerkleError, MerkleProof, Poseidon, PoseidonField, TreeSpec,
+};
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::constraints::CryptographicSpongeVar;
+use ark_crypto_primitives::sponge::poseidon::constraints::PoseidonSpongeVar;
+use ark_ff::PrimeField;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+/// A circuit proving that a private leaf is within the tree whose root is the single public
+/// input. Circuits are specific to the length of the proof, so keys generated for one depth only
+/// prove leaves at that depth.
+pub struct MembershipCircuit {
+    node_prefix: Vec<u8>,
+    leaf: [u8; 32],
+    siblings: Vec<[u8; 32]>,
+    root: [u8; 32],
+}
+
+impl MembershipCircuit {
+    /// Creates a circuit for a proof from a tree with the supplied spec, which must be a binary
+    /// tree with sorted pairs hashed by `Poseidon`.
+    pub fn new(
+        spec: &TreeSpec,
+        proof: &MerkleProof<Poseidon>,
+        leaf: FieldElement<Fr>,
+        root: FieldElement<Fr>,
+    ) -> Result<Self, MerkleError> {
+        if spec.arity != 2 {
+            return Err(MerkleError::ArityUnsupported);
+        }
+        if !spec.sort_pairs || spec.hash != <Poseidon>::NAME {
+            return Err(MerkleError::SchemeMismatch);
+        }
+        if !proof.is_well_formed() {
+            return Err(MerkleError::MalformedProof);
+        }
+        Ok(Self {
+            node_prefix: spec.node_prefix.clone(),
+            leaf: leaf.to_bytes(),
+            siblings: proof.iter().map(|sibling| sibling.to_bytes()).collect(),
+            root: root.to_bytes(),
+        })
+    }
+
+    /// The public inputs of the circuit, being the root as a field element.
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        vec![Fr::from_le_bytes_mod_order(&self.root)]
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for MembershipCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let root = FpVar::new_input(cs.clone(), || Ok(Fr::from_le_bytes_mod_order(&self.root)))?;
+        let leaf = UInt8::new_witness_vec(cs.clone(), &self.leaf)?;
+        let siblings = self
+            .siblings
+            .iter()
+            .map(|sibling| UInt8::new_witness_vec(cs.clone(), sibling))
+            .collect::<Result<Vec<_>, _>>()?;
+        verify_proof_gadget(cs, &self.node_prefix, &leaf, &siblings, &root)?
+            .enforce_equal(&Boolean::TRUE)
+    }
+}
+
+/// Whether the sibling hashes recompute `root` from `leaf` within the circuit, hashing each pair
+/// in sorted order after `node_prefix` as `verify_proof` does for sorted binary trees.
+pub fn verify_proof_gadget(
+    cs: ConstraintSystemRef<Fr>,
+    node_prefix: &[u8],
+    leaf: &[UInt8<Fr>],
+    siblings: &[Vec<UInt8<Fr>>],
+    root: &FpVar<Fr>,
+) -> Result<Boolean<Fr>, SynthesisError> {
+    let mut node = leaf.to_vec();
+    for sibling in siblings {
+        // Order the pair as sorting the hashes would
+        let first = less_than(&node, sibling)?;
+        let mut pair = UInt8::constant_vec(node_prefix);
+        for (a, b) in node.iter().zip(sibling) {
+            pair.push(UInt8::conditionally_select(&first, a, b)?);
+        }
+        for (a, b) in node.iter().zip(sibling) {
+            pair.push(UInt8::conditionally_select(&first, b, a)?);
+        }
+        node = hash(cs.clone(), &pair)?.to_bytes()?;
+    }
+    node.is_eq(&root.to_bytes()?)
+}
+
+/// Hashes bytes as `Poseidon` does.
+fn hash(cs: ConstraintSystemRef<Fr>, bytes: &[UInt8<Fr>]) -> Result<FpVar<Fr>, SynthesisError> {
+    let mut elements = vec![FpVar::constant(Fr::from(bytes.len() as u64))];
+    for chunk in bytes.chunks(poseidon::CHUNK) {
+        elements.push(Boolean::le_bits_to_fp_var(&chunk.to_bits_le()?)?);
+    }
+    let mut sponge = PoseidonSpongeVar::new(cs, Fr::config());
+    sponge.absorb(&elements)?;
+    Ok(sponge.squeeze_field_elements(1)?.remove(0))
+}
+
+/// Whether `a` orders before `b`, comparing bytes as `[u8; 32]` does.
+fn less_than(a: &[UInt8<Fr>], b: &[UInt8<Fr>]) -> Result<Boolean<Fr>, SynthesisError> {
+    let mut less = Boolean::FALSE;
+    let mut equal = Boolean::TRUE;
+    for (a, b) in a.iter().zip(b) {
+        // Compare each byte from its most significant bit
+        for (a, b) in a.to_bits_le()?.iter().zip(b.to_bits_le()?).rev() {
+            less = less.or(&equal.and(&a.not().and(&b)?)?)?;
+            equal = equal.and(&a.xor(&b)?.not())?;
+        }
+    }
+    Ok(less)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MembershipCircuit;
+    use crate::{verify_proof, Bytes, HashFunction, MerkleTree, Poseidon};
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use rand::{Rng, SeedableRng};
+
+    fn satisfied(circuit: MembershipCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn agrees_with_native_verification() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        for count in 1..=6 {
+            let values: Vec<[u8; 32]> = (0..count).map(|_| rng.gen()).collect();
+            let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+            let tree = MerkleTree::<Poseidon>::new(&leaves);
+            let spec = tree.spec();
+            let root = tree.root();
+
+            for leaf in tree.leaves() {
+                let proof = tree.proof(*leaf);
+                assert!(verify_proof(spec, &proof, *leaf, root));
+                let circuit = MembershipCircuit::new(spec, &proof, *leaf, root).unwrap();
+                assert_eq!(circuit.public_inputs().len(), 1);
+                assert!(satisfied(circuit), "{} leaves", count);
+
+                // Neither accepts another root or a tampered sibling
+                let other = <Poseidon>::hash(&rng.gen::<[u8; 32]>());
+                assert!(!verify_proof(spec, &proof, *leaf, other));
+                assert!(!satisfied(
+                    MembershipCircuit::new(spec, &proof, *leaf, other).unwrap()
+                ));
+                if !proof.is_empty() {
+                    let mut hashes = proof.hashes().to_vec();
+                    let step = rng.gen_range(0..hashes.len());
+                    hashes[step] = <Poseidon>::hash(&hashes[step].to_bytes());
+                    let tampered = crate::MerkleProof::new(hashes, None, None);
+                    assert!(!verify_proof(spec, &tampered, *leaf, root));
+                    assert!(!satisfied(
+                        MembershipCircuit::new(spec, &tampered, *leaf, root).unwrap()
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn requires_sorted_binary_trees() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Poseidon>::new_ordered(&leaves);
+        let leaf = tree.leaves()[0];
+        assert!(MembershipCircuit::new(tree.spec(), &tree.proof(leaf), leaf, tree.root()).is_err());
+    }
+}
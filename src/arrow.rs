@@ -0,0 +1,190 @@
+use crate::{Direction, HashFunction, MerkleTree};
+use arrow_array::builder::{FixedSizeBinaryBuilder, ListBuilder, StringBuilder, UInt64Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+const ROOT_METADATA_KEY: &str = "merkle_root";
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Schema of the record batches produced by `to_record_batch`: one row per leaf with its
+    /// index, hash and proof, with the hex encoded root stored in the schema metadata. The
+    /// direction of each sibling, as `left` or `right`, and the number of siblings in each layer
+    /// are null for proofs which do not carry them.
+    pub fn arrow_schema(&self) -> Schema {
+        let size = Self::hash_size();
+        let item = Field::new("item", DataType::FixedSizeBinary(size), false);
+        let direction = Field::new("item", DataType::Utf8, false);
+        let count = Field::new("item", DataType::UInt64, false);
+        Schema::new(vec![
+            Field::new("index", DataType::UInt64, false),
+            Field::new("leaf", DataType::FixedSizeBinary(size), false),
+            Field::new("proof", DataType::List(Arc::new(item)), false),
+            Field::new("directions", DataType::List(Arc::new(direction)), true),
+            Field::new("sibling_counts", DataType::List(Arc::new(count)), true),
+        ])
+        .with_metadata(HashMap::from([(
+            ROOT_METADATA_KEY.to_string(),
+            format!("0x{}", hex::encode(self.root())),
+        )]))
+    }
+
+    /// Exports the leaves and their proofs as an Arrow record batch.
+    pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+        let size = Self::hash_size();
+        let mut indices = UInt64Builder::with_capacity(self.leaves().len());
+        let mut leaves = FixedSizeBinaryBuilder::with_capacity(self.leaves().len(), size);
+        let mut proofs = ListBuilder::new(FixedSizeBinaryBuilder::new(size))
+            .with_field(Field::new("item", DataType::FixedSizeBinary(size), false));
+        let mut directions = ListBuilder::new(StringBuilder::new()).with_field(Field::new(
+            "item",
+            DataType::Utf8,
+            false,
+        ));
+        let mut sibling_counts = ListBuilder::new(UInt64Builder::new()).with_field(Field::new(
+            "item",
+            DataType::UInt64,
+            false,
+        ));
+
+        // Proofs are taken by index, so each duplicate of a leaf has the proof of its own position
+        for (index, leaf) in self.leaves().iter().enumerate() {
+            let proof = self.proof_by_index(index);
+            indices.append_value(index as u64);
+            leaves.append_value(leaf)?;
+            for hash in proof.iter() {
+                proofs.values().append_value(hash)?;
+            }
+            proofs.append(true);
+            for direction in proof.directions().unwrap_or_default() {
+                directions.values().append_value(match direction {
+                    Direction::Left => "left",
+                    Direction::Right => "right",
+                });
+            }
+            directions.append(proof.directions().is_some());
+            for count in proof.sibling_counts().unwrap_or_default() {
+                sibling_counts.values().append_value(*count as u64);
+            }
+            sibling_counts.append(proof.sibling_counts().is_some());
+        }
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(indices.finish()),
+            Arc::new(leaves.finish()),
+            Arc::new(proofs.finish()),
+            Arc::new(directions.finish()),
+            Arc::new(sibling_counts.finish()),
+        ];
+        RecordBatch::try_new(Arc::new(self.arrow_schema()), columns)
+    }
+
+    /// Writes the leaves and their proofs to `writer` as a Parquet file.
+    pub fn write_parquet<W: Write + Send>(&self, writer: W) -> Result<(), ParquetError> {
+        let batch = self.to_record_batch()?;
+        let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+
+    fn hash_size() -> i32 {
+        T::Hash::default().as_ref().len() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Direction, Keccak256, MerkleTree, MerkleTreeBuilder};
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::UInt64Type;
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn tree() -> MerkleTree<Keccak256> {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        MerkleTree::<Keccak256>::new(&leaves)
+    }
+
+    #[test]
+    fn exports_directions_and_sibling_counts() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c", "a"].iter().map(|x| x.as_bytes()).collect();
+        let ordered = MerkleTree::<Keccak256>::new_ordered(&leaves);
+        let batch = ordered.to_record_batch().unwrap();
+        let proofs = batch.column(2).as_list::<i32>();
+        let directions = batch.column(3).as_list::<i32>();
+        for i in 0..batch.num_rows() {
+            let expected = ordered.proof_by_index(i);
+            let proof = proofs.value(i);
+            assert_eq!(proof.as_fixed_size_binary().value(0), expected.hashes()[0]);
+            let sides = directions.value(i);
+            let sides: Vec<&str> = sides.as_string::<i32>().iter().flatten().collect();
+            let expected: Vec<&str> = expected
+                .directions()
+                .unwrap()
+                .iter()
+                .map(|d| match d {
+                    Direction::Left => "left",
+                    Direction::Right => "right",
+                })
+                .collect();
+            assert_eq!(sides, expected);
+        }
+        assert!(batch.column(4).is_null(0));
+
+        // Sorted proofs carry neither, and trees of higher arity count the siblings of each layer
+        assert!(tree().to_record_batch().unwrap().column(3).is_null(0));
+        let wide = MerkleTreeBuilder::<Keccak256>::new()
+            .arity(4)
+            .build(&leaves[..3]);
+        let batch = wide.to_record_batch().unwrap();
+        let counts = batch.column(4).as_list::<i32>().value(0);
+        assert_eq!(counts.as_primitive::<UInt64Type>().values(), &[2]);
+    }
+
+    #[test]
+    fn exports_record_batch() {
+        let tree = tree();
+        let batch = tree.to_record_batch().unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(
+            batch.schema().metadata()["merkle_root"],
+            format!("0x{}", hex::encode(tree.root()))
+        );
+
+        let indices = batch.column(0).as_primitive::<UInt64Type>();
+        let leaves = batch.column(1).as_fixed_size_binary();
+        let proofs = batch.column(2).as_list::<i32>();
+        for i in 0..batch.num_rows() {
+            let leaf = tree.leaves()[i];
+            assert_eq!(indices.value(i), i as u64);
+            assert_eq!(leaves.value(i), leaf);
+
+            let proof = proofs.value(i);
+            let proof = proof.as_fixed_size_binary();
+            let expected = tree.proof(leaf);
+            assert_eq!(proof.len(), expected.len());
+            for (j, hash) in expected.iter().enumerate() {
+                assert_eq!(proof.value(j), hash);
+            }
+        }
+    }
+
+    #[test]
+    fn writes_parquet() {
+        let tree = tree();
+        let mut buffer = Vec::new();
+        tree.write_parquet(&mut buffer).unwrap();
+
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(buffer))
+            .unwrap()
+            .build()
+            .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert_eq!(batch.columns(), tree.to_record_batch().unwrap().columns());
+    }
+}
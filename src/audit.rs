@@ -0,0 +1,139 @@
+use crate::{Bytes, HashFunction, MerkleTree, TreeSpec};
+use std::fmt::{Display, Formatter};
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Creates a tree from externally computed leaf hashes, first checking a random sample of them
+    /// against the raw values they were computed from. `values[i]` must be the pre-image of
+    /// `hashes[i]`, and `rate` is the fraction of hashes to check, between 0 and 1.
+    ///
+    /// This catches pipelines which hashed leaves with the wrong encoding before an incorrect root
+    /// gets published, without paying to rehash every leaf.
+    pub fn from_hashes_with_audit(
+        hashes: Vec<T::Hash>,
+        values: &[&Bytes],
+        rate: f64,
+    ) -> Result<Self, AuditError> {
+        let spec = TreeSpec::new(T::NAME);
+        audit::<T>(&spec, &hashes, values, rate)?;
+        Ok(MerkleTree::build_from_hashes(spec, hashes))
+    }
+}
+
+/// Checks a random sample of `hashes` against the leaf hashes of the corresponding `values`.
+pub(crate) fn audit<T: HashFunction>(
+    spec: &TreeSpec,
+    hashes: &[T::Hash],
+    values: &[&Bytes],
+    rate: f64,
+) -> Result<(), AuditError> {
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(AuditError::InvalidRate(rate));
+    }
+    if hashes.len() != values.len() {
+        return Err(AuditError::LengthMismatch {
+            hashes: hashes.len(),
+            values: values.len(),
+        });
+    }
+
+    // Sample at least one hash for any non-zero rate
+    let amount = ((hashes.len() as f64 * rate).ceil() as usize).min(hashes.len());
+    let mut rng = rand::thread_rng();
+    let mut sample = rand::seq::index::sample(&mut rng, hashes.len(), amount).into_vec();
+    sample.sort();
+
+    for index in sample {
+        if MerkleTree::<T>::hash_leaf(spec, values[index]) != hashes[index] {
+            return Err(AuditError::Mismatch { index });
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditError {
+    /// The sampling rate was outside of the range 0 to 1.
+    InvalidRate(f64),
+    /// The number of raw values does not match the number of hashes.
+    LengthMismatch { hashes: usize, values: usize },
+    /// The hash at `index` does not match the hash of its raw value.
+    Mismatch { index: usize },
+}
+
+impl Display for AuditError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditError::InvalidRate(rate) => {
+                write!(f, "audit rate {} must be between 0 and 1", rate)
+            }
+            AuditError::LengthMismatch { hashes, values } => write!(
+                f,
+                "{} hashes were supplied with {} values to audit",
+                hashes, values
+            ),
+            AuditError::Mismatch { index } => write!(
+                f,
+                "hash at index {} does not match the hash of its value",
+                index
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AuditError, Bytes, HashFunction, Keccak256, MerkleTree};
+
+    #[test]
+    fn accepts_matching_hashes() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let hashes = leaves.iter().map(|l| Keccak256::hash(l)).collect();
+        let tree = MerkleTree::<Keccak256>::from_hashes_with_audit(hashes, &leaves, 0.5).unwrap();
+        assert_eq!(tree.root(), MerkleTree::<Keccak256>::new(&leaves).root());
+    }
+
+    #[test]
+    fn detects_mismatched_hashes() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+
+        // Hashes computed with the wrong encoding
+        let hashes: Vec<[u8; 32]> = leaves
+            .iter()
+            .map(|l| Keccak256::hash(&l.repeat(2)))
+            .collect();
+        assert!(matches!(
+            MerkleTree::<Keccak256>::from_hashes_with_audit(hashes.clone(), &leaves, 0.1),
+            Err(AuditError::Mismatch { .. })
+        ));
+
+        // A full audit finds a single bad hash
+        let mut hashes: Vec<[u8; 32]> = leaves.iter().map(|l| Keccak256::hash(l)).collect();
+        hashes[1] = [0u8; 32];
+        assert_eq!(
+            MerkleTree::<Keccak256>::from_hashes_with_audit(hashes.clone(), &leaves, 1.0).err(),
+            Some(AuditError::Mismatch { index: 1 })
+        );
+        assert!(MerkleTree::<Keccak256>::from_hashes_with_audit(hashes, &leaves, 0.0).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        let leaves: Vec<&Bytes> = ["a", "b"].iter().map(|x| x.as_bytes()).collect();
+        let hashes: Vec<[u8; 32]> = leaves.iter().map(|l| Keccak256::hash(l)).collect();
+        assert_eq!(
+            MerkleTree::<Keccak256>::from_hashes_with_audit(hashes.clone(), &leaves[..1], 1.0)
+                .err(),
+            Some(AuditError::LengthMismatch {
+                hashes: 2,
+                values: 1
+            })
+        );
+        assert_eq!(
+            MerkleTree::<Keccak256>::from_hashes_with_audit(hashes, &leaves, 1.5).err(),
+            Some(AuditError::InvalidRate(1.5))
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{Bytes, HashFunction, MerkleTree, MerkleTreeBuilder, TreeSpec};
+
+impl<T: HashFunction> MerkleTreeBuilder<T>
+where
+    T::Hash: Send,
+{
+    /// Computes the root of the tree `build` would create from each batch of leaves, without
+    /// building the trees, for generating many small trees at once such as one per order.
+    ///
+    /// Batches are divided between the available threads, each reducing its batches in place
+    /// within a single buffer reused from batch to batch.
+    pub fn compute_roots(&self, batches: &[Vec<&Bytes>]) -> Vec<T::Hash> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let size = batches.len().div_ceil(threads).max(1);
+        let spec = self.spec();
+        let placeholders: Vec<T::Hash> = self.placeholders.iter().map(|p| p.hash).collect();
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = batches
+                .chunks(size)
+                .map(|batches| {
+                    let placeholders = placeholders.clone();
+                    scope.spawn(move || {
+                        let mut buffer = vec![];
+                        batches
+                            .iter()
+                            .map(|leaves| root::<T>(spec, &placeholders, leaves, &mut buffer))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("root computation panicked"))
+                .collect()
+        })
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T>
+where
+    T::Hash: Send,
+{
+    /// Computes the root `new` would create from each batch of leaves.
+    pub fn compute_roots(batches: &[Vec<&Bytes>]) -> Vec<T::Hash> {
+        MerkleTreeBuilder::<T>::new().compute_roots(batches)
+    }
+}
+
+/// The root of a tree of `leaves` and the reserved placeholders, replacing each layer with its parents within `buffer`.
+fn root<T: HashFunction>(
+    spec: &TreeSpec,
+    placeholders: &[T::Hash],
+    leaves: &[&Bytes],
+    buffer: &mut Vec<T::Hash>,
+) -> T::Hash {
+    buffer.clear();
+    buffer.extend(leaves.iter().map(|l| MerkleTree::<T>::hash_leaf(spec, l)));
+    buffer.extend_from_slice(placeholders);
+    if spec.sort_leaves {
+        buffer.sort_unstable();
+    }
+    if spec.deduplicate {
+        MerkleTree::<T>::deduplicate(spec, buffer);
+    }
+    MerkleTree::<T>::pad_leaves(spec, buffer);
+
+    while buffer.len() > 1 {
+        // Each parent is written at or before the first of its children, so is never read again
+        let len = buffer.len();
+        let parents = len.div_ceil(spec.arity);
+        for parent in 0..parents {
+            let start = parent * spec.arity;
+            let end = len.min(start + spec.arity);
+            buffer[parent] = MerkleTree::<T>::parent(spec, &buffer[start..end]);
+        }
+        buffer.truncate(parents);
+    }
+    buffer.first().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleTree, MerkleTreeBuilder, PlaceholderPolicy, TreeSpec};
+
+    #[test]
+    fn computes_roots() {
+        let values: Vec<[u8; 1]> = (0..20).map(|i| [i % 7]).collect();
+        let batches: Vec<Vec<&Bytes>> = (0..values.len())
+            .map(|len| values[..len].iter().map(|v| &v[..]).collect())
+            .collect();
+        for spec in [
+            "keccak256/sorted/promote-odd/arity2",
+            "keccak256/ordered/dup-odd/arity3",
+            "keccak256/sorted-leaves/zero-odd/arity2/pad-zero/dedup",
+        ] {
+            let builder =
+                MerkleTreeBuilder::<Keccak256>::from_spec(&TreeSpec::parse(spec).unwrap()).unwrap();
+            let roots: Vec<[u8; 32]> = batches.iter().map(|b| builder.build(b).root()).collect();
+            assert_eq!(builder.compute_roots(&batches), roots, "{}", spec);
+
+            let builder = builder.reserve_slots(3, PlaceholderPolicy::Blank);
+            let roots: Vec<[u8; 32]> = batches.iter().map(|b| builder.build(b).root()).collect();
+            assert_eq!(builder.compute_roots(&batches), roots, "{}", spec);
+        }
+        assert_eq!(
+            MerkleTree::<Keccak256>::compute_roots(&batches[3..4]),
+            [MerkleTree::<Keccak256>::new(&batches[3]).root()]
+        );
+        assert!(MerkleTree::<Keccak256>::compute_roots(&[]).is_empty());
+    }
+}
@@ -0,0 +1,346 @@
+//! The transaction trees of Bitcoin blocks, for validating SPV proofs without a full Bitcoin
+//! library. Blocks commit to their transactions with an ordered tree of double SHA-256 hashes,
+//! duplicating the last node of odd layers, and prove them to light clients with the partial
+//! trees of `merkleblock` messages, as in BIP 37.
+//!
+//! Hashes are in the byte order in which they are hashed, which is the reverse of the order in
+//! which txids and block hashes are displayed.
+
+use crate::{DoubleSha256, Hash, HashFunction, MerkleTree, MerkleTreeBuilder, OddStrategy};
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpvError {
+    /// The partial tree covers no transactions.
+    NoTransactions,
+    /// The partial tree holds more hashes than the block has transactions.
+    TooManyHashes,
+    /// The flags or hashes of the partial tree run out before every node is visited.
+    Truncated,
+    /// Flags or hashes of the partial tree are left over once every node is visited.
+    Unconsumed,
+    /// A node has identical children, which would allow transactions to be duplicated without
+    /// changing the root, as in CVE-2012-2459.
+    DuplicateChildren,
+    /// The partial tree does not compute the merkle root of the block.
+    RootMismatch,
+    /// The encoding of the partial tree is malformed.
+    Malformed,
+}
+
+impl Display for SpvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpvError::NoTransactions => write!(f, "partial tree has no transactions"),
+            SpvError::TooManyHashes => write!(f, "partial tree has more hashes than transactions"),
+            SpvError::Truncated => write!(f, "partial tree is truncated"),
+            SpvError::Unconsumed => write!(f, "partial tree has unused flags or hashes"),
+            SpvError::DuplicateChildren => write!(f, "node has identical children"),
+            SpvError::RootMismatch => write!(f, "partial tree does not match the merkle root"),
+            SpvError::Malformed => write!(f, "malformed partial tree"),
+        }
+    }
+}
+
+impl std::error::Error for SpvError {}
+
+/// Parses a txid or block hash as displayed, reversing it into the order in which it is hashed.
+pub fn parse_hash(hash: &str) -> Option<Hash<32>> {
+    let mut hash: Hash<32> = hex::decode(hash).ok()?.try_into().ok()?;
+    hash.reverse();
+    Some(hash)
+}
+
+/// Formats a hash as txids and block hashes are displayed, in reverse byte order.
+pub fn display_hash(hash: &Hash<32>) -> String {
+    let mut hash = *hash;
+    hash.reverse();
+    hex::encode(hash)
+}
+
+/// The builder of transaction trees, whose leaves are txids rather than transactions to hash.
+pub fn builder() -> MerkleTreeBuilder<DoubleSha256> {
+    MerkleTreeBuilder::new()
+        .hash_leaves(false)
+        .sort_leaves(false)
+        .sort_pairs(false)
+        .odd_strategy(OddStrategy::Duplicate)
+}
+
+/// The tree of the transactions of a block, from their txids in block order. The proofs of the
+/// tree are merkle branches, as returned by Electrum servers.
+pub fn transaction_tree(txids: &[Hash<32>]) -> MerkleTree<DoubleSha256> {
+    builder().build_from_hashes(txids.to_vec())
+}
+
+/// The merkle root of a block header committing to the transactions with the given txids.
+pub fn merkle_root(txids: &[Hash<32>]) -> Hash<32> {
+    transaction_tree(txids).root()
+}
+
+/// The partial tree of a `merkleblock` message, proving the inclusion of some transactions of a
+/// block. Each flag records whether a node, visited depth first, is an ancestor of a matched
+/// transaction, the hashes of the subtrees which are not descended into being included instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartialMerkleTree {
+    pub transactions: u32,
+    pub hashes: Vec<Hash<32>>,
+    pub flags: Vec<bool>,
+}
+
+impl PartialMerkleTree {
+    /// The partial tree proving the transactions at the matched positions of a block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `matched` does not hold a flag for each txid.
+    pub fn new(txids: &[Hash<32>], matched: &[bool]) -> Self {
+        assert_eq!(txids.len(), matched.len(), "one flag is required per txid");
+        let tree = transaction_tree(txids);
+        let mut partial = Self {
+            transactions: txids.len() as u32,
+            hashes: vec![],
+            flags: vec![],
+        };
+        if !txids.is_empty() {
+            partial.build(&tree, matched, partial.height(), 0);
+        }
+        partial
+    }
+
+    /// Verifies the partial tree against the merkle root of a block header, returning the
+    /// position and txid of each transaction it proves.
+    pub fn verify(&self, merkle_root: &Hash<32>) -> Result<Vec<(usize, Hash<32>)>, SpvError> {
+        let (root, matches) = self.extract()?;
+        match root == *merkle_root {
+            true => Ok(matches),
+            false => Err(SpvError::RootMismatch),
+        }
+    }
+
+    /// Computes the merkle root of the partial tree, along with the position and txid of each
+    /// transaction it proves.
+    pub fn extract(&self) -> Result<(Hash<32>, Vec<(usize, Hash<32>)>), SpvError> {
+        if self.transactions == 0 {
+            return Err(SpvError::NoTransactions);
+        }
+        if self.hashes.len() > self.transactions as usize {
+            return Err(SpvError::TooManyHashes);
+        }
+        if self.flags.len() < self.hashes.len() {
+            return Err(SpvError::Truncated);
+        }
+
+        let (mut flags, mut hashes, mut matches) = (0, 0, vec![]);
+        let root = self.traverse(self.height(), 0, &mut flags, &mut hashes, &mut matches)?;
+        // Flags are padded to a whole byte when encoded
+        if flags.div_ceil(8) != self.flags.len().div_ceil(8) || hashes != self.hashes.len() {
+            return Err(SpvError::Unconsumed);
+        }
+        Ok((root, matches))
+    }
+
+    /// Encodes the partial tree as it follows the block header of a `merkleblock` message.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = self.transactions.to_le_bytes().to_vec();
+        compact_size(&mut encoded, self.hashes.len());
+        for hash in &self.hashes {
+            encoded.extend_from_slice(hash);
+        }
+        let mut flags = vec![0u8; self.flags.len().div_ceil(8)];
+        for (i, flag) in self.flags.iter().enumerate() {
+            flags[i / 8] |= (*flag as u8) << (i % 8);
+        }
+        compact_size(&mut encoded, flags.len());
+        encoded.extend_from_slice(&flags);
+        encoded
+    }
+
+    /// Decodes a partial tree encoded as by `encode`.
+    pub fn decode(encoded: &[u8]) -> Result<Self, SpvError> {
+        let mut reader = Reader(encoded);
+        let transactions = u32::from_le_bytes(reader.take(4)?.try_into().unwrap_or_default());
+        let hashes = (0..reader.compact_size()?)
+            .map(|_| reader.take(32).map(|h| h.try_into().unwrap_or_default()))
+            .collect::<Result<_, _>>()?;
+        let bytes = reader.compact_size()?;
+        let flags = reader.take(bytes)?;
+        if !reader.0.is_empty() {
+            return Err(SpvError::Malformed);
+        }
+        Ok(Self {
+            transactions,
+            hashes,
+            flags: (0..bytes * 8)
+                .map(|i| flags[i / 8] >> (i % 8) & 1 == 1)
+                .collect(),
+        })
+    }
+
+    /// The number of layers above the transactions.
+    fn height(&self) -> usize {
+        let mut height = 0;
+        while self.width(height) > 1 {
+            height += 1;
+        }
+        height
+    }
+
+    /// The number of nodes in a layer.
+    fn width(&self, height: usize) -> usize {
+        (self.transactions as usize).div_ceil(1 << height)
+    }
+
+    fn build(
+        &mut self,
+        tree: &MerkleTree<DoubleSha256>,
+        matched: &[bool],
+        height: usize,
+        index: usize,
+    ) {
+        let start = index << height;
+        let end = matched.len().min((index + 1) << height);
+        let ancestor = matched[start..end].iter().any(|m| *m);
+        self.flags.push(ancestor);
+        if height == 0 || !ancestor {
+            self.hashes
+                .push(tree.node(height, index).unwrap_or_default());
+            return;
+        }
+        self.build(tree, matched, height - 1, index * 2);
+        if index * 2 + 1 < self.width(height - 1) {
+            self.build(tree, matched, height - 1, index * 2 + 1);
+        }
+    }
+
+    fn traverse(
+        &self,
+        height: usize,
+        index: usize,
+        flags: &mut usize,
+        hashes: &mut usize,
+        matches: &mut Vec<(usize, Hash<32>)>,
+    ) -> Result<Hash<32>, SpvError> {
+        let ancestor = *self.flags.get(*flags).ok_or(SpvError::Truncated)?;
+        *flags += 1;
+        if height == 0 || !ancestor {
+            let hash = *self.hashes.get(*hashes).ok_or(SpvError::Truncated)?;
+            *hashes += 1;
+            if height == 0 && ancestor {
+                matches.push((index, hash));
+            }
+            return Ok(hash);
+        }
+
+        let left = self.traverse(height - 1, index * 2, flags, hashes, matches)?;
+        let right = match index * 2 + 1 < self.width(height - 1) {
+            true => match self.traverse(height - 1, index * 2 + 1, flags, hashes, matches)? {
+                right if right == left => return Err(SpvError::DuplicateChildren),
+                right => right,
+            },
+            false => left,
+        };
+        Ok(DoubleSha256::hash(&[left, right].concat()))
+    }
+}
+
+fn compact_size(encoded: &mut Vec<u8>, len: usize) {
+    match len {
+        0..=0xfc => encoded.push(len as u8),
+        0xfd..=0xffff => {
+            encoded.push(0xfd);
+            encoded.extend_from_slice(&(len as u16).to_le_bytes());
+        }
+        _ => {
+            encoded.push(0xfe);
+            encoded.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SpvError> {
+        if self.0.len() < len {
+            return Err(SpvError::Malformed);
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn compact_size(&mut self) -> Result<usize, SpvError> {
+        let len = match self.take(1)?[0] {
+            0xfd => u16::from_le_bytes(self.take(2)?.try_into().unwrap_or_default()) as usize,
+            0xfe => u32::from_le_bytes(self.take(4)?.try_into().unwrap_or_default()) as usize,
+            0xff => return Err(SpvError::Malformed),
+            len => len as usize,
+        };
+        // Lengths are bounded by the remaining input, so cannot exhaust memory when decoded
+        match len <= self.0.len() {
+            true => Ok(len),
+            false => Err(SpvError::Malformed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpvError;
+    use super::{display_hash, merkle_root, parse_hash, transaction_tree, PartialMerkleTree};
+    use crate::Hash;
+
+    #[test]
+    fn proves_transactions() {
+        // The transactions of block 100000
+        let txids: Vec<Hash<32>> = [
+            "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+            "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+            "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+            "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+        ]
+        .iter()
+        .map(|txid| parse_hash(txid).unwrap())
+        .collect();
+        let root = merkle_root(&txids);
+        assert_eq!(
+            display_hash(&root),
+            "f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766"
+        );
+        assert_eq!(merkle_root(&txids[..1]), txids[0]);
+        let tree = transaction_tree(&txids);
+        assert!(tree.verify(tree.proof(txids[2]), txids[2], root));
+
+        // Partial trees prove any selection of transactions, including those of odd layers
+        for count in 1..=txids.len() {
+            let root = merkle_root(&txids[..count]);
+            for selection in 0..1u32 << count {
+                let matched: Vec<bool> = (0..count).map(|i| selection >> i & 1 == 1).collect();
+                let partial = PartialMerkleTree::new(&txids[..count], &matched);
+                let decoded = PartialMerkleTree::decode(&partial.encode()).unwrap();
+                let expected: Vec<_> = (0..count)
+                    .filter(|i| matched[*i])
+                    .map(|i| (i, txids[i]))
+                    .collect();
+                assert_eq!(decoded.verify(&root), Ok(expected));
+            }
+        }
+
+        let mut partial = PartialMerkleTree::new(&txids, &[false, true, false, false]);
+        assert_eq!(partial.verify(&[0; 32]), Err(SpvError::RootMismatch));
+        partial.hashes.pop();
+        assert_eq!(partial.verify(&root), Err(SpvError::Truncated));
+        assert_eq!(
+            PartialMerkleTree::decode(&[1, 0, 0, 0, 5]),
+            Err(SpvError::Malformed)
+        );
+
+        // Duplicating the last transaction of an odd block leaves its root unchanged, so the
+        // duplicate is rejected
+        let duplicated = [&txids[..3], &txids[2..3]].concat();
+        assert_eq!(merkle_root(&duplicated), merkle_root(&txids[..3]));
+        let partial = PartialMerkleTree::new(&duplicated, &[false, false, true, true]);
+        assert_eq!(partial.extract(), Err(SpvError::DuplicateChildren));
+    }
+}
@@ -0,0 +1,637 @@
+use crate::placeholder::Placeholder;
+use crate::{
+    Bytes, Canonicalization, HashFunction, LeafKey, MerkleError, MerkleTree, OddStrategy,
+    PaddingLeaf, Salted, SpecError, TreeSpec, MAX_ARITY,
+};
+#[cfg(not(feature = "std"))]
+use alloc::{string::ToString, vec, vec::Vec};
+use core::marker::PhantomData;
+
+/// Configures how a `MerkleTree` is constructed. Defaults match `MerkleTree::new`: sorted leaves
+/// and pairs, promotion of odd nodes and a binary tree.
+pub struct MerkleTreeBuilder<T: HashFunction> {
+    spec: TreeSpec,
+    /// Leaves reserved by `reserve_slots`, appended to the leaves of each tree built.
+    pub(crate) placeholders: Vec<Placeholder<T::Hash>>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    pub fn new() -> Self {
+        Self {
+            spec: TreeSpec::new(T::NAME),
+            placeholders: vec![],
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a builder hashing leaves with the supplied keyed hash function.
+    pub fn with_hasher(hasher: &Salted<T>) -> Self {
+        let mut builder = Self::new();
+        builder.spec.leaf_key = Some(hasher.key().clone());
+        builder
+    }
+
+    /// Creates a builder configured by the supplied spec, which must name the hash function `T`.
+    pub fn from_spec(spec: &TreeSpec) -> Result<Self, SpecError> {
+        if spec.hash != T::NAME {
+            return Err(SpecError::HashMismatch {
+                spec: spec.hash.clone(),
+                supplied: T::NAME.to_string(),
+            });
+        }
+
+        if let Some(PaddingLeaf::Custom(leaf)) = &spec.padding {
+            if T::Hash::try_from(leaf.clone()).is_err() {
+                return Err(SpecError::InvalidPadding(hex::encode(leaf)));
+            }
+        }
+
+        Ok(Self {
+            spec: spec.clone(),
+            placeholders: vec![],
+            phantom: PhantomData,
+        })
+    }
+
+    pub fn sort_leaves(mut self, sort: bool) -> Self {
+        self.spec.sort_leaves = sort;
+        self
+    }
+
+    pub fn sort_pairs(mut self, sort: bool) -> Self {
+        self.spec.sort_pairs = sort;
+        self
+    }
+
+    pub fn odd_strategy(mut self, strategy: OddStrategy) -> Self {
+        self.spec.odd_strategy = strategy;
+        self
+    }
+
+    /// Sets the number of children per node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arity` is less than 2 or greater than `MAX_ARITY`.
+    pub fn arity(mut self, arity: usize) -> Self {
+        assert!(
+            (2..=MAX_ARITY).contains(&arity),
+            "arity must be from 2 to {MAX_ARITY}"
+        );
+        self.spec.arity = arity;
+        self
+    }
+
+    /// Pads the leaves with the supplied leaf up to the next power of two, or of the arity for
+    /// trees of higher arity, so that every proof has the same length and the tree is complete as
+    /// some verifiers assume. Padding is appended after any sorting of the leaves.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a custom padding leaf is not the length of a hash. Use `try_pad_to_pow2` for
+    /// padding leaves from untrusted input.
+    pub fn pad_to_pow2(self, padding: PaddingLeaf) -> Self {
+        match self.try_pad_to_pow2(padding) {
+            Ok(builder) => builder,
+            Err(_) => panic!("padding leaf must be the length of a hash"),
+        }
+    }
+
+    /// Pads the leaves as `pad_to_pow2` does, failing if a custom padding leaf is not the length of
+    /// a hash.
+    pub fn try_pad_to_pow2(mut self, padding: PaddingLeaf) -> Result<Self, MerkleError> {
+        if let PaddingLeaf::Custom(leaf) = &padding {
+            MerkleTree::<T>::hash_from_slice(leaf)?;
+        }
+        self.spec.padding = Some(padding);
+        Ok(self)
+    }
+
+    /// Removes duplicate leaves, keeping the first occurrence of each, so that each leaf has a
+    /// single position. Leaves added by `push` or `extend` which are already present are skipped.
+    pub fn deduplicate(mut self, deduplicate: bool) -> Self {
+        self.spec.deduplicate = deduplicate;
+        self
+    }
+
+    /// Sets whether leaf values are hashed. Leaves which are not hashed are taken to be hashes
+    /// already, such as those computed by another system, and any leaf prefix is ignored.
+    ///
+    /// Building a tree from pre-hashed leaves panics if a leaf is not the length of a hash, unless
+    /// built with `try_build`.
+    pub fn hash_leaves(mut self, hash: bool) -> Self {
+        self.spec.hash_leaves = hash;
+        self
+    }
+
+    /// Sets bytes to prepend to each leaf value before it is hashed.
+    pub fn leaf_prefix(mut self, prefix: &[u8]) -> Self {
+        self.spec.leaf_prefix = prefix.to_vec();
+        self
+    }
+
+    /// Sets the steps normalizing each leaf value before it is prefixed and hashed, applied in the
+    /// order given, so that a value supplied as `0xABC...` or ` 0xabc...` is the same leaf.
+    pub fn canonicalize(mut self, steps: &[Canonicalization]) -> Self {
+        self.spec.canonicalize = steps.to_vec();
+        self
+    }
+
+    /// Hashes leaves under a secret salt with `HashFunction::hash_keyed`, so that the hashes of
+    /// known values such as addresses cannot be recomputed to discover the members of the tree.
+    pub fn salt(mut self, salt: &[u8]) -> Self {
+        self.spec.leaf_key = Some(LeafKey::new(salt));
+        self
+    }
+
+    /// Sets bytes to prepend to each group of child nodes before it is hashed.
+    pub fn node_prefix(mut self, prefix: &[u8]) -> Self {
+        self.spec.node_prefix = prefix.to_vec();
+        self
+    }
+
+    /// Separates leaf and node hashes as in RFC 6962, prefixing leaves with `0x00` and nodes with
+    /// `0x01`, so an internal node can never be presented as a leaf value. Disabling it removes
+    /// only these prefixes, leaving any other prefix set by `leaf_prefix` or `node_prefix`.
+    pub fn domain_separation(mut self, enabled: bool) -> Self {
+        if enabled {
+            return self.leaf_prefix(&[0]).node_prefix(&[1]);
+        }
+        if self.spec.leaf_prefix == [0] {
+            self.spec.leaf_prefix.clear();
+        }
+        if self.spec.node_prefix == [1] {
+            self.spec.node_prefix.clear();
+        }
+        self
+    }
+
+    pub fn spec(&self) -> &TreeSpec {
+        &self.spec
+    }
+
+    /// Hashes a raw leaf value as it would be when building a tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if leaves are pre-hashed and the value is not the length of a hash.
+    pub fn hash_leaf(&self, value: &Bytes) -> T::Hash {
+        MerkleTree::<T>::hash_leaf(&self.spec, value)
+    }
+
+    /// Hashes a raw leaf value as `hash_leaf` does, failing if leaves are pre-hashed and the value
+    /// is not the length of a hash.
+    pub fn try_hash_leaf(&self, value: &Bytes) -> Result<T::Hash, MerkleError> {
+        MerkleTree::<T>::try_hash_leaf(&self.spec, value)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if leaves are pre-hashed and a leaf is not the length of a hash. Use `try_build` for
+    /// leaves from untrusted input.
+    pub fn build(&self, leaves: &[&Bytes]) -> MerkleTree<T> {
+        match self.placeholders.is_empty() {
+            true => MerkleTree::build(self.spec.clone(), leaves),
+            false => self.build_from_hashes(MerkleTree::<T>::hash_leaves(&self.spec, leaves)),
+        }
+    }
+
+    /// Builds a tree as `build` does, failing if leaves are pre-hashed and a leaf is not the length
+    /// of a hash.
+    pub fn try_build(&self, leaves: &[&Bytes]) -> Result<MerkleTree<T>, MerkleError> {
+        let hashes = MerkleTree::<T>::try_hash_leaves(&self.spec, leaves)?;
+        Ok(self.build_from_hashes(hashes))
+    }
+
+    /// Builds a tree from leaves which are already hashed, skipping leaf hashing but otherwise
+    /// building the tree as configured. Leaves subsequently added are hashed unless the builder
+    /// was configured with `hash_leaves(false)`.
+    pub fn build_from_hashes(&self, mut hashes: Vec<T::Hash>) -> MerkleTree<T> {
+        hashes.extend(self.placeholders.iter().map(|placeholder| placeholder.hash));
+        let mut tree = MerkleTree::build_from_hashes(self.spec.clone(), hashes);
+        tree.placeholders = self.placeholders.clone();
+        tree
+    }
+
+    /// Builds a tree from leaves supplied by an iterator, such as one reading records from a
+    /// file, without holding the leaves themselves in memory.
+    pub fn build_from_iter<L: AsRef<Bytes>>(
+        &self,
+        leaves: impl IntoIterator<Item = L>,
+    ) -> MerkleTree<T> {
+        match self.placeholders.is_empty() {
+            true => MerkleTree::build_from_iter(self.spec.clone(), leaves),
+            false => {
+                self.build_from_hashes(MerkleTree::<T>::hash_batches(&self.spec, leaves).collect())
+            }
+        }
+    }
+}
+
+impl<T: HashFunction> Default for MerkleTreeBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Bytes, HashFunction, Keccak256, MerkleError, MerkleTree, MerkleTreeBuilder, OddStrategy,
+        PaddingLeaf, SpecError, TreeSpec,
+    };
+
+    fn leaves(count: u8) -> Vec<[u8; 1]> {
+        (0..count).map(|i| [i]).collect()
+    }
+
+    fn verify_all(tree: &MerkleTree<Keccak256>) {
+        for leaf in tree.leaves() {
+            let proof = tree.proof(*leaf);
+            assert!(tree.verify(proof.clone(), *leaf, tree.root()));
+            assert!(!tree.verify(proof, [0u8; 32], tree.root()));
+        }
+    }
+
+    #[test]
+    fn defaults_match_new() {
+        let values = leaves(5);
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new().build(&leaves);
+        assert_eq!(tree.root(), MerkleTree::<Keccak256>::new(&leaves).root());
+        assert_eq!(
+            tree.spec().to_string(),
+            "keccak256/sorted/promote-odd/arity2"
+        );
+    }
+
+    #[test]
+    fn from_spec() {
+        let spec = TreeSpec::parse("keccak256/sorted/dup-odd/arity2/leaf-prefix00").unwrap();
+        let builder = MerkleTreeBuilder::<Keccak256>::from_spec(&spec).unwrap();
+        assert_eq!(builder.spec(), &spec);
+
+        let values = leaves(3);
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let tree = builder.build(&leaves);
+
+        // Leaves are prefixed before hashing and the odd leaf is paired with itself
+        let mut hashes: Vec<[u8; 32]> =
+            values.iter().map(|v| Keccak256::hash(&[0, v[0]])).collect();
+        hashes.sort();
+        assert_eq!(tree.leaves(), &hashes);
+        let root = MerkleTree::<Keccak256>::hash_sorted_pair(
+            MerkleTree::<Keccak256>::hash_sorted_pair(hashes[0], hashes[1]),
+            MerkleTree::<Keccak256>::hash_pair(hashes[2], hashes[2]),
+        );
+        assert_eq!(tree.root(), root);
+        verify_all(&tree);
+
+        let spec = TreeSpec::parse("sha256/sorted").unwrap();
+        assert!(matches!(
+            MerkleTreeBuilder::<Keccak256>::from_spec(&spec),
+            Err(SpecError::HashMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn arity() {
+        for spec in [
+            "keccak256/sorted/promote-odd/arity3",
+            "keccak256/sorted/dup-odd/arity4",
+            "keccak256/ordered/promote-odd/arity3",
+            "keccak256/ordered/dup-odd/arity5",
+        ] {
+            let spec = TreeSpec::parse(spec).unwrap();
+            for count in 1..=17 {
+                let values = leaves(count);
+                let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+                let tree = MerkleTreeBuilder::<Keccak256>::from_spec(&spec)
+                    .unwrap()
+                    .build(&leaves);
+                verify_all(&tree);
+            }
+        }
+
+        // Root of a complete ternary tree
+        let values = leaves(3);
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(false)
+            .sort_pairs(false)
+            .arity(3)
+            .build(&leaves);
+        let hashes: Vec<[u8; 32]> = values.iter().map(|v| Keccak256::hash(v)).collect();
+        assert_eq!(tree.root(), MerkleTree::<Keccak256>::hash_nodes(&hashes));
+    }
+
+    #[test]
+    fn odd_strategies() {
+        for strategy in [
+            OddStrategy::Promote,
+            OddStrategy::Duplicate,
+            OddStrategy::ZeroPad,
+        ] {
+            for count in 1..=9 {
+                let values = leaves(count);
+                let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+                let builder = MerkleTreeBuilder::<Keccak256>::new().odd_strategy(strategy);
+                verify_all(&builder.build(&leaves));
+                verify_all(&builder.sort_pairs(false).build(&leaves));
+                let builder = MerkleTreeBuilder::<Keccak256>::new().odd_strategy(strategy);
+                verify_all(&builder.arity(3).build(&leaves));
+            }
+        }
+
+        // The odd leaf is paired with a zero hash
+        let values = leaves(3);
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(false)
+            .sort_pairs(false)
+            .odd_strategy(OddStrategy::ZeroPad)
+            .build(&leaves);
+        let hashes: Vec<[u8; 32]> = values.iter().map(|v| Keccak256::hash(v)).collect();
+        let root = MerkleTree::<Keccak256>::hash_pair(
+            MerkleTree::<Keccak256>::hash_pair(hashes[0], hashes[1]),
+            MerkleTree::<Keccak256>::hash_pair(hashes[2], [0; 32]),
+        );
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.proof(hashes[2]).hashes()[0], [0; 32]);
+        assert_eq!(tree.spec().to_string(), "keccak256/ordered/zero-odd/arity2");
+    }
+
+    #[test]
+    fn domain_separation() {
+        let values = leaves(4);
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for separated in [false, true] {
+            let builder = MerkleTreeBuilder::<Keccak256>::new()
+                .sort_pairs(false)
+                .domain_separation(separated);
+            let tree = builder.build(&leaves);
+            verify_all(&tree);
+
+            // Present the first internal node as a leaf whose value is its two children
+            let node = tree.layers[1][0];
+            let value = [tree.layers[0][0], tree.layers[0][1]].concat();
+            let proof = crate::MerkleProof::new(
+                vec![tree.layers[1][1]],
+                Some(vec![crate::Direction::Right]),
+                None,
+            );
+            assert!(tree.verify(proof.clone(), node, tree.root()));
+            assert_eq!(
+                tree.verify_leaf_value(proof, &value, tree.root()),
+                !separated
+            );
+        }
+
+        let builder = MerkleTreeBuilder::<Keccak256>::new().domain_separation(true);
+        assert_eq!(
+            builder.spec().to_string(),
+            "keccak256/sorted/promote-odd/arity2/leaf-prefix00/node-prefix01"
+        );
+        verify_all(&builder.build(&leaves));
+
+        // Disabling separation leaves other prefixes in place
+        let builder = MerkleTreeBuilder::<Keccak256>::new()
+            .domain_separation(true)
+            .leaf_prefix(b"leaf")
+            .domain_separation(false);
+        assert_eq!(builder.spec().leaf_prefix, b"leaf");
+        assert!(builder.spec().node_prefix.is_empty());
+    }
+
+    #[test]
+    fn salts_leaves() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let hasher = Keccak256::with_salt(b"salt".to_vec());
+        let tree = MerkleTree::with_hasher(&hasher, &leaves);
+        assert_eq!(
+            tree.root(),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .salt(b"salt")
+                .build(&leaves)
+                .root()
+        );
+        assert_ne!(tree.root(), MerkleTree::<Keccak256>::new(&leaves).root());
+        assert!(tree.leaves().contains(&hasher.hash(b"b")));
+        assert!(!tree.leaves().contains(&Keccak256::hash(b"b")));
+
+        // Leaf values verify only under the key, which is never printed
+        let leaf = hasher.hash(b"b");
+        assert!(tree.verify_leaf_value(tree.proof(leaf), b"b", tree.root()));
+        let unsalted = MerkleTree::<Keccak256>::new(&leaves);
+        assert!(!unsalted.verify_leaf_value(tree.proof(leaf), b"b", tree.root()));
+        assert!(format!("{:?}", tree.spec()).contains("LeafKey([REDACTED])"));
+        assert!(!tree.spec().to_string().contains("73616c74"));
+    }
+
+    #[test]
+    fn deduplicates() {
+        let values = [[2], [1], [2], [3], [1]];
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let hash = |v: u8| Keccak256::hash(&[v]);
+        for sort in [false, true] {
+            let builder = MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(sort)
+                .deduplicate(true);
+            let mut tree = builder.build(&leaves);
+            // The first occurrence of each leaf is kept
+            let expected = MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(sort)
+                .build(&[&[2], &[1], &[3]]);
+            assert_eq!(tree.root(), expected.root());
+            assert_eq!(tree.leaves().len(), 3);
+            verify_all(&tree);
+
+            // Leaves already present are not added again
+            tree.extend(&[&[3], &[4], &[4]]);
+            assert_eq!(tree.leaves().len(), 4);
+            assert_eq!(
+                tree.index_of(hash(4)),
+                tree.leaves().iter().position(|l| *l == hash(4))
+            );
+            let built = builder.build(&[&[2], &[1], &[3], &[4]]);
+            assert_eq!(tree.root(), built.root());
+            #[cfg(feature = "std")]
+            assert_eq!(
+                builder
+                    .build_in(crate::MemoryStore::new(), values)
+                    .unwrap()
+                    .root(),
+                expected.root()
+            );
+        }
+
+        // Padding leaves are not taken to be leaves already present
+        let padded = MerkleTreeBuilder::<Keccak256>::new()
+            .deduplicate(true)
+            .pad_to_pow2(PaddingLeaf::Zero);
+        let mut tree = padded.build(&leaves);
+        tree.extend(&[&[4], &[5], &[1]]);
+        assert_eq!(
+            tree.root(),
+            padded.build(&[&[2], &[1], &[3], &[4], &[5]]).root()
+        );
+        assert_eq!(
+            MerkleTreeBuilder::<Keccak256>::new()
+                .deduplicate(true)
+                .spec()
+                .to_string(),
+            "keccak256/sorted/promote-odd/arity2/dedup"
+        );
+    }
+
+    #[test]
+    fn deduplicates_updates() {
+        let hash = |v: u8| Keccak256::hash(&[v]);
+        for sort in [false, true] {
+            let builder = MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(sort)
+                .deduplicate(true);
+
+            // The tree matches one rebuilt from the updated leaves, whichever index was updated
+            for (index, value) in [(0, 2), (2, 1), (0, 3), (1, 1)] {
+                let mut tree = builder.build(&[&[1], &[2], &[3]]);
+                let previous = tree.leaves()[index];
+                let updated: Vec<[u8; 1]> = [1, 2, 3]
+                    .map(|v| if hash(v) == previous { [value] } else { [v] })
+                    .to_vec();
+                let updated: Vec<&Bytes> = updated.iter().map(|v| &v[..]).collect();
+                assert_eq!(tree.update(index, &[value]), previous);
+                assert_eq!(tree.root(), builder.build(&updated).root());
+                assert_eq!(tree.leaves().len(), 2);
+                tree.index_leaves();
+                for value in [1, 2, 3] {
+                    assert_eq!(
+                        tree.index_of(hash(value)),
+                        tree.leaves().iter().position(|l| *l == hash(value))
+                    );
+                }
+                verify_all(&tree);
+            }
+        }
+
+        // Padding leaves are not taken to be leaves already present
+        let padded = MerkleTreeBuilder::<Keccak256>::new()
+            .deduplicate(true)
+            .pad_to_pow2(PaddingLeaf::Custom(hash(4).to_vec()));
+        let mut tree = padded.build(&[&[1], &[2], &[3]]);
+        tree.update_hash(0, hash(4));
+        assert_eq!(tree.leaves().len(), 4);
+    }
+
+    #[test]
+    fn hash_leaves() {
+        let values = leaves(5);
+        let hashes: Vec<[u8; 32]> = values.iter().map(|v| Keccak256::hash(v)).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let hashed: Vec<&Bytes> = hashes.iter().map(|h| &h[..]).collect();
+
+        // Leaves hashed elsewhere give the same tree, and are never prefixed
+        let builder = MerkleTreeBuilder::<Keccak256>::new()
+            .hash_leaves(false)
+            .leaf_prefix(&[0]);
+        let mut tree = builder.build(&hashed);
+        assert_eq!(tree.root(), MerkleTree::<Keccak256>::new(&leaves).root());
+        assert_eq!(builder.hash_leaf(&hashes[0]), hashes[0]);
+        verify_all(&tree);
+        tree.push(&Keccak256::hash(&[5]));
+        assert_eq!(
+            tree.root(),
+            MerkleTree::<Keccak256>::new(&[leaves.as_slice(), &[&[5]]].concat()).root()
+        );
+        assert_eq!(
+            builder.spec().to_string(),
+            "keccak256/sorted/promote-odd/arity2/leaf-prefix00/prehashed"
+        );
+    }
+
+    #[test]
+    fn builds_from_hashes() {
+        let values = leaves(5);
+        let hashes: Vec<[u8; 32]> = values.iter().map(|v| Keccak256::hash(v)).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+
+        let mut tree = MerkleTree::<Keccak256>::from_hashes(hashes.clone());
+        let mut expected = MerkleTree::<Keccak256>::new(&leaves);
+        assert_eq!(tree.root(), expected.root());
+        assert_eq!(tree.spec(), expected.spec());
+        tree.push(&[5]);
+        expected.push(&[5]);
+        assert_eq!(tree.root(), expected.root());
+
+        let builder = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(false)
+            .arity(3);
+        assert_eq!(
+            builder.build_from_hashes(hashes).root(),
+            builder.build(&leaves).root()
+        );
+    }
+
+    #[test]
+    fn builds_from_iterators() {
+        let specs = [
+            "keccak256/ordered/promote-odd/arity2",
+            "keccak256/ordered/dup-odd/arity3",
+            "keccak256/ordered/zero-odd/arity4/pad-zero",
+            "keccak256/sorted/promote-odd/arity2",
+            "keccak256/ordered/promote-odd/arity2/dedup",
+        ];
+        for spec in specs {
+            let builder =
+                MerkleTreeBuilder::<Keccak256>::from_spec(&TreeSpec::parse(spec).unwrap()).unwrap();
+            for count in 0..=17 {
+                let values: Vec<Vec<u8>> = (0..count).map(|i| vec![i % 7]).collect();
+                let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+                let tree = builder.build_from_iter(values.iter());
+                assert_eq!(
+                    tree.layers,
+                    builder.build(&leaves).layers,
+                    "{} leaves with {}",
+                    count,
+                    spec
+                );
+            }
+        }
+
+        let values = leaves(5);
+        let tree: MerkleTree<Keccak256> = values.iter().collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        assert_eq!(tree.root(), MerkleTree::<Keccak256>::new(&leaves).root());
+    }
+
+    #[test]
+    fn rejects_short_pre_hashed_leaves() {
+        let builder = MerkleTreeBuilder::<Keccak256>::new().hash_leaves(false);
+        let invalid = MerkleError::InvalidHash {
+            expected: 32,
+            actual: 20,
+        };
+        assert_eq!(builder.try_build(&[&[0; 20]]).err(), Some(invalid.clone()));
+        assert_eq!(builder.try_hash_leaf(&[0; 20]), Err(invalid.clone()));
+
+        let mut tree = builder.try_build(&[&[1; 32], &[2; 32]]).unwrap();
+        let root = tree.root();
+        assert_eq!(tree.try_push(&[0; 20]), Err(invalid.clone()));
+        assert_eq!(tree.try_extend(&[&[3; 32], &[0; 20]]), Err(invalid.clone()));
+        assert_eq!(tree.try_update(0, &[0; 20]), Err(invalid.clone()));
+        assert_eq!(tree.try_update(2, &[3; 32]), Err(MerkleError::LeafNotFound));
+        assert_eq!(tree.root(), root);
+        assert_eq!(
+            tree.try_proof_for_value(&[0; 20]).err(),
+            Some(invalid.clone())
+        );
+        assert!(tree.proof_for_value(&[0; 20]).is_empty());
+        assert!(!tree.verify_leaf_value(tree.proof_for_value(&[1; 32]), &[0; 20], root));
+        assert_eq!(tree.try_push(&[3; 32]), Ok(()));
+        assert_eq!(tree.try_update(2, &[4; 32]), Ok([3; 32]));
+
+        let padding = PaddingLeaf::Custom(vec![0; 20]);
+        assert_eq!(builder.try_pad_to_pow2(padding).err(), Some(invalid));
+    }
+}
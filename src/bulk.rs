@@ -0,0 +1,135 @@
+use crate::{Direction, HashFunction, MerkleProof, MerkleTree, Store, TreeSpec};
+use std::ops::Range;
+
+/// The siblings of a node with their directions, or `None` if the node is promoted.
+type Siblings<H> = Option<Vec<(H, Direction)>>;
+
+/// An iterator over the proofs of a range of leaves in order, created by `MerkleTree::proofs`.
+///
+/// The siblings of each layer are shared by every leaf below the same node, so they are only
+/// looked up again once the path moves to another node of the layer.
+pub struct Proofs<'a, T: HashFunction, S> {
+    spec: &'a TreeSpec,
+    layers: &'a S,
+    leaves: Range<usize>,
+    /// The node of each layer whose siblings are held, along with those siblings.
+    steps: Vec<Option<(usize, Siblings<T::Hash>)>>,
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// Generates the proof of every leaf in order, each along with its leaf, for publishing the
+    /// proofs of a whole tree at once.
+    pub fn proofs(&self) -> Proofs<'_, T, S> {
+        Proofs::new(&self.spec, &self.layers, 0..self.layers.layer_len(0))
+    }
+
+    /// Generates the proof of every leaf as `proofs` does, dividing the leaves between the threads
+    /// of the rayon pool. Proofs are yielded in order when collected.
+    #[cfg(feature = "rayon")]
+    pub fn proofs_par(
+        &self,
+    ) -> impl rayon::iter::ParallelIterator<Item = (T::Hash, MerkleProof<T>)> + '_
+    where
+        T::Hash: Send + Sync,
+        S: Sync,
+    {
+        use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+        // Leaves are divided into runs, each sharing the siblings of its upper layers
+        const RUN: usize = 1024;
+        let (spec, layers) = (&self.spec, &self.layers);
+        let len = layers.layer_len(0);
+        (0..len.div_ceil(RUN))
+            .into_par_iter()
+            .flat_map_iter(move |run| {
+                Proofs::<T, S>::new(spec, layers, run * RUN..len.min(run * RUN + RUN))
+            })
+    }
+}
+
+impl<'a, T: HashFunction, S: Store<T::Hash>> Proofs<'a, T, S> {
+    fn new(spec: &'a TreeSpec, layers: &'a S, leaves: Range<usize>) -> Self {
+        Self {
+            spec,
+            layers,
+            leaves,
+            steps: vec![None; layers.layers().saturating_sub(1)],
+        }
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> Iterator for Proofs<'_, T, S> {
+    type Item = (T::Hash, MerkleProof<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let leaf = self.leaves.next()?;
+        let mut hashes = vec![];
+        let mut directions = vec![];
+        let mut sibling_counts = vec![];
+        let mut index = leaf;
+        for (layer, step) in self.steps.iter_mut().enumerate() {
+            if !matches!(step, Some((node, _)) if *node == index) {
+                let siblings = MerkleTree::<T, S>::siblings(self.spec, self.layers, layer, index);
+                *step = Some((index, siblings));
+            }
+            if let Some((_, Some(siblings))) = step {
+                sibling_counts.push(siblings.len());
+                for (sibling, direction) in siblings.iter() {
+                    hashes.push(*sibling);
+                    directions.push(*direction);
+                }
+            }
+            index /= self.spec.arity;
+        }
+
+        let proof = MerkleProof::new(
+            hashes,
+            (!self.spec.sort_pairs).then_some(directions),
+            (self.spec.arity > 2).then_some(sibling_counts),
+        )
+        .with_index(leaf);
+        Some((self.layers.get(0, leaf).unwrap_or_default(), proof))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.leaves.size_hint()
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> ExactSizeIterator for Proofs<'_, T, S> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleTreeBuilder, TreeSpec};
+
+    #[test]
+    fn generates_every_proof() {
+        let values: Vec<[u8; 2]> = (0..2500u16).map(|i| i.to_le_bytes()).collect();
+        for spec in [
+            "keccak256/sorted/promote-odd/arity2",
+            "keccak256/ordered/dup-odd/arity2",
+            "keccak256/ordered/promote-odd/arity3",
+            "keccak256/sorted-leaves/zero-odd/arity4",
+        ] {
+            let builder =
+                MerkleTreeBuilder::<Keccak256>::from_spec(&TreeSpec::parse(spec).unwrap()).unwrap();
+            for count in [0, 1, 2, 7, 40, 2500] {
+                let leaves: Vec<&Bytes> = values[..count].iter().map(|v| &v[..]).collect();
+                let tree = builder.build(&leaves);
+                let proofs: Vec<_> = tree.proofs().collect();
+                assert_eq!(tree.proofs().len(), count);
+                for (index, (leaf, proof)) in proofs.iter().enumerate() {
+                    assert_eq!(*leaf, tree.leaves()[index]);
+                    assert_eq!(*proof, tree.proof_by_index(index), "{} {}", spec, count);
+                }
+
+                #[cfg(feature = "rayon")]
+                {
+                    use rayon::iter::ParallelIterator;
+                    let parallel: Vec<_> = tree.proofs_par().collect();
+                    assert!(parallel == proofs);
+                }
+            }
+        }
+    }
+}
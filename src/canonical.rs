@@ -0,0 +1,122 @@
+use crate::{Canonicalization, HashFunction, Keccak256};
+use alloc::borrow::Cow;
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String};
+#[cfg(feature = "canonicalize-nfc")]
+use unicode_normalization::UnicodeNormalization;
+
+impl Canonicalization {
+    /// Applies the step to a leaf value, borrowing values it leaves unchanged.
+    pub fn apply<'a>(&self, value: &'a [u8]) -> Cow<'a, [u8]> {
+        let Ok(text) = core::str::from_utf8(value) else {
+            return Cow::Borrowed(value);
+        };
+        match self {
+            Canonicalization::Trim => Cow::Borrowed(text.trim().as_bytes()),
+            #[cfg(feature = "canonicalize-nfc")]
+            Canonicalization::Nfc => match unicode_normalization::is_nfc(text) {
+                true => Cow::Borrowed(value),
+                false => Cow::Owned(text.nfc().collect::<String>().into_bytes()),
+            },
+            Canonicalization::LowercaseHex => match is_hex(strip_hex_prefix(text)) {
+                true => Cow::Owned(text.to_ascii_lowercase().into_bytes()),
+                false => Cow::Borrowed(value),
+            },
+            Canonicalization::Eip55 => match strip_hex_prefix(text) {
+                address if address.len() == 40 && is_hex(address) => {
+                    Cow::Owned(checksum(address).into_bytes())
+                }
+                _ => Cow::Borrowed(value),
+            },
+        }
+    }
+}
+
+/// Applies each step in turn to a leaf value.
+pub(crate) fn canonicalize<'a>(steps: &[Canonicalization], value: &'a [u8]) -> Cow<'a, [u8]> {
+    let mut value = Cow::Borrowed(value);
+    for step in steps {
+        value = match value {
+            Cow::Borrowed(value) => step.apply(value),
+            Cow::Owned(value) => Cow::Owned(step.apply(&value).into_owned()),
+        };
+    }
+    value
+}
+
+fn strip_hex_prefix(text: &str) -> &str {
+    text.strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text)
+}
+
+fn is_hex(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The EIP-55 form of a hex address, each letter uppercased where the corresponding nibble of
+/// the Keccak256 hash of the lowercase address is at least 8.
+fn checksum(address: &str) -> String {
+    let address = address.to_ascii_lowercase();
+    let hash = Keccak256::hash(address.as_bytes());
+    let checksummed: String = address
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0xf;
+            match nibble >= 8 {
+                true => c.to_ascii_uppercase(),
+                false => c,
+            }
+        })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Canonicalization, Keccak256, MerkleTreeBuilder};
+
+    #[test]
+    fn canonicalizes_leaves() {
+        // Test vectors from EIP-55
+        let apply = |step: Canonicalization, value: &str| {
+            String::from_utf8(step.apply(value.as_bytes()).into_owned()).unwrap()
+        };
+        assert_eq!(
+            apply(
+                Canonicalization::Eip55,
+                "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+            ),
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+        assert_eq!(
+            apply(
+                Canonicalization::Eip55,
+                "FB6916095CA1DF60BB79CE92CE3EA74C37C5D359"
+            ),
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359"
+        );
+        assert_eq!(apply(Canonicalization::Eip55, "0xabc"), "0xabc");
+        assert_eq!(apply(Canonicalization::LowercaseHex, "0XaBc"), "0xabc");
+        assert_eq!(apply(Canonicalization::LowercaseHex, "Alice"), "Alice");
+        assert_eq!(apply(Canonicalization::Trim, " a\n"), "a");
+        #[cfg(feature = "canonicalize-nfc")]
+        assert_eq!(apply(Canonicalization::Nfc, "e\u{301}"), "\u{e9}");
+
+        // Differently presented addresses are the same leaf, when trimmed before checksumming
+        let address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let builder = MerkleTreeBuilder::<Keccak256>::new()
+            .canonicalize(&[Canonicalization::Trim, Canonicalization::Eip55]);
+        let tree = builder.build(&[address.as_bytes(), b"b"]);
+        for supplied in [
+            address.to_lowercase(),
+            format!(" {} ", address.to_uppercase()),
+        ] {
+            assert!(tree
+                .index_of(builder.hash_leaf(supplied.as_bytes()))
+                .is_some());
+        }
+        assert!(builder.spec().to_string().ends_with("/canon-trim+eip55"));
+    }
+}
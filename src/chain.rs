@@ -0,0 +1,97 @@
+use crate::{HashFunction, MerkleProof, MerkleTree, TreeSpec};
+
+/// Proofs through a stack of nested trees, such as a tree of the roots of per-shard trees, each
+/// proving the root of the tree below it to be a leaf of the tree above. The innermost proof
+/// proves the leaf, and the outermost proof recomputes the root of the outermost tree.
+///
+/// Each tree has its own spec, so trees with sorted and with positional pairs may be stacked.
+/// The root of a tree is its leaf within the tree above, hashed as a leaf value if the tree above
+/// hashes its leaves.
+pub struct ChainedProof<T: HashFunction> {
+    proofs: Vec<MerkleProof<T>>,
+}
+
+impl<T: HashFunction> MerkleProof<T> {
+    /// Chains the proof of a leaf of an inner tree with the proof of the root of the inner tree
+    /// within an outer tree.
+    pub fn chain(self, outer: MerkleProof<T>) -> ChainedProof<T> {
+        ChainedProof {
+            proofs: vec![self, outer],
+        }
+    }
+}
+
+impl<T: HashFunction> ChainedProof<T> {
+    /// Chains the proof of the outermost root within a further outer tree.
+    pub fn chain(mut self, outer: MerkleProof<T>) -> Self {
+        self.proofs.push(outer);
+        self
+    }
+
+    /// The proof within each tree, from the innermost tree outwards.
+    pub fn proofs(&self) -> &[MerkleProof<T>] {
+        &self.proofs
+    }
+
+    /// The root recomputed from the leaf through each tree, given the spec of each tree from the
+    /// innermost outwards, or `None` if there is not a spec per proof or a proof is malformed.
+    pub fn compute_root(&self, specs: &[&TreeSpec], leaf: T::Hash) -> Option<T::Hash> {
+        if specs.len() != self.proofs.len() {
+            return None;
+        }
+        let mut node = leaf;
+        for (level, (proof, spec)) in self.proofs.iter().zip(specs).enumerate() {
+            if level > 0 && spec.hash_leaves {
+                node = MerkleTree::<T>::hash_leaf(spec, node.as_ref());
+            }
+            node = proof.compute_root(spec, node)?;
+        }
+        Some(node)
+    }
+
+    /// Verifies the leaf of the innermost tree against the root of the outermost tree.
+    pub fn verify(&self, specs: &[&TreeSpec], leaf: T::Hash, root: T::Hash) -> bool {
+        self.compute_root(specs, leaf) == Some(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, HashFunction, Keccak256, MerkleTree, MerkleTreeBuilder};
+
+    #[test]
+    fn verifies_through_nested_trees() {
+        let shards: Vec<MerkleTree<Keccak256>> = (0..5u8)
+            .map(|shard| {
+                let values: Vec<[u8; 2]> = (0..7).map(|i| [shard, i]).collect();
+                let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+                MerkleTree::new(&leaves)
+            })
+            .collect();
+        let roots: Vec<[u8; 32]> = shards.iter().map(|s| s.root()).collect();
+        let roots: Vec<&Bytes> = roots.iter().map(|r| &r[..]).collect();
+
+        // The roots of the shards are leaves of a positional tree, hashed or as they are
+        for hash_leaves in [true, false] {
+            let builder = MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .sort_pairs(false)
+                .hash_leaves(hash_leaves);
+            let global = builder.build(&roots);
+            let top_builder = MerkleTreeBuilder::<Keccak256>::new().hash_leaves(hash_leaves);
+            let top = top_builder.build(&[&global.root()[..], &[0; 32]]);
+            let specs = [shards[3].spec(), global.spec(), top.spec()];
+
+            let leaf = Keccak256::hash(&[3, 4]);
+            let outer = global.proof(global.leaves()[3]);
+            let chained = shards[3].proof(leaf).chain(outer);
+            assert!(chained.verify(&specs[..2], leaf, global.root()));
+            assert!(!chained.verify(&specs[..2], Keccak256::hash(&[2, 4]), global.root()));
+            assert!(!chained.verify(&specs[..1], leaf, global.root()));
+
+            let chained = chained.chain(top.proof(top_builder.hash_leaf(&global.root())));
+            assert_eq!(chained.proofs().len(), 3);
+            assert!(chained.verify(&specs, leaf, top.root()));
+        }
+    }
+}
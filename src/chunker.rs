@@ -0,0 +1,159 @@
+//! Trees over a stream split into fixed-size chunks, so content can be addressed by a single root
+//! and each chunk of a download verified as it arrives, as in BitTorrent or IPFS.
+
+use crate::{verify_proof, HashFunction, MerkleProof, MerkleTree, MerkleTreeBuilder};
+use std::io::Read;
+
+/// A tree over the chunks of a stream, with one leaf per chunk in stream order and a proof for
+/// each chunk.
+pub struct ChunkedTree<T: HashFunction> {
+    tree: MerkleTree<T>,
+    chunk_size: usize,
+    len: u64,
+    proofs: Vec<MerkleProof<T>>,
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Splits `reader` into chunks of `chunk_size` bytes, the last of which may be shorter, and
+    /// builds a tree with each chunk as a leaf. Only the hash of each chunk is held, so streams
+    /// need not fit in memory. An empty stream is a single empty chunk, so every stream has a root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn from_reader(
+        mut reader: impl Read,
+        chunk_size: usize,
+    ) -> std::io::Result<ChunkedTree<T>> {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+        let builder = builder::<T>();
+        let mut hashes = vec![];
+        let mut chunk = Vec::with_capacity(chunk_size);
+        let mut len = 0;
+        loop {
+            // Reads may return fewer bytes than requested before the end of the stream
+            chunk.clear();
+            let read = reader
+                .by_ref()
+                .take(chunk_size as u64)
+                .read_to_end(&mut chunk)?;
+            if read == 0 && !hashes.is_empty() {
+                break;
+            }
+            hashes.push(builder.hash_leaf(&chunk));
+            len += read as u64;
+            if read < chunk_size {
+                break;
+            }
+        }
+
+        let tree = builder.build_from_hashes(hashes);
+        let proofs = (0..tree.leaves().len())
+            .map(|index| tree.proof_by_index(index))
+            .collect();
+        Ok(ChunkedTree {
+            tree,
+            chunk_size,
+            len,
+            proofs,
+        })
+    }
+}
+
+/// Verifies that a chunk is within the stream with the supplied root, without the rest of the
+/// stream.
+pub fn verify_chunk<T: HashFunction>(
+    chunk: &[u8],
+    proof: &MerkleProof<T>,
+    root: impl Into<T::Hash>,
+) -> bool {
+    let builder = builder::<T>();
+    verify_proof(builder.spec(), proof, builder.hash_leaf(chunk), root.into())
+}
+
+impl<T: HashFunction> ChunkedTree<T> {
+    pub fn tree(&self) -> &MerkleTree<T> {
+        &self.tree
+    }
+
+    pub fn root(&self) -> T::Hash {
+        self.tree.root()
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// The length of the stream in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of chunks, being at least one.
+    pub fn chunks(&self) -> usize {
+        self.proofs.len()
+    }
+
+    /// The proof of the chunk at `index`, if present.
+    pub fn proof(&self, index: usize) -> Option<&MerkleProof<T>> {
+        self.proofs.get(index)
+    }
+
+    /// The proof of each chunk, in stream order.
+    pub fn proofs(&self) -> &[MerkleProof<T>] {
+        &self.proofs
+    }
+}
+
+/// Chunks are hashed in stream order, with leaf and node hashes separated so that no internal
+/// node can be presented as a chunk. Repeated chunks are kept, so each chunk has its own leaf.
+fn builder<T: HashFunction>() -> MerkleTreeBuilder<T> {
+    MerkleTreeBuilder::new()
+        .sort_leaves(false)
+        .sort_pairs(false)
+        .domain_separation(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_chunk;
+    use crate::{Keccak256, MerkleTree};
+
+    #[test]
+    fn chunks_streams() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let chunked = MerkleTree::<Keccak256>::from_reader(&data[..], 256).unwrap();
+        assert_eq!((chunked.len(), chunked.chunks()), (1000, 4));
+
+        // Each chunk verifies on its own, but not with other contents
+        let root = chunked.root();
+        for (index, chunk) in data.chunks(256).enumerate() {
+            let proof = chunked.proof(index).unwrap();
+            assert!(verify_chunk(chunk, proof, root));
+            assert!(!verify_chunk(&chunk[1..], proof, root));
+        }
+        assert!(chunked.proof(4).is_none());
+
+        // Chunks are read in full however the stream is split
+        let split = std::io::Read::chain(&data[..100], &data[100..]);
+        let chained = MerkleTree::<Keccak256>::from_reader(split, 256).unwrap();
+        assert_eq!(chained.root(), root);
+    }
+
+    #[test]
+    fn chunks_empty_and_exact_streams() {
+        let empty = MerkleTree::<Keccak256>::from_reader(&[][..], 4).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.chunks(), 1);
+        assert!(verify_chunk(b"", empty.proof(0).unwrap(), empty.root()));
+
+        // A stream ending on a chunk boundary has no trailing empty chunk
+        let exact = MerkleTree::<Keccak256>::from_reader(&[7; 8][..], 4).unwrap();
+        assert_eq!(exact.chunks(), 2);
+        assert_eq!(exact.proofs().len(), 2);
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of the current time, wherever a timestamp is written. Supplying a clock other than
+/// `SystemClock` makes output deterministic for tests and replayed builds, and lets signing
+/// environments without a trusted system clock supply their own time.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    /// The current time in milliseconds since the Unix epoch, or zero for times before it.
+    fn now_millis(&self) -> u128 {
+        self.now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default()
+    }
+}
+
+/// The time of the system clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock which always reads the same time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedClock(pub SystemTime);
+
+impl FixedClock {
+    /// A clock reading `millis` milliseconds after the Unix epoch.
+    pub fn from_millis(millis: u64) -> Self {
+        Self(UNIX_EPOCH + std::time::Duration::from_millis(millis))
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{HashFunction, MerkleTree, Store};
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodegenError {
+    /// Generated verifiers only support binary trees.
+    UnsupportedArity(usize),
+}
+
+impl Display for CodegenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodegenError::UnsupportedArity(arity) => write!(
+                f,
+                "verifiers can only be generated for binary trees, not arity {}",
+                arity
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// Generates the source of a dependency-free Rust module which verifies proofs against the
+    /// current root of this tree, for firmware which only needs to check one specific commitment.
+    ///
+    /// The module holds the root and depth of the tree as constants and a `verify` function
+    /// specialised to its spec. The hash function is supplied by the caller, typically a hardware
+    /// hasher, as a function hashing the concatenation of its parts so that no allocation is
+    /// required. The source is suitable for writing to `OUT_DIR` from a build script and including
+    /// with `include!`.
+    pub fn verifier_source(&self) -> Result<String, CodegenError> {
+        if self.spec.arity != 2 {
+            return Err(CodegenError::UnsupportedArity(self.spec.arity));
+        }
+
+        let root = self.root();
+        let size = root.as_ref().len();
+
+        // Sorted pairs need no directions, otherwise the side of each sibling is given by a bit
+        let (directions, parameter, siblings, condition) = match self.spec.sort_pairs {
+            true => ("", "", "sibling in proof", "*sibling < node"),
+            false => (
+                "\n///\n/// Bit `i` of `left` is set when the `i`th sibling is on the left.",
+                "\n    left: u64,",
+                "(i, sibling) in proof.iter().enumerate()",
+                "(left >> i) & 1 == 1",
+            ),
+        };
+        let source = format!(
+            "\
+// Generated by merkle-tree for a tree with spec `{spec}`.
+// Do not edit: regenerate this file whenever the tree changes.
+
+/// Root of the tree.
+pub const ROOT: [u8; {size}] = {root};
+/// Maximum number of siblings in a proof.
+pub const DEPTH: usize = {depth};
+
+const LEAF_PREFIX: &[u8] = &{leaf_prefix};
+const NODE_PREFIX: &[u8] = &{node_prefix};
+
+/// Verifies that `proof` recomputes `ROOT` from the leaf `value`, where `hash` computes the {hash}
+/// hash of the concatenation of its parts.{directions}
+pub fn verify(
+    value: &[u8],
+    proof: &[[u8; {size}]],{parameter}
+    hash: fn(&[&[u8]]) -> [u8; {size}],
+) -> bool {{
+    if proof.len() > DEPTH {{
+        return false;
+    }}
+    let mut node = hash(&[LEAF_PREFIX, value]);
+    for {siblings} {{
+        node = if {condition} {{
+            hash(&[NODE_PREFIX, sibling, &node])
+        }} else {{
+            hash(&[NODE_PREFIX, &node, sibling])
+        }};
+    }}
+    node == ROOT
+}}
+",
+            spec = self.spec,
+            root = bytes(root.as_ref()),
+            depth = self.layers.layers().saturating_sub(1),
+            leaf_prefix = bytes(&self.spec.leaf_prefix),
+            node_prefix = bytes(&self.spec.node_prefix),
+            hash = T::NAME,
+        );
+
+        Ok(source)
+    }
+}
+
+/// Formats bytes as an array literal, wrapping long arrays over several lines.
+fn bytes(bytes: &[u8]) -> String {
+    let bytes: Vec<String> = bytes.iter().map(|b| format!("0x{:02x}", b)).collect();
+    if bytes.len() <= 8 {
+        return format!("[{}]", bytes.join(", "));
+    }
+
+    let lines: Vec<String> = bytes
+        .chunks(8)
+        .map(|chunk| format!("    {},\n", chunk.join(", ")))
+        .collect();
+    format!("[\n{}]", lines.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Bytes, CodegenError, Direction, HashFunction, Keccak256, MerkleTree, MerkleTreeBuilder,
+    };
+
+    /// Verifiers generated from the trees below, regenerated by writing the output of
+    /// `verifier_source` over each file.
+    mod sorted {
+        include!("../fixtures/codegen/sorted.rs");
+    }
+    mod ordered {
+        include!("../fixtures/codegen/ordered.rs");
+    }
+
+    const VALUES: [&str; 5] = ["alice", "bob", "carol", "dave", "eve"];
+
+    fn tree(sorted: bool) -> MerkleTree<Keccak256> {
+        let leaves: Vec<&Bytes> = VALUES.iter().map(|v| v.as_bytes()).collect();
+        MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(sorted)
+            .sort_pairs(sorted)
+            .domain_separation(true)
+            .build(&leaves)
+    }
+
+    fn keccak256(parts: &[&[u8]]) -> [u8; 32] {
+        Keccak256::hash(&parts.concat())
+    }
+
+    #[test]
+    fn matches_fixtures() {
+        assert_eq!(
+            tree(true).verifier_source().unwrap(),
+            include_str!("../fixtures/codegen/sorted.rs")
+        );
+        assert_eq!(
+            tree(false).verifier_source().unwrap(),
+            include_str!("../fixtures/codegen/ordered.rs")
+        );
+        assert_eq!(
+            MerkleTreeBuilder::<Keccak256>::new()
+                .arity(3)
+                .build(&[])
+                .verifier_source(),
+            Err(CodegenError::UnsupportedArity(3))
+        );
+    }
+
+    #[test]
+    fn generated_verifiers() {
+        let (tree, root) = (tree(true), sorted::ROOT);
+        assert_eq!(root, tree.root());
+        for value in VALUES {
+            let proof = tree.proof(MerkleTree::<Keccak256>::hash_leaf(
+                tree.spec(),
+                value.as_bytes(),
+            ));
+            assert!(sorted::verify(value.as_bytes(), proof.hashes(), keccak256));
+            assert!(!sorted::verify(b"mallory", proof.hashes(), keccak256));
+        }
+
+        let tree = self::tree(false);
+        for (index, value) in VALUES.iter().enumerate() {
+            let proof = tree.proof(tree.leaves()[index]);
+            let left = proof
+                .directions()
+                .unwrap()
+                .iter()
+                .enumerate()
+                .filter(|(_, d)| **d == Direction::Left)
+                .fold(0, |left, (i, _)| left | 1 << i);
+            assert!(ordered::verify(
+                value.as_bytes(),
+                proof.hashes(),
+                left,
+                keccak256
+            ));
+            assert!(!ordered::verify(
+                value.as_bytes(),
+                proof.hashes(),
+                !left,
+                keccak256
+            ));
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{verify_proof, Direction, HashFunction, MerkleError, MerkleProof, TreeSpec};
+use std::collections::BTreeMap;
+
+const DIRECTIONS: u8 = 1;
+const SIBLING_COUNTS: u8 = 2;
+const INDEX: u8 = 4;
+
+/// A batch of proofs sharing a single copy of each sibling hash, such as the proofs of an airdrop
+/// distributed to many users. Siblings near the root appear in most proofs, so each proof holds
+/// only references to shared nodes along with its directions packed into bits.
+pub struct CompressedProofs<T: HashFunction> {
+    /// The distinct siblings of the batch, most frequent first so they have the shortest
+    /// references.
+    nodes: Vec<T::Hash>,
+    proofs: Vec<CompressedProof>,
+}
+
+/// A proof within a batch, referring to the shared nodes of the batch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CompressedProof {
+    siblings: Vec<usize>,
+    /// A bit per sibling, set when the sibling is on the left.
+    directions: Option<Vec<u8>>,
+    sibling_counts: Option<Vec<usize>>,
+    index: Option<usize>,
+}
+
+impl<T: HashFunction> CompressedProofs<T> {
+    pub fn compress(proofs: &[MerkleProof<T>]) -> Self {
+        // Order nodes by frequency, then by first appearance
+        let mut frequency: BTreeMap<T::Hash, (usize, usize)> = BTreeMap::new();
+        for (order, hash) in proofs.iter().flat_map(|p| p.iter()).enumerate() {
+            frequency.entry(*hash).or_insert((0, order)).0 += 1;
+        }
+        let mut nodes: Vec<T::Hash> = frequency.keys().copied().collect();
+        nodes.sort_by_key(|node| {
+            let (count, order) = frequency[node];
+            (std::cmp::Reverse(count), order)
+        });
+        let references: BTreeMap<T::Hash, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (*node, i))
+            .collect();
+
+        let proofs = proofs
+            .iter()
+            .map(|proof| CompressedProof {
+                siblings: proof.iter().map(|hash| references[hash]).collect(),
+                directions: proof.directions().map(|directions| {
+                    let mut bits = vec![0; directions.len().div_ceil(8)];
+                    for (i, direction) in directions.iter().enumerate() {
+                        if *direction == Direction::Left {
+                            bits[i / 8] |= 1 << (i % 8);
+                        }
+                    }
+                    bits
+                }),
+                sibling_counts: proof.sibling_counts().map(|counts| counts.to_vec()),
+                index: proof.index(),
+            })
+            .collect();
+        Self { nodes, proofs }
+    }
+
+    /// Restores every proof of the batch, in the order compressed.
+    pub fn decompress(&self) -> Vec<MerkleProof<T>> {
+        (0..self.proofs.len())
+            .filter_map(|position| self.proof(position))
+            .collect()
+    }
+
+    /// Restores the proof at `position` within the batch, if present.
+    pub fn proof(&self, position: usize) -> Option<MerkleProof<T>> {
+        let proof = self.proofs.get(position)?;
+        let directions = proof.directions.as_ref().map(|bits| {
+            (0..proof.siblings.len())
+                .map(|i| match bits[i / 8] & (1 << (i % 8)) {
+                    0 => Direction::Right,
+                    _ => Direction::Left,
+                })
+                .collect()
+        });
+        let proof = MerkleProof::new(
+            proof.siblings.iter().map(|i| self.nodes[*i]).collect(),
+            directions,
+            proof.sibling_counts.clone(),
+        )
+        .with_optional_index(proof.index);
+        Some(proof)
+    }
+
+    /// Verifies the proof at `position` within the batch for a tree with the supplied spec,
+    /// without restoring the rest of the batch.
+    pub fn verify(
+        &self,
+        spec: &TreeSpec,
+        position: usize,
+        leaf: impl Into<T::Hash>,
+        root: impl Into<T::Hash>,
+    ) -> bool {
+        match self.proof(position) {
+            Some(proof) => verify_proof(spec, &proof, leaf.into(), root.into()),
+            None => false,
+        }
+    }
+
+    /// The number of proofs in the batch.
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    /// The distinct sibling hashes shared by the proofs of the batch.
+    pub fn nodes(&self) -> &[T::Hash] {
+        &self.nodes
+    }
+
+    /// Encodes the batch as the shared nodes followed by each proof, with references, counts and
+    /// indices as LEB128 varints.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_varint(&mut bytes, self.nodes.len());
+        for node in &self.nodes {
+            bytes.extend_from_slice(node.as_ref());
+        }
+        write_varint(&mut bytes, self.proofs.len());
+        for proof in &self.proofs {
+            let mut flags = 0;
+            if proof.directions.is_some() {
+                flags |= DIRECTIONS;
+            }
+            if proof.sibling_counts.is_some() {
+                flags |= SIBLING_COUNTS;
+            }
+            if proof.index.is_some() {
+                flags |= INDEX;
+            }
+            bytes.push(flags);
+            write_varint(&mut bytes, proof.siblings.len());
+            for sibling in &proof.siblings {
+                write_varint(&mut bytes, *sibling);
+            }
+            if let Some(directions) = &proof.directions {
+                bytes.extend_from_slice(directions);
+            }
+            if let Some(counts) = &proof.sibling_counts {
+                write_varint(&mut bytes, counts.len());
+                for count in counts {
+                    write_varint(&mut bytes, *count);
+                }
+            }
+            if let Some(index) = proof.index {
+                write_varint(&mut bytes, index);
+            }
+        }
+        bytes
+    }
+
+    /// Decodes a batch encoded by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        let size = T::Hash::default().as_ref().len();
+
+        let count = reader.varint()?;
+        let mut nodes = Vec::with_capacity(count.min(bytes.len() / size.max(1)));
+        for _ in 0..count {
+            let node = reader.take(size)?;
+            nodes.push(T::Hash::try_from(node.to_vec()).map_err(|_| MerkleError::MalformedProof)?);
+        }
+
+        let count = reader.varint()?;
+        let mut proofs = Vec::with_capacity(count.min(bytes.len()));
+        for _ in 0..count {
+            let flags = reader.take(1)?[0];
+            let len = reader.varint()?;
+            let siblings = (0..len)
+                .map(|_| match reader.varint()? {
+                    i if i < nodes.len() => Ok(i),
+                    _ => Err(MerkleError::MalformedProof),
+                })
+                .collect::<Result<_, _>>()?;
+            let directions = match flags & DIRECTIONS {
+                0 => None,
+                _ => Some(reader.take(len.div_ceil(8))?.to_vec()),
+            };
+            let sibling_counts = match flags & SIBLING_COUNTS {
+                0 => None,
+                _ => {
+                    let steps = reader.varint()?;
+                    Some(
+                        (0..steps)
+                            .map(|_| reader.varint())
+                            .collect::<Result<_, _>>()?,
+                    )
+                }
+            };
+            let index = match flags & INDEX {
+                0 => None,
+                _ => Some(reader.varint()?),
+            };
+            proofs.push(CompressedProof {
+                siblings,
+                directions,
+                sibling_counts,
+                index,
+            });
+        }
+
+        match reader.offset == bytes.len() {
+            true => Ok(Self { nodes, proofs }),
+            false => Err(MerkleError::MalformedProof),
+        }
+    }
+}
+
+impl<T: HashFunction> MerkleProof<T> {
+    fn with_optional_index(self, index: Option<usize>) -> Self {
+        match index {
+            Some(index) => self.with_index(index),
+            None => self,
+        }
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MerkleError> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(MerkleError::MalformedProof)?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<usize, MerkleError> {
+        let mut value = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as usize)
+                .checked_shl(shift)
+                .ok_or(MerkleError::MalformedProof)?;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(MerkleError::MalformedProof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompressedProofs;
+    use crate::{Bytes, Keccak256, MerkleError, MerkleProof, MerkleTreeBuilder, TreeSpec};
+
+    #[test]
+    fn compresses_batches() {
+        let values: Vec<[u8; 2]> = (0..100u16).map(|i| i.to_le_bytes()).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for spec in [
+            "keccak256/sorted/promote-odd/arity2",
+            "keccak256/ordered/dup-odd/arity2",
+            "keccak256/ordered/promote-odd/arity3",
+        ] {
+            let spec = TreeSpec::parse(spec).unwrap();
+            let tree = MerkleTreeBuilder::<Keccak256>::from_spec(&spec)
+                .unwrap()
+                .build(&leaves);
+            let proofs: Vec<MerkleProof<Keccak256>> = (0..tree.leaves().len())
+                .map(|i| tree.proof_by_index(i))
+                .collect();
+
+            let compressed = CompressedProofs::compress(&proofs);
+            assert_eq!(compressed.decompress(), proofs);
+            let bytes = compressed.to_bytes();
+            let uncompressed: usize = proofs.iter().map(|p| p.len() * 32).sum();
+            assert!(
+                bytes.len() * 2 < uncompressed,
+                "{} with {}",
+                bytes.len(),
+                spec
+            );
+
+            let decoded = CompressedProofs::<Keccak256>::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.decompress(), proofs);
+            let root = tree.root();
+            assert!(decoded.verify(&spec, 7, tree.leaves()[7], root));
+            assert!(!decoded.verify(&spec, 7, tree.leaves()[8], root));
+            assert!(!decoded.verify(&spec, 100, tree.leaves()[7], root));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_bytes() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_pairs(false)
+            .build(&leaves);
+        let proofs = vec![tree.proof_by_index(0), tree.proof_by_index(2)];
+        let bytes = CompressedProofs::compress(&proofs).to_bytes();
+
+        let parse = |bytes: &[u8]| CompressedProofs::<Keccak256>::from_bytes(bytes).err();
+        assert_eq!(
+            parse(&bytes[..bytes.len() - 1]),
+            Some(MerkleError::MalformedProof)
+        );
+        assert_eq!(
+            parse(&[bytes.clone(), vec![0]].concat()),
+            Some(MerkleError::MalformedProof)
+        );
+        assert_eq!(parse(&[0xff; 11]), Some(MerkleError::MalformedProof));
+        assert!(CompressedProofs::<Keccak256>::from_bytes(&[0, 0])
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,376 @@
+use crate::{
+    hash_function, Bytes, DynMerkleProof, DynMerkleTree, HashFunction, Keccak256, MerkleError,
+    MerkleTree, MerkleTreeBuilder, OddStrategy, SpecError, TreeSpec,
+};
+use std::fmt::{Display, Formatter};
+
+/// The construction of trees by other widely used tooling, so that roots and proofs agree with
+/// those produced elsewhere without working out the equivalent options.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompatibilityProfile {
+    /// `SimpleMerkleTree` from `@openzeppelin/merkle-tree`, and `StandardMerkleTree` given the
+    /// leaf hashes of its values. Leaves are hashes, sorted, with sorted pairs hashed by keccak256
+    /// and the nodes laid out as a binary heap.
+    ///
+    /// For counts of leaves which are not a power of two the heap places leaves at different
+    /// depths to `MerkleTree`, so only `root` and `proof` of the profile match exactly, and
+    /// `MerkleTreeBuilder::from_profile` fails for the profile.
+    OpenZeppelin,
+    /// merkletreejs as constructed by `new MerkleTree(leaves.map(keccak256), keccak256,
+    /// { sortPairs: true })`, the form verified by OpenZeppelin's `MerkleProof`.
+    MerkleTreeJs,
+    /// The Merkle tree hash of RFC 6962, hashing leaves in order with sha256 under the `0x00` leaf
+    /// and `0x01` node prefixes.
+    Rfc6962,
+    /// The merkle root of a Bitcoin block, from txids in internal byte order.
+    Bitcoin,
+}
+
+impl CompatibilityProfile {
+    pub const ALL: [CompatibilityProfile; 4] = [
+        CompatibilityProfile::OpenZeppelin,
+        CompatibilityProfile::MerkleTreeJs,
+        CompatibilityProfile::Rfc6962,
+        CompatibilityProfile::Bitcoin,
+    ];
+
+    /// The spec of trees built with the profile. The spec names `sha256` or `sha256d` for the RFC
+    /// 6962 and Bitcoin profiles, whose trees are built when the `sha256` feature is enabled.
+    pub fn spec(&self) -> TreeSpec {
+        let mut spec = TreeSpec::new(Keccak256::NAME);
+        match self {
+            CompatibilityProfile::OpenZeppelin => spec.hash_leaves = false,
+            CompatibilityProfile::MerkleTreeJs => spec.sort_leaves = false,
+            CompatibilityProfile::Rfc6962 => {
+                spec.hash = "sha256".to_string();
+                spec.sort_leaves = false;
+                spec.sort_pairs = false;
+                spec.leaf_prefix = vec![0];
+                spec.node_prefix = vec![1];
+            }
+            CompatibilityProfile::Bitcoin => {
+                spec.hash = "sha256d".to_string();
+                spec.sort_leaves = false;
+                spec.sort_pairs = false;
+                spec.hash_leaves = false;
+                spec.odd_strategy = OddStrategy::Duplicate;
+            }
+        }
+        spec
+    }
+
+    /// The root of a tree of the leaves as the tooling of the profile computes it, with the leaves
+    /// given as they would be to that tooling. Profiles taking hashes as leaves fail if a leaf is
+    /// not the length of a hash.
+    pub fn root(&self, leaves: &[&Bytes]) -> Result<Box<[u8]>, ProfileError> {
+        match self {
+            CompatibilityProfile::OpenZeppelin => {
+                let (tree, _) = heap(&hashes(leaves)?);
+                Ok(tree.first().copied().unwrap_or_default().into())
+            }
+            _ => Ok(self.build(leaves)?.root()),
+        }
+    }
+
+    /// The proof of the leaf at `index` among the leaves as supplied, as the tooling of the
+    /// profile generates it, or `None` if there is no such leaf. Profiles taking hashes as leaves
+    /// fail if a leaf is not the length of a hash.
+    pub fn proof(
+        &self,
+        leaves: &[&Bytes],
+        index: usize,
+    ) -> Result<Option<DynMerkleProof>, ProfileError> {
+        if index >= leaves.len() {
+            return Ok(None);
+        }
+        if *self != CompatibilityProfile::OpenZeppelin {
+            let tree = self.build(leaves)?;
+            let mut proof = tree.proof(&tree.leaves()[index]);
+            proof.index = Some(index);
+            return Ok(Some(proof));
+        }
+
+        let (tree, positions) = heap(&hashes(leaves)?);
+        let mut node = positions[index];
+        let mut hashes: Vec<Box<[u8]>> = vec![];
+        while node > 0 {
+            let sibling = if node % 2 == 1 { node + 1 } else { node - 1 };
+            hashes.push(tree[sibling].as_ref().into());
+            node = (node - 1) / 2;
+        }
+        Ok(Some(DynMerkleProof {
+            hashes,
+            directions: None,
+            sibling_counts: None,
+            index: None,
+        }))
+    }
+
+    /// Builds the tree of a profile other than OpenZeppelin, checking the length of pre-hashed
+    /// leaves first.
+    fn build(&self, leaves: &[&Bytes]) -> Result<DynMerkleTree, ProfileError> {
+        let spec = self.spec();
+        if !spec.hash_leaves {
+            let hash_function = hash_function(&spec.hash)
+                .ok_or_else(|| SpecError::UnknownHash(spec.hash.clone()))?;
+            let expected = hash_function.hash_dyn(&[]).len();
+            if let Some(leaf) = leaves.iter().find(|leaf| leaf.len() != expected) {
+                return Err(ProfileError::Leaf(MerkleError::InvalidHash {
+                    expected,
+                    actual: leaf.len(),
+                }));
+            }
+        }
+        Ok(DynMerkleTree::from_spec(&spec, leaves)?)
+    }
+
+    /// Verifies a proof of the profile, as its tooling would.
+    pub fn verify(
+        &self,
+        proof: &DynMerkleProof,
+        leaf: &[u8],
+        root: &[u8],
+    ) -> Result<bool, SpecError> {
+        let spec = self.spec();
+        let hash_function =
+            hash_function(&spec.hash).ok_or_else(|| SpecError::UnknownHash(spec.hash.clone()))?;
+        Ok(hash_function.verify(&spec, proof, leaf, root))
+    }
+}
+
+impl Display for CompatibilityProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompatibilityProfile::OpenZeppelin => write!(f, "openzeppelin"),
+            CompatibilityProfile::MerkleTreeJs => write!(f, "merkletreejs"),
+            CompatibilityProfile::Rfc6962 => write!(f, "rfc6962"),
+            CompatibilityProfile::Bitcoin => write!(f, "bitcoin"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProfileError {
+    Spec(SpecError),
+    /// A leaf of a profile taking hashes as leaves is not the length of a hash.
+    Leaf(MerkleError),
+    /// The profile lays out its nodes in a way `MerkleTree` cannot build, so its roots would not
+    /// match those of the tooling of the profile.
+    Unsupported(CompatibilityProfile),
+}
+
+impl From<SpecError> for ProfileError {
+    fn from(e: SpecError) -> Self {
+        ProfileError::Spec(e)
+    }
+}
+
+impl Display for ProfileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfileError::Spec(e) => write!(f, "{}", e),
+            ProfileError::Leaf(e) => write!(f, "invalid leaf: {}", e),
+            ProfileError::Unsupported(profile) => {
+                write!(f, "trees of profile '{}' cannot be built", profile)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Creates a builder configured by the spec of a profile, which must name the hash function
+    /// `T`. The OpenZeppelin profile fails, as its heap cannot be built by `MerkleTree`.
+    pub fn from_profile(profile: CompatibilityProfile) -> Result<Self, ProfileError> {
+        if profile == CompatibilityProfile::OpenZeppelin {
+            return Err(ProfileError::Unsupported(profile));
+        }
+        Ok(Self::from_spec(&profile.spec())?)
+    }
+}
+
+/// Converts leaves supplied as bytes to keccak256 hashes.
+fn hashes(leaves: &[&Bytes]) -> Result<Vec<[u8; 32]>, ProfileError> {
+    leaves
+        .iter()
+        .map(|leaf| MerkleTree::<Keccak256>::hash_from_slice(leaf).map_err(ProfileError::Leaf))
+        .collect()
+}
+
+/// The nodes of an OpenZeppelin tree as a binary heap with the root first, where the sorted
+/// leaves fill the end of the heap in reverse order, along with the position within the heap of
+/// each leaf in the order supplied.
+pub(crate) fn heap(leaves: &[[u8; 32]]) -> (Vec<[u8; 32]>, Vec<usize>) {
+    let mut sorted: Vec<([u8; 32], usize)> = leaves.iter().copied().zip(0..).collect();
+    sorted.sort_unstable();
+
+    let length = (2 * sorted.len()).saturating_sub(1);
+    let mut tree = vec![[0; 32]; length];
+    let mut positions = vec![0; sorted.len()];
+    for (i, (leaf, index)) in sorted.iter().enumerate() {
+        tree[length - 1 - i] = *leaf;
+        positions[*index] = length - 1 - i;
+    }
+    for i in (0..length - sorted.len()).rev() {
+        tree[i] = MerkleTree::<Keccak256>::hash_sorted_pair(tree[2 * i + 1], tree[2 * i + 2]);
+    }
+    (tree, positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompatibilityProfile, ProfileError};
+    use crate::{Bytes, HashFunction, Keccak256, MerkleError, MerkleTreeBuilder};
+
+    fn check(profile: CompatibilityProfile, leaves: &[&Bytes], root: &str) {
+        let computed = profile.root(leaves).unwrap();
+        assert_eq!(hex::encode(&computed), root, "{}", profile);
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = profile.proof(leaves, index).unwrap().unwrap();
+            let leaf = match profile.spec().hash_leaves {
+                true => profile
+                    .spec()
+                    .leaf_prefix
+                    .iter()
+                    .chain(*leaf)
+                    .copied()
+                    .collect(),
+                false => leaf.to_vec(),
+            };
+            let leaf = match profile {
+                CompatibilityProfile::MerkleTreeJs => Keccak256::hash(&leaf).to_vec(),
+                #[cfg(feature = "sha256")]
+                CompatibilityProfile::Rfc6962 => crate::Sha256::hash(&leaf).to_vec(),
+                _ => leaf,
+            };
+            assert!(
+                profile.verify(&proof, &leaf, &computed).unwrap(),
+                "{}",
+                profile
+            );
+        }
+        assert_eq!(profile.proof(leaves, leaves.len()).unwrap(), None);
+    }
+
+    #[test]
+    fn reproduces_published_vectors() {
+        let leaves: Vec<[u8; 32]> = (1..=5u8).map(|i| Keccak256::hash(&[i])).collect();
+        let leaves: Vec<&Bytes> = leaves.iter().map(|l| &l[..]).collect();
+        let oz = CompatibilityProfile::OpenZeppelin;
+        // Four leaves form a balanced tree, whose root is that of the spec of the profile, but the
+        // heap of five leaves cannot be built
+        let builder = MerkleTreeBuilder::<Keccak256>::from_spec(&oz.spec()).unwrap();
+        assert_eq!(
+            &*oz.root(&leaves[..4]).unwrap(),
+            &builder.build(&leaves[..4]).root()[..]
+        );
+        assert_ne!(
+            &*oz.root(&leaves).unwrap(),
+            &builder.build(&leaves).root()[..]
+        );
+        assert_eq!(
+            MerkleTreeBuilder::<Keccak256>::from_profile(oz).err(),
+            Some(ProfileError::Unsupported(oz))
+        );
+        assert!(
+            MerkleTreeBuilder::<Keccak256>::from_profile(CompatibilityProfile::Rfc6962).is_err()
+        );
+        assert!(
+            MerkleTreeBuilder::<Keccak256>::from_profile(CompatibilityProfile::MerkleTreeJs)
+                .is_ok()
+        );
+        check(oz, &leaves, &hex::encode(oz.root(&leaves).unwrap()));
+
+        // Duplicate leaves each have a proof of their own position
+        let duplicated = [leaves[0], leaves[1], leaves[0]];
+        for index in 0..3 {
+            let proof = oz.proof(&duplicated, index).unwrap().unwrap();
+            let root = oz.root(&duplicated).unwrap();
+            assert!(oz.verify(&proof, duplicated[index], &root).unwrap());
+        }
+
+        // Leaves of profiles taking hashes which are not the length of a hash are rejected
+        let invalid = Some(ProfileError::Leaf(MerkleError::InvalidHash {
+            expected: 32,
+            actual: 20,
+        }));
+        for profile in [oz, CompatibilityProfile::Bitcoin] {
+            if profile == CompatibilityProfile::Bitcoin && !cfg!(feature = "sha256") {
+                continue;
+            }
+            let short: [&Bytes; 2] = [leaves[0], &[0; 20]];
+            assert_eq!(profile.root(&short).err(), invalid);
+            assert_eq!(profile.proof(&short, 0).err(), invalid);
+        }
+
+        #[cfg(feature = "openzeppelin")]
+        {
+            // The heap of `StandardMerkleTree` over the leaf hashes of its values
+            use serde_json::json;
+            let values = (0..5)
+                .map(|i| vec![json!(i), json!(format!("-{}", i)), json!(i % 2 == 0)])
+                .collect();
+            let tree = crate::StandardMerkleTree::of(values, &["uint8", "int16", "bool"]).unwrap();
+            let leaves: Vec<[u8; 32]> = (0..5).map(|i| tree.leaf(i)).collect();
+            let leaves: Vec<&Bytes> = leaves.iter().map(|l| &l[..]).collect();
+            check(oz, &leaves, &hex::encode(tree.root()));
+            for i in 0..5 {
+                let proof = oz.proof(&leaves, i).unwrap().unwrap();
+                assert_eq!(proof.typed::<Keccak256>().unwrap(), tree.proof(i));
+            }
+        }
+
+        assert_eq!(
+            CompatibilityProfile::ALL.map(|p| p.to_string()),
+            ["openzeppelin", "merkletreejs", "rfc6962", "bitcoin"]
+        );
+
+        #[cfg(feature = "sha256")]
+        {
+            // The test vectors of the Certificate Transparency implementation of RFC 6962
+            let inputs = [
+                "",
+                "00",
+                "10",
+                "2021",
+                "3031",
+                "40414243",
+                "5051525354555657",
+                "606162636465666768696a6b6c6d6e6f",
+            ]
+            .map(|input| hex::decode(input).unwrap());
+            let inputs: Vec<&Bytes> = inputs.iter().map(|i| &i[..]).collect();
+            let roots = [
+                "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+                "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+                "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+                "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+                "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+                "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+                "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+            ];
+            for (count, root) in (1..).zip(roots) {
+                check(CompatibilityProfile::Rfc6962, &inputs[..count], root);
+            }
+
+            // The transactions of Bitcoin block 100000
+            let txids: Vec<[u8; 32]> = [
+                "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+                "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+                "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+                "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+            ]
+            .iter()
+            .map(|txid| crate::bitcoin::parse_hash(txid).unwrap())
+            .collect();
+            let txids: Vec<&Bytes> = txids.iter().map(|t| &t[..]).collect();
+            let mut root =
+                hex::decode("f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766")
+                    .unwrap();
+            root.reverse();
+            check(CompatibilityProfile::Bitcoin, &txids, &hex::encode(root));
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{HashFunction, Publication};
+use lz4_flex::frame::{FrameDecoder, FrameEncoder};
+use std::io::{BufRead, Read, Write};
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const LZ4_MAGIC: [u8; 4] = [0x04, 0x22, 0x4d, 0x18];
+
+/// Streaming compression for exported proofs and serialized trees, which are dominated by
+/// repeated sibling hashes and so compress well.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Zstandard at the supplied level, where 0 selects the default level.
+    Zstd(i32),
+    /// LZ4 frames, trading ratio for speed.
+    Lz4,
+}
+
+impl Compression {
+    /// Wraps a writer so that everything written is compressed as it is written, without
+    /// buffering the whole output. The compressor must be finished to complete the stream.
+    ///
+    /// Serialized trees are compressed by serializing into the compressor, such as with
+    /// `serde_json::to_writer`.
+    pub fn writer<W: Write>(self, writer: W) -> std::io::Result<Compressor<W>> {
+        Ok(Compressor(match self {
+            Compression::Zstd(level) => Encoder::Zstd(zstd::Encoder::new(writer, level)?),
+            Compression::Lz4 => Encoder::Lz4(FrameEncoder::new(writer)),
+        }))
+    }
+}
+
+/// A writer compressing everything written to it.
+pub struct Compressor<W: Write>(Encoder<W>);
+
+enum Encoder<W: Write> {
+    Zstd(zstd::Encoder<'static, W>),
+    Lz4(FrameEncoder<W>),
+}
+
+impl<W: Write> Compressor<W> {
+    /// Completes the compressed stream, returning the underlying writer.
+    pub fn finish(self) -> std::io::Result<W> {
+        match self.0 {
+            Encoder::Zstd(encoder) => encoder.finish(),
+            Encoder::Lz4(encoder) => encoder.finish().map_err(std::io::Error::other),
+        }
+    }
+}
+
+impl<W: Write> Write for Compressor<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match &mut self.0 {
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            Encoder::Lz4(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.0 {
+            Encoder::Zstd(encoder) => encoder.flush(),
+            Encoder::Lz4(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Wraps a reader so that a zstd or LZ4 compressed stream is decompressed as it is read, detected
+/// by its magic number. Uncompressed streams are read unchanged.
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> std::io::Result<Box<dyn Read + 'a>> {
+    let magic = reader
+        .fill_buf()?
+        .get(..4)
+        .map(|m| [m[0], m[1], m[2], m[3]]);
+    Ok(match magic {
+        Some(ZSTD_MAGIC) => Box::new(zstd::Decoder::with_buffer(reader)?),
+        Some(LZ4_MAGIC) => Box::new(FrameDecoder::new(reader)),
+        _ => Box::new(reader),
+    })
+}
+
+impl<T: HashFunction> Publication<T> {
+    /// Exports every leaf as compressed newline delimited JSON, returning the underlying writer.
+    pub fn write_ndjson_compressed<W: Write>(
+        &self,
+        writer: W,
+        compression: Compression,
+    ) -> std::io::Result<W> {
+        let mut compressor = compression.writer(writer)?;
+        self.write_ndjson(&mut compressor)?;
+        compressor.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compression::decompress;
+    use crate::{Bytes, Compression, Keccak256, MerkleTreeBuilder, Visibility};
+    use std::io::Read;
+
+    #[test]
+    fn compresses_exports() {
+        let values: Vec<[u8; 4]> = (0..2000u32).map(|i| i.to_be_bytes()).collect();
+        let leaves: Vec<(&Bytes, Visibility)> = values
+            .iter()
+            .map(|v| (&v[..], Visibility::Public))
+            .collect();
+        let publication = MerkleTreeBuilder::<Keccak256>::new().build_tagged(&leaves);
+        let mut expected = vec![];
+        publication.write_ndjson(&mut expected).unwrap();
+
+        for compression in [Compression::Zstd(0), Compression::Lz4] {
+            let compressed = publication
+                .write_ndjson_compressed(vec![], compression)
+                .unwrap();
+            assert!(compressed.len() < expected.len() / 2, "{:?}", compression);
+
+            let mut decompressed = vec![];
+            decompress(&compressed[..])
+                .unwrap()
+                .read_to_end(&mut decompressed)
+                .unwrap();
+            assert_eq!(decompressed, expected);
+        }
+
+        // Uncompressed input is passed through
+        let mut passed = vec![];
+        decompress(&expected[..])
+            .unwrap()
+            .read_to_end(&mut passed)
+            .unwrap();
+        assert_eq!(passed, expected);
+    }
+}
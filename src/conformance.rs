@@ -0,0 +1,301 @@
+use crate::{Direction, HashFunction, MerkleProof, MerkleTreeBuilder, OddStrategy, PaddingLeaf};
+use std::fmt::Write;
+use std::path::Path;
+
+/// The numbers of leaves of the trees within a kit, covering the empty tree, complete trees and
+/// trees with odd nodes on several layers.
+const LEAF_COUNTS: [usize; 8] = [0, 1, 2, 3, 4, 5, 8, 13];
+
+/// Test vectors and a description of verification for a tree spec, for teams implementing
+/// verification in other languages. `vectors.json` holds trees of known leaves with their roots
+/// and the proof of every leaf, along with proofs which must be rejected, and `VERIFY.md`
+/// describes verification for the spec in pseudo-code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConformanceKit {
+    /// The spec the kit was generated for, in its string form.
+    pub spec: String,
+    /// The reference vectors, as JSON.
+    pub vectors: String,
+    /// The verification procedure, as markdown.
+    pub pseudocode: String,
+}
+
+impl ConformanceKit {
+    /// Writes `vectors.json` and `VERIFY.md` into `directory`, creating it if required.
+    pub fn write(&self, directory: impl AsRef<Path>) -> std::io::Result<()> {
+        let directory = directory.as_ref();
+        std::fs::create_dir_all(directory)?;
+        std::fs::write(directory.join("vectors.json"), &self.vectors)?;
+        std::fs::write(directory.join("VERIFY.md"), &self.pseudocode)
+    }
+}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Generates a conformance kit for trees built by this builder. Leaves are the UTF-8 strings
+    /// `leaf0`, `leaf1` and so on, or the hashes of those strings for pre-hashed leaves.
+    pub fn conformance_kit(&self) -> ConformanceKit {
+        let spec = self.spec();
+        let mut cases = vec![];
+        for count in LEAF_COUNTS {
+            let values: Vec<Vec<u8>> = (0..count)
+                .map(|i| {
+                    let value = format!("leaf{}", i).into_bytes();
+                    match spec.hash_leaves {
+                        true => value,
+                        false => T::hash(&value).into(),
+                    }
+                })
+                .collect();
+            let leaves: Vec<&[u8]> = values.iter().map(|v| &v[..]).collect();
+            let tree = self.build(&leaves);
+            let proofs: Vec<(T::Hash, MerkleProof<T>)> = tree.proofs().collect();
+
+            let mut vectors: Vec<String> = proofs
+                .iter()
+                .map(|(leaf, proof)| proof_json::<T>(*leaf, proof, true))
+                .collect();
+            // Each proof must be rejected for any other leaf
+            if let [(_, proof), (other, _), ..] = &proofs[..] {
+                vectors.push(proof_json::<T>(*other, proof, false));
+            }
+            cases.push(format!(
+                "    {{\n      \"values\": [{}],\n      \"leaves\": [{}],\n      \"root\": \"{}\",\n      \"proofs\": [{}]\n    }}",
+                values.iter().map(|v| quoted(v)).collect::<Vec<_>>().join(", "),
+                tree.leaves().iter().map(|l| quoted(l.as_ref())).collect::<Vec<_>>().join(", "),
+                hex(tree.root().as_ref()),
+                match vectors.is_empty() {
+                    true => String::new(),
+                    false => format!("\n{}\n      ", vectors.join(",\n")),
+                },
+            ));
+        }
+
+        let vectors = format!(
+            "{{\n  \"spec\": \"{}\",\n  \"hash\": \"{}\",\n  \"cases\": [\n{}\n  ]\n}}\n",
+            spec,
+            T::NAME,
+            cases.join(",\n")
+        );
+        ConformanceKit {
+            spec: spec.to_string(),
+            vectors,
+            pseudocode: self.pseudocode(),
+        }
+    }
+
+    fn pseudocode(&self) -> String {
+        let spec = self.spec();
+        let mut out = String::new();
+        let _ = writeln!(out, "# Verifying `{}` proofs\n", spec);
+        let _ = writeln!(
+            out,
+            "`H` is {}, and `||` concatenates byte strings. Hashes are {} bytes, and are written \
+             in `vectors.json` as `0x` prefixed hex, as are leaf values.\n",
+            T::NAME,
+            T::Hash::default().as_ref().len()
+        );
+
+        let _ = writeln!(out, "## Leaf hashes\n");
+        match spec.hash_leaves {
+            false => {
+                let _ = writeln!(
+                    out,
+                    "Leaves are supplied already hashed, so `leaf_hash(value) = value`.\n"
+                );
+            }
+            true => {
+                let steps: Vec<&str> = spec.canonicalize.iter().map(|s| s.name()).collect();
+                if !steps.is_empty() {
+                    let _ = writeln!(
+                        out,
+                        "Values are first canonicalized by the steps `{}`, in that order.\n",
+                        steps.join("`, `")
+                    );
+                }
+                let _ = match spec.leaf_prefix.is_empty() {
+                    true => writeln!(out, "```\nleaf_hash(value) = H(value)\n```\n"),
+                    false => writeln!(
+                        out,
+                        "```\nleaf_hash(value) = H({} || value)\n```\n",
+                        hex(&spec.leaf_prefix)
+                    ),
+                };
+                if spec.leaf_key.is_some() {
+                    let _ = writeln!(out, "Leaves are hashed under a secret key, which this kit omits, so its vectors use unkeyed leaf hashes.\n");
+                }
+            }
+        }
+
+        let _ = writeln!(out, "## Node hashes\n");
+        let prefix = match spec.node_prefix.is_empty() {
+            true => String::new(),
+            false => format!("{} || ", hex(&spec.node_prefix)),
+        };
+        let _ = writeln!(
+            out,
+            "```\nnode_hash(children) = H({}children[0] || children[1] || ...)\n```\n",
+            prefix
+        );
+        let _ = writeln!(
+            out,
+            "{}",
+            match spec.sort_pairs {
+                true => "The children of each node are sorted in ascending byte order before being hashed, so proofs carry no directions.\n",
+                false => "The children of each node are hashed in position order, so proofs carry the direction of each sibling.\n",
+            }
+        );
+
+        let _ = writeln!(out, "## Verification\n");
+        let (siblings, group) = match spec.sort_pairs {
+            true => ("siblings of the step", "sort(siblings ++ [node])"),
+            false => (
+                "siblings of the step with their directions",
+                "[siblings on the left] ++ [node] ++ [siblings on the right], in proof order",
+            ),
+        };
+        let _ = writeln!(
+            out,
+            "```\nverify(proof, leaf, root):\n    node = leaf\n    for each step of the proof, from the leaf upwards:\n        siblings = the {}\n        node = node_hash({})\n    return node == root\n```\n",
+            siblings, group
+        );
+        let _ = match spec.arity {
+            2 => writeln!(out, "Each step holds a single sibling, so proofs carry no sibling counts."),
+            arity => writeln!(out, "Nodes have up to {} children, so each step holds up to {} siblings, as given by the `sibling_counts` of the proof.", arity, arity - 1),
+        };
+        let _ = writeln!(
+            out,
+            "\n{}",
+            match spec.odd_strategy {
+                OddStrategy::Promote => "A node left without siblings at the end of a layer is promoted unchanged, so proofs of such nodes skip that step.",
+                OddStrategy::Duplicate => "A group left incomplete at the end of a layer is completed by repeating its last node, which then appears in proofs as a sibling.",
+                OddStrategy::ZeroPad => "A group left incomplete at the end of a layer is completed with hashes of zero bytes, which then appear in proofs as siblings.",
+            }
+        );
+        if let Some(padding) = &spec.padding {
+            let leaf = match padding {
+                PaddingLeaf::Zero => "a hash of zero bytes".to_string(),
+                PaddingLeaf::Custom(leaf) => format!("the leaf `{}`", hex(leaf)),
+            };
+            let _ = writeln!(
+                out,
+                "\nLeaves are padded to a power of {} with {}.",
+                spec.arity, leaf
+            );
+        }
+        if spec.sort_leaves {
+            let _ = writeln!(out, "\nLeaf hashes are sorted before the tree is built.");
+        }
+        if spec.deduplicate {
+            let _ = writeln!(
+                out,
+                "\nDuplicate leaves are removed before the tree is built."
+            );
+        }
+        let _ = writeln!(
+            out,
+            "\n## Vectors\n\nFor each case of `vectors.json`, hashing each of `values` gives `leaves`, and each of `proofs` must verify against `root` exactly when `valid` is true."
+        );
+        out
+    }
+}
+
+fn proof_json<T: HashFunction>(leaf: T::Hash, proof: &MerkleProof<T>, valid: bool) -> String {
+    let mut json = format!(
+        "        {{ \"leaf\": \"{}\", \"siblings\": [{}]",
+        hex(leaf.as_ref()),
+        proof
+            .iter()
+            .map(|h| quoted(h.as_ref()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if let Some(directions) = proof.directions() {
+        let directions: Vec<&str> = directions
+            .iter()
+            .map(|d| match d {
+                Direction::Left => "\"left\"",
+                Direction::Right => "\"right\"",
+            })
+            .collect();
+        let _ = write!(json, ", \"directions\": [{}]", directions.join(", "));
+    }
+    if let Some(counts) = proof.sibling_counts() {
+        let counts: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+        let _ = write!(json, ", \"sibling_counts\": [{}]", counts.join(", "));
+    }
+    let _ = write!(json, ", \"valid\": {} }}", valid);
+    json
+}
+
+fn hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn quoted(bytes: &[u8]) -> String {
+    format!("\"{}\"", hex(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{verify_proof, Direction, Keccak256, MerkleProof, MerkleTreeBuilder, TreeSpec};
+
+    #[test]
+    fn generates_kits() {
+        let decode = |value: &serde_json::Value| -> [u8; 32] {
+            let value = value.as_str().unwrap();
+            hex::decode(&value[2..]).unwrap().try_into().unwrap()
+        };
+        for spec in [
+            "keccak256/sorted/promote-odd/arity2",
+            "keccak256/ordered/dup-odd/arity3/leaf-prefix00/node-prefix01",
+        ] {
+            let spec = TreeSpec::parse(spec).unwrap();
+            let kit = MerkleTreeBuilder::<Keccak256>::from_spec(&spec)
+                .unwrap()
+                .conformance_kit();
+            assert!(kit
+                .pseudocode
+                .contains(&format!("# Verifying `{}` proofs", spec)));
+
+            // Every vector verifies as expected using only the kit
+            let vectors: serde_json::Value = serde_json::from_str(&kit.vectors).unwrap();
+            assert_eq!(vectors["spec"], spec.to_string());
+            let cases = vectors["cases"].as_array().unwrap();
+            assert_eq!(cases.len(), 8);
+            let mut rejected = 0;
+            for case in cases {
+                for proof in case["proofs"].as_array().unwrap() {
+                    let directions = proof.get("directions").map(|directions| {
+                        directions
+                            .as_array()
+                            .unwrap()
+                            .iter()
+                            .map(|d| match d.as_str() {
+                                Some("left") => Direction::Left,
+                                _ => Direction::Right,
+                            })
+                            .collect()
+                    });
+                    let counts = proof.get("sibling_counts").map(|counts| {
+                        serde_json::from_value::<Vec<usize>>(counts.clone()).unwrap()
+                    });
+                    let siblings = proof["siblings"].as_array().unwrap();
+                    let parsed = MerkleProof::<Keccak256>::new(
+                        siblings.iter().map(decode).collect(),
+                        directions,
+                        counts,
+                    );
+                    let valid = verify_proof(
+                        &spec,
+                        &parsed,
+                        decode(&proof["leaf"]),
+                        decode(&case["root"]),
+                    );
+                    assert_eq!(valid, proof["valid"].as_bool().unwrap());
+                    rejected += !valid as usize;
+                }
+            }
+            assert_eq!(rejected, 6);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{HashFunction, MerkleTree, OddStrategy};
+use std::fmt::{Display, Formatter};
+
+/// A proof that the tree of `old_size` leaves is a prefix of the tree of `new_size` leaves, as
+/// defined by RFC 6962.
+pub struct ConsistencyProof<T: HashFunction> {
+    old_size: usize,
+    new_size: usize,
+    hashes: Vec<T::Hash>,
+}
+
+impl<T: HashFunction> ConsistencyProof<T> {
+    pub fn new(old_size: usize, new_size: usize, hashes: Vec<T::Hash>) -> Self {
+        Self {
+            old_size,
+            new_size,
+            hashes,
+        }
+    }
+
+    pub fn old_size(&self) -> usize {
+        self.old_size
+    }
+
+    pub fn new_size(&self) -> usize {
+        self.new_size
+    }
+
+    pub fn hashes(&self) -> &[T::Hash] {
+        &self.hashes
+    }
+}
+
+impl<T: HashFunction> Clone for ConsistencyProof<T> {
+    fn clone(&self) -> Self {
+        Self {
+            old_size: self.old_size,
+            new_size: self.new_size,
+            hashes: self.hashes.clone(),
+        }
+    }
+}
+
+impl<T: HashFunction> std::fmt::Debug for ConsistencyProof<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConsistencyProof")
+            .field("old_size", &self.old_size)
+            .field("new_size", &self.new_size)
+            .field(
+                "hashes",
+                &self
+                    .hashes
+                    .iter()
+                    .map(|hash| format!("0x{}", hex::encode(hash)))
+                    .collect::<Vec<String>>(),
+            )
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConsistencyError {
+    /// Consistency proofs require a binary tree with leaves in insertion order, positional pairs
+    /// and promoted odd nodes, as created by `MerkleTree::new_ordered`.
+    UnorderedTree,
+    /// The sizes must satisfy `0 < old <= new <= leaves`.
+    InvalidSizes { old: usize, new: usize },
+}
+
+impl Display for ConsistencyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConsistencyError::UnorderedTree => {
+                write!(f, "consistency proofs require an ordered binary tree")
+            }
+            ConsistencyError::InvalidSizes { old, new } => {
+                write!(f, "invalid tree sizes {} and {}", old, new)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConsistencyError {}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Generates a proof that the tree formed by the first `old_size` leaves is a prefix of the
+    /// tree formed by the first `new_size` leaves, as for a transparency log.
+    pub fn consistency_proof(
+        &self,
+        old_size: usize,
+        new_size: usize,
+    ) -> Result<ConsistencyProof<T>, ConsistencyError> {
+        if !self.is_ordered() {
+            return Err(ConsistencyError::UnorderedTree);
+        }
+        if old_size == 0 || old_size > new_size || new_size > self.leaves().len() {
+            return Err(ConsistencyError::InvalidSizes {
+                old: old_size,
+                new: new_size,
+            });
+        }
+
+        let mut hashes = vec![];
+        self.subproof(old_size, 0, new_size, true, &mut hashes);
+        Ok(ConsistencyProof::new(old_size, new_size, hashes))
+    }
+
+    /// Verifies that `old_root` is the root of a prefix of the tree with `new_root`, following the
+    /// verification algorithm of RFC 9162.
+    pub fn verify_consistency(
+        &self,
+        old_root: impl Into<T::Hash>,
+        new_root: impl Into<T::Hash>,
+        proof: &ConsistencyProof<T>,
+    ) -> bool {
+        let (old_root, new_root) = (old_root.into(), new_root.into());
+        let (old_size, new_size) = (proof.old_size, proof.new_size);
+        if !self.is_ordered() || old_size == 0 || old_size > new_size {
+            return false;
+        }
+        if old_size == new_size {
+            return proof.hashes.is_empty() && old_root == new_root;
+        }
+
+        // A complete old tree is itself a node of the new tree, so is omitted from the proof
+        let mut path = proof.hashes.clone();
+        if old_size.is_power_of_two() {
+            path.insert(0, old_root);
+        }
+        let (first, rest) = match path.split_first() {
+            Some(split) => split,
+            None => return false,
+        };
+
+        let (mut old_node, mut new_node) = (old_size - 1, new_size - 1);
+        while old_node & 1 == 1 {
+            old_node >>= 1;
+            new_node >>= 1;
+        }
+
+        let (mut old_hash, mut new_hash) = (*first, *first);
+        for hash in rest {
+            if new_node == 0 {
+                return false;
+            }
+            if old_node & 1 == 1 || old_node == new_node {
+                old_hash = MerkleTree::<T>::hash_children(&self.spec, &[*hash, old_hash]);
+                new_hash = MerkleTree::<T>::hash_children(&self.spec, &[*hash, new_hash]);
+                while old_node & 1 == 0 && old_node != 0 {
+                    old_node >>= 1;
+                    new_node >>= 1;
+                }
+            } else {
+                new_hash = MerkleTree::<T>::hash_children(&self.spec, &[new_hash, *hash]);
+            }
+            old_node >>= 1;
+            new_node >>= 1;
+        }
+
+        new_node == 0 && old_hash == old_root && new_hash == new_root
+    }
+
+    /// Whether the tree is constructed as RFC 6962 requires.
+    fn is_ordered(&self) -> bool {
+        !self.spec.sort_leaves
+            && !self.spec.sort_pairs
+            && self.spec.arity == 2
+            && self.spec.odd_strategy == OddStrategy::Promote
+    }
+
+    /// Adds the nodes proving that the first `old_size` leaves of the range `start..end` form a
+    /// prefix of it, where `complete` indicates the old tree is known to the verifier.
+    fn subproof(
+        &self,
+        old_size: usize,
+        start: usize,
+        end: usize,
+        complete: bool,
+        hashes: &mut Vec<T::Hash>,
+    ) {
+        let size = end - start;
+        if old_size == size {
+            if !complete {
+                hashes.push(self.range_hash(start, end));
+            }
+            return;
+        }
+
+        let split = split(size);
+        if old_size <= split {
+            self.subproof(old_size, start, start + split, complete, hashes);
+            hashes.push(self.range_hash(start + split, end));
+        } else {
+            self.subproof(old_size - split, start + split, end, false, hashes);
+            hashes.push(self.range_hash(start, start + split));
+        }
+    }
+
+    /// The root of the tree formed by the leaves `start..end`, reusing the nodes of complete
+    /// subtrees.
+    fn range_hash(&self, start: usize, end: usize) -> T::Hash {
+        let size = end - start;
+        if size.is_power_of_two() && start.is_multiple_of(size) {
+            let layer = size.trailing_zeros() as usize;
+            return self.layers[layer][start >> layer];
+        }
+
+        let split = split(size);
+        MerkleTree::<T>::hash_children(
+            &self.spec,
+            &[
+                self.range_hash(start, start + split),
+                self.range_hash(start + split, end),
+            ],
+        )
+    }
+}
+
+/// The largest power of two smaller than `size`, where the tree of `size` leaves is split.
+fn split(size: usize) -> usize {
+    1 << (usize::BITS - 1 - (size - 1).leading_zeros())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, ConsistencyError, Keccak256, MerkleTree, MerkleTreeBuilder};
+
+    fn tree(size: u8) -> MerkleTree<Keccak256> {
+        let values: Vec<[u8; 1]> = (0..size).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(false)
+            .sort_pairs(false)
+            .domain_separation(true)
+            .build(&leaves)
+    }
+
+    #[test]
+    fn proves_consistency() {
+        let full = tree(20);
+        for new in 1..=20 {
+            let new_tree = tree(new);
+            let new_root = new_tree.root();
+            for old in 1..=new {
+                let old_root = tree(old).root();
+                let proof = full.consistency_proof(old as usize, new as usize).unwrap();
+                assert!(full.verify_consistency(old_root, new_root, &proof));
+                assert_eq!(
+                    new_tree
+                        .consistency_proof(old as usize, new as usize)
+                        .unwrap()
+                        .hashes(),
+                    proof.hashes()
+                );
+
+                // Any other root fails
+                if old < new {
+                    assert!(!full.verify_consistency(new_root, new_root, &proof));
+                    assert!(!full.verify_consistency(old_root, old_root, &proof));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_rfc_example() {
+        // Example from RFC 6962 section 2.1.3, with a tree of seven leaves
+        let tree = tree(7);
+        let layers = &tree.layers;
+        let (c, d, g) = (layers[0][2], layers[0][3], layers[1][0]);
+        let (i, j, k, l) = (layers[1][2], layers[1][3], layers[2][0], layers[2][1]);
+        assert_eq!(
+            tree.consistency_proof(3, 7).unwrap().hashes(),
+            &[c, d, g, l]
+        );
+        assert_eq!(tree.consistency_proof(4, 7).unwrap().hashes(), &[l]);
+        assert_eq!(tree.consistency_proof(6, 7).unwrap().hashes(), &[i, j, k]);
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        let four = tree(4);
+        assert_eq!(
+            four.consistency_proof(0, 4).err(),
+            Some(ConsistencyError::InvalidSizes { old: 0, new: 4 })
+        );
+        assert!(four.consistency_proof(3, 2).is_err());
+        assert!(four.consistency_proof(2, 5).is_err());
+        assert_eq!(
+            MerkleTree::<Keccak256>::new(&[b"a", b"b"])
+                .consistency_proof(1, 2)
+                .err(),
+            Some(ConsistencyError::UnorderedTree)
+        );
+
+        // Tampered proofs are rejected
+        let mut proof = four.consistency_proof(3, 4).unwrap();
+        assert!(four.verify_consistency(tree(3).root(), four.root(), &proof));
+        proof.hashes.pop();
+        assert!(!four.verify_consistency(tree(3).root(), four.root(), &proof));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{Bytes, HashFunction, MerkleTree, MerkleTreeBuilder};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// The leaves supplied more than once when building a tree, so the source of a dataset can be
+/// corrected rather than the tree silently holding fewer leaves than supplied.
+pub struct DedupReport<T: HashFunction> {
+    /// Each duplicated leaf, in order of first occurrence.
+    pub duplicates: Vec<Duplicate<T>>,
+    /// The number of leaves removed, being zero unless the tree was built deduplicated.
+    pub removed: usize,
+}
+
+/// A leaf supplied more than once.
+pub struct Duplicate<T: HashFunction> {
+    pub leaf: T::Hash,
+    pub value: Vec<u8>,
+    /// The position of each occurrence among the supplied leaves, the first being retained.
+    pub indices: Vec<usize>,
+    /// The source row of each occurrence, if supplied by `DedupReport::with_rows`.
+    pub rows: Option<Vec<usize>>,
+}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Builds a tree as `build` does, also reporting every leaf supplied more than once. Duplicates
+    /// are reported whether or not the builder removes them.
+    pub fn build_with_dedup_report(&self, leaves: &[&Bytes]) -> (MerkleTree<T>, DedupReport<T>) {
+        let hashes: Vec<T::Hash> = leaves.iter().map(|l| self.hash_leaf(l)).collect();
+        let mut occurrences: BTreeMap<T::Hash, Vec<usize>> = BTreeMap::new();
+        for (index, hash) in hashes.iter().enumerate() {
+            occurrences.entry(*hash).or_default().push(index);
+        }
+
+        let mut duplicates: Vec<Duplicate<T>> = occurrences
+            .into_iter()
+            .filter(|(_, indices)| indices.len() > 1)
+            .map(|(leaf, indices)| Duplicate {
+                leaf,
+                value: leaves[indices[0]].to_vec(),
+                indices,
+                rows: None,
+            })
+            .collect();
+        duplicates.sort_by_key(|d| d.indices[0]);
+        let removed = match self.spec().deduplicate {
+            true => duplicates.iter().map(|d| d.indices.len() - 1).sum(),
+            false => 0,
+        };
+
+        let tree = MerkleTree::build_from_hashes(self.spec().clone(), hashes);
+        (
+            tree,
+            DedupReport {
+                duplicates,
+                removed,
+            },
+        )
+    }
+}
+
+impl<T: HashFunction> DedupReport<T> {
+    pub fn is_empty(&self) -> bool {
+        self.duplicates.is_empty()
+    }
+
+    /// Records the source row of each occurrence, where `rows[i]` is the row the leaf at position
+    /// `i` was read from, such as a line number of the file the leaves were ingested from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rows` is shorter than the leaves supplied.
+    pub fn with_rows(mut self, rows: &[usize]) -> Self {
+        for duplicate in &mut self.duplicates {
+            duplicate.rows = Some(duplicate.indices.iter().map(|i| rows[*i]).collect());
+        }
+        self
+    }
+}
+
+/// Lists each duplicate on its own line, with the rows it occurs at if known and otherwise its
+/// positions.
+impl<T: HashFunction> Display for DedupReport<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for duplicate in &self.duplicates {
+            let (label, positions) = match &duplicate.rows {
+                Some(rows) => ("rows", rows),
+                None => ("indices", &duplicate.indices),
+            };
+            let positions: Vec<String> = positions.iter().map(|p| p.to_string()).collect();
+            writeln!(
+                f,
+                "0x{} at {} {}",
+                hex::encode(duplicate.leaf),
+                label,
+                positions.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleTreeBuilder};
+
+    #[test]
+    fn reports_duplicates() {
+        let leaves: Vec<&Bytes> = ["a", "b", "a", "c", "b", "a"]
+            .iter()
+            .map(|x| x.as_bytes())
+            .collect();
+        let builder = MerkleTreeBuilder::<Keccak256>::new().deduplicate(true);
+        let (tree, report) = builder.build_with_dedup_report(&leaves);
+        assert_eq!(tree.root(), builder.build(&leaves).root());
+        assert_eq!(tree.leaves().len(), 3);
+        assert_eq!(report.removed, 3);
+        let duplicates: Vec<(&[u8], &[usize])> = report
+            .duplicates
+            .iter()
+            .map(|d| (d.value.as_slice(), d.indices.as_slice()))
+            .collect();
+        assert_eq!(
+            duplicates,
+            [(&b"a"[..], &[0, 2, 5][..]), (&b"b"[..], &[1, 4][..])]
+        );
+
+        // Rows of the source dataset are reported instead of positions once supplied
+        let rows: Vec<usize> = (0..leaves.len()).map(|i| i + 2).collect();
+        let report = report.with_rows(&rows);
+        assert_eq!(report.duplicates[1].rows, Some(vec![3, 6]));
+        assert!(report
+            .to_string()
+            .lines()
+            .all(|line| line.contains(" at rows ")));
+
+        // Duplicates are reported but kept unless deduplicating
+        let (tree, report) = MerkleTreeBuilder::<Keccak256>::new().build_with_dedup_report(&leaves);
+        assert_eq!((tree.leaves().len(), report.removed), (6, 0));
+        assert_eq!(report.duplicates.len(), 2);
+        let (_, report) = builder.build_with_dedup_report(&leaves[..2]);
+        assert!(report.is_empty());
+    }
+}
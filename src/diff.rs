@@ -0,0 +1,114 @@
+use crate::{HashFunction, MerkleTree, Store};
+use std::collections::BTreeSet;
+
+/// The leaves present in only one of two trees, as found by `MerkleTree::diff`.
+pub struct TreeDiff<T: HashFunction> {
+    /// Leaves of the other tree which are not in this tree, in hash order.
+    pub added: Vec<T::Hash>,
+    /// Leaves of this tree which are not in the other tree, in hash order.
+    pub removed: Vec<T::Hash>,
+}
+
+impl<T: HashFunction> TreeDiff<T> {
+    /// Whether both trees have the same leaves.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// Compares the leaves of two trees built with the same spec, such as successive snapshots of
+    /// an allowlist. Both trees are walked down from the root, skipping subtrees whose hashes are
+    /// identical at the same position, so only the leaves below changed nodes are compared.
+    pub fn diff<O: Store<T::Hash>>(&self, other: &MerkleTree<T, O>) -> TreeDiff<T> {
+        let (mut ours, mut theirs) = (BTreeSet::new(), BTreeSet::new());
+        let height = self.layers.layers().max(other.layers.layers());
+        if height > 0 {
+            self.walk(other, height - 1, 0, &mut ours, &mut theirs);
+        }
+
+        // Leaves below changed nodes may have moved rather than changed, or occur again in an
+        // identical subtree, so each is looked up in the other tree before being reported
+        TreeDiff {
+            added: theirs
+                .difference(&ours)
+                .filter(|leaf| self.index_of(**leaf).is_none())
+                .copied()
+                .collect(),
+            removed: ours
+                .difference(&theirs)
+                .filter(|leaf| other.index_of(**leaf).is_none())
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Collects the leaves below the node at `index` of `layer` in either tree, unless the node is
+    /// identical in both.
+    fn walk<O: Store<T::Hash>>(
+        &self,
+        other: &MerkleTree<T, O>,
+        layer: usize,
+        index: usize,
+        ours: &mut BTreeSet<T::Hash>,
+        theirs: &mut BTreeSet<T::Hash>,
+    ) {
+        let (node, other_node) = (
+            self.layers.get(layer, index),
+            other.layers.get(layer, index),
+        );
+        if node.is_some() && node == other_node {
+            return;
+        }
+        if layer == 0 {
+            ours.extend(node);
+            theirs.extend(other_node);
+            return;
+        }
+
+        let arity = self.spec.arity;
+        let children = index * arity..(index + 1) * arity;
+        let len = self
+            .layers
+            .layer_len(layer - 1)
+            .max(other.layers.layer_len(layer - 1));
+        for child in children.start..children.end.min(len) {
+            self.walk(other, layer - 1, child, ours, theirs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, HashFunction, Keccak256, MerkleTree};
+
+    #[test]
+    fn diffs_trees() {
+        let values: Vec<[u8; 2]> = (0..100u16).map(|i| i.to_le_bytes()).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for build in [
+            MerkleTree::<Keccak256>::new,
+            MerkleTree::<Keccak256>::new_ordered,
+        ] {
+            let tree = build(&leaves);
+            assert!(tree.diff(&tree).is_empty());
+
+            let mut changed = build(&leaves);
+            changed.push(b"added");
+            let index = changed.index_of(Keccak256::hash(&values[40])).unwrap();
+            changed.remove(index);
+
+            let diff = tree.diff(&changed);
+            assert_eq!(diff.added, [Keccak256::hash(b"added")]);
+            assert_eq!(diff.removed, [Keccak256::hash(&values[40])]);
+            let reverse = changed.diff(&tree);
+            assert_eq!((reverse.added, reverse.removed), (diff.removed, diff.added));
+        }
+
+        // Trees of different heights are compared all the same
+        let small = MerkleTree::<Keccak256>::new(&leaves[..3]);
+        let large = MerkleTree::<Keccak256>::new(&leaves);
+        assert_eq!(small.diff(&large).added.len(), 97);
+        assert!(small.diff(&large).removed.is_empty());
+    }
+}
@@ -0,0 +1,332 @@
+use crate::{verify_proof, HashFunction, MerkleError, MerkleProof, MerkleTree, TreeSpec};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Payload type of envelopes holding an inclusion proof.
+pub const PROOF_PAYLOAD_TYPE: &str = "application/vnd.merkle-tree.inclusion-proof+json";
+
+/// Signs envelopes, such as with a key held by a signing service.
+pub trait EnvelopeSigner {
+    /// An identifier of the key, which may be empty.
+    fn key_id(&self) -> String;
+
+    fn sign(&self, message: &[u8]) -> Vec<u8>;
+}
+
+/// Checks the signatures of envelopes against trusted keys.
+pub trait EnvelopeVerifier {
+    /// Whether `signature` is a valid signature of `message` by the key identified by `key_id`.
+    fn verify(&self, key_id: &str, message: &[u8], signature: &[u8]) -> bool;
+}
+
+impl EnvelopeSigner for SigningKey {
+    fn key_id(&self) -> String {
+        hex::encode(self.verifying_key().as_bytes())
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        Signer::sign(self, message).to_bytes().to_vec()
+    }
+}
+
+/// Accepts signatures by this key, whatever their key ID.
+impl EnvelopeVerifier for VerifyingKey {
+    fn verify(&self, _: &str, message: &[u8], signature: &[u8]) -> bool {
+        match ed25519_dalek::Signature::from_slice(signature) {
+            Ok(signature) => Verifier::verify(self, message, &signature).is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+/// A signed payload in the Dead Simple Signing Envelope format used by Sigstore and in-toto.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Envelope {
+    pub payload_type: String,
+    pub payload: Vec<u8>,
+    pub signatures: Vec<EnvelopeSignature>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvelopeSignature {
+    pub key_id: String,
+    pub signature: Vec<u8>,
+}
+
+/// The statement signed within an envelope: that `leaf` is within the tree with `root`.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct InclusionStatement<T: HashFunction> {
+    pub spec: TreeSpec,
+    #[serde(with = "hash")]
+    pub root: T::Hash,
+    #[serde(with = "hash")]
+    pub leaf: T::Hash,
+    pub proof: MerkleProof<T>,
+}
+
+impl Envelope {
+    /// Signs `payload` by the pre-authentication encoding of DSSE.
+    pub fn sign(payload_type: &str, payload: Vec<u8>, signer: &impl EnvelopeSigner) -> Self {
+        let signature = signer.sign(&pae(payload_type, &payload));
+        Self {
+            payload_type: payload_type.to_string(),
+            payload,
+            signatures: vec![EnvelopeSignature {
+                key_id: signer.key_id(),
+                signature,
+            }],
+        }
+    }
+
+    /// Whether any signature of the envelope is accepted by the verifier.
+    pub fn is_signed_by(&self, verifier: &impl EnvelopeVerifier) -> bool {
+        let message = pae(&self.payload_type, &self.payload);
+        self.signatures
+            .iter()
+            .any(|s| verifier.verify(&s.key_id, &message, &s.signature))
+    }
+
+    /// Checks the envelope is signed by the verifier and holds a valid inclusion proof, returning
+    /// the statement it proves.
+    pub fn verify_inclusion<T: HashFunction>(
+        &self,
+        verifier: &impl EnvelopeVerifier,
+    ) -> Result<InclusionStatement<T>, DsseError> {
+        if self.payload_type != PROOF_PAYLOAD_TYPE {
+            return Err(DsseError::PayloadType(self.payload_type.clone()));
+        }
+        if !self.is_signed_by(verifier) {
+            return Err(DsseError::Signature);
+        }
+        let statement: InclusionStatement<T> =
+            serde_json::from_slice(&self.payload).map_err(|e| DsseError::Payload(e.to_string()))?;
+        if statement.spec.hash != T::NAME {
+            return Err(DsseError::Payload(format!(
+                "statement uses hash function '{}'",
+                statement.spec.hash
+            )));
+        }
+        match verify_proof(
+            &statement.spec,
+            &statement.proof,
+            statement.leaf,
+            statement.root,
+        ) {
+            true => Ok(statement),
+            false => Err(DsseError::Proof),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&EnvelopeRepr::from(self)).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, DsseError> {
+        let repr: EnvelopeRepr =
+            serde_json::from_str(json).map_err(|e| DsseError::Json(e.to_string()))?;
+        let decode = |value: &str| {
+            STANDARD
+                .decode(value)
+                .map_err(|e| DsseError::Json(e.to_string()))
+        };
+        Ok(Self {
+            payload_type: repr.payload_type,
+            payload: decode(&repr.payload)?,
+            signatures: repr
+                .signatures
+                .iter()
+                .map(|s| {
+                    Ok(EnvelopeSignature {
+                        key_id: s.keyid.clone(),
+                        signature: decode(&s.sig)?,
+                    })
+                })
+                .collect::<Result<_, DsseError>>()?,
+        })
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Generates a proof for a leaf, signed within an envelope for attestation pipelines.
+    pub fn proof_envelope(
+        &self,
+        leaf: impl Into<T::Hash>,
+        signer: &impl EnvelopeSigner,
+    ) -> Result<Envelope, MerkleError> {
+        let leaf = leaf.into();
+        let statement = InclusionStatement {
+            spec: self.spec.clone(),
+            root: self.try_root()?,
+            leaf,
+            proof: self.try_proof(leaf)?,
+        };
+        let payload = serde_json::to_vec(&statement).unwrap_or_default();
+        Ok(Envelope::sign(PROOF_PAYLOAD_TYPE, payload, signer))
+    }
+}
+
+/// The pre-authentication encoding of DSSE, binding the payload type to the payload.
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut message = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    message.extend_from_slice(payload);
+    message
+}
+
+/// The JSON form of an envelope, with base64 encoded payload and signatures.
+#[derive(Serialize, Deserialize)]
+struct EnvelopeRepr {
+    #[serde(rename = "payloadType")]
+    payload_type: String,
+    payload: String,
+    signatures: Vec<SignatureRepr>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SignatureRepr {
+    #[serde(default)]
+    keyid: String,
+    sig: String,
+}
+
+impl From<&Envelope> for EnvelopeRepr {
+    fn from(envelope: &Envelope) -> Self {
+        Self {
+            payload_type: envelope.payload_type.clone(),
+            payload: STANDARD.encode(&envelope.payload),
+            signatures: envelope
+                .signatures
+                .iter()
+                .map(|s| SignatureRepr {
+                    keyid: s.key_id.clone(),
+                    sig: STANDARD.encode(&s.signature),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Serializes a single hash as the `0x` prefixed hex strings used for lists of hashes.
+mod hash {
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<H: AsRef<[u8]>, S: Serializer>(
+        hash: &H,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        crate::serialization::hashes::serialize(std::slice::from_ref(hash), serializer)
+    }
+
+    pub fn deserialize<'de, H, D>(deserializer: D) -> Result<H, D::Error>
+    where
+        H: Default + AsRef<[u8]> + TryFrom<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        let mut hashes = crate::serialization::hashes::deserialize::<H, D>(deserializer)?;
+        match hashes.len() {
+            1 => Ok(hashes.remove(0)),
+            _ => Err(serde::de::Error::custom("expected a single hash")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DsseError {
+    /// The envelope is not valid JSON or its fields are not valid base64.
+    Json(String),
+    /// The envelope does not hold an inclusion proof.
+    PayloadType(String),
+    /// No signature of the envelope is accepted by the verifier.
+    Signature,
+    /// The payload is not a valid inclusion statement.
+    Payload(String),
+    /// The proof does not recompute the root from the leaf.
+    Proof,
+}
+
+impl Display for DsseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DsseError::Json(e) => write!(f, "invalid envelope: {}", e),
+            DsseError::PayloadType(t) => write!(f, "unexpected payload type '{}'", t),
+            DsseError::Signature => write!(f, "envelope is not signed by a trusted key"),
+            DsseError::Payload(e) => write!(f, "invalid inclusion statement: {}", e),
+            DsseError::Proof => write!(f, "inclusion proof does not match the root"),
+        }
+    }
+}
+
+impl std::error::Error for DsseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{pae, DsseError, Envelope, EnvelopeSigner, PROOF_PAYLOAD_TYPE};
+    use crate::{Bytes, Keccak256, MerkleTree};
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn encodes_pae() {
+        // Example from the DSSE specification
+        assert_eq!(
+            pae("http://example.com/HelloWorld", b"hello world"),
+            b"DSSEv1 29 http://example.com/HelloWorld 11 hello world"
+        );
+    }
+
+    #[test]
+    fn verifies_signed_proofs() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Keccak256>::new_ordered(&leaves);
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let leaf = tree.leaves()[2];
+
+        let envelope = tree.proof_envelope(leaf, &key).unwrap();
+        assert_eq!(envelope.signatures[0].key_id, key.key_id());
+        let envelope = Envelope::from_json(&envelope.to_json()).unwrap();
+        let statement = envelope
+            .verify_inclusion::<Keccak256>(&key.verifying_key())
+            .unwrap();
+        assert_eq!((statement.leaf, statement.root), (leaf, tree.root()));
+        assert_eq!(&statement.spec, tree.spec());
+
+        // Envelopes must be signed by a trusted key and hold a valid proof
+        let other = SigningKey::from_bytes(&[8; 32]).verifying_key();
+        assert_eq!(
+            envelope.verify_inclusion::<Keccak256>(&other).err(),
+            Some(DsseError::Signature)
+        );
+        let mut tampered = envelope.clone();
+        tampered.payload[10] ^= 1;
+        assert_eq!(
+            tampered
+                .verify_inclusion::<Keccak256>(&key.verifying_key())
+                .err(),
+            Some(DsseError::Signature)
+        );
+        let forged = tree.proof(tree.leaves()[0]);
+        let payload = serde_json::to_vec(&super::InclusionStatement::<Keccak256> {
+            spec: tree.spec().clone(),
+            root: tree.root(),
+            leaf,
+            proof: forged,
+        })
+        .unwrap();
+        let forged = Envelope::sign(PROOF_PAYLOAD_TYPE, payload, &key);
+        assert_eq!(
+            forged
+                .verify_inclusion::<Keccak256>(&key.verifying_key())
+                .err(),
+            Some(DsseError::Proof)
+        );
+        assert!(tree.proof_envelope([0; 32], &key).is_err());
+    }
+}
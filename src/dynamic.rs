@@ -0,0 +1,255 @@
+use crate::{
+    Bytes, Direction, HashFunction, Keccak256, MerkleProof, MerkleTree, MerkleTreeBuilder,
+    SpecError, TreeSpec,
+};
+
+/// A hash function chosen at runtime, such as by the spec of a request, rather than as a type
+/// parameter. Hashes are boxed bytes of the length of the hash.
+///
+/// Every `HashFunction` is a `DynHashFunction`, so hash functions outside the crate are used in
+/// the same way as those it provides.
+pub trait DynHashFunction {
+    /// Name identifying the hash function within a `TreeSpec`.
+    fn name(&self) -> &'static str;
+
+    /// Hashes a value, named apart from `HashFunction::hash` so that both traits may be imported
+    /// together.
+    fn hash_dyn(&self, value: &Bytes) -> Box<[u8]>;
+
+    /// Builds a tree with the hash function, failing if the spec names another hash function.
+    fn build(&self, spec: &TreeSpec, leaves: &[&Bytes]) -> Result<DynMerkleTree, SpecError>;
+
+    /// Verifies a proof for a tree with the supplied spec, as `verify_proof` does. Hashes of the
+    /// wrong length fail verification.
+    fn verify(&self, spec: &TreeSpec, proof: &DynMerkleProof, leaf: &[u8], root: &[u8]) -> bool;
+}
+
+impl<T: HashFunction + 'static> DynHashFunction for T {
+    fn name(&self) -> &'static str {
+        T::NAME
+    }
+
+    fn hash_dyn(&self, value: &Bytes) -> Box<[u8]> {
+        T::hash(value).as_ref().into()
+    }
+
+    fn build(&self, spec: &TreeSpec, leaves: &[&Bytes]) -> Result<DynMerkleTree, SpecError> {
+        let tree = MerkleTreeBuilder::<T>::from_spec(spec)?.build(leaves);
+        Ok(DynMerkleTree {
+            tree: Box::new(tree),
+        })
+    }
+
+    fn verify(&self, spec: &TreeSpec, proof: &DynMerkleProof, leaf: &[u8], root: &[u8]) -> bool {
+        verify::<T>(spec, proof, leaf, root).unwrap_or(false)
+    }
+}
+
+/// The hash function of the crate with the given name, if its feature is enabled.
+pub fn hash_function(name: &str) -> Option<Box<dyn DynHashFunction>> {
+    Some(match name {
+        Keccak256::NAME => Box::new(Keccak256::default()),
+        #[cfg(feature = "ripemd160")]
+        crate::Ripemd160::NAME => Box::new(crate::Ripemd160::default()),
+        #[cfg(feature = "sha1")]
+        crate::Sha1::NAME => Box::new(crate::Sha1::default()),
+        #[cfg(feature = "sha256")]
+        crate::Sha256::NAME => Box::new(crate::Sha256::default()),
+        #[cfg(feature = "sha256")]
+        crate::DoubleSha256::NAME => Box::new(crate::DoubleSha256::default()),
+        #[cfg(feature = "poseidon")]
+        "poseidon-bn254" => Box::new(crate::Poseidon::<ark_bn254::Fr>::default()),
+        #[cfg(feature = "poseidon")]
+        "poseidon-bls12-381" => Box::new(crate::Poseidon::<ark_bls12_381::Fr>::default()),
+        _ => return None,
+    })
+}
+
+/// A proof from a `DynMerkleTree`, with each sibling as boxed bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DynMerkleProof {
+    pub hashes: Vec<Box<[u8]>>,
+    pub directions: Option<Vec<Direction>>,
+    pub sibling_counts: Option<Vec<usize>>,
+    /// The index of the leaf the proof is for, if known.
+    pub index: Option<usize>,
+}
+
+impl<T: HashFunction> From<MerkleProof<T>> for DynMerkleProof {
+    fn from(proof: MerkleProof<T>) -> Self {
+        Self {
+            hashes: proof.iter().map(|hash| hash.as_ref().into()).collect(),
+            directions: proof.directions().map(|d| d.to_vec()),
+            sibling_counts: proof.sibling_counts().map(|c| c.to_vec()),
+            index: proof.index(),
+        }
+    }
+}
+
+impl DynMerkleProof {
+    /// The proof typed by its hash function, or `None` if a sibling is not the length of its
+    /// hashes.
+    pub fn typed<T: HashFunction>(&self) -> Option<MerkleProof<T>> {
+        let hashes = self
+            .hashes
+            .iter()
+            .map(|hash| T::Hash::try_from(hash.to_vec()).ok())
+            .collect::<Option<_>>()?;
+        let proof = MerkleProof::new(hashes, self.directions.clone(), self.sibling_counts.clone());
+        Some(match self.index {
+            Some(index) => proof.with_index(index),
+            None => proof,
+        })
+    }
+}
+
+/// A tree whose hash function is chosen at runtime, offering the API of `MerkleTree` with hashes
+/// as boxed bytes.
+pub struct DynMerkleTree {
+    tree: Box<dyn ErasedTree>,
+}
+
+impl DynMerkleTree {
+    /// Builds a tree with the default spec of the hash function.
+    pub fn new(hash_function: &dyn DynHashFunction, leaves: &[&Bytes]) -> Self {
+        match hash_function.build(&TreeSpec::new(hash_function.name()), leaves) {
+            Ok(tree) => tree,
+            Err(_) => unreachable!("default spec names the hash function"),
+        }
+    }
+
+    /// Builds a tree as configured by a spec, with the hash function of the crate it names.
+    pub fn from_spec(spec: &TreeSpec, leaves: &[&Bytes]) -> Result<Self, SpecError> {
+        hash_function(&spec.hash)
+            .ok_or_else(|| SpecError::UnknownHash(spec.hash.clone()))?
+            .build(spec, leaves)
+    }
+
+    pub fn spec(&self) -> &TreeSpec {
+        self.tree.spec()
+    }
+
+    pub fn root(&self) -> Box<[u8]> {
+        self.tree.root()
+    }
+
+    pub fn leaves(&self) -> Vec<Box<[u8]>> {
+        self.tree.leaves()
+    }
+
+    /// Generates a proof for a leaf, which is empty if the leaf is not in the tree.
+    pub fn proof(&self, leaf: &[u8]) -> DynMerkleProof {
+        self.tree.proof(leaf)
+    }
+
+    pub fn verify(&self, proof: &DynMerkleProof, leaf: &[u8], root: &[u8]) -> bool {
+        self.tree.verify(proof, leaf, root)
+    }
+
+    /// Appends a leaf to the tree, as `MerkleTree::push` does.
+    pub fn push(&mut self, leaf: &Bytes) {
+        self.tree.push(leaf)
+    }
+}
+
+/// A `MerkleTree` with its hash function erased.
+trait ErasedTree {
+    fn spec(&self) -> &TreeSpec;
+    fn root(&self) -> Box<[u8]>;
+    fn leaves(&self) -> Vec<Box<[u8]>>;
+    fn proof(&self, leaf: &[u8]) -> DynMerkleProof;
+    fn verify(&self, proof: &DynMerkleProof, leaf: &[u8], root: &[u8]) -> bool;
+    fn push(&mut self, leaf: &Bytes);
+}
+
+impl<T: HashFunction> ErasedTree for MerkleTree<T> {
+    fn spec(&self) -> &TreeSpec {
+        MerkleTree::spec(self)
+    }
+
+    fn root(&self) -> Box<[u8]> {
+        MerkleTree::root(self).as_ref().into()
+    }
+
+    fn leaves(&self) -> Vec<Box<[u8]>> {
+        MerkleTree::leaves(self)
+            .iter()
+            .map(|leaf| leaf.as_ref().into())
+            .collect()
+    }
+
+    fn proof(&self, leaf: &[u8]) -> DynMerkleProof {
+        match T::Hash::try_from(leaf.to_vec()) {
+            Ok(leaf) => MerkleTree::proof(self, leaf).into(),
+            Err(_) => MerkleProof::<T>::new(vec![], None, None).into(),
+        }
+    }
+
+    fn verify(&self, proof: &DynMerkleProof, leaf: &[u8], root: &[u8]) -> bool {
+        verify::<T>(MerkleTree::spec(self), proof, leaf, root).unwrap_or(false)
+    }
+
+    fn push(&mut self, leaf: &Bytes) {
+        MerkleTree::push(self, leaf)
+    }
+}
+
+fn verify<T: HashFunction>(
+    spec: &TreeSpec,
+    proof: &DynMerkleProof,
+    leaf: &[u8],
+    root: &[u8],
+) -> Option<bool> {
+    let leaf = T::Hash::try_from(leaf.to_vec()).ok()?;
+    let root = T::Hash::try_from(root.to_vec()).ok()?;
+    Some(crate::verify_proof(spec, &proof.typed::<T>()?, leaf, root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_function, DynMerkleTree};
+    use crate::{Bytes, DynHashFunction, HashFunction, Keccak256, MerkleTree, SpecError, TreeSpec};
+
+    #[test]
+    fn selects_hash_function_at_runtime() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let expected = MerkleTree::<Keccak256>::new_ordered(&leaves);
+        let spec = TreeSpec::parse("keccak256/ordered/promote-odd/arity2").unwrap();
+        let mut tree = DynMerkleTree::from_spec(&spec, &leaves).unwrap();
+        assert_eq!(&*tree.root(), &expected.root()[..]);
+
+        let keccak = hash_function("keccak256").unwrap();
+        let leaf = keccak.hash_dyn(b"b");
+        let proof = tree.proof(&leaf);
+        assert!(tree.verify(&proof, &leaf, &tree.root()));
+        assert!(keccak.verify(tree.spec(), &proof, &leaf, &tree.root()));
+        assert!(!tree.verify(&proof, &keccak.hash_dyn(b"a"), &tree.root()));
+        assert!(!tree.verify(&proof, &leaf[..20], &tree.root()));
+        assert_eq!(
+            proof.typed::<Keccak256>().unwrap(),
+            expected.proof(Keccak256::hash(b"b"))
+        );
+        assert_eq!(*Keccak256::default().hash_dyn(b"b"), Keccak256::hash(b"b"));
+
+        tree.push(b"d");
+        assert_eq!(tree.leaves().len(), 4);
+        assert_eq!(
+            &*DynMerkleTree::new(&Keccak256::default(), &leaves).root(),
+            &MerkleTree::<Keccak256>::new(&leaves).root()[..]
+        );
+        assert_eq!(
+            DynMerkleTree::from_spec(&TreeSpec::new("md5"), &leaves).err(),
+            Some(SpecError::UnknownHash("md5".to_string()))
+        );
+
+        #[cfg(feature = "sha256")]
+        {
+            let spec = TreeSpec::parse("sha256/sorted").unwrap();
+            let tree = DynMerkleTree::from_spec(&spec, &leaves).unwrap();
+            let expected = crate::MerkleTreeBuilder::<crate::Sha256>::from_spec(&spec)
+                .unwrap()
+                .build(&leaves);
+            assert_eq!(&*tree.root(), &expected.root()[..]);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{HashFunction, Keccak256, MerkleProof, MerkleTree, MerkleTreeBuilder};
+use std::marker::PhantomData;
+
+/// Converts typed values into the raw bytes which are hashed as leaves, so trees can be built
+/// from and queried by the values themselves rather than pre-encoded byte slices.
+pub trait LeafEncoder {
+    type Value;
+
+    fn encode(&self, value: &Self::Value) -> Vec<u8>;
+}
+
+/// Encodes values as OpenZeppelin's `StandardMerkleTree` does, hashing `abi.encode(value)` with
+/// keccak256 so that the leaf hash of a keccak256 tree is the double hash
+/// `keccak256(keccak256(abi.encode(value)))`, as is expected for airdrop claims.
+pub struct AbiEncoder<V> {
+    phantom: PhantomData<V>,
+}
+
+impl<V> AbiEncoder<V> {
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<V> Default for AbiEncoder<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: AbiEncode> LeafEncoder for AbiEncoder<V> {
+    type Value = V;
+
+    fn encode(&self, value: &V) -> Vec<u8> {
+        let mut encoded = vec![];
+        value.encode_abi(&mut encoded);
+        Keccak256::hash(&encoded).to_vec()
+    }
+}
+
+/// A value of a static Solidity type, encoded as by `abi.encode`. Tuples encode each of their
+/// elements in turn.
+pub trait AbiEncode {
+    fn encode_abi(&self, out: &mut Vec<u8>);
+}
+
+impl AbiEncode for bool {
+    fn encode_abi(&self, out: &mut Vec<u8>) {
+        (*self as u8).encode_abi(out)
+    }
+}
+
+macro_rules! abi_unsigned {
+    ($($t:ty),*) => {$(
+        impl AbiEncode for $t {
+            fn encode_abi(&self, out: &mut Vec<u8>) {
+                let bytes = self.to_be_bytes();
+                out.extend(std::iter::repeat_n(0, 32 - bytes.len()));
+                out.extend_from_slice(&bytes);
+            }
+        }
+    )*};
+}
+
+macro_rules! abi_signed {
+    ($($t:ty),*) => {$(
+        impl AbiEncode for $t {
+            fn encode_abi(&self, out: &mut Vec<u8>) {
+                // Sign extend to a full word
+                let bytes = self.to_be_bytes();
+                let fill = if *self < 0 { 0xff } else { 0 };
+                out.extend(std::iter::repeat_n(fill, 32 - bytes.len()));
+                out.extend_from_slice(&bytes);
+            }
+        }
+    )*};
+}
+
+abi_unsigned!(u8, u16, u32, u64, u128);
+abi_signed!(i8, i16, i32, i64, i128);
+
+macro_rules! abi_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: AbiEncode),+> AbiEncode for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn encode_abi(&self, out: &mut Vec<u8>) {
+                let ($($name,)+) = self;
+                $($name.encode_abi(out);)+
+            }
+        }
+    };
+}
+
+abi_tuple!(A);
+abi_tuple!(A, B);
+abi_tuple!(A, B, C);
+abi_tuple!(A, B, C, D);
+abi_tuple!(A, B, C, D, E);
+abi_tuple!(A, B, C, D, E, F);
+
+/// Addresses, 32 byte values and 256 bit integers from `primitive-types`.
+#[cfg(feature = "abi")]
+mod primitive {
+    use super::AbiEncode;
+    use primitive_types::{H160, H256, U256};
+
+    impl AbiEncode for H160 {
+        fn encode_abi(&self, out: &mut Vec<u8>) {
+            out.extend_from_slice(&[0; 12]);
+            out.extend_from_slice(self.as_bytes());
+        }
+    }
+
+    impl AbiEncode for H256 {
+        fn encode_abi(&self, out: &mut Vec<u8>) {
+            out.extend_from_slice(self.as_bytes());
+        }
+    }
+
+    impl AbiEncode for U256 {
+        fn encode_abi(&self, out: &mut Vec<u8>) {
+            let mut word = [0; 32];
+            self.to_big_endian(&mut word);
+            out.extend_from_slice(&word);
+        }
+    }
+}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Builds a tree from typed values, each encoded by `encoder` before being hashed.
+    pub fn build_values<E: LeafEncoder>(&self, encoder: &E, values: &[E::Value]) -> MerkleTree<T> {
+        let encoded: Vec<Vec<u8>> = values.iter().map(|v| encoder.encode(v)).collect();
+        let leaves: Vec<&[u8]> = encoded.iter().map(|e| &e[..]).collect();
+        self.build(&leaves)
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Creates a tree with the default settings from typed values, each encoded by `encoder`.
+    pub fn from_values<E: LeafEncoder>(encoder: &E, values: &[E::Value]) -> Self {
+        MerkleTreeBuilder::<T>::new().build_values(encoder, values)
+    }
+
+    /// The leaf hash of a typed value, as encoded by `encoder`.
+    pub fn value_hash<E: LeafEncoder>(&self, encoder: &E, value: &E::Value) -> T::Hash {
+        MerkleTree::<T>::hash_leaf(&self.spec, &encoder.encode(value))
+    }
+
+    /// Generates a proof for a typed value, which is empty if the value is not in the tree.
+    pub fn value_proof<E: LeafEncoder>(&self, encoder: &E, value: &E::Value) -> MerkleProof<T> {
+        self.proof(self.value_hash(encoder, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AbiEncode, AbiEncoder, Keccak256, MerkleTree};
+
+    fn encode(value: impl AbiEncode) -> String {
+        let mut out = vec![];
+        value.encode_abi(&mut out);
+        hex::encode(out)
+    }
+
+    #[test]
+    fn encodes_static_types() {
+        assert_eq!(encode(true), format!("{:0>64}", "1"));
+        assert_eq!(encode(0x1234u16), format!("{:0>64}", "1234"));
+        assert_eq!(encode(-2i8), format!("{:f>64}", "e"));
+        assert_eq!(encode((1u8, false)), format!("{:0>64}{:0>64}", "1", "0"));
+    }
+
+    #[test]
+    fn proves_values() {
+        let encoder = AbiEncoder::<(u64, u128)>::new();
+        let values: Vec<(u64, u128)> = (0..5).map(|i| (i, 1000 * i as u128)).collect();
+        let tree = MerkleTree::<Keccak256>::from_values(&encoder, &values);
+
+        for value in &values {
+            let leaf = tree.value_hash(&encoder, value);
+            assert!(tree.verify(tree.value_proof(&encoder, value), leaf, tree.root()));
+        }
+        assert!(tree.value_proof(&encoder, &(5, 5000)).is_empty());
+    }
+
+    #[cfg(feature = "abi")]
+    #[test]
+    fn matches_openzeppelin() {
+        use primitive_types::{H160, U256};
+
+        // Example from the @openzeppelin/merkle-tree readme
+        let values = [
+            (
+                H160::repeat_byte(0x11),
+                U256::from_dec_str("5000000000000000000").unwrap(),
+            ),
+            (
+                H160::repeat_byte(0x22),
+                U256::from_dec_str("2500000000000000000").unwrap(),
+            ),
+        ];
+        let tree = MerkleTree::<Keccak256>::from_values(&AbiEncoder::new(), &values);
+        assert_eq!(
+            hex::encode(tree.root()),
+            "d4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77"
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use crate::{Bytes, Direction, HashFunction, MerkleProof, MerkleTree, Store};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+/// Errors from the fallible counterparts of methods which otherwise return empty proofs or
+/// default hashes, either of which is easily mistaken for a valid result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MerkleError {
+    /// The leaf is not in the tree.
+    LeafNotFound,
+    /// The tree has no leaves, so has no root.
+    EmptyTree,
+    /// The sibling counts or directions of the proof are inconsistent with its hashes.
+    MalformedProof,
+    /// The bytes are not the length of a hash.
+    InvalidHash { expected: usize, actual: usize },
+    /// A proof for sorted pairs was used with positional pairs, or vice versa.
+    SchemeMismatch,
+    /// The leaf is in the tree, so its absence cannot be proven.
+    LeafFound,
+    /// Absence can only be proven by trees with sorted leaves and positional pairs, and without
+    /// padding.
+    AbsenceUnsupported,
+    /// The operation is only supported by binary trees.
+    ArityUnsupported,
+    /// The proof has more steps than the tree has layers above its leaves.
+    ProofTooDeep { depth: usize, max: usize },
+    /// A step of the proof has more siblings than a node of the tree has.
+    TooManySiblings { step: usize, siblings: usize },
+    /// The proof is well formed but does not recompute the root.
+    RootMismatch,
+    /// The value and nonce do not open the commitment held by the leaf, or no nonce was supplied.
+    CommitmentMismatch,
+    /// The leaf is not a placeholder awaiting its reveal.
+    NotPlaceholder,
+    /// The tree holds as many leaves as its depth allows.
+    TreeFull,
+}
+
+impl Display for MerkleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MerkleError::LeafNotFound => write!(f, "leaf is not in the tree"),
+            MerkleError::EmptyTree => write!(f, "tree has no leaves"),
+            MerkleError::MalformedProof => write!(f, "malformed proof"),
+            MerkleError::InvalidHash { expected, actual } => write!(
+                f,
+                "hash of {} bytes supplied where {} bytes were expected",
+                actual, expected
+            ),
+            MerkleError::SchemeMismatch => write!(
+                f,
+                "proof and tree disagree on whether pairs are sorted or positional"
+            ),
+            MerkleError::LeafFound => write!(f, "leaf is in the tree"),
+            MerkleError::AbsenceUnsupported => write!(
+                f,
+                "absence can only be proven by unpadded trees with sorted leaves and positional pairs"
+            ),
+            MerkleError::ArityUnsupported => write!(f, "only binary trees are supported"),
+            MerkleError::ProofTooDeep { depth, max } => write!(
+                f,
+                "proof has {} steps but the tree has at most {}",
+                depth, max
+            ),
+            MerkleError::TooManySiblings { step, siblings } => write!(
+                f,
+                "step {} of the proof has {} siblings, more than the tree allows",
+                step, siblings
+            ),
+            MerkleError::RootMismatch => write!(f, "proof does not recompute the root"),
+            MerkleError::CommitmentMismatch => {
+                write!(f, "value does not open the commitment of the leaf")
+            }
+            MerkleError::NotPlaceholder => write!(f, "leaf is not an unrevealed placeholder"),
+            MerkleError::TreeFull => write!(f, "tree is full"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MerkleError {}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// The root of the tree, failing if the tree is empty rather than returning a default hash.
+    pub fn try_root(&self) -> Result<T::Hash, MerkleError> {
+        match self.layers.layer_len(0) {
+            0 => Err(MerkleError::EmptyTree),
+            _ => Ok(self.root()),
+        }
+    }
+
+    /// Generates a proof for a leaf, failing if the leaf is not in the tree rather than returning
+    /// an empty proof.
+    pub fn try_proof(&self, leaf: impl Into<T::Hash>) -> Result<MerkleProof<T>, MerkleError> {
+        let leaf = leaf.into();
+        if self.layers.layer_len(0) == 0 {
+            return Err(MerkleError::EmptyTree);
+        }
+        self.index_of(leaf).ok_or(MerkleError::LeafNotFound)?;
+        Ok(self.proof(leaf))
+    }
+
+    /// Generates a proof for the leaf holding the raw `value`, failing if the value is not in the
+    /// tree.
+    pub fn try_proof_for_value(&self, value: &Bytes) -> Result<MerkleProof<T>, MerkleError> {
+        self.try_proof(MerkleTree::<T>::try_hash_leaf(&self.spec, value)?)
+    }
+
+    /// Verifies a proof as `verify` does, failing if the proof is malformed rather than treating
+    /// it as invalid.
+    pub fn try_verify(
+        &self,
+        proof: MerkleProof<T>,
+        leaf: impl Into<T::Hash>,
+        root: impl Into<T::Hash>,
+    ) -> Result<bool, MerkleError> {
+        match proof.is_well_formed() {
+            true => Ok(self.verify(proof, leaf, root)),
+            false => Err(MerkleError::MalformedProof),
+        }
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// Verifies a proof from an untrusted source, failing with the reason it is rejected. Beyond
+    /// recomputing the root, the proof must match the scheme of the tree and have no more steps
+    /// than the tree has layers, nor more siblings per step than its arity allows, so overlong
+    /// proofs are rejected rather than folded into a root.
+    pub fn verify_checked(
+        &self,
+        proof: &MerkleProof<T>,
+        leaf: impl Into<T::Hash>,
+        root: impl Into<T::Hash>,
+    ) -> Result<(), MerkleError> {
+        if !proof.is_well_formed() {
+            return Err(MerkleError::MalformedProof);
+        }
+        if proof.directions().is_some() == self.spec.sort_pairs {
+            return Err(MerkleError::SchemeMismatch);
+        }
+        if proof.depth() > self.depth() {
+            return Err(MerkleError::ProofTooDeep {
+                depth: proof.depth(),
+                max: self.depth(),
+            });
+        }
+        let counts = proof.sibling_counts().unwrap_or_default();
+        if let Some((step, siblings)) = counts
+            .iter()
+            .enumerate()
+            .find(|(_, count)| **count >= self.spec.arity)
+        {
+            return Err(MerkleError::TooManySiblings {
+                step,
+                siblings: *siblings,
+            });
+        }
+
+        match proof.compute_root(&self.spec, leaf.into()) == Some(root.into()) {
+            true => Ok(()),
+            false => Err(MerkleError::RootMismatch),
+        }
+    }
+}
+
+impl<T: HashFunction> MerkleProof<T> {
+    /// Creates a proof from sibling hashes supplied as bytes, such as those decoded from an
+    /// untrusted request, failing if any is not the length of a hash.
+    pub fn try_from_slices(
+        hashes: &[&[u8]],
+        directions: Option<Vec<Direction>>,
+        sibling_counts: Option<Vec<usize>>,
+    ) -> Result<Self, MerkleError> {
+        let hashes = hashes
+            .iter()
+            .map(|hash| MerkleTree::<T>::hash_from_slice(hash))
+            .collect::<Result<_, _>>()?;
+        Ok(MerkleProof::new(hashes, directions, sibling_counts))
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Appends a leaf as `push` does, failing if the tree has pre-hashed leaves and the leaf is not
+    /// the length of a hash.
+    pub fn try_push(&mut self, leaf: &Bytes) -> Result<(), MerkleError> {
+        self.try_extend(&[leaf])
+    }
+
+    /// Appends several leaves as `extend` does, failing without changing the tree if the tree has
+    /// pre-hashed leaves and a leaf is not the length of a hash.
+    pub fn try_extend(&mut self, leaves: &[&Bytes]) -> Result<(), MerkleError> {
+        let hashes = MerkleTree::<T>::try_hash_leaves(&self.spec, leaves)?;
+        self.extend_hashes(hashes);
+        Ok(())
+    }
+
+    /// Replaces the leaf at `index` as `update` does, failing if there is no leaf at `index` or if
+    /// the tree has pre-hashed leaves and the leaf is not the length of a hash.
+    pub fn try_update(&mut self, index: usize, leaf: &Bytes) -> Result<T::Hash, MerkleError> {
+        let hash = MerkleTree::<T>::try_hash_leaf(&self.spec, leaf)?;
+        if index >= self.layers[0].len() {
+            return Err(MerkleError::LeafNotFound);
+        }
+        Ok(self.update_hash(index, hash))
+    }
+
+    /// Creates a tree as `from_hashes` does from hashes supplied as bytes, such as those decoded
+    /// from hex, failing if a hash is not the length of a hash.
+    pub fn try_from_hashes(hashes: &[&Bytes]) -> Result<Self, MerkleError> {
+        let hashes = hashes
+            .iter()
+            .map(|hash| MerkleTree::<T>::hash_from_slice(hash))
+            .collect::<Result<_, _>>()?;
+        Ok(MerkleTree::from_hashes(hashes))
+    }
+
+    /// Converts bytes, such as a decoded hex string, to a hash.
+    pub fn hash_from_slice(bytes: &[u8]) -> Result<T::Hash, MerkleError> {
+        T::Hash::try_from(bytes.to_vec()).map_err(|_| MerkleError::InvalidHash {
+            expected: T::Hash::default().as_ref().len(),
+            actual: bytes.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Direction, Keccak256, MerkleError, MerkleProof, MerkleTree};
+
+    #[test]
+    fn fails_instead_of_defaulting() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Keccak256>::new_ordered(&leaves);
+        assert_eq!(tree.try_root(), Ok(tree.root()));
+        let proof = tree.try_proof_for_value(b"b").unwrap();
+        assert_eq!(proof.hashes(), tree.proof_for_value(b"b").hashes());
+        assert_eq!(
+            tree.try_verify(proof.clone(), tree.leaves()[1], tree.root()),
+            Ok(true)
+        );
+        assert_eq!(
+            tree.try_proof([0u8; 32]).err(),
+            Some(MerkleError::LeafNotFound)
+        );
+
+        // Directions must accompany every hash
+        let malformed = MerkleProof::<Keccak256>::new(
+            proof.hashes().to_vec(),
+            Some(vec![Direction::Left]),
+            None,
+        );
+        assert_eq!(
+            tree.try_verify(malformed, tree.leaves()[1], tree.root()),
+            Err(MerkleError::MalformedProof)
+        );
+
+        let empty = MerkleTree::<Keccak256>::new(&[]);
+        assert_eq!(empty.try_root(), Err(MerkleError::EmptyTree));
+        assert_eq!(
+            empty.try_proof([0u8; 32]).err(),
+            Some(MerkleError::EmptyTree)
+        );
+
+        assert_eq!(
+            MerkleTree::<Keccak256>::hash_from_slice(&tree.root()),
+            Ok(tree.root())
+        );
+        assert!(matches!(
+            MerkleProof::<Keccak256>::try_from_slices(&[&[0; 32], &[0; 31]], None, None),
+            Err(MerkleError::InvalidHash {
+                expected: 32,
+                actual: 31
+            })
+        ));
+        assert_eq!(
+            MerkleTree::<Keccak256>::hash_from_slice(&[0; 20]),
+            Err(MerkleError::InvalidHash {
+                expected: 32,
+                actual: 20
+            })
+        );
+    }
+
+    #[test]
+    fn builds_from_untrusted_hashes() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Keccak256>::new(&leaves);
+        let hashes: Vec<&Bytes> = tree.leaves().iter().map(|h| &h[..]).collect();
+        assert_eq!(
+            MerkleTree::<Keccak256>::try_from_hashes(&hashes).map(|t| t.root()),
+            Ok(tree.root())
+        );
+        assert_eq!(
+            MerkleTree::<Keccak256>::try_from_hashes(&[hashes[0], &[0; 31]]).err(),
+            Some(MerkleError::InvalidHash {
+                expected: 32,
+                actual: 31
+            })
+        );
+    }
+
+    #[test]
+    fn verifies_untrusted_proofs() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c", "d"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Keccak256>::new_ordered(&leaves);
+        let (leaf, root) = (tree.leaves()[1], tree.root());
+        let proof = tree.proof(leaf);
+        assert_eq!(proof.compute_root(tree.spec(), leaf), Some(root));
+        assert_eq!(tree.verify_checked(&proof, leaf, root), Ok(()));
+        assert_eq!(
+            tree.verify_checked(&proof, tree.leaves()[0], root),
+            Err(MerkleError::RootMismatch)
+        );
+
+        // A proof folding extra steps into a root is rejected as too deep
+        let mut hashes = proof.hashes().to_vec();
+        let mut directions = proof.directions().unwrap().to_vec();
+        hashes.push([0; 32]);
+        directions.push(Direction::Right);
+        let overlong = MerkleProof::<Keccak256>::new(hashes, Some(directions), None);
+        let folded = overlong.compute_root(tree.spec(), leaf).unwrap();
+        assert_eq!(
+            tree.verify_checked(&overlong, leaf, folded),
+            Err(MerkleError::ProofTooDeep { depth: 3, max: 2 })
+        );
+
+        let wide = MerkleProof::<Keccak256>::new(
+            proof.hashes().to_vec(),
+            proof.directions().map(|d| d.to_vec()),
+            Some(vec![2]),
+        );
+        assert_eq!(
+            tree.verify_checked(&wide, leaf, root),
+            Err(MerkleError::TooManySiblings {
+                step: 0,
+                siblings: 2
+            })
+        );
+        let sorted = MerkleProof::<Keccak256>::new(proof.hashes().to_vec(), None, None);
+        assert_eq!(
+            tree.verify_checked(&sorted, leaf, root),
+            Err(MerkleError::SchemeMismatch)
+        );
+
+        // Proofs of another scheme or with empty steps do not recompute any root
+        let mut sorted_spec = tree.spec().clone();
+        sorted_spec.sort_pairs = true;
+        assert_eq!(proof.compute_root(&sorted_spec, leaf), None);
+        assert!(!crate::verify_proof(&sorted_spec, &proof, leaf, root));
+        assert_eq!(sorted.compute_root(tree.spec(), leaf), None);
+        let empty_step = MerkleProof::<Keccak256>::new(
+            proof.hashes().to_vec(),
+            proof.directions().map(|d| d.to_vec()),
+            Some(vec![1, 0, 1]),
+        );
+        assert_eq!(empty_step.compute_root(tree.spec(), leaf), None);
+    }
+}
@@ -0,0 +1,357 @@
+use crate::{HashFunction, LeafKey, MerkleTree, SpecError, TreeSpec};
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 4] = b"MRKT";
+const VERSION: u8 = 1;
+const MAC: u8 = 1;
+
+/// The on-disk format of a tree, being a header holding the spec followed by each layer. Each
+/// layer holds its length, its nodes and a checksum, being the hash of its length and nodes, so
+/// corruption can be located to a layer. The file optionally ends with a MAC of everything before
+/// it, keyed by a secret, so tampering can be detected as well as corruption.
+impl<T: HashFunction> MerkleTree<T> {
+    /// Writes the tree to `writer`, followed by a MAC keyed by `mac_key` if supplied.
+    pub fn save(&self, mut writer: impl Write, mac_key: Option<&[u8]>) -> std::io::Result<()> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.push(match mac_key {
+            Some(_) => MAC,
+            None => 0,
+        });
+        let spec = self.spec.to_string();
+        bytes.extend_from_slice(&(spec.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(spec.as_bytes());
+        bytes.extend_from_slice(&(self.layers.len() as u32).to_le_bytes());
+
+        for layer in &self.layers {
+            let start = bytes.len();
+            bytes.extend_from_slice(&(layer.len() as u64).to_le_bytes());
+            for node in layer {
+                bytes.extend_from_slice(node.as_ref());
+            }
+            let checksum = T::hash(&bytes[start..]);
+            bytes.extend_from_slice(checksum.as_ref());
+        }
+
+        if let Some(key) = mac_key {
+            let mac = mac::<T>(key, &bytes);
+            bytes.extend_from_slice(mac.as_ref());
+        }
+        writer.write_all(&bytes)?;
+        writer.flush()
+    }
+
+    /// Reads a tree written by `save`, locating any corruption or truncation to the layer and
+    /// byte offset at which it was detected. Files saved with a MAC must be loaded with its key,
+    /// and files loaded with a key must have a MAC, so a MAC cannot be stripped undetected.
+    ///
+    /// The key of a keyed spec is not saved, so trees with a keyed spec fail to load with
+    /// `SpecError::MissingKey` and are loaded with `load_keyed`.
+    pub fn load(reader: impl Read, mac_key: Option<&[u8]>) -> Result<Self, LoadError> {
+        MerkleTree::load_with_key(reader, mac_key, None)
+    }
+
+    /// Reads a tree written by `save` whose spec is keyed, supplying the key which is not saved.
+    pub fn load_keyed(
+        reader: impl Read,
+        mac_key: Option<&[u8]>,
+        leaf_key: LeafKey,
+    ) -> Result<Self, LoadError> {
+        MerkleTree::load_with_key(reader, mac_key, Some(leaf_key))
+    }
+
+    fn load_with_key(
+        mut reader: impl Read,
+        mac_key: Option<&[u8]>,
+        leaf_key: Option<LeafKey>,
+    ) -> Result<Self, LoadError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).map_err(LoadError::Io)?;
+        let size = T::Hash::default().as_ref().len();
+
+        let mut reader = Reader {
+            bytes: &bytes,
+            offset: 0,
+            layer: None,
+        };
+        if reader.take(MAGIC.len())? != MAGIC || reader.take(1)?[0] != VERSION {
+            return Err(LoadError::Format);
+        }
+        let flags = reader.take(1)?[0];
+
+        // Check the MAC before trusting any of the contents
+        let body = match (flags & MAC, mac_key) {
+            (0, None) => bytes.len(),
+            (0, Some(_)) => return Err(LoadError::MacMissing),
+            (_, None) => return Err(LoadError::MacRequired),
+            (_, Some(key)) => {
+                let body = bytes.len().checked_sub(size).ok_or(LoadError::Truncated {
+                    layer: None,
+                    offset: bytes.len(),
+                })?;
+                if !macs_equal(mac::<T>(key, &bytes[..body]).as_ref(), &bytes[body..]) {
+                    return Err(LoadError::MacMismatch);
+                }
+                body
+            }
+        };
+        reader.bytes = &bytes[..body];
+
+        let length = reader.u32()? as usize;
+        let spec = std::str::from_utf8(reader.take(length)?).map_err(|_| LoadError::Format)?;
+        let spec = TreeSpec::parse_with_key(spec, leaf_key).map_err(LoadError::Spec)?;
+        if spec.hash != T::NAME {
+            return Err(LoadError::Spec(SpecError::HashMismatch {
+                spec: spec.hash,
+                supplied: T::NAME.to_string(),
+            }));
+        }
+
+        let count = reader.u32()? as usize;
+        let mut layers: Vec<Vec<T::Hash>> = Vec::with_capacity(count.min(64));
+        for layer in 0..count {
+            reader.layer = Some(layer);
+            let offset = reader.offset;
+            let length = reader.u64()? as usize;
+            let nodes = reader.take(length.checked_mul(size).ok_or(LoadError::Format)?)?;
+            let checksum = reader.take(size)?;
+            if T::hash(&bytes[offset..offset + 8 + nodes.len()]).as_ref() != checksum {
+                return Err(LoadError::Checksum { layer, offset });
+            }
+
+            // Check the layer has the shape implied by the layer below
+            let expected = match layers.last() {
+                Some(below) if below.len() > 1 => below.len().div_ceil(spec.arity),
+                Some(_) => return Err(LoadError::Shape { layer }),
+                None => length,
+            };
+            if length != expected {
+                return Err(LoadError::Shape { layer });
+            }
+            layers.push(
+                nodes
+                    .chunks(size)
+                    .map(|node| T::Hash::try_from(node.to_vec()).unwrap_or_default())
+                    .collect(),
+            );
+        }
+        match layers.last() {
+            Some(top) if top.len() <= 1 => {}
+            _ => return Err(LoadError::Shape { layer: count }),
+        }
+        if reader.offset != body {
+            return Err(LoadError::Format);
+        }
+
+        Ok(MerkleTree::from_layers(spec, layers))
+    }
+}
+
+/// A MAC of `bytes` keyed by `key`, being the HMAC of the bytes under the hash of the tree.
+fn mac<T: HashFunction>(key: &[u8], bytes: &[u8]) -> T::Hash {
+    crate::hash_functions::hmac::<T>(key, bytes)
+}
+
+/// Compares a computed MAC with one read from a file in constant time, so the time taken does
+/// not reveal how many leading bytes of a forged MAC are correct.
+fn macs_equal(computed: &[u8], read: &[u8]) -> bool {
+    let difference = computed
+        .iter()
+        .zip(read)
+        .fold(0, |difference, (a, b)| difference | (a ^ b));
+    computed.len() == read.len() && core::hint::black_box(difference) == 0
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    layer: Option<usize>,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], LoadError> {
+        match self.offset.checked_add(length) {
+            Some(end) if end <= self.bytes.len() => {
+                let taken = &self.bytes[self.offset..end];
+                self.offset = end;
+                Ok(taken)
+            }
+            _ => Err(LoadError::Truncated {
+                layer: self.layer,
+                offset: self.bytes.len(),
+            }),
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, LoadError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(std::io::Error),
+    /// The file is not a saved tree.
+    Format,
+    Spec(SpecError),
+    /// The file ends at `offset`, part way through the header or the `layer`.
+    Truncated {
+        layer: Option<usize>,
+        offset: usize,
+    },
+    /// The checksum of the layer starting at `offset` does not match its contents.
+    Checksum {
+        layer: usize,
+        offset: usize,
+    },
+    /// The layer does not have the length implied by the layer below.
+    Shape {
+        layer: usize,
+    },
+    /// The file was saved with a MAC, but no key was supplied to check it.
+    MacRequired,
+    /// A key was supplied, but the file was saved without a MAC.
+    MacMissing,
+    /// The MAC does not match the contents of the file, which has been corrupted or tampered with
+    /// or was saved with a different key.
+    MacMismatch,
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "could not read tree: {}", e),
+            LoadError::Format => write!(f, "not a saved tree"),
+            LoadError::Spec(e) => write!(f, "invalid tree spec: {}", e),
+            LoadError::Truncated {
+                layer: Some(layer),
+                offset,
+            } => write!(f, "tree truncated in layer {} at offset {}", layer, offset),
+            LoadError::Truncated {
+                layer: None,
+                offset,
+            } => write!(f, "tree truncated in header at offset {}", offset),
+            LoadError::Checksum { layer, offset } => write!(
+                f,
+                "checksum mismatch in layer {} starting at offset {}",
+                layer, offset
+            ),
+            LoadError::Shape { layer } => write!(f, "layer {} has an invalid length", layer),
+            LoadError::MacRequired => write!(f, "tree has a MAC but no key was supplied"),
+            LoadError::MacMissing => write!(f, "tree has no MAC to check the key against"),
+            LoadError::MacMismatch => write!(f, "tree MAC does not match"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, LeafKey, LoadError, MerkleTree, MerkleTreeBuilder, SpecError};
+
+    fn tree() -> MerkleTree<Keccak256> {
+        let values: Vec<[u8; 1]> = (0..7).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        MerkleTreeBuilder::<Keccak256>::new()
+            .arity(3)
+            .domain_separation(true)
+            .build(&leaves)
+    }
+
+    #[test]
+    fn round_trips() {
+        let tree = tree();
+        for key in [None, Some(&b"secret"[..])] {
+            let mut bytes = vec![];
+            tree.save(&mut bytes, key).unwrap();
+            let loaded = MerkleTree::<Keccak256>::load(&bytes[..], key).unwrap();
+            assert_eq!(loaded.root(), tree.root());
+            assert_eq!(loaded.spec(), tree.spec());
+            assert_eq!(loaded.leaves(), tree.leaves());
+        }
+        let mut bytes = vec![];
+        MerkleTree::<Keccak256>::new(&[])
+            .save(&mut bytes, None)
+            .unwrap();
+        let loaded = MerkleTree::<Keccak256>::load(&bytes[..], None).unwrap();
+        assert!(loaded.leaves().is_empty());
+        assert!(matches!(
+            MerkleTree::<Keccak256>::load(&bytes[..], Some(b"secret")),
+            Err(LoadError::MacMissing)
+        ));
+    }
+
+    #[test]
+    fn locates_corruption() {
+        let tree = tree();
+        let mut bytes = vec![];
+        tree.save(&mut bytes, None).unwrap();
+        let header = 4 + 2 + 4 + tree.spec().to_string().len() + 4;
+        let second = header + 8 + 7 * 32 + 32;
+
+        // Flip a bit of a node in the second layer
+        let mut corrupt = bytes.clone();
+        corrupt[second + 8 + 40] ^= 1;
+        assert!(matches!(
+            MerkleTree::<Keccak256>::load(&corrupt[..], None),
+            Err(LoadError::Checksum { layer: 1, offset }) if offset == second
+        ));
+
+        assert!(matches!(
+            MerkleTree::<Keccak256>::load(&bytes[..second + 20], None),
+            Err(LoadError::Truncated { layer: Some(1), offset }) if offset == second + 20
+        ));
+        assert!(matches!(
+            MerkleTree::<Keccak256>::load(&bytes[..8], None),
+            Err(LoadError::Truncated { layer: None, .. })
+        ));
+        assert!(matches!(
+            MerkleTree::<Keccak256>::load(&b"not a tree"[..], None),
+            Err(LoadError::Format)
+        ));
+    }
+
+    #[test]
+    fn checks_macs() {
+        let tree = tree();
+        let mut bytes = vec![];
+        tree.save(&mut bytes, Some(b"secret")).unwrap();
+        assert!(matches!(
+            MerkleTree::<Keccak256>::load(&bytes[..], None),
+            Err(LoadError::MacRequired)
+        ));
+        assert!(matches!(
+            MerkleTree::<Keccak256>::load(&bytes[..], Some(b"other")),
+            Err(LoadError::MacMismatch)
+        ));
+        bytes[20] ^= 1;
+        assert!(matches!(
+            MerkleTree::<Keccak256>::load(&bytes[..], Some(b"secret")),
+            Err(LoadError::MacMismatch)
+        ));
+    }
+
+    #[test]
+    fn requires_leaf_key() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .salt(b"salt")
+            .build(&leaves);
+        let mut bytes = vec![];
+        tree.save(&mut bytes, None).unwrap();
+        assert!(matches!(
+            MerkleTree::<Keccak256>::load(&bytes[..], None),
+            Err(LoadError::Spec(SpecError::MissingKey))
+        ));
+        let key = LeafKey::new(b"salt".to_vec());
+        let loaded = MerkleTree::<Keccak256>::load_keyed(&bytes[..], None, key).unwrap();
+        assert_eq!(loaded.spec(), tree.spec());
+        assert_eq!(loaded.proof_for_value(b"b"), tree.proof_for_value(b"b"));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{Bytes, HashFunction, MerkleError, MerkleTree};
+
+/// A binary tree of exactly `DEPTH` layers above its leaves, as kept by deposit contracts and
+/// privacy pools such as Tornado Cash. Leaves are appended in order, and the leaves not yet
+/// appended hold a zero leaf, so each missing subtree is the precomputed root of a subtree of zero
+/// leaves. Every proof therefore has exactly `DEPTH` siblings, as circuits and on-chain verifiers
+/// of fixed depth require.
+///
+/// Nodes are the hash of the concatenation of their children, as positional pairs, so proofs
+/// also verify with `verify_fixed` without a node prefix.
+pub struct FixedMerkleTree<T: HashFunction, const DEPTH: usize> {
+    /// The nodes of each layer up to the last node covering an appended leaf, from the leaves to
+    /// the root. Nodes beyond are the roots of subtrees of zero leaves.
+    layers: Vec<Vec<T::Hash>>,
+    /// The root of a subtree of zero leaves of each height.
+    zeros: Vec<T::Hash>,
+}
+
+/// A proof of exactly `DEPTH` siblings, from the leaf towards the root.
+pub struct FixedProof<T: HashFunction, const DEPTH: usize> {
+    pub siblings: [T::Hash; DEPTH],
+    /// The index of the leaf, whose bit `i` is set when the `i`th sibling is on the left, as the
+    /// `left` argument of `verify_fixed` expects.
+    pub index: u64,
+}
+
+impl<T: HashFunction, const DEPTH: usize> FixedMerkleTree<T, DEPTH> {
+    /// Creates an empty tree whose missing leaves are the default hash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `DEPTH` is 64 or more, as leaves are indexed by `u64`.
+    pub fn new() -> Self {
+        Self::with_zero(T::Hash::default())
+    }
+
+    /// Creates an empty tree whose missing leaves are `zero`, such as the hash of a value no leaf
+    /// can hold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `DEPTH` is 64 or more, as leaves are indexed by `u64`.
+    pub fn with_zero(zero: T::Hash) -> Self {
+        assert!(DEPTH < 64, "depth must be less than 64");
+        let mut zeros = vec![zero];
+        for height in 0..DEPTH {
+            zeros.push(MerkleTree::<T>::hash_nodes(&[zeros[height]; 2]));
+        }
+        Self {
+            layers: vec![vec![]; DEPTH + 1],
+            zeros,
+        }
+    }
+
+    /// The number of leaves appended.
+    pub fn len(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers[0].is_empty()
+    }
+
+    /// The number of leaves the tree can hold.
+    pub fn capacity(&self) -> u64 {
+        1 << DEPTH
+    }
+
+    pub fn leaves(&self) -> &[T::Hash] {
+        &self.layers[0]
+    }
+
+    /// The root, which is the root of a subtree of zero leaves until a leaf is appended.
+    pub fn root(&self) -> T::Hash {
+        self.node(DEPTH, 0)
+    }
+
+    /// The root of a subtree of zero leaves of each height, from the zero leaf to the root of an
+    /// empty tree.
+    pub fn zeros(&self) -> &[T::Hash] {
+        &self.zeros
+    }
+
+    /// Hashes and appends a leaf, returning its index.
+    pub fn push(&mut self, leaf: &Bytes) -> Result<u64, MerkleError> {
+        self.push_hash(T::hash(leaf))
+    }
+
+    /// Appends a leaf which is already hashed, such as a commitment, returning its index. Only
+    /// the `DEPTH` nodes of the path from the leaf to the root are recomputed.
+    pub fn push_hash(&mut self, leaf: T::Hash) -> Result<u64, MerkleError> {
+        let index = self.len();
+        if index as u64 >= self.capacity() {
+            return Err(MerkleError::TreeFull);
+        }
+        self.layers[0].push(leaf);
+        let mut node = index;
+        for height in 0..DEPTH {
+            let pair = [self.node(height, node & !1), self.node(height, node | 1)];
+            let parent = MerkleTree::<T>::hash_nodes(&pair);
+            node >>= 1;
+            match self.layers[height + 1].get_mut(node) {
+                Some(existing) => *existing = parent,
+                None => self.layers[height + 1].push(parent),
+            }
+        }
+        Ok(index as u64)
+    }
+
+    /// The proof of the leaf at `index`, if that leaf has been appended.
+    pub fn proof(&self, index: u64) -> Option<FixedProof<T, DEPTH>> {
+        if index >= self.len() as u64 {
+            return None;
+        }
+        let mut siblings = [T::Hash::default(); DEPTH];
+        for (height, sibling) in siblings.iter_mut().enumerate() {
+            *sibling = self.node(height, (index >> height) as usize ^ 1);
+        }
+        Some(FixedProof { siblings, index })
+    }
+
+    fn node(&self, height: usize, index: usize) -> T::Hash {
+        match self.layers[height].get(index) {
+            Some(node) => *node,
+            None => self.zeros[height],
+        }
+    }
+}
+
+impl<T: HashFunction, const DEPTH: usize> Default for FixedMerkleTree<T, DEPTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: HashFunction, const DEPTH: usize> FixedProof<T, DEPTH> {
+    /// Verifies the proof of a leaf against a root.
+    pub fn verify(&self, leaf: T::Hash, root: T::Hash) -> bool {
+        let mut node = leaf;
+        for (height, sibling) in self.siblings.iter().enumerate() {
+            node = match (self.index >> height) & 1 {
+                1 => MerkleTree::<T>::hash_nodes(&[*sibling, node]),
+                _ => MerkleTree::<T>::hash_nodes(&[node, *sibling]),
+            };
+        }
+        node == root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        verify_fixed, FixedMerkleTree, HashFunction, Keccak256, MerkleError, MerkleTreeBuilder,
+    };
+
+    #[test]
+    fn pads_with_zero_subtrees() {
+        let mut tree = FixedMerkleTree::<Keccak256, 4>::new();
+        assert_eq!(tree.root(), tree.zeros()[4]);
+        let builder = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(false)
+            .sort_pairs(false)
+            .hash_leaves(false);
+        for i in 0..16u8 {
+            let index = tree.push(&[i]).unwrap();
+            assert_eq!(index, i as u64);
+
+            // The root is that of the complete tree with the missing leaves zero
+            let mut leaves = tree.leaves().to_vec();
+            leaves.resize(16, [0; 32]);
+            assert_eq!(tree.root(), builder.build_from_hashes(leaves).root());
+
+            for index in 0..=index {
+                let proof = tree.proof(index).unwrap();
+                let leaf = tree.leaves()[index as usize];
+                assert!(proof.verify(leaf, tree.root()));
+                assert!(!proof.verify(Keccak256::hash(b"other"), tree.root()));
+                assert!(verify_fixed::<Keccak256, 32, 4>(
+                    &[],
+                    false,
+                    &proof.siblings,
+                    proof.index,
+                    leaf,
+                    tree.root()
+                ));
+            }
+        }
+        assert_eq!(tree.push(b"full"), Err(MerkleError::TreeFull));
+        assert!(tree.proof(16).is_none());
+
+        let mut tree = FixedMerkleTree::<Keccak256, 0>::with_zero(Keccak256::hash(b"zero"));
+        assert_eq!(tree.root(), Keccak256::hash(b"zero"));
+        tree.push(b"a").unwrap();
+        assert_eq!(tree.root(), Keccak256::hash(b"a"));
+    }
+}
@@ -0,0 +1,216 @@
+//! Trees over the files of a directory, so release artifacts can be attested by a single root and
+//! each file verified on its own.
+
+use crate::{verify_proof, Bytes, HashFunction, MerkleProof, MerkleTree, MerkleTreeBuilder};
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// How symbolic links within a directory are hashed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Links are omitted.
+    Skip,
+    /// Links are hashed as the file or directory they point to. Links which form a cycle are
+    /// omitted once the directory has been visited.
+    Follow,
+    /// Links are hashed as a file holding their target path, as git does.
+    Target,
+    /// Hashing fails if the directory contains a link.
+    Reject,
+}
+
+/// A tree over the files of a directory, with one leaf per file in order of path. Each leaf is
+/// the length of the path relative to the directory, the path itself and the contents of the file,
+/// so neither a file nor its contents can be substituted for another.
+pub struct DirectoryTree<T: HashFunction> {
+    tree: MerkleTree<T>,
+    paths: Vec<String>,
+}
+
+/// Builds a tree over the files of the directory at `path`. Paths are relative to the directory
+/// and separated by `/` on every platform, so trees of the same files match across platforms.
+pub fn hash_directory<T: HashFunction>(
+    path: impl AsRef<Path>,
+    policy: SymlinkPolicy,
+) -> std::io::Result<DirectoryTree<T>> {
+    let mut files = BTreeMap::new();
+    let mut visited = vec![path.as_ref().canonicalize()?];
+    collect(path.as_ref(), "", policy, &mut visited, &mut files)?;
+
+    let builder = builder::<T>();
+    let leaves: Vec<Vec<u8>> = files
+        .iter()
+        .map(|(path, contents)| leaf(path, contents))
+        .collect();
+    let leaves: Vec<&Bytes> = leaves.iter().map(|l| l.as_slice()).collect();
+    Ok(DirectoryTree {
+        tree: builder.build(&leaves),
+        paths: files.into_keys().collect(),
+    })
+}
+
+/// Verifies that a file with the supplied relative path and contents is within the directory
+/// with the supplied root, without the rest of the directory.
+pub fn verify_file<T: HashFunction>(
+    path: &str,
+    contents: &[u8],
+    proof: &MerkleProof<T>,
+    root: impl Into<T::Hash>,
+) -> bool {
+    let builder = builder::<T>();
+    let leaf = builder.hash_leaf(&leaf(path, contents));
+    verify_proof(builder.spec(), proof, leaf, root.into())
+}
+
+impl<T: HashFunction> DirectoryTree<T> {
+    pub fn tree(&self) -> &MerkleTree<T> {
+        &self.tree
+    }
+
+    pub fn root(&self) -> T::Hash {
+        self.tree.root()
+    }
+
+    /// The relative path of each file, in leaf order.
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Generates a proof for the file at the relative `path`, if present.
+    pub fn proof(&self, path: &str) -> Option<MerkleProof<T>> {
+        let index = self.paths.binary_search_by(|p| p.as_str().cmp(path)).ok()?;
+        Some(self.tree.proof_by_index(index))
+    }
+}
+
+/// Files are hashed in path order, with leaf and node hashes separated so that no internal node
+/// can be presented as a file.
+fn builder<T: HashFunction>() -> MerkleTreeBuilder<T> {
+    MerkleTreeBuilder::new()
+        .sort_leaves(false)
+        .sort_pairs(false)
+        .domain_separation(true)
+}
+
+fn leaf(path: &str, contents: &[u8]) -> Vec<u8> {
+    let mut leaf = (path.len() as u64).to_le_bytes().to_vec();
+    leaf.extend_from_slice(path.as_bytes());
+    leaf.extend_from_slice(contents);
+    leaf
+}
+
+/// Reads the files below `directory` into `files`, keyed by their path relative to the root.
+fn collect(
+    directory: &Path,
+    prefix: &str,
+    policy: SymlinkPolicy,
+    visited: &mut Vec<PathBuf>,
+    files: &mut BTreeMap<String, Vec<u8>>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string().map_err(|name| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("file name {:?} is not valid UTF-8", name),
+            )
+        })?;
+        let path = format!("{}{}", prefix, name);
+        let mut kind = entry.file_type()?;
+
+        if kind.is_symlink() {
+            match policy {
+                SymlinkPolicy::Skip => continue,
+                SymlinkPolicy::Target => {
+                    let target = std::fs::read_link(entry.path())?;
+                    files.insert(path, target.to_string_lossy().as_bytes().to_vec());
+                    continue;
+                }
+                SymlinkPolicy::Reject => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("'{}' is a symbolic link", path),
+                    ))
+                }
+                SymlinkPolicy::Follow => kind = std::fs::metadata(entry.path())?.file_type(),
+            }
+        }
+
+        if kind.is_dir() {
+            let canonical = entry.path().canonicalize()?;
+            if visited.contains(&canonical) {
+                continue;
+            }
+            visited.push(canonical);
+            collect(&entry.path(), &format!("{}/", path), policy, visited, files)?;
+        } else {
+            files.insert(path, std::fs::read(entry.path())?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_directory, verify_file, SymlinkPolicy};
+    use crate::Keccak256;
+    use std::path::PathBuf;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("merkle-tree-fs-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(directory.join("bin")).unwrap();
+        std::fs::write(directory.join("README"), "readme").unwrap();
+        std::fs::write(directory.join("bin/tool"), "tool").unwrap();
+        std::fs::write(directory.join("bin.txt"), "notes").unwrap();
+        directory
+    }
+
+    #[test]
+    fn hashes_directories() {
+        let directory = directory("hash");
+        let tree = hash_directory::<Keccak256>(&directory, SymlinkPolicy::Reject).unwrap();
+        assert_eq!(tree.paths(), ["README", "bin.txt", "bin/tool"]);
+
+        // Each file verifies on its own, but not with other contents or at another path
+        let root = tree.root();
+        let proof = tree.proof("bin/tool").unwrap();
+        assert!(verify_file("bin/tool", b"tool", &proof, root));
+        assert!(!verify_file("bin/tool", b"tampered", &proof, root));
+        assert!(!verify_file("bin.txt", b"tool", &proof, root));
+        assert!(tree.proof("missing").is_none());
+
+        // Trees change with the contents of any file
+        std::fs::write(directory.join("bin.txt"), "changed").unwrap();
+        let changed = hash_directory::<Keccak256>(&directory, SymlinkPolicy::Reject).unwrap();
+        assert_ne!(changed.root(), root);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn applies_symlink_policies() {
+        let directory = directory("links");
+        std::os::unix::fs::symlink("README", directory.join("link")).unwrap();
+        std::os::unix::fs::symlink("..", directory.join("bin/parent")).unwrap();
+
+        let hash = |policy| hash_directory::<Keccak256>(&directory, policy);
+        assert!(hash(SymlinkPolicy::Reject).is_err());
+        assert_eq!(
+            hash(SymlinkPolicy::Skip).unwrap().paths(),
+            ["README", "bin.txt", "bin/tool"]
+        );
+
+        let target = hash(SymlinkPolicy::Target).unwrap();
+        let proof = target.proof("link").unwrap();
+        assert!(verify_file("link", b"README", &proof, target.root()));
+
+        // Followed links are hashed as what they point to, without following cycles
+        let followed = hash(SymlinkPolicy::Follow).unwrap();
+        assert_eq!(followed.paths(), ["README", "bin.txt", "bin/tool", "link"]);
+        let proof = followed.proof("link").unwrap();
+        assert!(verify_file("link", b"readme", &proof, followed.root()));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
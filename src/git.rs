@@ -0,0 +1,272 @@
+use crate::HashFunction;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// The mode of an entry within a git tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitMode {
+    File,
+    Executable,
+    Symlink,
+    Tree,
+    /// A commit of another repository.
+    Submodule,
+}
+
+impl GitMode {
+    /// The mode as written within tree objects, which omits the leading zero of directories.
+    fn as_str(&self) -> &'static str {
+        match self {
+            GitMode::File => "100644",
+            GitMode::Executable => "100755",
+            GitMode::Symlink => "120000",
+            GitMode::Tree => "40000",
+            GitMode::Submodule => "160000",
+        }
+    }
+}
+
+/// A directory hashed as a git tree object, so object IDs can be computed and checked against
+/// those of a repository using `T` as its object hash, being `Sha1` or `Sha256`.
+pub struct GitTree<T: HashFunction> {
+    entries: Vec<(String, GitMode, T::Hash)>,
+}
+
+impl<T: HashFunction> GitTree<T> {
+    pub fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    /// The ID of an object, being the hash of its contents prefixed with a header of its kind and
+    /// length.
+    pub fn object_id(kind: &str, contents: &[u8]) -> T::Hash {
+        let mut object = format!("{} {}\0", kind, contents.len()).into_bytes();
+        object.extend_from_slice(contents);
+        T::hash(&object)
+    }
+
+    /// The ID of a blob holding `contents`, as given by `git hash-object`.
+    pub fn blob_id(contents: &[u8]) -> T::Hash {
+        GitTree::<T>::object_id("blob", contents)
+    }
+
+    /// Adds an entry, replacing any entry of the same name.
+    pub fn insert(&mut self, name: &str, mode: GitMode, id: T::Hash) {
+        self.entries.retain(|(n, ..)| n != name);
+        self.entries.push((name.to_string(), mode, id));
+    }
+
+    /// Adds a file holding `contents`, returning its blob ID.
+    pub fn insert_blob(&mut self, name: &str, contents: &[u8]) -> T::Hash {
+        let id = GitTree::<T>::blob_id(contents);
+        self.insert(name, GitMode::File, id);
+        id
+    }
+
+    /// Adds a subdirectory, returning its tree ID.
+    pub fn insert_tree(&mut self, name: &str, tree: &GitTree<T>) -> T::Hash {
+        let id = tree.id();
+        self.insert(name, GitMode::Tree, id);
+        id
+    }
+
+    /// Hashes a directory as `git write-tree` would after adding all of its contents. As in git,
+    /// empty directories are omitted and `.git` directories are skipped.
+    pub fn from_dir(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let mut tree = GitTree::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let name = entry.file_name().into_string().map_err(|name| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("file name {:?} is not valid UTF-8", name),
+                )
+            })?;
+            let kind = entry.file_type()?;
+            if kind.is_dir() {
+                if name == ".git" {
+                    continue;
+                }
+                let subtree = GitTree::from_dir(entry.path())?;
+                if !subtree.is_empty() {
+                    tree.insert_tree(&name, &subtree);
+                }
+            } else if kind.is_symlink() {
+                let target = std::fs::read_link(entry.path())?;
+                let target = target.to_string_lossy();
+                tree.insert(
+                    &name,
+                    GitMode::Symlink,
+                    GitTree::<T>::blob_id(target.as_bytes()),
+                );
+            } else {
+                let id = GitTree::<T>::blob_id(&std::fs::read(entry.path())?);
+                let mode = match is_executable(&entry.metadata()?) {
+                    true => GitMode::Executable,
+                    false => GitMode::File,
+                };
+                tree.insert(&name, mode, id);
+            }
+        }
+        Ok(tree)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, GitMode, T::Hash)> {
+        self.entries
+            .iter()
+            .map(|(name, mode, id)| (name.as_str(), *mode, *id))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The contents of the tree object, being each entry in git's order. Git orders entries by
+    /// name, comparing the names of subdirectories as though they end with `/`.
+    pub fn encode(&self) -> Vec<u8> {
+        let key = |(name, mode, _): &(String, GitMode, T::Hash)| match mode {
+            GitMode::Tree => format!("{}/", name),
+            _ => name.clone(),
+        };
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by_key(|entry| key(entry));
+
+        let mut contents = vec![];
+        for (name, mode, id) in entries {
+            contents.extend_from_slice(format!("{} {}\0", mode.as_str(), name).as_bytes());
+            contents.extend_from_slice(id.as_ref());
+        }
+        contents
+    }
+
+    /// The ID of the tree, as given by `git write-tree`.
+    pub fn id(&self) -> T::Hash {
+        GitTree::<T>::object_id("tree", &self.encode())
+    }
+
+    /// Whether the tree has the supplied ID, such as one taken from a signed commit.
+    pub fn verify(&self, id: impl Into<T::Hash>) -> bool {
+        self.id() == id.into()
+    }
+}
+
+impl<T: HashFunction> Default for GitTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(all(test, feature = "sha1"))]
+mod tests {
+    use crate::{GitMode, GitTree, HashFunction, Sha1};
+
+    fn id(hex: &str) -> [u8; 20] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn hashes_objects() {
+        assert_eq!(
+            GitTree::<Sha1>::blob_id(b""),
+            id("e69de29bb2d1d6434b8b29ae775ad8c2e48c5391")
+        );
+        assert_eq!(
+            GitTree::<Sha1>::blob_id(b"hello\n"),
+            id("ce013625030ba8dba906f756967f9e9ca394464a")
+        );
+        assert_eq!(
+            GitTree::<Sha1>::new().id(),
+            id("4b825dc642cb6eb9a060e54bf8d69288fbee4904")
+        );
+    }
+
+    /// Builds the tree of a directory as committed by git, with `src` ordered after `src.txt`.
+    fn committed<T: HashFunction>() -> GitTree<T> {
+        let mut src = GitTree::new();
+        src.insert_blob("main.rs", b"fn main() {}\n");
+        let mut tree = GitTree::new();
+        tree.insert_tree("src", &src);
+        tree.insert_blob("src.txt", b"x");
+        tree.insert_blob("hello.txt", b"hello\n");
+        tree.insert(
+            "run.sh",
+            GitMode::Executable,
+            GitTree::<T>::blob_id(b"#!/bin/sh\n"),
+        );
+        tree.insert(
+            "link",
+            GitMode::Symlink,
+            GitTree::<T>::blob_id(b"hello.txt"),
+        );
+        tree
+    }
+
+    #[test]
+    fn hashes_trees() {
+        let mut tree = committed::<Sha1>();
+        assert!(tree.verify(id("9f48dfec0ff90907898165a5fbce324e69a56ee0")));
+
+        // Entries are replaced by name, and ordered by git rather than by insertion
+        tree.insert_blob("hello.txt", b"changed");
+        assert_eq!(tree.entries().count(), 5);
+        assert!(!tree.verify(id("9f48dfec0ff90907898165a5fbce324e69a56ee0")));
+        tree.insert_blob("hello.txt", b"hello\n");
+        assert!(tree.verify(id("9f48dfec0ff90907898165a5fbce324e69a56ee0")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hashes_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory =
+            std::env::temp_dir().join(format!("merkle-tree-git-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("src/empty")).unwrap();
+        std::fs::create_dir_all(directory.join(".git")).unwrap();
+        std::fs::write(directory.join(".git/HEAD"), "ignored").unwrap();
+        std::fs::write(directory.join("hello.txt"), "hello\n").unwrap();
+        std::fs::write(directory.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(directory.join("src.txt"), "x").unwrap();
+        std::fs::write(directory.join("run.sh"), "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(
+            directory.join("run.sh"),
+            std::fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink("hello.txt", directory.join("link")).unwrap();
+
+        let tree = GitTree::<Sha1>::from_dir(&directory).unwrap();
+        assert_eq!(tree.id(), committed::<Sha1>().id());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn hashes_sha256_objects() {
+        use crate::Sha256;
+
+        assert_eq!(
+            hex::encode(GitTree::<Sha256>::blob_id(b"hello\n")),
+            "2cf8d83d9ee29543b34a87727421fdecb7e3f3a183d337639025de576db9ebb4"
+        );
+        assert_eq!(
+            hex::encode(GitTree::<Sha256>::new().id()),
+            "6ef19b41225c5369f1c104d45d8d85efa9b057b53b14b4b9b939dd74decc5321"
+        );
+        assert_eq!(
+            hex::encode(committed::<Sha256>().id()),
+            "b68435972a1d8b5f111c313a6471bc46c03a7111cbed9baad44a756d295011fa"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{HashFunction, MerkleTree};
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Exports the tree as a Graphviz digraph, with an edge from each node to its children and the
+    /// leaves ranked together, for rendering with `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        let layers = self.layers_hex_encoded();
+        let mut dot = String::from("digraph merkle {\n  node [shape=box, fontname=monospace];\n");
+        for (layer, nodes) in layers.iter().enumerate() {
+            for (index, hash) in nodes.iter().enumerate() {
+                dot.push_str(&format!("  n{}_{} [label=\"{}\"];\n", layer, index, hash));
+            }
+        }
+        for (layer, nodes) in layers.iter().enumerate().skip(1) {
+            for index in 0..nodes.len() {
+                for child in self.children(layer, index) {
+                    dot.push_str(&format!(
+                        "  n{}_{} -> n{}_{};\n",
+                        layer,
+                        index,
+                        layer - 1,
+                        child
+                    ));
+                }
+            }
+        }
+        if let Some(leaves) = layers.first().filter(|leaves| !leaves.is_empty()) {
+            let ranked: Vec<String> = (0..leaves.len()).map(|i| format!("n0_{}", i)).collect();
+            dot.push_str(&format!("  {{ rank=same; {}; }}\n", ranked.join("; ")));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Exports the tree as nested JSON nodes from the root, each with its `hash` and `children`,
+    /// or `null` for an empty tree.
+    pub fn to_json(&self) -> String {
+        let layers = self.layers_hex_encoded();
+        match layers.last().filter(|root| !root.is_empty()) {
+            Some(_) => self.node_json(&layers, layers.len() - 1, 0),
+            None => "null".to_string(),
+        }
+    }
+
+    fn node_json(&self, layers: &[Vec<String>], layer: usize, index: usize) -> String {
+        let children: Vec<String> = match layer {
+            0 => vec![],
+            _ => self
+                .children(layer, index)
+                .map(|child| self.node_json(layers, layer - 1, child))
+                .collect(),
+        };
+        format!(
+            "{{\"hash\":\"{}\",\"children\":[{}]}}",
+            layers[layer][index],
+            children.join(",")
+        )
+    }
+
+    /// The indices of the children of a node within the layer below it.
+    fn children(&self, layer: usize, index: usize) -> std::ops::Range<usize> {
+        let below = self.layers[layer - 1].len();
+        let start = (index * self.spec.arity).min(below);
+        start..below.min(start + self.spec.arity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleTree};
+
+    #[test]
+    fn exports_structure() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Keccak256>::new(&leaves);
+        let hex = |hash: [u8; 32]| format!("0x{}", hex::encode(hash));
+
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph merkle {"));
+        assert!(dot.contains(&format!("n2_0 [label=\"{}\"];", hex(tree.root()))));
+        for edge in [
+            "n2_0 -> n1_0;",
+            "n2_0 -> n1_1;",
+            "n1_0 -> n0_1;",
+            "n1_1 -> n0_2;",
+        ] {
+            assert!(dot.contains(edge), "{}", edge);
+        }
+        assert!(!dot.contains("n1_1 -> n0_3"));
+        assert!(dot.contains("{ rank=same; n0_0; n0_1; n0_2; }"));
+
+        let json = tree.to_json();
+        let leaf = |i: usize| format!("{{\"hash\":\"{}\",\"children\":[]}}", hex(tree.leaves()[i]));
+        assert!(json.starts_with(&format!(
+            "{{\"hash\":\"{}\",\"children\":[",
+            hex(tree.root())
+        )));
+        assert!(json.contains(&format!("[{},{}]", leaf(0), leaf(1))));
+        assert!(json.contains(&format!("[{}]", leaf(2))));
+
+        let empty = MerkleTree::<Keccak256>::new(&[]);
+        assert_eq!(empty.to_json(), "null");
+        assert_eq!(
+            empty.to_dot(),
+            "digraph merkle {\n  node [shape=box, fontname=monospace];\n}\n"
+        );
+    }
+}
@@ -1,34 +1,221 @@
+//! The hash functions trees are built with, each enabled by the feature of the same name other
+//! than Keccak256, which is always available. Other hash functions implement `HashFunction`.
+
+use crate::LeafKey;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::marker::PhantomData;
 use sha3::Digest;
+use zeroize::Zeroize;
+
+/// A hash of `N` bytes, as output by each of the fixed length hash functions. Proofs, leaves and
+/// roots are typed by the hash function, so those of hashes of different lengths cannot be mixed.
+pub type Hash<const N: usize> = [u8; N];
 
 pub trait HashFunction: Default {
     type Hash: Copy + PartialEq + Into<Vec<u8>> + TryFrom<Vec<u8>> + Ord + Default + AsRef<[u8]>;
 
+    /// Name identifying the hash function within a `TreeSpec`.
+    const NAME: &'static str;
+
+    /// The number of bytes the hash function processes at a time, which keys are padded to by
+    /// HMAC. Most hashes have 64 byte blocks, while sponges such as Keccak256 use their rate.
+    const BLOCK_SIZE: usize = 64;
+
     fn hash(value: &[u8]) -> Self::Hash;
+
+    /// Hashes several values at once, in order. Leaves and the groups of nodes of each layer are
+    /// hashed in batches through this, so backends hashing several values in parallel, such as
+    /// SIMD Keccak or a GPU, override it to speed up building trees. By default each value is
+    /// hashed in turn.
+    fn hash_many(values: &[&[u8]]) -> Vec<Self::Hash> {
+        values.iter().map(|value| Self::hash(value)).collect()
+    }
+
+    /// Hashes a value under a secret key, so that the hashes of guessable values such as
+    /// addresses cannot be recomputed without the key. By default the length of the key, the key
+    /// and then the value are hashed together, which is a secure MAC for sponges such as Keccak.
+    /// Hashes open to length extension override this with HMAC.
+    fn hash_keyed(key: &[u8], value: &[u8]) -> Self::Hash {
+        let mut keyed = Vec::with_capacity(8 + key.len() + value.len());
+        keyed.extend_from_slice(&(key.len() as u64).to_le_bytes());
+        keyed.extend_from_slice(key);
+        keyed.extend_from_slice(value);
+        let hash = Self::hash(&keyed);
+        keyed.zeroize();
+        hash
+    }
+
+    /// Creates an instance of the hash function keyed by a secret salt, to build trees with by
+    /// `MerkleTree::with_hasher`.
+    fn with_salt(salt: impl Into<Vec<u8>>) -> Salted<Self> {
+        Salted {
+            key: LeafKey::new(salt),
+            phantom: PhantomData,
+        }
+    }
 }
 
-pub struct Keccak256 {}
+/// A hash function keyed by a secret salt, with which leaf hashes are computed by `hash_keyed`.
+pub struct Salted<T: HashFunction> {
+    key: LeafKey,
+    phantom: PhantomData<T>,
+}
+
+impl<T: HashFunction> Salted<T> {
+    pub fn key(&self) -> &LeafKey {
+        &self.key
+    }
 
-impl Default for Keccak256 {
-    fn default() -> Self {
-        Self {}
+    pub fn hash(&self, value: &[u8]) -> T::Hash {
+        T::hash_keyed(self.key.expose_secret(), value)
     }
 }
 
+/// HMAC as in RFC 2104, with keys padded to the block size of the hash function.
+#[cfg(any(
+    feature = "std",
+    feature = "ripemd160",
+    feature = "sha1",
+    feature = "sha256"
+))]
+pub(crate) fn hmac<T: HashFunction>(key: &[u8], value: &[u8]) -> T::Hash {
+    let mut block = alloc::vec![0u8; T::BLOCK_SIZE];
+    match key.len() > T::BLOCK_SIZE {
+        true => {
+            let hash = T::hash(key);
+            block[..hash.as_ref().len()].copy_from_slice(hash.as_ref());
+        }
+        false => block[..key.len()].copy_from_slice(key),
+    }
+
+    let mut inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    inner.extend_from_slice(value);
+    let mut outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(T::hash(&inner).as_ref());
+    let hash = T::hash(&outer);
+    block.zeroize();
+    inner.zeroize();
+    outer.zeroize();
+    hash
+}
+
+#[derive(Default)]
+pub struct Keccak256 {}
+
 impl HashFunction for Keccak256 {
-    type Hash = [u8; 32];
+    type Hash = Hash<32>;
+
+    const NAME: &'static str = "keccak256";
+
+    const BLOCK_SIZE: usize = 136;
 
     fn hash(value: &[u8]) -> Self::Hash {
         let mut hasher = sha3::Keccak256::default();
         hasher.update(value);
         hasher.finalize().into()
     }
+
+    /// With the `keccak-simd` feature, several values are permuted together in SIMD lanes.
+    #[cfg(feature = "keccak-simd")]
+    fn hash_many(values: &[&[u8]]) -> Vec<Self::Hash> {
+        match values.len() {
+            0 | 1 => values.iter().map(|value| Self::hash(value)).collect(),
+            _ => crate::keccak::keccak256_many(values),
+        }
+    }
+}
+
+/// RIPEMD-160, for interoperating with legacy systems such as Bitcoin script hashes.
+#[cfg(feature = "ripemd160")]
+#[derive(Default)]
+pub struct Ripemd160 {}
+
+#[cfg(feature = "ripemd160")]
+impl HashFunction for Ripemd160 {
+    type Hash = Hash<20>;
+
+    const NAME: &'static str = "ripemd160";
+
+    fn hash(value: &[u8]) -> Self::Hash {
+        let mut hasher = ripemd::Ripemd160::default();
+        hasher.update(value);
+        hasher.finalize().into()
+    }
+
+    fn hash_keyed(key: &[u8], value: &[u8]) -> Self::Hash {
+        hmac::<Self>(key, value)
+    }
+}
+
+/// SHA-256, as used by Bitcoin and by git repositories using SHA-256 object IDs.
+#[cfg(feature = "sha256")]
+#[derive(Default)]
+pub struct Sha256 {}
+
+#[cfg(feature = "sha256")]
+impl HashFunction for Sha256 {
+    type Hash = Hash<32>;
+
+    const NAME: &'static str = "sha256";
+
+    fn hash(value: &[u8]) -> Self::Hash {
+        let mut hasher = sha2::Sha256::default();
+        hasher.update(value);
+        hasher.finalize().into()
+    }
+
+    fn hash_keyed(key: &[u8], value: &[u8]) -> Self::Hash {
+        hmac::<Self>(key, value)
+    }
+}
+
+/// SHA-256 applied twice, as Bitcoin hashes transactions and the nodes of their trees.
+#[cfg(feature = "sha256")]
+#[derive(Default)]
+pub struct DoubleSha256 {}
+
+#[cfg(feature = "sha256")]
+impl HashFunction for DoubleSha256 {
+    type Hash = Hash<32>;
+
+    const NAME: &'static str = "sha256d";
+
+    fn hash(value: &[u8]) -> Self::Hash {
+        Sha256::hash(&Sha256::hash(value))
+    }
+}
+
+/// SHA-1, for interoperating with legacy systems such as git object trees. SHA-1 is not collision
+/// resistant, so should not be used where proofs may be forged by an adversary.
+#[cfg(feature = "sha1")]
+#[derive(Default)]
+pub struct Sha1 {}
+
+#[cfg(feature = "sha1")]
+impl HashFunction for Sha1 {
+    type Hash = Hash<20>;
+
+    const NAME: &'static str = "sha1";
+
+    fn hash(value: &[u8]) -> Self::Hash {
+        let mut hasher = sha1::Sha1::default();
+        hasher.update(value);
+        hasher.finalize().into()
+    }
+
+    fn hash_keyed(key: &[u8], value: &[u8]) -> Self::Hash {
+        hmac::<Self>(key, value)
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
+#[allow(clippy::needless_borrow)]
 mod tests {
     use crate::hash_functions::Keccak256;
     use crate::HashFunction;
     use primitive_types::H160;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn hashes_address() {
@@ -53,4 +240,136 @@ mod tests {
             "3ac225168df54212a25c1c01fd35bebfea408fdac2e31ddd6f80a4bbf9a5f1cb"
         );
     }
+
+    #[cfg(feature = "ripemd160")]
+    #[test]
+    fn hashes_ripemd160() {
+        use crate::hash_functions::Ripemd160;
+
+        assert_eq!(
+            hex::encode(Ripemd160::hash(b"abc")),
+            "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"
+        );
+        verify_all::<Ripemd160>();
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn hashes_sha1() {
+        use crate::hash_functions::Sha1;
+
+        assert_eq!(
+            hex::encode(Sha1::hash(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        verify_all::<Sha1>();
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn hashes_sha256() {
+        use crate::hash_functions::Sha256;
+
+        assert_eq!(
+            hex::encode(Sha256::hash(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        // RFC 4231 test cases 2 and 6, the latter with a key longer than a block
+        assert_eq!(
+            hex::encode(Sha256::hash_keyed(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex::encode(Sha256::hash_keyed(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn hashes_keyed() {
+        let salted = Keccak256::with_salt(b"salt".to_vec());
+        assert_eq!(salted.hash(b"a"), Keccak256::hash_keyed(b"salt", b"a"));
+        assert_ne!(salted.hash(b"a"), Keccak256::hash(b"a"));
+        assert_ne!(salted.hash(b"a"), Keccak256::hash_keyed(b"salt2", b"a"));
+        // The key is length prefixed, so cannot be shifted into the value
+        assert_ne!(
+            Keccak256::hash_keyed(b"ab", b"c"),
+            Keccak256::hash_keyed(b"a", b"bc")
+        );
+    }
+
+    /// Keccak256, counting the leaves and the groups of nodes hashed in batches as a vectorized
+    /// backend would.
+    #[derive(Default)]
+    struct Batched;
+
+    static LEAVES: AtomicUsize = AtomicUsize::new(0);
+    static NODES: AtomicUsize = AtomicUsize::new(0);
+
+    impl HashFunction for Batched {
+        type Hash = [u8; 32];
+
+        const NAME: &'static str = "keccak256";
+
+        fn hash(value: &[u8]) -> Self::Hash {
+            Keccak256::hash(value)
+        }
+
+        fn hash_many(values: &[&[u8]]) -> Vec<Self::Hash> {
+            for value in values {
+                match value.len() {
+                    64 => NODES.fetch_add(1, Ordering::SeqCst),
+                    _ => LEAVES.fetch_add(1, Ordering::SeqCst),
+                };
+            }
+            values.iter().map(|value| Keccak256::hash(value)).collect()
+        }
+    }
+
+    #[test]
+    fn hashes_leaves_in_batches() {
+        use crate::{Bytes, MerkleTree, MerkleTreeBuilder};
+
+        let values: Vec<[u8; 2]> = (0..600u16).map(|i| i.to_be_bytes()).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let mut tree = MerkleTree::<Batched>::new(&leaves[..500]);
+        // Each pair hashed reduces the nodes of the tree by one until the root remains
+        assert_eq!(NODES.load(Ordering::SeqCst), 499);
+        tree.extend(&leaves[500..]);
+        let streamed = MerkleTreeBuilder::<Batched>::new()
+            .leaf_prefix(&[0])
+            .build_from_iter(&leaves);
+        assert_eq!(LEAVES.load(Ordering::SeqCst), 1200);
+
+        assert_eq!(tree.root(), MerkleTree::<Keccak256>::new(&leaves).root());
+        let expected = MerkleTreeBuilder::<Keccak256>::new()
+            .leaf_prefix(&[0])
+            .build(&leaves);
+        assert_eq!(streamed.root(), expected.root());
+    }
+
+    /// Builds trees of 20 byte hashes, checking every proof verifies.
+    #[cfg(any(feature = "ripemd160", feature = "sha1"))]
+    fn verify_all<T: HashFunction>() {
+        use crate::{Bytes, MerkleTree};
+
+        let leaves: Vec<&Bytes> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|x| x.as_bytes())
+            .collect();
+        for tree in [
+            MerkleTree::<T>::new(&leaves),
+            MerkleTree::<T>::new_ordered(&leaves),
+        ] {
+            assert_eq!(tree.root().as_ref().len(), 20);
+            assert!(tree.spec().to_string().starts_with(T::NAME));
+            for leaf in tree.leaves() {
+                assert!(tree.verify(tree.proof(*leaf), *leaf, tree.root()));
+            }
+        }
+    }
 }
@@ -0,0 +1,123 @@
+use crate::{HashFunction, MerkleProof, MerkleTree, MerkleTreeBuilder};
+use std::borrow::Cow;
+
+/// A value which can be a leaf, given by the bytes which are hashed for it. Byte strings and
+/// strings are hashed as they are, integers as their big endian bytes and, with the `abi`
+/// feature, addresses and 32 byte values from `primitive-types` as their raw bytes.
+pub trait Hashable {
+    fn to_leaf_bytes(&self) -> Cow<'_, [u8]>;
+}
+
+impl<H: Hashable + ?Sized> Hashable for &H {
+    fn to_leaf_bytes(&self) -> Cow<'_, [u8]> {
+        (**self).to_leaf_bytes()
+    }
+}
+
+impl Hashable for [u8] {
+    fn to_leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<const N: usize> Hashable for [u8; N] {
+    fn to_leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl Hashable for Vec<u8> {
+    fn to_leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl Hashable for str {
+    fn to_leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+}
+
+impl Hashable for String {
+    fn to_leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+}
+
+macro_rules! hashable_integer {
+    ($($t:ty),*) => {$(
+        impl Hashable for $t {
+            fn to_leaf_bytes(&self) -> Cow<'_, [u8]> {
+                Cow::Owned(self.to_be_bytes().to_vec())
+            }
+        }
+    )*};
+}
+
+hashable_integer!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+#[cfg(feature = "abi")]
+mod primitive {
+    use super::Hashable;
+    use primitive_types::{H160, H256};
+    use std::borrow::Cow;
+
+    impl Hashable for H160 {
+        fn to_leaf_bytes(&self) -> Cow<'_, [u8]> {
+            Cow::Borrowed(self.as_bytes())
+        }
+    }
+
+    impl Hashable for H256 {
+        fn to_leaf_bytes(&self) -> Cow<'_, [u8]> {
+            Cow::Borrowed(self.as_bytes())
+        }
+    }
+}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Builds a tree from items, each hashed from the bytes given by `Hashable`.
+    pub fn build_items<I: Hashable>(&self, items: &[I]) -> MerkleTree<T> {
+        let bytes: Vec<Cow<[u8]>> = items.iter().map(|i| i.to_leaf_bytes()).collect();
+        let leaves: Vec<&[u8]> = bytes.iter().map(|b| &b[..]).collect();
+        self.build(&leaves)
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Creates a tree with the default settings from items of any `Hashable` type.
+    pub fn from_items<I: Hashable>(items: &[I]) -> Self {
+        MerkleTreeBuilder::<T>::new().build_items(items)
+    }
+
+    /// The leaf hash of an item.
+    pub fn item_hash(&self, item: &(impl Hashable + ?Sized)) -> T::Hash {
+        MerkleTree::<T>::hash_leaf(&self.spec, &item.to_leaf_bytes())
+    }
+
+    /// Generates a proof for an item, which is empty if the item is not in the tree.
+    pub fn item_proof(&self, item: &(impl Hashable + ?Sized)) -> MerkleProof<T> {
+        self.proof(self.item_hash(item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HashFunction, Keccak256, MerkleTree};
+
+    #[test]
+    fn builds_from_items() {
+        let tree = MerkleTree::<Keccak256>::from_items(&[1u32, 2, 3]);
+        assert_eq!(tree.item_hash(&2u32), Keccak256::hash(&[0, 0, 0, 2]));
+        let proof = tree.item_proof(&3u32);
+        assert!(tree.verify(proof, tree.item_hash(&3u32), tree.root()));
+        assert!(tree.item_proof(&4u32).is_empty());
+
+        // Strings and byte strings of the same bytes are the same leaf
+        let tree = MerkleTree::<Keccak256>::from_items(&["a", "b", "c"]);
+        let bytes =
+            MerkleTree::<Keccak256>::from_items(&[b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+        assert_eq!(tree.root(), bytes.root());
+        assert_eq!(tree.item_proof("b"), bytes.item_proof(b"b"));
+    }
+}
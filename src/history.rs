@@ -0,0 +1,187 @@
+use crate::store::{out_of_bounds, position};
+use crate::{HashFunction, MerkleProof, MerkleTree, Store};
+use im::Vector;
+use std::collections::BTreeMap;
+
+/// Earlier versions of a tree, keyed by generation. Each layer is held in a persistent vector, so
+/// a version shares every node which is unchanged with the versions either side of it and only
+/// the nodes touched by a mutation take up additional memory.
+pub(crate) struct History<H: Clone> {
+    current: Vec<Vector<H>>,
+    versions: BTreeMap<u64, Vec<Vector<H>>>,
+}
+
+impl<H: Clone> History<H> {
+    fn new(layers: &[Vec<H>]) -> Self {
+        Self {
+            current: layers.iter().map(|l| l.iter().cloned().collect()).collect(),
+            versions: BTreeMap::new(),
+        }
+    }
+
+    /// Copies the nodes of each layer derived from leaves at or beyond the `dirty` index.
+    pub(crate) fn sync(&mut self, layers: &[Vec<H>], mut dirty: usize, arity: usize) {
+        self.current.truncate(layers.len());
+        for (i, layer) in layers.iter().enumerate() {
+            if i == self.current.len() {
+                self.current.push(Vector::new());
+            }
+
+            let nodes = &mut self.current[i];
+            nodes.truncate(dirty.min(nodes.len()));
+            nodes.extend(layer[dirty.min(layer.len())..].iter().cloned());
+            dirty /= arity;
+        }
+    }
+
+    /// Copies the nodes on the path from the leaf at `index` to the root.
+    pub(crate) fn sync_path(&mut self, layers: &[Vec<H>], mut index: usize, arity: usize) {
+        for (nodes, layer) in self.current.iter_mut().zip(layers) {
+            nodes.set(index, layer[index].clone());
+            index /= arity;
+        }
+    }
+
+    fn record(&mut self, generation: u64) {
+        self.versions.insert(generation, self.current.clone());
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Starts keeping every version of the tree from the current generation onwards, so roots and
+    /// proofs remain available after later mutations.
+    pub fn keep_history(&mut self) {
+        if self.history.is_none() {
+            let mut history = History::new(&self.layers);
+            history.record(self.generation());
+            self.history = Some(history);
+        }
+    }
+
+    /// Records the current version, if history is being kept.
+    pub(crate) fn record_version(&mut self) {
+        let generation = self.generation();
+        if let Some(history) = &mut self.history {
+            history.record(generation)
+        }
+    }
+
+    /// The generations for which a version of the tree is available.
+    pub fn versions(&self) -> Vec<u64> {
+        match &self.history {
+            Some(history) => history.versions.keys().copied().collect(),
+            None => vec![],
+        }
+    }
+
+    /// The root of the tree as it was at the supplied generation, if that version was kept.
+    pub fn root_at_version(&self, version: u64) -> Option<T::Hash> {
+        let layers = self.history.as_ref()?.versions.get(&version)?;
+        Some(match layers.last().and_then(|layer| layer.front()) {
+            Some(root) => *root,
+            None => T::Hash::default(),
+        })
+    }
+
+    /// Generates a proof for a leaf of the tree as it was at the supplied generation. An empty
+    /// proof is returned if that version was not kept or the leaf was not in the tree at the time.
+    pub fn proof_at_version(&self, version: u64, leaf: impl Into<T::Hash>) -> MerkleProof<T> {
+        match self
+            .history
+            .as_ref()
+            .and_then(|history| history.versions.get(&version))
+        {
+            Some(layers) => match position(layers, leaf.into()) {
+                Some(index) => self.proof_from(layers, index),
+                None => Default::default(),
+            },
+            None => Default::default(),
+        }
+    }
+
+    /// Discards the versions before the supplied generation.
+    pub fn forget_versions_before(&mut self, version: u64) {
+        if let Some(history) = &mut self.history {
+            history.versions = history.versions.split_off(&version);
+        }
+    }
+}
+
+/// Versions are only read, but may still be modified like any other store.
+impl<H: Clone> Store<H> for Vec<Vector<H>> {
+    fn layers(&self) -> usize {
+        self.len()
+    }
+
+    fn layer_len(&self, layer: usize) -> usize {
+        self.as_slice().get(layer).map_or(0, |l| l.len())
+    }
+
+    fn get(&self, layer: usize, index: usize) -> Option<H> {
+        self.as_slice().get(layer)?.get(index).cloned()
+    }
+
+    fn put(&mut self, layer: usize, index: usize, hash: H) -> std::io::Result<()> {
+        if layer == self.len() {
+            self.push(Vector::new());
+        }
+        match self.get_mut(layer) {
+            Some(nodes) if index < nodes.len() => {
+                nodes.set(index, hash);
+            }
+            Some(nodes) if index == nodes.len() => nodes.push_back(hash),
+            _ => return Err(out_of_bounds(layer, index)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, HashFunction, Keccak256, MerkleTreeBuilder, OddStrategy};
+
+    #[test]
+    fn proves_earlier_versions() {
+        let values: Vec<[u8; 1]> = (0..9).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for builder in [
+            MerkleTreeBuilder::<Keccak256>::new(),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .odd_strategy(OddStrategy::Duplicate)
+                .arity(3),
+        ] {
+            let mut tree = builder.build(&leaves[..3]);
+            tree.keep_history();
+
+            // Snapshot each version as it is produced
+            let mut expected = vec![builder.build(&leaves[..3])];
+            for leaf in &leaves[3..] {
+                tree.push(leaf);
+                expected.push(builder.build(&leaves[..tree.leaves().len()]));
+            }
+            tree.update(0, &[42]);
+            tree.remove(1);
+            assert_eq!(tree.versions(), (0..=8).collect::<Vec<u64>>());
+
+            for (version, snapshot) in expected.iter().enumerate() {
+                let version = version as u64;
+                assert_eq!(tree.root_at_version(version), Some(snapshot.root()));
+                for leaf in snapshot.leaves() {
+                    let proof = tree.proof_at_version(version, *leaf);
+                    assert_eq!(proof, snapshot.proof(*leaf));
+                    assert!(tree.verify(proof, *leaf, snapshot.root()));
+                }
+            }
+            assert_eq!(tree.root_at_version(8), Some(tree.root()));
+
+            // Leaves added later are not part of earlier versions
+            assert!(tree.proof_at_version(4, Keccak256::hash(&[8])).is_empty());
+
+            tree.forget_versions_before(6);
+            assert_eq!(tree.versions(), vec![6, 7, 8]);
+            assert_eq!(tree.root_at_version(5), None);
+            assert!(tree.proof_at_version(5, Keccak256::hash(&[0])).is_empty());
+        }
+    }
+}
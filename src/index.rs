@@ -0,0 +1,185 @@
+use crate::store::position;
+use crate::{HashFunction, MerkleProof, MerkleTree, Store};
+use alloc::collections::BTreeSet;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// The positions of every leaf, so leaves can be located without scanning the leaf layer.
+///
+/// Pairs are ordered by hash and then position, so the last position of a leaf which occurs more
+/// than once is found as the last pair with that hash, matching a reverse scan of the leaves.
+pub(crate) struct LeafIndex<H> {
+    positions: BTreeSet<(H, usize)>,
+}
+
+impl<H: Copy + Ord> LeafIndex<H> {
+    pub(crate) fn new(leaves: &[H]) -> Self {
+        Self {
+            positions: leaves.iter().copied().zip(0..).collect(),
+        }
+    }
+
+    fn get(&self, leaf: H) -> Option<usize> {
+        self.positions
+            .range((leaf, 0)..=(leaf, usize::MAX))
+            .next_back()
+            .map(|(_, index)| *index)
+    }
+
+    pub(crate) fn insert(&mut self, leaf: H, index: usize) {
+        self.positions.insert((leaf, index));
+    }
+
+    pub(crate) fn remove(&mut self, leaf: H, index: usize) {
+        self.positions.remove(&(leaf, index));
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// Indexes the leaves of a tree created from a store, so that subsequent lookups no longer
+    /// scan the leaf layer. Trees held in memory are always indexed.
+    pub fn index_leaves(&mut self) {
+        let leaves: Vec<T::Hash> = (0..self.layers.layer_len(0))
+            .map(|i| self.layers.get(0, i).unwrap_or_default())
+            .collect();
+        self.index = Some(LeafIndex::new(&leaves));
+    }
+
+    /// The index of a leaf within the tree, being the last index should the leaf occur more than
+    /// once.
+    pub fn index_of(&self, leaf: impl Into<T::Hash>) -> Option<usize> {
+        let leaf = leaf.into();
+        match &self.index {
+            Some(index) => index.get(leaf),
+            None => position(&self.layers, leaf),
+        }
+    }
+
+    /// The index of the leaf supplied at `position` when a deduplicated tree was built, so that
+    /// callers can find the proof of each of their leaves once duplicates have been removed. This
+    /// follows the leaf as the tree changes, and is `None` once the leaf is removed or if the tree
+    /// was not built deduplicated from a list of leaves.
+    pub fn leaf_index_for_input(&self, position: usize) -> Option<usize> {
+        let leaf = *self.inputs.as_ref()?.get(position)?;
+        self.index_of(leaf)
+    }
+
+    /// Generates a proof for the leaf at `index`, which is empty if there is no such leaf.
+    pub fn proof_by_index(&self, index: usize) -> MerkleProof<T> {
+        if index >= self.layers.layer_len(0) {
+            return Default::default();
+        }
+        self.proof_from(&self.layers, index)
+    }
+
+    /// Generates a proof for the leaf at `index`, if there is one. Trees with sorted leaves hold
+    /// leaves in sorted order, so `index` is then a position among the sorted leaves rather than
+    /// the order of insertion.
+    pub fn proof_at(&self, index: usize) -> Option<MerkleProof<T>> {
+        match index < self.layers.layer_len(0) {
+            true => Some(self.proof_from(&self.layers, index)),
+            false => None,
+        }
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Rebuilds the leaf index after leaves have shifted position.
+    pub(crate) fn reindex(&mut self) {
+        if self.index.is_some() {
+            self.index = Some(LeafIndex::new(&self.layers[0]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, HashFunction, Keccak256, MerkleTree, MerkleTreeBuilder};
+
+    #[test]
+    fn locates_leaves() {
+        let values: Vec<[u8; 1]> = (0..10).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for mut tree in [
+            MerkleTree::<Keccak256>::new(&leaves),
+            MerkleTree::<Keccak256>::new_ordered(&leaves),
+        ] {
+            tree.push(&[3]);
+            tree.push(&[42]);
+            tree.update(1, &[43]);
+            tree.remove(4);
+
+            for (index, leaf) in tree.leaves().clone().into_iter().enumerate() {
+                let expected = tree.leaves().iter().rposition(|l| *l == leaf);
+                assert_eq!(tree.index_of(leaf), expected);
+                if expected == Some(index) {
+                    assert_eq!(
+                        tree.proof_by_index(index).hashes(),
+                        tree.proof(leaf).hashes()
+                    );
+                }
+            }
+            assert_eq!(tree.index_of(Keccak256::hash(&[0xff])), None);
+            assert!(tree.proof_by_index(tree.leaves().len()).is_empty());
+            assert!(tree.proof_at(tree.leaves().len()).is_none());
+            assert_eq!(
+                tree.proof_at(0).unwrap().hashes(),
+                tree.proof_by_index(0).hashes()
+            );
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn indexes_stores() {
+        use crate::MemoryStore;
+
+        let values: Vec<[u8; 1]> = (0..10).map(|i| [i]).collect();
+        let builder = MerkleTreeBuilder::<Keccak256>::new();
+        let mut tree = builder.build_in(MemoryStore::new(), &values).unwrap();
+        let leaf = builder.hash_leaf(&[7]);
+        let index = tree.index_of(leaf);
+        assert!(index.is_some());
+        tree.index_leaves();
+        assert_eq!(tree.index_of(leaf), index);
+    }
+
+    #[test]
+    fn maps_inputs_to_deduplicated_leaves() {
+        let values = [[3], [1], [3], [2], [1]];
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for sort in [false, true] {
+            let builder = MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(sort)
+                .deduplicate(true);
+            let mut tree = builder.build(&leaves);
+            for (position, value) in values.iter().enumerate() {
+                let index = tree.leaf_index_for_input(position).unwrap();
+                assert_eq!(tree.leaves()[index], Keccak256::hash(value));
+                let proof = tree.proof_by_index(index);
+                assert!(tree.verify(proof, Keccak256::hash(value), tree.root()));
+            }
+            assert_eq!(tree.leaf_index_for_input(5), None);
+            assert_eq!(tree.leaf_index_for_input(0), tree.leaf_index_for_input(2));
+
+            // Positions follow their leaves as the tree changes
+            let removed = tree.leaf_index_for_input(1).unwrap();
+            tree.remove(removed);
+            assert_eq!(tree.leaf_index_for_input(4), None);
+            let index = tree.leaf_index_for_input(3).unwrap();
+            assert_eq!(tree.leaves()[index], Keccak256::hash(&[2]));
+            #[cfg(feature = "std")]
+            {
+                let summarised = builder.build_with_summary(&leaves).0;
+                assert_eq!(
+                    summarised.leaf_index_for_input(4),
+                    builder.build(&leaves).leaf_index_for_input(4)
+                );
+            }
+        }
+        assert_eq!(
+            MerkleTree::<Keccak256>::new(&leaves).leaf_index_for_input(0),
+            None
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{Bytes, HashFunction, MerkleTree, MerkleTreeBuilder};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// The distribution of input leaf lengths, used to catch leaves which were encoded inconsistently,
+/// such as addresses supplied partly as 42 byte checksummed hex strings and partly as 20 raw bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeafLengths {
+    counts: BTreeMap<usize, usize>,
+}
+
+impl LeafLengths {
+    pub fn of(leaves: &[&Bytes]) -> Self {
+        let mut counts = BTreeMap::new();
+        for leaf in leaves {
+            *counts.entry(leaf.len()).or_insert(0) += 1;
+        }
+        Self { counts }
+    }
+
+    /// The number of leaves of each length, in ascending order of length.
+    pub fn counts(&self) -> &BTreeMap<usize, usize> {
+        &self.counts
+    }
+
+    /// Whether every leaf has the same length.
+    pub fn is_uniform(&self) -> bool {
+        self.counts.len() <= 1
+    }
+
+    /// The most common length, preferring the longest when several are equally common.
+    pub fn most_common(&self) -> Option<usize> {
+        self.counts
+            .iter()
+            .max_by_key(|(length, count)| (**count, **length))
+            .map(|(length, _)| *length)
+    }
+}
+
+impl Display for LeafLengths {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let counts: Vec<String> = self
+            .counts
+            .iter()
+            .map(|(length, count)| format!("{} bytes x {}", length, count))
+            .collect();
+        write!(f, "leaf lengths: {}", counts.join(", "))
+    }
+}
+
+/// How leaves shorter than the required width are normalized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthPolicy {
+    /// Leaves of any other length are rejected.
+    Reject,
+    /// Shorter leaves are padded with leading zeros, as for big endian integers.
+    PadLeft,
+    /// Shorter leaves are padded with trailing zeros, as for fixed size byte strings.
+    PadRight,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LeafLengthError {
+    /// The leaf at `index` has a length which the policy cannot normalize to the width.
+    InvalidLength {
+        index: usize,
+        length: usize,
+        width: usize,
+    },
+}
+
+impl Display for LeafLengthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeafLengthError::InvalidLength {
+                index,
+                length,
+                width,
+            } => write!(
+                f,
+                "leaf at index {} is {} bytes but {} bytes are required",
+                index, length, width
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LeafLengthError {}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Reports the distribution of leaf lengths, which should normally be uniform.
+    pub fn diagnose(&self, leaves: &[&Bytes]) -> LeafLengths {
+        LeafLengths::of(leaves)
+    }
+
+    /// Builds a tree after normalizing every leaf to `width` bytes according to `policy`. Leaves
+    /// longer than the width are always rejected rather than truncated.
+    ///
+    /// Only the supplied leaves are normalized, so leaves later pushed to the tree must already be
+    /// of the required width.
+    pub fn build_normalized(
+        &self,
+        leaves: &[&Bytes],
+        width: usize,
+        policy: LengthPolicy,
+    ) -> Result<MerkleTree<T>, LeafLengthError> {
+        let mut normalized = Vec::with_capacity(leaves.len());
+        for (index, leaf) in leaves.iter().enumerate() {
+            let padding = vec![0; width.saturating_sub(leaf.len())];
+            let leaf = match policy {
+                _ if leaf.len() == width => leaf.to_vec(),
+                LengthPolicy::PadLeft if leaf.len() < width => [&padding[..], leaf].concat(),
+                LengthPolicy::PadRight if leaf.len() < width => [leaf, &padding[..]].concat(),
+                _ => {
+                    return Err(LeafLengthError::InvalidLength {
+                        index,
+                        length: leaf.len(),
+                        width,
+                    })
+                }
+            };
+            normalized.push(leaf);
+        }
+
+        let leaves: Vec<&Bytes> = normalized.iter().map(|l| &l[..]).collect();
+        Ok(self.build(&leaves))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, LeafLengthError, LengthPolicy, MerkleTreeBuilder};
+
+    #[test]
+    fn diagnoses_mixed_lengths() {
+        let raw = [0x11u8; 20];
+        let hex = "0x1111111111111111111111111111111111111111";
+        let leaves: Vec<&Bytes> = vec![&raw, &raw, hex.as_bytes(), &raw];
+
+        let lengths = MerkleTreeBuilder::<Keccak256>::new().diagnose(&leaves);
+        assert!(!lengths.is_uniform());
+        assert_eq!(lengths.most_common(), Some(20));
+        assert_eq!(
+            lengths.to_string(),
+            "leaf lengths: 20 bytes x 3, 42 bytes x 1"
+        );
+        assert!(MerkleTreeBuilder::<Keccak256>::new()
+            .diagnose(&leaves[..2])
+            .is_uniform());
+    }
+
+    #[test]
+    fn normalizes_leaves() {
+        let leaves: Vec<&Bytes> = vec![&[1], &[0, 2], &[0, 0, 3]];
+        let builder = MerkleTreeBuilder::<Keccak256>::new();
+
+        let padded: Vec<&Bytes> = vec![&[0, 0, 1], &[0, 0, 2], &[0, 0, 3]];
+        let tree = builder
+            .build_normalized(&leaves, 3, LengthPolicy::PadLeft)
+            .unwrap();
+        assert_eq!(tree.root(), builder.build(&padded).root());
+
+        let padded: Vec<&Bytes> = vec![&[1, 0, 0], &[0, 2, 0], &[0, 0, 3]];
+        let tree = builder
+            .build_normalized(&leaves, 3, LengthPolicy::PadRight)
+            .unwrap();
+        assert_eq!(tree.root(), builder.build(&padded).root());
+
+        assert_eq!(
+            builder
+                .build_normalized(&leaves, 3, LengthPolicy::Reject)
+                .err(),
+            Some(LeafLengthError::InvalidLength {
+                index: 0,
+                length: 1,
+                width: 3
+            })
+        );
+        assert_eq!(
+            builder
+                .build_normalized(&leaves, 2, LengthPolicy::PadLeft)
+                .err(),
+            Some(LeafLengthError::InvalidLength {
+                index: 2,
+                length: 3,
+                width: 2
+            })
+        );
+    }
+}
@@ -1,129 +1,827 @@
-use crate::hash_functions::HashFunction;
-use std::fmt::{Display, Formatter};
-use std::marker::PhantomData;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-mod hash_functions;
+extern crate alloc;
+
+use alloc::borrow::Cow;
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec, vec::Vec};
+use core::fmt::{Display, Formatter};
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use history::History;
+use index::LeafIndex;
+#[cfg(feature = "std")]
+use watch::Subscribers;
+use zeroize::Zeroize;
+
+#[cfg(feature = "std")]
+mod absence;
+#[cfg(feature = "std")]
+mod anchor;
+#[cfg(feature = "std")]
+mod anonymity;
+#[cfg(feature = "std")]
+mod arena;
+#[cfg(feature = "arkworks")]
+mod arkworks;
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(feature = "std")]
+mod audit;
+#[cfg(feature = "std")]
+mod batch;
+#[cfg(all(feature = "std", feature = "sha256"))]
+pub mod bitcoin;
+mod builder;
+#[cfg(feature = "std")]
+mod bulk;
+mod canonical;
+#[cfg(feature = "std")]
+mod chain;
+#[cfg(feature = "std")]
+pub mod chunker;
+#[cfg(feature = "std")]
+mod clock;
+#[cfg(feature = "std")]
+mod codegen;
+#[cfg(feature = "std")]
+mod compact;
+#[cfg(feature = "std")]
+mod compat;
+#[cfg(feature = "compression")]
+mod compression;
+#[cfg(feature = "std")]
+mod conformance;
+#[cfg(feature = "std")]
+mod consistency;
+#[cfg(feature = "std")]
+mod dedup;
+#[cfg(feature = "std")]
+mod diff;
+#[cfg(feature = "dsse")]
+mod dsse;
+#[cfg(feature = "std")]
+mod dynamic;
+#[cfg(feature = "std")]
+mod encoding;
+mod error;
+#[cfg(feature = "std")]
+mod file;
+#[cfg(feature = "std")]
+mod fixed;
+#[cfg(feature = "std")]
+pub mod fs;
+#[cfg(feature = "git")]
+mod git;
+#[cfg(feature = "std")]
+mod graph;
+pub mod hash_functions;
+#[cfg(feature = "std")]
+mod hashable;
+#[cfg(feature = "std")]
+mod history;
+mod index;
+#[cfg(feature = "keccak-simd")]
+mod keccak;
+#[cfg(feature = "std")]
+mod lengths;
+#[cfg(all(test, feature = "std"))]
+mod merkletreejs;
+#[cfg(feature = "tower")]
+mod middleware;
+#[cfg(feature = "std")]
+mod mmr;
+#[cfg(feature = "std")]
+pub mod mpt;
+#[cfg(feature = "std")]
+mod multiproof;
+#[cfg(feature = "openzeppelin")]
+mod openzeppelin;
+mod padding;
+#[cfg(feature = "std")]
+mod pagination;
+mod placeholder;
+#[cfg(feature = "poseidon")]
+mod poseidon;
+#[cfg(feature = "std")]
+mod prefetch;
+pub mod prelude;
+mod proof;
+#[cfg(feature = "std")]
+mod publication;
+#[cfg(feature = "std")]
+mod qr;
+#[cfg(feature = "record")]
+mod record;
+#[cfg(feature = "std")]
+mod schemes;
+#[cfg(feature = "std")]
+mod secret;
+#[cfg(feature = "std")]
+mod selftest;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "std")]
+mod solidity;
+#[cfg(all(test, feature = "std"))]
+mod soundness;
+#[cfg(feature = "std")]
+mod sparse;
+mod spec;
+#[cfg(feature = "std")]
+mod stats;
+mod store;
+#[cfg(feature = "std")]
+mod summary;
+#[cfg(feature = "std")]
+mod sync;
+mod verify;
+#[cfg(feature = "std")]
+mod versioned;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "std")]
+mod watch;
+#[cfg(feature = "std")]
+mod zk;
+
+#[cfg(feature = "std")]
+pub use crate::absence::{verify_absence, AbsenceProof, Neighbour};
+#[cfg(feature = "std")]
+pub use crate::anchor::{AnchorPolicy, Anchorer};
+#[cfg(feature = "std")]
+pub use crate::anonymity::{AnonymityPolicy, AnonymityWarning, MIN_ANONYMITY_SET};
+#[cfg(feature = "std")]
+pub use crate::arena::ArenaStore;
+#[cfg(feature = "arkworks")]
+pub use crate::arkworks::{verify_proof_gadget, MembershipCircuit};
+#[cfg(feature = "std")]
+pub use crate::audit::AuditError;
+pub use crate::builder::MerkleTreeBuilder;
+#[cfg(feature = "std")]
+pub use crate::bulk::Proofs;
+#[cfg(feature = "std")]
+pub use crate::chain::ChainedProof;
+#[cfg(feature = "std")]
+pub use crate::clock::{Clock, FixedClock, SystemClock};
+#[cfg(feature = "std")]
+pub use crate::codegen::CodegenError;
+#[cfg(feature = "std")]
+pub use crate::compact::CompressedProofs;
+#[cfg(feature = "std")]
+pub use crate::compat::{CompatibilityProfile, ProfileError};
+#[cfg(feature = "compression")]
+pub use crate::compression::{decompress, Compression, Compressor};
+#[cfg(feature = "std")]
+pub use crate::conformance::ConformanceKit;
+#[cfg(feature = "std")]
+pub use crate::consistency::{ConsistencyError, ConsistencyProof};
+#[cfg(feature = "std")]
+pub use crate::dedup::{DedupReport, Duplicate};
+#[cfg(feature = "std")]
+pub use crate::diff::TreeDiff;
+#[cfg(feature = "dsse")]
+pub use crate::dsse::{
+    DsseError, Envelope, EnvelopeSignature, EnvelopeSigner, EnvelopeVerifier, InclusionStatement,
+    PROOF_PAYLOAD_TYPE,
+};
+#[cfg(feature = "std")]
+pub use crate::dynamic::{hash_function, DynHashFunction, DynMerkleProof, DynMerkleTree};
+#[cfg(feature = "std")]
+pub use crate::encoding::{AbiEncode, AbiEncoder, LeafEncoder};
+pub use crate::error::MerkleError;
+#[cfg(feature = "std")]
+pub use crate::file::LoadError;
+#[cfg(feature = "std")]
+pub use crate::fixed::{FixedMerkleTree, FixedProof};
+#[cfg(feature = "git")]
+pub use crate::git::{GitMode, GitTree};
+#[cfg(feature = "ripemd160")]
+pub use crate::hash_functions::Ripemd160;
+#[cfg(feature = "sha1")]
+pub use crate::hash_functions::Sha1;
+#[cfg(feature = "sha256")]
+pub use crate::hash_functions::{DoubleSha256, Sha256};
+pub use crate::hash_functions::{Hash, HashFunction, Keccak256, Salted};
+#[cfg(feature = "std")]
+pub use crate::hashable::Hashable;
+#[cfg(feature = "std")]
+pub use crate::lengths::{LeafLengthError, LeafLengths, LengthPolicy};
+#[cfg(feature = "tower")]
+pub use crate::middleware::{
+    VerifiedLeaf, VerifyProof, VerifyProofLayer, DIRECTIONS_HEADER, LEAF_HEADER, PROOF_HEADER,
+    SIBLING_COUNTS_HEADER,
+};
+#[cfg(feature = "std")]
+pub use crate::mmr::{leaf_index_to_pos, MerkleMountainRange, MmrProof};
+#[cfg(feature = "std")]
+pub use crate::multiproof::MerkleMultiProof;
+#[cfg(feature = "openzeppelin")]
+pub use crate::openzeppelin::{StandardMerkleTree, StandardTreeError};
+#[cfg(feature = "std")]
+pub use crate::pagination::{LeafCursor, LeafPage, PageError};
+pub use crate::placeholder::{PlaceholderPolicy, Reveal};
+#[cfg(feature = "poseidon")]
+pub use crate::poseidon::{FieldElement, Poseidon, PoseidonField};
+pub use crate::proof::{Direction, MerkleProof};
+#[cfg(feature = "std")]
+pub use crate::publication::{Publication, PublishedLeaf, Visibility};
+#[cfg(feature = "std")]
+pub use crate::qr::{CompactError, CompactProof, QR_CAPACITY};
+#[cfg(feature = "record")]
+pub use crate::record::{Replay, ReplayError};
+#[cfg(feature = "std")]
+pub use crate::schemes::{PositionalProof, SortedProof};
+#[cfg(feature = "std")]
+pub use crate::secret::SecretLeaf;
+#[cfg(feature = "std")]
+pub use crate::selftest::SelfTestError;
+#[cfg(feature = "std")]
+pub use crate::sparse::{SparseMerkleProof, SparseMerkleTree};
+pub use crate::spec::{
+    Canonicalization, LeafKey, OddStrategy, PaddingLeaf, SpecError, TreeSpec, MAX_ARITY,
+};
+#[cfg(feature = "std")]
+pub use crate::stats::ProofStats;
+#[cfg(feature = "mmap")]
+pub use crate::store::MmapStore;
+#[cfg(feature = "sled")]
+pub use crate::store::SledStore;
+pub use crate::store::{MemoryStore, Store, StoreError};
+#[cfg(feature = "std")]
+pub use crate::summary::BuildSummary;
+#[cfg(feature = "std")]
+pub use crate::sync::SyncMerkleTree;
+pub use crate::verify::{verify_fixed, verify_proof, MAX_FIXED_PREFIX};
+#[cfg(feature = "std")]
+pub use crate::versioned::VersionedMerkleTree;
+#[cfg(feature = "wasm")]
+pub use crate::wasm::{JsMerkleProof, JsMerkleTree};
+#[cfg(feature = "std")]
+pub use crate::zk::ZkWitness;
 
 type Bytes = [u8];
-#[allow(type_alias_bounds)]
-type MerkleProof<T: HashFunction> = Vec<T::Hash>;
 
-pub struct MerkleTree<T: HashFunction> {
-    leaves: Vec<T::Hash>,
-    layers: Vec<Vec<T::Hash>>,
+/// The highest arity whose groups of siblings are completed and sorted on the stack.
+const STACK_ARITY: usize = 16;
+
+/// The number of leaves of an iterator hashed together by `HashFunction::hash_many`.
+const HASH_BATCH: usize = 256;
+
+/// A merkle tree, whose nodes are held in memory unless another store is supplied.
+pub struct MerkleTree<T: HashFunction, S = MemoryStore<<T as HashFunction>::Hash>> {
+    layers: S,
+    spec: TreeSpec,
+    #[cfg(feature = "std")]
+    subscribers: Subscribers<T::Hash>,
+    #[cfg(feature = "std")]
+    history: Option<History<T::Hash>>,
+    index: Option<LeafIndex<T::Hash>>,
+    /// The hash of each leaf supplied when a deduplicated tree was built, in the order supplied.
+    inputs: Option<Vec<T::Hash>>,
+    /// The placeholders reserved by `MerkleTreeBuilder::reserve_slots` which are yet to be
+    /// revealed.
+    placeholders: Vec<placeholder::Placeholder<T::Hash>>,
+    #[cfg(feature = "record")]
+    recorder: Option<std::sync::Mutex<record::Recorder>>,
     phantom: PhantomData<T>,
 }
 
 impl<T: HashFunction> MerkleTree<T> {
     pub fn new(leaves: &[&Bytes]) -> Self {
-        // Hash and sort leaves
-        let mut leaves: Vec<T::Hash> = leaves.iter().map(|l| MerkleTree::<T>::hash(l)).collect();
-        leaves.sort();
+        MerkleTreeBuilder::<T>::new().build(leaves)
+    }
 
-        // todo: deduplicate
+    /// Creates a tree which preserves the insertion order of leaves and hashes each pair in
+    /// position order, as used by RFC 6962 or Bitcoin. Proofs generated by the resulting tree
+    /// carry the direction of each sibling, which `verify` honours.
+    pub fn new_ordered(leaves: &[&Bytes]) -> Self {
+        MerkleTreeBuilder::<T>::new()
+            .sort_leaves(false)
+            .sort_pairs(false)
+            .build(leaves)
+    }
 
-        // Initialise layers with leaves
-        let mut layers = vec![leaves.clone()];
+    /// Creates a tree as `new` does, hashing leaves with a keyed hash function such as one created
+    /// by `Keccak256::with_salt`.
+    pub fn with_hasher(hasher: &Salted<T>, leaves: &[&Bytes]) -> Self {
+        MerkleTreeBuilder::<T>::with_hasher(hasher).build(leaves)
+    }
 
-        let mut nodes = leaves.clone();
-        while nodes.len() > 1 {
-            let layer_index = layers.len();
+    /// Creates a tree from leaves which are already hashed, such as those computed by another
+    /// system, giving the same tree as `new` would from the values they are the hashes of.
+    pub fn from_hashes(hashes: Vec<T::Hash>) -> Self {
+        MerkleTreeBuilder::<T>::new().build_from_hashes(hashes)
+    }
 
-            // Add next layer for resulting nodes
-            layers.push(vec![]);
+    pub(crate) fn build(spec: TreeSpec, leaves: &[&Bytes]) -> Self {
+        let leaves = MerkleTree::<T>::hash_leaves(&spec, leaves);
+        Self::build_from_hashes(spec, leaves)
+    }
 
-            // Process nodes in pairs
-            for i in (0..nodes.len()).step_by(2) {
-                if i + 1 == nodes.len() {
-                    if nodes.len() % 2 == 1 {
-                        // push copy of hash and continue iteration
-                        layers[layer_index].push(nodes[i]);
-                        continue;
-                    }
-                }
+    pub(crate) fn build_from_hashes(spec: TreeSpec, mut leaves: Vec<T::Hash>) -> Self {
+        // Sort and deduplicate leaves, retaining the supplied order to map positions to leaves
+        let inputs = spec.deduplicate.then(|| leaves.clone());
+        if spec.sort_leaves {
+            leaves.sort();
+        }
+        if spec.deduplicate {
+            MerkleTree::<T>::deduplicate(&spec, &mut leaves);
+        }
+        MerkleTree::<T>::pad_leaves(&spec, &mut leaves);
 
-                // Select pair and then sort
-                let left = nodes[i];
-                let right = if i + 1 == nodes.len() {
-                    left
-                } else {
-                    nodes[i + 1]
-                };
-                let mut pair = [left, right];
-                pair.sort();
+        // Initialise layers with leaves and then build remaining layers
+        let mut tree = Self::from_layers(spec, vec![leaves]);
+        tree.inputs = inputs;
+        tree.rehash(0);
+        tree
+    }
+
+    /// Builds a tree from leaves supplied one at a time, so that only their hashes are held.
+    ///
+    /// Each group of siblings is hashed into its parent as soon as it is complete, merging towards
+    /// the root as leaves arrive, with only the incomplete groups at the end of each layer hashed
+    /// once all leaves have been supplied. Trees with sorted or deduplicated leaves can only be
+    /// built once every leaf is known, so their leaf hashes are collected first.
+    pub(crate) fn build_from_iter<L: AsRef<Bytes>>(
+        spec: TreeSpec,
+        leaves: impl IntoIterator<Item = L>,
+    ) -> Self {
+        let leaves = MerkleTree::<T>::hash_batches(&spec, leaves);
+        if spec.sort_leaves || spec.deduplicate {
+            let leaves = leaves.collect();
+            return Self::build_from_hashes(spec, leaves);
+        }
 
-                // Create hash from pair and add to layer
-                let hash = MerkleTree::<T>::hash_pair(pair[0], pair[1]);
-                layers[layer_index].push(hash)
+        let arity = spec.arity;
+        let mut layers: Vec<Vec<T::Hash>> = vec![vec![]];
+        for leaf in leaves {
+            layers[0].push(leaf);
+
+            // Merge each completed group into its parent
+            let mut layer = 0;
+            while layers[layer].len() % arity == 0 {
+                let group = &layers[layer][layers[layer].len() - arity..];
+                let parent = MerkleTree::<T>::parent(&spec, group);
+                if layer + 1 == layers.len() {
+                    layers.push(vec![]);
+                }
+                layers[layer + 1].push(parent);
+                layer += 1;
             }
+        }
 
-            // Process next layer
-            nodes = layers[layer_index].clone()
+        // Complete the groups left incomplete at the end of each layer
+        let complete = layers[0].len() - layers[0].len() % arity;
+        MerkleTree::<T>::pad_leaves(&spec, &mut layers[0]);
+        let mut tree = Self::from_layers(spec, layers);
+        tree.rehash(complete);
+        tree
+    }
+
+    /// Removes all but the first occurrence of each leaf.
+    pub(crate) fn deduplicate(spec: &TreeSpec, leaves: &mut Vec<T::Hash>) {
+        if spec.sort_leaves {
+            leaves.dedup();
+        } else {
+            let mut seen = alloc::collections::BTreeSet::new();
+            leaves.retain(|leaf| seen.insert(*leaf));
         }
+    }
 
+    /// Creates a tree from layers which have already been computed.
+    pub(crate) fn from_layers(spec: TreeSpec, layers: Vec<Vec<T::Hash>>) -> Self {
         Self {
-            leaves,
+            index: Some(LeafIndex::new(&layers[0])),
+            inputs: None,
+            placeholders: vec![],
             layers,
+            spec,
+            #[cfg(feature = "std")]
+            subscribers: Default::default(),
+            #[cfg(feature = "std")]
+            history: None,
+            #[cfg(feature = "record")]
+            recorder: None,
             phantom: PhantomData,
         }
     }
 
-    pub fn root(&self) -> T::Hash {
-        if self.layers.len() == 0 {
-            return T::Hash::default();
-        }
-        self.layers[self.layers.len() - 1][0]
+    /// Appends a leaf to the tree, recomputing only the affected nodes.
+    ///
+    /// Leaves of a tree with unsorted leaves are appended in insertion order, so only the path
+    /// from the new leaf to the root is recomputed. Trees with sorted leaves insert the leaf at its
+    /// sorted position, which requires recomputing every node to the right of that position.
+    ///
+    /// Every node is held rather than only the peaks of the complete subtrees, so that proofs can
+    /// be generated for any leaf, and memory grows with the number of leaves.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tree has pre-hashed leaves and the leaf is not the length of a hash. Use
+    /// `try_push` for leaves from untrusted input.
+    pub fn push(&mut self, leaf: &Bytes) {
+        self.extend(&[leaf])
     }
 
-    pub fn proof(&self, leaf: T::Hash) -> MerkleProof<T> {
-        // Attempt to locate leaf index
-        let mut index = None;
-        for i in 0..self.leaves.len() {
-            if leaf == self.leaves[i] {
-                index = Some(i)
+    /// Appends several leaves to the tree, recomputing the affected nodes once for all of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tree has pre-hashed leaves and a leaf is not the length of a hash. Use
+    /// `try_extend` for leaves from untrusted input.
+    pub fn extend(&mut self, leaves: &[&Bytes]) {
+        self.extend_hashes(MerkleTree::<T>::hash_leaves(&self.spec, leaves))
+    }
+
+    /// Appends the hashes of several leaves, as `extend` does.
+    pub(crate) fn extend_hashes(&mut self, hashes: Vec<T::Hash>) {
+        if hashes.is_empty() {
+            return;
+        }
+
+        // Duplicates are found through the index, whose positions from `len` onwards are padding
+        // or leaves of this batch, which are tracked separately
+        self.unpad();
+        let len = self.layers[0].len();
+        let mut dirty = len;
+        let mut added = alloc::collections::BTreeSet::new();
+        for hash in hashes {
+            if self.spec.deduplicate
+                && (added.contains(&hash) || self.index_of(hash).is_some_and(|i| i < len))
+            {
+                continue;
+            }
+            if self.spec.deduplicate {
+                added.insert(hash);
+            }
+            let index = if self.spec.sort_leaves {
+                self.layers[0].partition_point(|l| *l <= hash)
+            } else {
+                self.layers[0].len()
+            };
+            self.layers[0].insert(index, hash);
+            dirty = dirty.min(index);
+            if let (false, Some(leaves)) = (self.spec.sort_leaves, &mut self.index) {
+                leaves.insert(hash, index);
             }
         }
+        self.pad();
 
-        if index.is_none() {
-            return Default::default();
+        if self.spec.sort_leaves || self.spec.padding.is_some() {
+            self.reindex();
         }
+        self.rehash(dirty);
+        #[cfg(feature = "std")]
+        self.publish();
+    }
 
-        let mut index = index.unwrap();
-        let mut proof = vec![];
-        for i in 0..self.layers.len() {
-            let layer = &self.layers[i];
-            let right_node = (index % 2) > 0;
-            let pair_index = if right_node { index - 1 } else { index + 1 };
+    /// Replaces the leaf at `index`, returning the hash of the previous leaf.
+    ///
+    /// For trees with unsorted leaves only the path from the leaf to the root is recomputed. Trees
+    /// with sorted leaves move the new leaf to its sorted position, recomputing every node to the
+    /// right of the earlier of the two positions.
+    ///
+    /// Deduplicated trees keep only the first occurrence of a leaf, so should the new leaf already
+    /// be present at another index, the later of the two is removed as `remove` would.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds, or if the tree has pre-hashed leaves and the leaf is not
+    /// the length of a hash. Use `try_update` for leaves from untrusted input.
+    pub fn update(&mut self, index: usize, leaf: &Bytes) -> T::Hash {
+        self.update_hash(index, MerkleTree::<T>::hash_leaf(&self.spec, leaf))
+    }
 
-            if pair_index < layer.len() {
-                proof.push(layer[pair_index])
+    /// Replaces the leaf at `index` with the hash of a leaf, as `update` does.
+    pub(crate) fn update_hash(&mut self, index: usize, hash: T::Hash) -> T::Hash {
+        if self.spec.deduplicate {
+            let len = self.unpadded_len();
+            if let Some(other) = self.index_of(hash).filter(|i| *i != index && *i < len) {
+                if self.spec.sort_leaves || other < index {
+                    return self.remove(index);
+                }
+                self.remove(other);
             }
+        }
+        if !self.spec.sort_leaves {
+            let previous = core::mem::replace(&mut self.layers[0][index], hash);
+            if let Some(leaves) = &mut self.index {
+                leaves.remove(previous, index);
+                leaves.insert(hash, index);
+            }
+            self.rehash_path(index);
+            #[cfg(feature = "std")]
+            self.publish();
+            return previous;
+        }
+
+        self.unpad();
+        let previous = self.layers[0].remove(index);
+        let position = self.layers[0].partition_point(|l| *l <= hash);
+        self.layers[0].insert(position, hash);
+        self.pad();
+        self.reindex();
+        self.rehash(index.min(position));
+        #[cfg(feature = "std")]
+        self.publish();
+        previous
+    }
+
+    /// Removes the leaf at `index`, returning its hash. As subsequent leaves shift to fill the
+    /// gap, every node to the right of the leaf is recomputed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T::Hash {
+        self.unpad();
+        let previous = self.layers[0].remove(index);
+        self.pad();
+        self.reindex();
+        self.rehash(index);
+        #[cfg(feature = "std")]
+        self.publish();
+        previous
+    }
+
+    /// Recomputes the nodes on the path from the leaf at `index` to the root, where the number of
+    /// leaves is unchanged.
+    fn rehash_path(&mut self, leaf: usize) {
+        let arity = self.spec.arity;
+        let mut index = leaf;
+        for layer in 0..self.layers.len() - 1 {
+            let start = index - index % arity;
+            let end = self.layers[layer].len().min(start + arity);
+            let parent = MerkleTree::<T>::parent(&self.spec, &self.layers[layer][start..end]);
 
             // set index to parent index
-            index = (index / 2) | 0
+            index /= arity;
+            self.layers[layer + 1][index] = parent;
+        }
+
+        #[cfg(feature = "std")]
+        if let Some(history) = &mut self.history {
+            history.sync_path(&self.layers, leaf, arity)
+        }
+    }
+
+    /// Recomputes all nodes derived from leaves at or beyond the `dirty` index, adding or removing
+    /// layers as the number of leaves requires.
+    fn rehash(&mut self, leaf: usize) {
+        let arity = self.spec.arity;
+        let mut dirty = leaf;
+        let mut layer = 0;
+        while self.layers[layer].len() > 1 {
+            if layer + 1 == self.layers.len() {
+                let len = self.layers[layer].len().div_ceil(arity);
+                self.layers.push(Vec::with_capacity(len));
+            }
+
+            // Recompute the parents of the dirty groups onwards, directly into the next layer
+            let start = dirty / arity;
+            let (below, above) = self.layers.split_at_mut(layer + 1);
+            let next = &mut above[0];
+            next.truncate(start);
+            next.extend(MerkleTree::<T>::parents(
+                &self.spec,
+                &below[layer][start * arity..],
+            ));
+
+            // Process next layer
+            dirty = start;
+            layer += 1;
         }
+        self.layers.truncate(layer + 1);
 
-        return proof;
+        #[cfg(feature = "std")]
+        if let Some(history) = &mut self.history {
+            history.sync(&self.layers, leaf, arity)
+        }
     }
 
-    pub fn leaves(&self) -> &Vec<T::Hash> {
-        &self.leaves
+    /// Computes the parents of consecutive groups of siblings, of which only the last may be
+    /// incomplete. Complete groups are hashed in batches through `HashFunction::hash_many`.
+    pub(crate) fn parents(spec: &TreeSpec, nodes: &[T::Hash]) -> Vec<T::Hash> {
+        let arity = spec.arity;
+        let complete = nodes.len() - nodes.len() % arity;
+        let mut parents = Vec::with_capacity(nodes.len().div_ceil(arity));
+        let mut preimages = vec![];
+        let mut ends = vec![];
+        let mut group = vec![];
+        for batch in nodes[..complete].chunks(arity * HASH_BATCH) {
+            preimages.clear();
+            ends.clear();
+            for siblings in batch.chunks(arity) {
+                group.clear();
+                group.extend_from_slice(siblings);
+                if spec.sort_pairs {
+                    group.sort_unstable();
+                }
+                preimages.extend_from_slice(&spec.node_prefix);
+                for node in &group {
+                    preimages.extend_from_slice(node.as_ref());
+                }
+                ends.push(preimages.len());
+            }
+            let values: Vec<&[u8]> = ends
+                .iter()
+                .scan(0, |start, end| {
+                    let value = &preimages[*start..*end];
+                    *start = *end;
+                    Some(value)
+                })
+                .collect();
+            parents.extend(T::hash_many(&values));
+        }
+        if complete < nodes.len() {
+            parents.push(MerkleTree::<T>::parent(spec, &nodes[complete..]));
+        }
+        parents
     }
 
-    pub fn verify(&self, proof: MerkleProof<T>, leaf: T::Hash, root: T::Hash) -> bool {
-        let mut hash = leaf.clone();
+    /// Computes the parent of a group of siblings, which is incomplete only at the end of a layer.
+    pub(crate) fn parent(spec: &TreeSpec, group: &[T::Hash]) -> T::Hash {
+        if group.len() == 1 && spec.odd_strategy == OddStrategy::Promote {
+            // promote copy of hash
+            return group[0];
+        }
+
+        // Complete group by duplicating its last node or with zero hashes if required, on the
+        // stack unless the arity is unusually high
+        let mut stack = [T::Hash::default(); STACK_ARITY];
+        let mut heap = vec![];
+        let complete = match spec.odd_strategy {
+            OddStrategy::Promote => group.len(),
+            OddStrategy::Duplicate | OddStrategy::ZeroPad => spec.arity,
+        };
+        let nodes = match complete <= STACK_ARITY {
+            true => &mut stack[..complete],
+            false => {
+                heap.resize(complete, T::Hash::default());
+                &mut heap[..]
+            }
+        };
+        nodes[..group.len()].copy_from_slice(group);
+        if spec.odd_strategy == OddStrategy::Duplicate {
+            nodes[group.len()..].fill(group[group.len() - 1]);
+        }
 
-        for i in 0..proof.len() {
-            let node = proof[i];
+        // Sort group, unless position is significant
+        if spec.sort_pairs {
+            nodes.sort_unstable();
+        }
 
-            if hash < node {
-                hash = MerkleTree::<T>::hash_pair(hash, node);
-            } else {
-                hash = MerkleTree::<T>::hash_pair(node, hash);
+        MerkleTree::<T>::hash_children(spec, nodes)
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    pub fn root(&self) -> T::Hash {
+        match self.layers.layers() {
+            0 => T::Hash::default(),
+            layers => self.layers.get(layers - 1, 0).unwrap_or_default(),
+        }
+    }
+
+    /// Generates a proof for a leaf, which is empty if the leaf is not in the tree.
+    ///
+    /// The leaf may be given as any type convertible to a hash, such as the `digest::Output` of a
+    /// RustCrypto hasher.
+    pub fn proof(&self, leaf: impl Into<T::Hash>) -> MerkleProof<T> {
+        let leaf = leaf.into();
+        let proof = match self.index_of(leaf) {
+            Some(index) => self.proof_from(&self.layers, index),
+            None => Default::default(),
+        };
+        #[cfg(feature = "record")]
+        self.record_proof(leaf, &proof);
+        proof
+    }
+
+    /// Generates a proof for the leaf holding the raw `value`, hashing it as the tree hashes
+    /// leaves. The proof is empty if the value is not in the tree, including when the tree has
+    /// pre-hashed leaves and the value is not the length of a hash.
+    pub fn proof_for_value(&self, value: &Bytes) -> MerkleProof<T> {
+        match MerkleTree::<T>::try_hash_leaf(&self.spec, value) {
+            Ok(leaf) => self.proof(leaf),
+            Err(_) => Default::default(),
+        }
+    }
+
+    /// Generates a proof for the leaf at `index` from the supplied layers, which are either those
+    /// of the tree or those of an earlier version.
+    fn proof_from<L: Store<T::Hash>>(&self, layers: &L, leaf: usize) -> MerkleProof<T> {
+        let mut index = leaf;
+        let arity = self.spec.arity;
+        let mut proof = vec![];
+        let mut directions = vec![];
+        let mut sibling_counts = vec![];
+        for layer in 0..layers.layers() - 1 {
+            if let Some(siblings) = MerkleTree::<T, S>::siblings(&self.spec, layers, layer, index) {
+                sibling_counts.push(siblings.len());
+                for (sibling, direction) in siblings {
+                    proof.push(sibling);
+                    directions.push(direction);
+                }
             }
+
+            // set index to parent index
+            index /= arity
+        }
+
+        MerkleProof::new(
+            proof,
+            (!self.spec.sort_pairs).then_some(directions),
+            (arity > 2).then_some(sibling_counts),
+        )
+        .with_index(leaf)
+    }
+
+    /// The siblings of the node at `index` within a layer along with the direction of each, or
+    /// `None` if the node is promoted without siblings.
+    pub(crate) fn siblings<L: Store<T::Hash>>(
+        spec: &TreeSpec,
+        layers: &L,
+        layer: usize,
+        index: usize,
+    ) -> Option<Vec<(T::Hash, Direction)>> {
+        let arity = spec.arity;
+        let node = |i| layers.get(layer, i).unwrap_or_default();
+
+        // Determine the group of siblings containing the node
+        let start = index - index % arity;
+        let end = layers.layer_len(layer).min(start + arity);
+        if end - start <= 1 && spec.odd_strategy == OddStrategy::Promote {
+            return None;
+        }
+
+        let mut siblings: Vec<(T::Hash, Direction)> = (start..end)
+            .filter(|i| *i != index)
+            .map(|i| match i < index {
+                true => (node(i), Direction::Left),
+                false => (node(i), Direction::Right),
+            })
+            .collect();
+
+        // Include any duplicates of the last node or zero hashes which complete the group
+        let filler = match spec.odd_strategy {
+            OddStrategy::Promote => None,
+            OddStrategy::Duplicate => Some(node(end - 1)),
+            OddStrategy::ZeroPad => Some(T::Hash::default()),
+        };
+        if let Some(filler) = filler {
+            siblings.extend((end..start + arity).map(|_| (filler, Direction::Right)));
+        }
+        Some(siblings)
+    }
+
+    /// The spec describing how this tree was constructed.
+    pub fn spec(&self) -> &TreeSpec {
+        &self.spec
+    }
+
+    /// Verifies that a proof recomputes the root from the leaf, either of which may be given as any
+    /// type convertible to a hash.
+    pub fn verify(
+        &self,
+        proof: MerkleProof<T>,
+        leaf: impl Into<T::Hash>,
+        root: impl Into<T::Hash>,
+    ) -> bool {
+        verify_proof(&self.spec, &proof, leaf.into(), root.into())
+    }
+
+    /// Verifies a proof for a raw leaf value, hashing it as the tree hashes its leaves.
+    ///
+    /// Unlike `verify`, this cannot be satisfied by presenting an internal node as a leaf when the
+    /// tree separates leaf and node hashes with distinct prefixes, as with `domain_separation`.
+    /// Values which are not the length of a hash fail verification for pre-hashed leaves.
+    pub fn verify_leaf_value(
+        &self,
+        proof: MerkleProof<T>,
+        value: &Bytes,
+        root: impl Into<T::Hash>,
+    ) -> bool {
+        match MerkleTree::<T>::try_hash_leaf(&self.spec, value) {
+            Ok(leaf) => self.verify(proof, leaf, root),
+            Err(_) => false,
         }
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    pub fn leaves(&self) -> &Vec<T::Hash> {
+        &self.layers[0]
+    }
 
-        hash == root
+    /// The nodes of a layer, where layer zero holds the leaves and layer `depth()` the root. Layers
+    /// beyond the root are empty.
+    pub fn layer(&self, layer: usize) -> &[T::Hash] {
+        self.layers
+            .as_slice()
+            .get(layer)
+            .map_or(&[], |nodes| nodes.as_slice())
     }
 
     fn layers_hex_encoded(&self) -> Vec<Vec<String>> {
@@ -132,46 +830,161 @@ impl<T: HashFunction> MerkleTree<T> {
             .map(|layer| {
                 layer
                     .iter()
-                    .map(|hash| format!("0x{}", hex::encode(&hash)))
+                    .map(|hash| format!("0x{}", hex::encode(hash)))
                     .collect()
             })
             .collect()
     }
 
-    fn hash(value: &[u8]) -> T::Hash {
-        T::hash(value)
+    /// Hashes a raw leaf value as the spec requires, failing if leaves are pre-hashed and the
+    /// value is not the length of a hash.
+    pub(crate) fn try_hash_leaf(spec: &TreeSpec, value: &[u8]) -> Result<T::Hash, MerkleError> {
+        match spec.hash_leaves {
+            true => Ok(MerkleTree::<T>::hash_leaf(spec, value)),
+            false => MerkleTree::<T>::hash_from_slice(value),
+        }
+    }
+
+    /// Hashes leaves as `hash_leaves` does, failing as `try_hash_leaf` does.
+    pub(crate) fn try_hash_leaves(
+        spec: &TreeSpec,
+        values: &[&[u8]],
+    ) -> Result<Vec<T::Hash>, MerkleError> {
+        match spec.hash_leaves {
+            true => Ok(MerkleTree::<T>::hash_leaves(spec, values)),
+            false => values
+                .iter()
+                .map(|value| MerkleTree::<T>::hash_from_slice(value))
+                .collect(),
+        }
+    }
+
+    fn hash_leaf(spec: &TreeSpec, value: &[u8]) -> T::Hash {
+        if !spec.hash_leaves {
+            return match T::Hash::try_from(value.to_vec()) {
+                Ok(hash) => hash,
+                Err(_) => panic!("pre-hashed leaf must be the length of a hash"),
+            };
+        }
+        let mut value = canonical::canonicalize(&spec.canonicalize, value);
+        let hash = if spec.leaf_prefix.is_empty() && spec.leaf_key.is_none() {
+            T::hash(&value)
+        } else {
+            // Leaf values may be confidential, so clear the copy once hashed
+            let mut prefixed = spec.leaf_prefix.clone();
+            prefixed.extend_from_slice(&value);
+            let hash = match &spec.leaf_key {
+                Some(key) => T::hash_keyed(key.expose_secret(), &prefixed),
+                None => T::hash(&prefixed),
+            };
+            prefixed.zeroize();
+            hash
+        };
+        if let Cow::Owned(value) = &mut value {
+            value.zeroize();
+        }
+        hash
+    }
+
+    /// Hashes leaves as `hash_leaf` does, through `HashFunction::hash_many` unless leaves are
+    /// keyed.
+    pub(crate) fn hash_leaves(spec: &TreeSpec, values: &[&[u8]]) -> Vec<T::Hash> {
+        if !spec.hash_leaves || spec.leaf_key.is_some() {
+            return values
+                .iter()
+                .map(|value| MerkleTree::<T>::hash_leaf(spec, value))
+                .collect();
+        }
+        if spec.canonicalize.is_empty() && spec.leaf_prefix.is_empty() {
+            return T::hash_many(values);
+        }
+
+        // Leaf values may be confidential, so clear the copies once hashed
+        let mut prepared: Vec<Vec<u8>> = values
+            .iter()
+            .map(|value| {
+                let mut prefixed = spec.leaf_prefix.clone();
+                let mut value = canonical::canonicalize(&spec.canonicalize, value);
+                prefixed.extend_from_slice(&value);
+                if let Cow::Owned(value) = &mut value {
+                    value.zeroize();
+                }
+                prefixed
+            })
+            .collect();
+        let hashes = T::hash_many(&prepared.iter().map(|p| &p[..]).collect::<Vec<_>>());
+        prepared.iter_mut().for_each(|p| p.zeroize());
+        hashes
+    }
+
+    /// Hashes leaves supplied by an iterator, a batch at a time.
+    pub(crate) fn hash_batches<'a, L: AsRef<Bytes>>(
+        spec: &'a TreeSpec,
+        leaves: impl IntoIterator<Item = L> + 'a,
+    ) -> impl Iterator<Item = T::Hash> + 'a {
+        let mut leaves = leaves.into_iter();
+        core::iter::from_fn(move || {
+            let batch: Vec<L> = leaves.by_ref().take(HASH_BATCH).collect();
+            let values: Vec<&Bytes> = batch.iter().map(|l| l.as_ref()).collect();
+            (!batch.is_empty()).then(|| MerkleTree::<T>::hash_leaves(spec, &values))
+        })
+        .flatten()
     }
 
+    /// Hashes a group of child nodes into their parent, prefixed as the spec requires.
+    fn hash_children(spec: &TreeSpec, nodes: &[T::Hash]) -> T::Hash {
+        verify::hash_children::<T>(spec, nodes)
+    }
+
+    #[cfg(any(feature = "std", test))]
     fn hash_pair(left: T::Hash, right: T::Hash) -> T::Hash {
-        let mut combined: Vec<u8> = left.into();
-        let mut right: Vec<u8> = right.into();
-        combined.append(&mut right);
-        T::hash(&combined)
+        MerkleTree::<T>::hash_nodes(&[left, right])
+    }
+
+    #[cfg(any(feature = "std", test))]
+    fn hash_nodes(nodes: &[T::Hash]) -> T::Hash {
+        verify::hash_nodes::<T>(nodes)
+    }
+
+    #[cfg(any(feature = "std", test))]
+    fn hash_sorted_pair(a: T::Hash, b: T::Hash) -> T::Hash {
+        if a < b {
+            MerkleTree::<T>::hash_pair(a, b)
+        } else {
+            MerkleTree::<T>::hash_pair(b, a)
+        }
+    }
+}
+
+/// Creates a tree as `new` does from leaves supplied by an iterator, hashing each leaf as it
+/// arrives rather than requiring every leaf to be held in memory first.
+impl<T: HashFunction, L: AsRef<Bytes>> FromIterator<L> for MerkleTree<T> {
+    fn from_iter<I: IntoIterator<Item = L>>(leaves: I) -> Self {
+        MerkleTreeBuilder::<T>::new().build_from_iter(leaves)
     }
 }
 
 impl<T: HashFunction> Display for MerkleTree<T> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         // Loop through layers, building nodes
         let layers = self.layers_hex_encoded();
         let mut nodes = vec![];
-        for i in 0..self.layers.len() {
+        for layer in layers {
             let mut temp = vec![];
-            for j in 0..self.layers[i].len() {
+            for hash in layer {
                 // Create node
                 let mut node = Node {
-                    hash: layers[i][j].clone(),
+                    hash,
                     nodes: Default::default(),
                 };
 
-                if nodes.len() > 0 {
+                for _ in 0..self.spec.arity {
+                    if nodes.is_empty() {
+                        break;
+                    }
+
                     // Remove node from previous layer and add as child
                     node.nodes.push(nodes.remove(0));
-
-                    if nodes.len() > 0 {
-                        // Remove node from previous layer and add as child
-                        node.nodes.push(nodes.remove(0));
-                    }
                 }
 
                 temp.push(node);
@@ -190,11 +1003,11 @@ impl<T: HashFunction> Display for MerkleTree<T> {
 
         // Recursively output the nodes as a tree
         fn output(
-            nodes: &Vec<Node>,
+            nodes: &[Node],
             level: usize,
             peers: usize,
             f: &mut Formatter<'_>,
-        ) -> std::fmt::Result {
+        ) -> core::fmt::Result {
             const INDENTATION: &str = "  ";
 
             let length = nodes.len();
@@ -207,7 +1020,7 @@ impl<T: HashFunction> Display for MerkleTree<T> {
 
                 // Write output with appropriate prefix
                 let prefix = if i == length - 1 { "└" } else { "├" };
-                write!(f, "{}─ {}\n", prefix, node.hash)?;
+                writeln!(f, "{}─ {}", prefix, node.hash)?;
 
                 // Update peer tally and then output child nodes
                 let peers = if i != length - 1 { peers + 1 } else { peers };
@@ -221,10 +1034,17 @@ impl<T: HashFunction> Display for MerkleTree<T> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
+#[allow(
+    clippy::bool_assert_comparison,
+    clippy::needless_range_loop,
+    clippy::useless_vec
+)]
 mod tests {
     use crate::hash_functions::Keccak256;
-    use crate::{Bytes, MerkleTree};
+    use crate::{
+        Bytes, Direction, HashFunction, MerkleProof, MerkleTree, MerkleTreeBuilder, OddStrategy,
+    };
     use primitive_types::H160;
 
     #[test]
@@ -250,6 +1070,186 @@ mod tests {
         test(leaves, 1)
     }
 
+    #[test]
+    fn ordered() {
+        let leaves: Vec<&Bytes> = ["b", "a", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Keccak256>::new_ordered(&leaves);
+
+        // Leaves retain insertion order and pairs are hashed by position
+        let hashes: Vec<[u8; 32]> = leaves.iter().map(|l| Keccak256::hash(l)).collect();
+        assert_eq!(tree.leaves(), &hashes);
+        let expected = MerkleTree::<Keccak256>::hash_pair(
+            MerkleTree::<Keccak256>::hash_pair(hashes[0], hashes[1]),
+            hashes[2],
+        );
+        assert_eq!(tree.root(), expected);
+        assert_ne!(tree.root(), MerkleTree::<Keccak256>::new(&leaves).root());
+
+        for (i, leaf) in hashes.iter().enumerate() {
+            let proof = tree.proof(*leaf);
+            assert!(tree.verify(proof.clone(), *leaf, tree.root()));
+
+            for (j, other) in hashes.iter().enumerate() {
+                if i != j {
+                    assert!(!tree.verify(proof.clone(), *other, tree.root()));
+                }
+            }
+        }
+
+        let proof = tree.proof(hashes[1]);
+        assert_eq!(proof.hashes(), tree.proof_for_value(b"a").hashes());
+        assert!(tree.proof_for_value(b"d").is_empty());
+        assert_eq!(proof.hashes(), &[hashes[0], hashes[2]]);
+        assert_eq!(
+            proof.directions(),
+            Some(&[Direction::Left, Direction::Right][..])
+        );
+    }
+
+    #[test]
+    fn wide_groups() {
+        // Groups too wide to be completed or concatenated on the stack are hashed the same way
+        let values: Vec<[u8; 1]> = (0..19).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let hashes: Vec<[u8; 32]> = leaves.iter().map(|l| Keccak256::hash(l)).collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(false)
+            .sort_pairs(false)
+            .arity(20)
+            .odd_strategy(OddStrategy::Duplicate)
+            .node_prefix(&[1])
+            .build(&leaves);
+        let mut expected = vec![1];
+        for hash in hashes.iter().chain([&hashes[18]]) {
+            expected.extend_from_slice(hash);
+        }
+        assert_eq!(tree.root(), Keccak256::hash(&expected));
+    }
+
+    #[test]
+    fn structure() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|x| x.as_bytes())
+            .collect();
+        let tree = MerkleTree::<Keccak256>::new_ordered(&leaves);
+        assert_eq!((tree.len(), tree.leaf_count(), tree.depth()), (5, 5, 3));
+        assert!(!tree.is_empty());
+        assert_eq!(tree.layer(0), &tree.leaves()[..]);
+        assert_eq!(tree.layer(1).len(), 3);
+        assert_eq!(tree.layer(3), [tree.root()]);
+        assert!(tree.layer(4).is_empty());
+        assert_eq!(tree.node(1, 2), Some(tree.leaves()[4]));
+        assert_eq!(tree.node(3, 0), Some(tree.root()));
+        assert_eq!(tree.node(1, 3), None);
+
+        let empty = MerkleTree::<Keccak256>::new(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.depth(), 0);
+    }
+
+    #[test]
+    fn push() {
+        let values: Vec<[u8; 1]> = (0..17).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for builder in [
+            MerkleTreeBuilder::<Keccak256>::new(),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .sort_pairs(false),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .odd_strategy(OddStrategy::Duplicate)
+                .arity(3),
+        ] {
+            // Appending leaves one at a time matches building the tree from scratch
+            let mut tree = builder.build(&[]);
+            for i in 0..leaves.len() {
+                tree.push(leaves[i]);
+                let expected = builder.build(&leaves[..=i]);
+                assert_eq!(tree.root(), expected.root());
+                assert_eq!(tree.layers, expected.layers);
+
+                let leaf = tree.leaves()[i / 2];
+                assert!(tree.verify(tree.proof(leaf), leaf, tree.root()));
+            }
+
+            let mut tree = builder.build(&leaves[..5]);
+            tree.extend(&leaves[5..]);
+            assert_eq!(tree.layers, builder.build(&leaves).layers);
+        }
+    }
+
+    #[test]
+    fn update_and_remove() {
+        let values: Vec<[u8; 1]> = (0..11).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for builder in [
+            MerkleTreeBuilder::<Keccak256>::new(),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .sort_pairs(false),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .odd_strategy(OddStrategy::Duplicate)
+                .arity(3),
+        ] {
+            // Updating a leaf matches building the tree with the new value
+            let mut tree = builder.build(&leaves);
+            let index = tree
+                .leaves()
+                .iter()
+                .position(|l| *l == Keccak256::hash(&[4]))
+                .unwrap();
+            assert_eq!(tree.update(index, &[42]), Keccak256::hash(&[4]));
+            let mut expected = leaves.clone();
+            expected[4] = &[42];
+            assert_eq!(tree.layers, builder.build(&expected).layers);
+
+            // Removing a leaf matches building the tree without it
+            let index = tree
+                .leaves()
+                .iter()
+                .position(|l| *l == Keccak256::hash(&[7]))
+                .unwrap();
+            assert_eq!(tree.remove(index), Keccak256::hash(&[7]));
+            expected.remove(7);
+            assert_eq!(tree.layers, builder.build(&expected).layers);
+
+            for leaf in tree.leaves() {
+                assert!(tree.verify(tree.proof(*leaf), *leaf, tree.root()));
+            }
+
+            // Removing every leaf leaves an empty tree
+            while !tree.leaves().is_empty() {
+                tree.remove(0);
+            }
+            assert_eq!(tree.root(), [0u8; 32]);
+        }
+    }
+
+    #[test]
+    fn digest_outputs() {
+        use sha3::Digest;
+
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Keccak256>::new(&leaves);
+
+        // RustCrypto outputs are accepted wherever hashes are consumed
+        let leaf = sha3::Keccak256::digest(b"b");
+        let root = sha3::digest::Output::<sha3::Keccak256>::from(tree.root());
+        let proof = tree.proof(leaf);
+        assert_eq!(proof, tree.proof(Keccak256::hash(b"b")));
+        assert!(tree.verify(proof.clone(), leaf, root));
+
+        let rebuilt: MerkleProof<Keccak256> = proof
+            .iter()
+            .map(|hash| sha3::digest::Output::<sha3::Keccak256>::from(*hash))
+            .collect();
+        assert!(tree.verify(rebuilt, leaf, root));
+        let multi = tree.multi_proof(&[leaf, sha3::Keccak256::digest(b"c")]);
+        assert!(tree.verify_multi_proof(&multi, root));
+    }
+
     fn test(leaves: Vec<&Bytes>, index: usize) {
         let tree = MerkleTree::<Keccak256>::new(&leaves);
         let root = tree.root();
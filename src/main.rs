@@ -0,0 +1,386 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use merkle_tree::{Direction, Keccak256, MerkleProof, MerkleTree, MerkleTreeBuilder, TreeSpec};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::process::ExitCode;
+
+/// Exit code when a proof fails verification.
+const VERIFICATION_FAILED: u8 = 2;
+/// Exit code when the input could not be read or parsed.
+const INPUT_ERROR: u8 = 3;
+
+/// Builds merkle trees from leaf files and generates and verifies proofs.
+///
+/// Leaf files contain one leaf per line, or are CSV files or JSON arrays of strings. Values
+/// starting with `0x` are decoded as hex, others are used as raw UTF-8 bytes.
+#[derive(Parser)]
+#[command(name = "merkle-tree", version)]
+struct Cli {
+    /// Output format
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    output: Output,
+    /// Tree spec describing how the tree is constructed
+    #[arg(
+        long,
+        global = true,
+        default_value = "keccak256/sorted/promote-odd/arity2"
+    )]
+    spec: String,
+    /// Format of leaf files, by default inferred from the extension of the file
+    #[arg(long, value_enum, global = true)]
+    format: Option<Format>,
+    /// Column of CSV leaf files holding the leaf value, counted from zero
+    #[arg(long, global = true, default_value = "0")]
+    column: usize,
+    /// Skips the first row of CSV leaf files
+    #[arg(long, global = true)]
+    header: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the root of the tree built from a leaf file ('-' reads from stdin)
+    Root { leaves: String },
+    /// Prints the proof for a leaf of the tree built from a leaf file ('-' reads from stdin)
+    Proof {
+        leaves: String,
+        /// Leaf value, encoded as in the leaf file
+        #[arg(long)]
+        leaf: String,
+    },
+    /// Writes the proof of every leaf of the tree built from a leaf file ('-' reads from stdin) to
+    /// a JSON file, printing the root
+    Proofs {
+        leaves: String,
+        /// File to write the proofs to ('-' writes to stdout)
+        #[arg(long)]
+        out: String,
+    },
+    /// Verifies a JSON proof, as output by `proof --output json`, read from stdin
+    Verify,
+    /// Writes a conformance kit of test vectors and verification pseudo-code for the tree spec
+    /// into a directory, for implementing verification in other languages
+    Conformance {
+        /// Directory to write `vectors.json` and `VERIFY.md` to
+        #[arg(long)]
+        out: String,
+    },
+    /// Prints the source of a dependency-free verifier for the root of the tree built from a leaf
+    /// file ('-' reads from stdin), for embedding in firmware
+    Codegen { leaves: String },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Output {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    /// One leaf per line
+    Lines,
+    /// One leaf per row, from the column given by `--column`
+    Csv,
+    /// An array of strings
+    Json,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProofDocument {
+    root: String,
+    leaf: String,
+    proof: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    directions: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sibling_counts: Option<Vec<usize>>,
+}
+
+/// The proofs of every leaf, each verifiable on its own by `verify`.
+#[derive(Serialize)]
+struct ProofsDocument {
+    root: String,
+    proofs: Vec<ProofDocument>,
+}
+
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if !e.use_stderr() => {
+            // Help or version requested
+            let _ = e.print();
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            let _ = e.print();
+            return ExitCode::from(INPUT_ERROR);
+        }
+    };
+
+    match run(&cli) {
+        Ok(code) => code,
+        Err(e) => {
+            match cli.output {
+                Output::Text => eprintln!("error: {}", e),
+                Output::Json => eprintln!("{}", serde_json::json!({ "error": e })),
+            }
+            ExitCode::from(INPUT_ERROR)
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<ExitCode, String> {
+    let spec = TreeSpec::parse(&cli.spec).map_err(|e| e.to_string())?;
+    let builder = MerkleTreeBuilder::<Keccak256>::from_spec(&spec).map_err(|e| e.to_string())?;
+
+    match &cli.command {
+        Command::Root { leaves } => {
+            let tree = build(cli, &builder, leaves)?;
+            let root = encode(tree.root());
+            match cli.output {
+                Output::Text => println!("{}", root),
+                Output::Json => println!("{}", serde_json::json!({ "root": root })),
+            }
+        }
+        Command::Proof { leaves, leaf } => {
+            let tree = build(cli, &builder, leaves)?;
+            let leaf = builder
+                .try_hash_leaf(&parse_value(leaf)?)
+                .map_err(|e| format!("invalid leaf: {}", e))?;
+            if !tree.leaves().contains(&leaf) {
+                return Err(format!("leaf {} not found in tree", encode(leaf)));
+            }
+
+            let document = document(tree.root(), leaf, &tree.proof(leaf));
+            match cli.output {
+                Output::Text => {
+                    for hash in document.proof {
+                        println!("{}", hash)
+                    }
+                }
+                Output::Json => println!("{}", serde_json::to_string(&document).unwrap()),
+            }
+        }
+        Command::Proofs { leaves, out } => {
+            let tree = build(cli, &builder, leaves)?;
+            let root = tree.root();
+            let document = ProofsDocument {
+                root: encode(root),
+                proofs: tree
+                    .proofs()
+                    .map(|(leaf, proof)| document(root, leaf, &proof))
+                    .collect(),
+            };
+            let json = serde_json::to_string_pretty(&document).unwrap();
+            if out == "-" {
+                println!("{}", json);
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            // The root is printed once the proofs are written, for use in the contract
+            std::fs::write(out, json + "\n")
+                .map_err(|e| format!("unable to write {}: {}", out, e))?;
+            match cli.output {
+                Output::Text => println!("{}", document.root),
+                Output::Json => println!(
+                    "{}",
+                    serde_json::json!({ "root": document.root, "proofs": document.proofs.len() })
+                ),
+            }
+        }
+        Command::Conformance { out } => {
+            let kit = builder.conformance_kit();
+            kit.write(out)
+                .map_err(|e| format!("unable to write {}: {}", out, e))?;
+            match cli.output {
+                Output::Text => println!("{}", kit.spec),
+                Output::Json => println!("{}", serde_json::json!({ "spec": kit.spec })),
+            }
+        }
+        Command::Codegen { leaves } => {
+            let tree = build(cli, &builder, leaves)?;
+            let source = tree.verifier_source().map_err(|e| e.to_string())?;
+            match cli.output {
+                Output::Text => print!("{}", source),
+                Output::Json => println!("{}", serde_json::json!({ "source": source })),
+            }
+        }
+        Command::Verify => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| e.to_string())?;
+            let document: ProofDocument =
+                serde_json::from_str(&input).map_err(|e| format!("invalid proof: {}", e))?;
+
+            let directions = match document.directions {
+                Some(directions) => Some(
+                    directions
+                        .iter()
+                        .map(|d| match d.as_str() {
+                            "left" => Ok(Direction::Left),
+                            "right" => Ok(Direction::Right),
+                            _ => Err(format!("invalid direction '{}'", d)),
+                        })
+                        .collect::<Result<Vec<Direction>, String>>()?,
+                ),
+                None => None,
+            };
+            let hashes = document
+                .proof
+                .iter()
+                .map(|h| decode(h))
+                .collect::<Result<Vec<[u8; 32]>, String>>()?;
+            let proof = MerkleProof::<Keccak256>::new(hashes, directions, document.sibling_counts);
+
+            // Verification only depends upon the tree spec
+            let valid =
+                builder
+                    .build(&[])
+                    .verify(proof, decode(&document.leaf)?, decode(&document.root)?);
+            match cli.output {
+                Output::Text => println!("{}", if valid { "valid" } else { "invalid" }),
+                Output::Json => println!("{}", serde_json::json!({ "valid": valid })),
+            }
+            if !valid {
+                return Ok(ExitCode::from(VERIFICATION_FAILED));
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn document(root: [u8; 32], leaf: [u8; 32], proof: &MerkleProof<Keccak256>) -> ProofDocument {
+    ProofDocument {
+        root: encode(root),
+        leaf: encode(leaf),
+        proof: proof.iter().map(|h| encode(*h)).collect(),
+        directions: proof.directions().map(|directions| {
+            directions
+                .iter()
+                .map(|d| match d {
+                    Direction::Left => "left".to_string(),
+                    Direction::Right => "right".to_string(),
+                })
+                .collect()
+        }),
+        sibling_counts: proof.sibling_counts().map(|c| c.to_vec()),
+    }
+}
+
+fn build(
+    cli: &Cli,
+    builder: &MerkleTreeBuilder<Keccak256>,
+    path: &str,
+) -> Result<MerkleTree<Keccak256>, String> {
+    let input = if path == "-" {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| e.to_string())?;
+        input
+    } else {
+        std::fs::read_to_string(path).map_err(|e| format!("unable to read {}: {}", path, e))?
+    };
+
+    let format = cli.format.unwrap_or(match path.rsplit_once('.') {
+        Some((_, "csv")) => Format::Csv,
+        Some((_, "json")) => Format::Json,
+        _ => Format::Lines,
+    });
+    let values = match format {
+        Format::Lines => input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(parse_value)
+            .collect::<Result<Vec<Vec<u8>>, String>>()?,
+        Format::Csv => input
+            .lines()
+            .skip(cli.header as usize)
+            .filter(|line| !line.trim().is_empty())
+            .map(|row| match parse_row(row).get(cli.column) {
+                Some(field) => parse_value(field),
+                None => Err(format!("row '{}' has no column {}", row, cli.column)),
+            })
+            .collect::<Result<Vec<Vec<u8>>, String>>()?,
+        Format::Json => serde_json::from_str::<Vec<String>>(&input)
+            .map_err(|e| format!("invalid JSON leaves: {}", e))?
+            .iter()
+            .map(|value| parse_value(value))
+            .collect::<Result<Vec<Vec<u8>>, String>>()?,
+    };
+    let leaves: Vec<&[u8]> = values.iter().map(|v| v.as_slice()).collect();
+    builder
+        .try_build(&leaves)
+        .map_err(|e| format!("invalid leaf: {}", e))
+}
+
+/// Splits a CSV row into its fields, which may be quoted to contain commas, with quotes escaped
+/// by doubling them.
+fn parse_row(row: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+fn parse_value(line: &str) -> Result<Vec<u8>, String> {
+    let line = line.trim();
+    match line.strip_prefix("0x") {
+        Some(hex) => hex::decode(hex).map_err(|e| format!("invalid hex value '{}': {}", line, e)),
+        None => Ok(line.as_bytes().to_vec()),
+    }
+}
+
+fn encode(hash: [u8; 32]) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+fn decode(hash: &str) -> Result<[u8; 32], String> {
+    let bytes = hex::decode(hash.trim_start_matches("0x"))
+        .map_err(|e| format!("invalid hash '{}': {}", hash, e))?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("invalid hash '{}': expected 32 bytes", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{decode, encode, parse_row, parse_value};
+
+    #[test]
+    fn parses_values() {
+        assert_eq!(parse_value("alice").unwrap(), b"alice");
+        assert_eq!(parse_value(" 0x0102 ").unwrap(), vec![1, 2]);
+        assert!(parse_value("0xzz").is_err());
+    }
+
+    #[test]
+    fn parses_rows() {
+        assert_eq!(parse_row("0x01,100"), ["0x01", "100"]);
+        assert_eq!(parse_row(r#""a, ""b""",c"#), [r#"a, "b""#, "c"]);
+        assert_eq!(parse_row(""), [""]);
+    }
+
+    #[test]
+    fn decodes_hashes() {
+        let hash = [7u8; 32];
+        assert_eq!(decode(&encode(hash)).unwrap(), hash);
+        assert!(decode("0x0102").is_err());
+    }
+}
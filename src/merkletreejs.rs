@@ -0,0 +1,81 @@
+//! Comparison against fixtures generated from merkletreejs across its option matrix of
+//! `sortLeaves` × `sortPairs` × `duplicateOdd`, asserting that each root can be reproduced by the
+//! corresponding builder configuration. Fixtures are regenerated by
+//! `fixtures/merkletreejs/generate.js`.
+
+use crate::{Bytes, CompatibilityProfile, Keccak256, MerkleTreeBuilder, OddStrategy};
+use serde_json::Value;
+use std::collections::HashSet;
+
+const FIXTURES: &str = include_str!("../fixtures/merkletreejs/fixtures.json");
+
+/// Maps merkletreejs options onto the equivalent builder configuration.
+fn builder(
+    sort_leaves: bool,
+    sort_pairs: bool,
+    duplicate_odd: bool,
+) -> MerkleTreeBuilder<Keccak256> {
+    MerkleTreeBuilder::new()
+        .sort_leaves(sort_leaves)
+        .sort_pairs(sort_pairs)
+        .odd_strategy(if duplicate_odd {
+            OddStrategy::Duplicate
+        } else {
+            OddStrategy::Promote
+        })
+}
+
+fn decode(value: &Value) -> Vec<u8> {
+    hex::decode(value.as_str().unwrap().trim_start_matches("0x")).unwrap()
+}
+
+#[test]
+fn reproduces_fixtures() {
+    let fixtures: Value = serde_json::from_str(FIXTURES).unwrap();
+    for case in fixtures["cases"].as_array().unwrap() {
+        let values: Vec<Vec<u8>> = case["leaves"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(decode)
+            .collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| v.as_slice()).collect();
+
+        let mut options = HashSet::new();
+        for fixture in case["trees"].as_array().unwrap() {
+            let sort_leaves = fixture["sortLeaves"].as_bool().unwrap();
+            let sort_pairs = fixture["sortPairs"].as_bool().unwrap();
+            let duplicate_odd = fixture["duplicateOdd"].as_bool().unwrap();
+            options.insert((sort_leaves, sort_pairs, duplicate_odd));
+
+            let builder = builder(sort_leaves, sort_pairs, duplicate_odd);
+            let tree = builder.build(&leaves);
+            let context = format!("{} with {}", case["name"], builder.spec());
+            assert_eq!(
+                tree.root().to_vec(),
+                decode(&fixture["root"]),
+                "{}",
+                context
+            );
+
+            // The profile matches the `sortPairs` configuration verified by OpenZeppelin
+            if (sort_leaves, sort_pairs, duplicate_odd) == (false, true, false) {
+                let root = CompatibilityProfile::MerkleTreeJs.root(&leaves).unwrap();
+                assert_eq!(root.to_vec(), decode(&fixture["root"]), "{}", context);
+            }
+
+            if let Some(expected) = fixture.get("proof") {
+                let leaf = builder.hash_leaf(leaves[0]);
+                let proof = tree.proof(leaf);
+                let expected: Vec<Vec<u8>> =
+                    expected.as_array().unwrap().iter().map(decode).collect();
+                let hashes: Vec<Vec<u8>> = proof.iter().map(|h| h.to_vec()).collect();
+                assert_eq!(hashes, expected, "{}", context);
+                assert!(tree.verify(proof, leaf, tree.root()), "{}", context);
+            }
+        }
+
+        // Every combination of options is covered
+        assert_eq!(options.len(), 8, "{}", case["name"]);
+    }
+}
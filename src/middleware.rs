@@ -0,0 +1,427 @@
+use crate::{
+    verify_proof, Direction, HashFunction, MerkleProof, MerkleTree, OddStrategy, TreeSpec,
+};
+use http::{HeaderMap, Request, Response, StatusCode};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// The header holding the hex encoded value of the leaf being proven, which is hashed as the tree
+/// hashes leaves.
+pub const LEAF_HEADER: &str = "x-merkle-leaf";
+/// The header holding the hex encoded siblings of the proof, separated by commas.
+pub const PROOF_HEADER: &str = "x-merkle-proof";
+/// The header holding the direction of each sibling as `left` or `right`, separated by commas,
+/// for trees with positional pairs.
+pub const DIRECTIONS_HEADER: &str = "x-merkle-directions";
+/// The header holding the number of siblings at each step of the proof, separated by commas, for
+/// trees with an arity above two.
+pub const SIBLING_COUNTS_HEADER: &str = "x-merkle-sibling-counts";
+
+/// A leaf whose proof was verified, inserted into the extensions of each request which
+/// `VerifyProofLayer` passes on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifiedLeaf<H>(pub H);
+
+/// A tower layer admitting only requests with a valid proof of a leaf against a configured root,
+/// rejecting all others with `403 Forbidden`. Proofs are read from the `x-merkle-leaf`,
+/// `x-merkle-proof`, `x-merkle-directions` and `x-merkle-sibling-counts` headers, so an axum
+/// route is protected with `.layer(tree.verify_proof_layer())`.
+///
+/// The leaf is supplied as its value and hashed as a leaf, and the proof must have as many steps
+/// as the proof of some leaf of a tree with the configured number of leaves, so neither the root
+/// nor an internal node can be presented as a leaf with a shortened proof. Without domain
+/// separation, a tree whose promoted leaves have shorter proofs than the others may still admit
+/// a node with a proof as short as theirs, as described for `verify_leaf_value`.
+pub struct VerifyProofLayer<T: HashFunction> {
+    spec: Arc<TreeSpec>,
+    root: T::Hash,
+    /// The numbers of steps of the proofs of the leaves of the tree.
+    depths: Arc<[usize]>,
+}
+
+impl<T: HashFunction> VerifyProofLayer<T> {
+    /// Creates a layer verifying proofs against the root of a tree of `leaves` leaves, including
+    /// any padding leaves, constructed as described by the spec.
+    pub fn new(spec: TreeSpec, root: impl Into<T::Hash>, leaves: usize) -> Self {
+        Self {
+            depths: proof_depths(&spec, leaves).into(),
+            spec: Arc::new(spec),
+            root: root.into(),
+        }
+    }
+
+    /// Whether the proof of the leaf holding `value` is valid, with the number of steps of the
+    /// proof of a leaf of the tree.
+    fn verify(&self, value: &[u8], proof: &MerkleProof<T>) -> Option<T::Hash> {
+        let leaf = MerkleTree::<T>::try_hash_leaf(&self.spec, value).ok()?;
+        let verified = self.depths.contains(&proof.depth())
+            && verify_proof(&self.spec, proof, leaf, self.root);
+        verified.then_some(leaf)
+    }
+}
+
+impl<T: HashFunction> Clone for VerifyProofLayer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            spec: self.spec.clone(),
+            root: self.root,
+            depths: self.depths.clone(),
+        }
+    }
+}
+
+impl<S, T: HashFunction> Layer<S> for VerifyProofLayer<T> {
+    type Service = VerifyProof<S, T>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        VerifyProof {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// The service created by `VerifyProofLayer`.
+pub struct VerifyProof<S, T: HashFunction> {
+    inner: S,
+    layer: VerifyProofLayer<T>,
+}
+
+impl<S: Clone, T: HashFunction> Clone for VerifyProof<S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S, T, B, R> Service<Request<B>> for VerifyProof<S, T>
+where
+    S: Service<Request<B>, Response = Response<R>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    T: HashFunction,
+    T::Hash: Send + Sync + 'static,
+    R: Default + Send + 'static,
+{
+    type Response = Response<R>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response<R>, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        let verified = extract_proof::<T>(request.headers())
+            .and_then(|(value, proof)| self.layer.verify(&value, &proof));
+        match verified {
+            Some(leaf) => {
+                request.extensions_mut().insert(VerifiedLeaf(leaf));
+                Box::pin(self.inner.call(request))
+            }
+            _ => {
+                let mut response = Response::new(R::default());
+                *response.status_mut() = StatusCode::FORBIDDEN;
+                Box::pin(std::future::ready(Ok(response)))
+            }
+        }
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// A layer admitting requests proving a leaf of the tree as it currently is.
+    pub fn verify_proof_layer(&self) -> VerifyProofLayer<T> {
+        VerifyProofLayer::<T>::new(self.spec.clone(), self.root(), self.layers[0].len())
+    }
+}
+
+/// The distinct numbers of steps of the proofs of the leaves of a tree of `leaves` leaves. Every
+/// proof has a step per layer above the leaves, other than for layers where a node is promoted
+/// without siblings, which only occurs on the path of the last node of a layer.
+fn proof_depths(spec: &TreeSpec, leaves: usize) -> Vec<usize> {
+    let arity = spec.arity;
+    let steps = |mut index: usize| {
+        let (mut len, mut steps) = (leaves, 0);
+        while len > 1 {
+            let start = index - index % arity;
+            let promoted = len.min(start + arity) - start == 1;
+            if !(promoted && spec.odd_strategy == OddStrategy::Promote) {
+                steps += 1;
+            }
+            index /= arity;
+            len = len.div_ceil(arity);
+        }
+        steps
+    };
+
+    // The first leaf below the last node of each layer, whose paths include every promotion
+    let mut depths = vec![steps(0)];
+    let (mut span, mut len) = (1usize, leaves);
+    while len > 1 {
+        depths.push(steps((leaves - 1) / span * span));
+        span = span.saturating_mul(arity);
+        len = len.div_ceil(arity);
+    }
+    depths.sort_unstable();
+    depths.dedup();
+    depths
+}
+
+/// Reads the leaf value and proof from the headers of a request, if present and well formed.
+fn extract_proof<T: HashFunction>(headers: &HeaderMap) -> Option<(Vec<u8>, MerkleProof<T>)> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let leaf = header(LEAF_HEADER)?.trim();
+    let leaf = hex::decode(leaf.strip_prefix("0x").unwrap_or(leaf)).ok()?;
+    let hashes = match header(PROOF_HEADER)?.trim() {
+        "" => vec![],
+        hashes => hashes.split(',').map(decode::<T>).collect::<Option<_>>()?,
+    };
+    let directions = match header(DIRECTIONS_HEADER) {
+        Some(directions) => Some(
+            directions
+                .split(',')
+                .filter(|d| !d.trim().is_empty())
+                .map(|d| match d.trim() {
+                    "left" => Some(Direction::Left),
+                    "right" => Some(Direction::Right),
+                    _ => None,
+                })
+                .collect::<Option<_>>()?,
+        ),
+        None => None,
+    };
+    let sibling_counts = match header(SIBLING_COUNTS_HEADER) {
+        Some(counts) => Some(
+            counts
+                .split(',')
+                .filter(|c| !c.trim().is_empty())
+                .map(|c| c.trim().parse().ok())
+                .collect::<Option<_>>()?,
+        ),
+        None => None,
+    };
+    Some((leaf, MerkleProof::new(hashes, directions, sibling_counts)))
+}
+
+fn decode<T: HashFunction>(hash: &str) -> Option<T::Hash> {
+    let hash = hash.trim();
+    let bytes = hex::decode(hash.strip_prefix("0x").unwrap_or(hash)).ok()?;
+    T::Hash::try_from(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        VerifiedLeaf, DIRECTIONS_HEADER, LEAF_HEADER, PROOF_HEADER, SIBLING_COUNTS_HEADER,
+    };
+    use crate::{
+        Bytes, Direction, HashFunction, Keccak256, MerkleProof, MerkleTree, MerkleTreeBuilder,
+    };
+    use http::{Request, Response, StatusCode};
+    use std::convert::Infallible;
+    use std::future::{ready, Future, Ready};
+    use std::task::{Context, Poll, Waker};
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    /// Responds with the verified leaf, as a handler reading the extension would.
+    struct Echo;
+
+    impl Service<Request<()>> for Echo {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = Ready<Result<Response<String>, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<()>) -> Self::Future {
+            let leaf = request.extensions().get::<VerifiedLeaf<[u8; 32]>>();
+            ready(Ok(Response::new(
+                leaf.map(|l| hex::encode(l.0)).unwrap_or_default(),
+            )))
+        }
+    }
+
+    fn respond(
+        service: &mut impl Service<Request<()>, Response = Response<String>>,
+        headers: &[(&str, String)],
+    ) -> Response<String> {
+        let mut request = Request::builder();
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        let mut future = Box::pin(service.call(request.body(()).unwrap()));
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(Ok(response)) => response,
+            _ => panic!("service did not respond immediately"),
+        }
+    }
+
+    /// The headers of a request proving the leaf holding `value`.
+    fn headers(value: &[u8], proof: &MerkleProof<Keccak256>) -> Vec<(&'static str, String)> {
+        let hex = |hash: &[u8; 32]| format!("0x{}", hex::encode(hash));
+        let mut headers = vec![
+            (LEAF_HEADER, format!("0x{}", hex::encode(value))),
+            (
+                PROOF_HEADER,
+                proof.iter().map(hex).collect::<Vec<_>>().join(","),
+            ),
+        ];
+        if let Some(directions) = proof.directions() {
+            let directions: Vec<&str> = directions
+                .iter()
+                .map(|d| match d {
+                    Direction::Left => "left",
+                    Direction::Right => "right",
+                })
+                .collect();
+            headers.push((DIRECTIONS_HEADER, directions.join(",")));
+        }
+        if let Some(counts) = proof.sibling_counts() {
+            let counts: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+            headers.push((SIBLING_COUNTS_HEADER, counts.join(",")));
+        }
+        headers
+    }
+
+    #[test]
+    fn verifies_proofs() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        for tree in [
+            MerkleTree::<Keccak256>::new(&leaves),
+            MerkleTree::<Keccak256>::new_ordered(&leaves),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .arity(3)
+                .build(&leaves),
+        ] {
+            let mut service = tree.verify_proof_layer().layer(Echo);
+            let leaf = Keccak256::hash(b"b");
+            let mut headers = headers(b"b", &tree.proof(leaf));
+
+            let response = respond(&mut service, &headers);
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.body(), &hex::encode(leaf));
+
+            // Proofs for other leaves, malformed proofs and missing proofs are forbidden
+            headers[0].1 = hex::encode(b"a");
+            assert_eq!(
+                respond(&mut service, &headers).status(),
+                StatusCode::FORBIDDEN
+            );
+            headers[0].1 = "0xzz".to_string();
+            assert_eq!(
+                respond(&mut service, &headers).status(),
+                StatusCode::FORBIDDEN
+            );
+            assert_eq!(respond(&mut service, &[]).status(), StatusCode::FORBIDDEN);
+        }
+    }
+
+    #[test]
+    fn verifies_proofs_with_several_siblings() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|x| x.as_bytes())
+            .collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .arity(3)
+            .build(&leaves);
+        let mut service = tree.verify_proof_layer().layer(Echo);
+        for value in &leaves {
+            let proof = tree.proof_for_value(value);
+            assert_eq!(
+                respond(&mut service, &headers(value, &proof)).status(),
+                StatusCode::OK
+            );
+        }
+
+        // Without the counts the siblings are taken to be one per step
+        let proof = tree.proof_for_value(b"a");
+        assert_eq!(proof.sibling_counts(), Some(&[2, 1][..]));
+        let mut headers = headers(b"a", &proof);
+        headers.retain(|(name, _)| *name != SIBLING_COUNTS_HEADER);
+        assert_eq!(
+            respond(&mut service, &headers).status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[test]
+    fn rejects_nodes_presented_as_leaves() {
+        let values: Vec<[u8; 32]> = (0..4).map(|i| Keccak256::hash(&[i])).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for hash_leaves in [true, false] {
+            let tree = MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .sort_pairs(false)
+                .hash_leaves(hash_leaves)
+                .build(&leaves);
+            let mut service = tree.verify_proof_layer().layer(Echo);
+            let leaf = tree.leaves()[2];
+            let proof = tree.proof(leaf);
+            assert_eq!(
+                respond(&mut service, &headers(leaves[2], &proof)).status(),
+                StatusCode::OK
+            );
+
+            // Without domain separation the children of a node concatenated hash to the node, and
+            // pre-hashed leaves are the node itself, so only the length of the proof betrays them
+            let (node, root) = (tree.layers[1][0], tree.root());
+            let children = [tree.leaves()[0], tree.leaves()[1]].concat();
+            let as_leaf = |node: [u8; 32]| match hash_leaves {
+                true => children.clone(),
+                false => node.to_vec(),
+            };
+            let shortened =
+                MerkleProof::new(vec![tree.layers[1][1]], Some(vec![Direction::Right]), None);
+            assert_eq!(shortened.compute_root(tree.spec(), node), Some(root));
+            assert_eq!(
+                respond(&mut service, &headers(&as_leaf(node), &shortened)).status(),
+                StatusCode::FORBIDDEN
+            );
+
+            // The root is its own root with an empty proof
+            if !hash_leaves {
+                let empty = MerkleProof::new(vec![], Some(vec![]), None);
+                assert_eq!(empty.compute_root(tree.spec(), root), Some(root));
+                assert_eq!(
+                    respond(&mut service, &headers(&root, &empty)).status(),
+                    StatusCode::FORBIDDEN
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn accepts_proofs_of_promoted_leaves() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|x| x.as_bytes())
+            .collect();
+        let tree = MerkleTree::<Keccak256>::new_ordered(&leaves);
+        let mut service = tree.verify_proof_layer().layer(Echo);
+        let depths: Vec<usize> = leaves
+            .iter()
+            .map(|value| {
+                let proof = tree.proof_for_value(value);
+                assert_eq!(
+                    respond(&mut service, &headers(value, &proof)).status(),
+                    StatusCode::OK
+                );
+                proof.depth()
+            })
+            .collect();
+        assert_eq!(depths, [3, 3, 3, 3, 1]);
+    }
+}
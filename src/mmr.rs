@@ -0,0 +1,364 @@
+use crate::{Bytes, HashFunction, MerkleTree};
+
+/// An append-only Merkle Mountain Range, as used by Substrate and Nervos light clients.
+///
+/// Nodes are stored in insertion order, each leaf being followed by the parents it completes, so
+/// the range is a list of perfect binary trees (peaks) of decreasing height. The root bags the
+/// peaks from right to left, and proofs are laid out as in `ckb-merkle-mountain-range`: the peaks
+/// to the left of the leaf, the siblings from the leaf to its peak and then the bagged peaks to
+/// the right of it.
+pub struct MerkleMountainRange<T: HashFunction> {
+    nodes: Vec<T::Hash>,
+}
+
+impl<T: HashFunction> MerkleMountainRange<T> {
+    pub fn new() -> Self {
+        Self { nodes: vec![] }
+    }
+
+    /// Appends a leaf, returning its position within the range.
+    pub fn push(&mut self, leaf: &Bytes) -> u64 {
+        let position = self.nodes.len() as u64;
+        self.nodes.push(T::hash(leaf));
+
+        // Add the parents completed by the leaf
+        let mut height = 0;
+        let mut pos = position;
+        while height_of(pos + 1) > height {
+            pos += 1;
+            let left = pos - parent_offset(height);
+            let right = left + sibling_offset(height);
+            let parent = MerkleTree::<T>::hash_nodes(&[
+                self.nodes[left as usize],
+                self.nodes[right as usize],
+            ]);
+            self.nodes.push(parent);
+            height += 1;
+        }
+
+        position
+    }
+
+    /// The number of nodes in the range.
+    pub fn mmr_size(&self) -> u64 {
+        self.nodes.len() as u64
+    }
+
+    pub fn root(&self) -> T::Hash {
+        let peaks: Vec<T::Hash> = peaks(self.mmr_size())
+            .into_iter()
+            .map(|pos| self.nodes[pos as usize])
+            .collect();
+        bag::<T>(peaks).unwrap_or_default()
+    }
+
+    /// Generates a proof for the leaf at `pos`, which is empty if there is no leaf at `pos`.
+    pub fn gen_proof(&self, pos: u64) -> MmrProof<T> {
+        let size = self.mmr_size();
+        if pos >= size || height_of(pos) != 0 {
+            return Default::default();
+        }
+
+        // The first peak at or beyond the leaf is the peak of the tree containing it
+        let peaks = peaks(size);
+        let target = peaks.iter().position(|peak| *peak >= pos).unwrap_or(0);
+        let mut items: Vec<T::Hash> = peaks[..target]
+            .iter()
+            .map(|peak| self.nodes[*peak as usize])
+            .collect();
+        self.path(pos, peaks[target], &mut items);
+        let right = peaks[target + 1..]
+            .iter()
+            .map(|peak| self.nodes[*peak as usize])
+            .collect();
+        if let Some(bagged) = bag::<T>(right) {
+            items.push(bagged);
+        }
+
+        MmrProof { size, items }
+    }
+
+    /// Verifies a proof that `leaf` is at `pos` within the range with the supplied root.
+    pub fn verify(&self, proof: &MmrProof<T>, pos: u64, leaf: &Bytes, root: T::Hash) -> bool {
+        proof.verify(pos, leaf, root)
+    }
+
+    /// Adds the siblings on the path from the node at `pos` to the peak at `peak`.
+    fn path(&self, mut pos: u64, peak: u64, items: &mut Vec<T::Hash>) {
+        let mut height = 0;
+        while pos < peak {
+            let (parent, sibling) = step(pos, height);
+            items.push(self.nodes[sibling as usize]);
+            pos = parent;
+            height += 1;
+        }
+    }
+}
+
+impl<T: HashFunction> Default for MerkleMountainRange<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A proof that a leaf belongs to a Merkle Mountain Range of a given size.
+pub struct MmrProof<T: HashFunction> {
+    size: u64,
+    items: Vec<T::Hash>,
+}
+
+impl<T: HashFunction> MmrProof<T> {
+    pub fn new(size: u64, items: Vec<T::Hash>) -> Self {
+        Self { size, items }
+    }
+
+    /// The number of nodes in the range the proof was generated from.
+    pub fn mmr_size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn items(&self) -> &[T::Hash] {
+        &self.items
+    }
+
+    /// Verifies that `leaf` is at `pos` within a range with the supplied root, without requiring
+    /// the range itself, as a light client would.
+    pub fn verify(&self, pos: u64, leaf: &Bytes, root: T::Hash) -> bool {
+        // Positions within larger ranges would overflow as the path to their peak is walked
+        if pos >= self.size || self.size > MAX_SIZE || height_of(pos) != 0 {
+            return false;
+        }
+        let peaks = peaks(self.size);
+        let target = match peaks.iter().position(|peak| *peak >= pos) {
+            Some(target) => target,
+            None => return false,
+        };
+
+        // Peaks to the left of the leaf
+        let mut items = self.items.iter().copied();
+        let mut hashes: Vec<T::Hash> = items.by_ref().take(target).collect();
+        if hashes.len() != target {
+            return false;
+        }
+
+        // Recompute the peak containing the leaf
+        let mut hash = T::hash(leaf);
+        let (mut pos, mut height) = (pos, 0);
+        while pos < peaks[target] {
+            let sibling = match items.next() {
+                Some(sibling) => sibling,
+                None => return false,
+            };
+            let (parent, sibling_pos) = step(pos, height);
+            hash = match sibling_pos < pos {
+                true => MerkleTree::<T>::hash_nodes(&[sibling, hash]),
+                false => MerkleTree::<T>::hash_nodes(&[hash, sibling]),
+            };
+            pos = parent;
+            height += 1;
+        }
+        hashes.push(hash);
+
+        // Peaks to the right of the leaf, already bagged
+        if target + 1 < peaks.len() {
+            match items.next() {
+                Some(bagged) => hashes.push(bagged),
+                None => return false,
+            }
+        }
+
+        items.next().is_none() && bag::<T>(hashes) == Some(root)
+    }
+}
+
+impl<T: HashFunction> Clone for MmrProof<T> {
+    fn clone(&self) -> Self {
+        Self {
+            size: self.size,
+            items: self.items.clone(),
+        }
+    }
+}
+
+impl<T: HashFunction> std::fmt::Debug for MmrProof<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmrProof")
+            .field("mmr_size", &self.size)
+            .field(
+                "items",
+                &self
+                    .items
+                    .iter()
+                    .map(|hash| format!("0x{}", hex::encode(hash)))
+                    .collect::<Vec<String>>(),
+            )
+            .finish()
+    }
+}
+
+impl<T: HashFunction> Default for MmrProof<T> {
+    fn default() -> Self {
+        Self {
+            size: 0,
+            items: vec![],
+        }
+    }
+}
+
+/// The largest size of a range whose proofs can be verified, being far more nodes than can be
+/// appended.
+const MAX_SIZE: u64 = u64::MAX >> 1;
+
+/// Position of the leaf with the supplied index, as each leaf is followed by the parents it
+/// completes.
+pub fn leaf_index_to_pos(index: u64) -> u64 {
+    2 * index - index.count_ones() as u64
+}
+
+/// Bags peaks from right to left, hashing each bag with the next peak to its left.
+fn bag<T: HashFunction>(mut peaks: Vec<T::Hash>) -> Option<T::Hash> {
+    while peaks.len() > 1 {
+        let right = peaks.pop()?;
+        let left = peaks.pop()?;
+        peaks.push(MerkleTree::<T>::hash_nodes(&[right, left]));
+    }
+    peaks.pop()
+}
+
+/// The parent and sibling positions of the node at `pos`, which has the supplied height.
+fn step(pos: u64, height: u32) -> (u64, u64) {
+    if height_of(pos + 1) > height {
+        // Right child, so the parent immediately follows
+        (pos + 1, pos - sibling_offset(height))
+    } else {
+        (pos + parent_offset(height), pos + sibling_offset(height))
+    }
+}
+
+/// The height of the node at `pos`, where leaves have a height of zero.
+fn height_of(pos: u64) -> u32 {
+    // Jump left until the one based position is all ones, the position of a peak
+    let mut pos = pos + 1;
+    while !(pos + 1).is_power_of_two() {
+        let length = 64 - pos.leading_zeros();
+        pos -= (1 << (length - 1)) - 1;
+    }
+    63 - pos.leading_zeros()
+}
+
+fn parent_offset(height: u32) -> u64 {
+    2 << height
+}
+
+fn sibling_offset(height: u32) -> u64 {
+    (2 << height) - 1
+}
+
+/// The positions of the peaks of a range with `size` nodes, from left to right.
+fn peaks(size: u64) -> Vec<u64> {
+    let mut peaks = vec![];
+    let (mut height, mut pos): (u32, u64) = (0, 0);
+
+    // Find the highest peak, then each successively lower peak to its right
+    while 1u64
+        .checked_shl(height + 1)
+        .is_some_and(|tree| tree - 1 <= size)
+    {
+        height += 1;
+    }
+    let mut remaining = size;
+    while height > 0 {
+        let tree = (1u64 << height) - 1;
+        if tree <= remaining {
+            pos += tree;
+            peaks.push(pos - 1);
+            remaining -= tree;
+        }
+        height -= 1;
+    }
+    peaks
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mmr::{height_of, leaf_index_to_pos, peaks, MAX_SIZE};
+    use crate::{Keccak256, MerkleMountainRange, MmrProof};
+
+    #[test]
+    fn positions() {
+        let heights: Vec<u32> = (0..11).map(height_of).collect();
+        assert_eq!(heights, vec![0, 0, 1, 0, 0, 1, 2, 0, 0, 1, 0]);
+        assert_eq!(peaks(11), vec![6, 9, 10]);
+        assert_eq!(peaks(7), vec![6]);
+        assert_eq!(peaks(0), Vec::<u64>::new());
+        let positions: Vec<u64> = (0..6).map(leaf_index_to_pos).collect();
+        assert_eq!(positions, vec![0, 1, 3, 4, 7, 8]);
+    }
+
+    #[test]
+    fn proves_leaves() {
+        let mut mmr = MerkleMountainRange::<Keccak256>::new();
+        assert_eq!(mmr.root(), [0u8; 32]);
+
+        for count in 0..20u64 {
+            assert_eq!(mmr.push(&count.to_be_bytes()), leaf_index_to_pos(count));
+            let root = mmr.root();
+
+            for index in 0..=count {
+                let pos = leaf_index_to_pos(index);
+                let leaf = index.to_be_bytes();
+                let proof = mmr.gen_proof(pos);
+                assert!(mmr.verify(&proof, pos, &leaf, root), "{} {}", count, index);
+                assert!(!proof.verify(pos, &(index + 1).to_be_bytes(), root));
+                if count > 0 {
+                    let other = leaf_index_to_pos((index + 1) % (count + 1));
+                    assert!(!proof.verify(other, &leaf, root));
+                }
+            }
+        }
+
+        // Positions of parent nodes cannot be proven
+        assert!(mmr.gen_proof(2).items().is_empty());
+        assert!(!mmr.gen_proof(2).verify(2, &[], mmr.root()));
+    }
+
+    #[test]
+    fn rejects_oversized_proofs() {
+        let root = [0; 32];
+        for size in [u64::MAX, u64::MAX - 1, MAX_SIZE + 1, MAX_SIZE, MAX_SIZE - 1] {
+            let proof = MmrProof::<Keccak256>::new(size, vec![[1; 32]; 64]);
+            for pos in [0, 1, 3, size / 2, size - 2, size - 1] {
+                assert!(!proof.verify(pos, b"a", root), "{} {}", size, pos);
+            }
+        }
+        assert_eq!(peaks(u64::MAX)[0], (1 << 63) - 2);
+    }
+
+    #[test]
+    fn matches_ckb() {
+        // Root and proof produced by ckb-merkle-mountain-range for the same leaves
+        let mut mmr = MerkleMountainRange::<Keccak256>::new();
+        for i in 0..11u64 {
+            mmr.push(&i.to_be_bytes());
+        }
+        assert_eq!(
+            hex::encode(mmr.root()),
+            "376486e5490b18afa73ef0524a597ed6fb8daac63381f9ba26a7458d7a044aa2"
+        );
+        let items: Vec<String> = mmr
+            .gen_proof(leaf_index_to_pos(5))
+            .items()
+            .iter()
+            .map(hex::encode)
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                "d2ed8d75f801ae8a206c07ff9b104f0e005238dcd1cbaf844fd9f40d63174c56",
+                "37e9108cecf68a87ef8945922adc49a3d5220b1d9a49b69f1bbd69da0316902b",
+                "128aca6dfe349b4023f86c148d1338cf5331d065cf356054d78f1e02da936602",
+                "ff96ab772a0340c393d39eb3c6f7ad6ab5d7ef7816851feb76438c9067e2be71",
+            ]
+        );
+    }
+}
@@ -0,0 +1,699 @@
+//! Ethereum's hexary Merkle Patricia Trie, for proving the accounts and storage slots of a state
+//! root as returned by `eth_getProof`. Nodes are RLP encoded and hashed with Keccak256, so roots
+//! and proofs are interchangeable with those of Ethereum clients.
+
+use crate::{Bytes, Hash, HashFunction, Keccak256};
+use std::fmt::{Display, Formatter};
+
+/// The root of an empty trie, being the hash of the RLP encoding of an empty string.
+pub const EMPTY_ROOT: Hash<32> = [
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+];
+
+/// The RLP encoding of an empty string, which is also the encoding of an empty node.
+const EMPTY_STRING: u8 = 0x80;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MptError {
+    /// The proof ends before reaching the node holding the key.
+    MissingNode,
+    /// A node of the proof does not hash to the reference held by its parent.
+    HashMismatch,
+    /// A node of the proof, or the value proven, is not validly encoded.
+    Malformed,
+}
+
+impl Display for MptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MptError::MissingNode => write!(f, "proof is missing a node"),
+            MptError::HashMismatch => write!(f, "node does not match its reference"),
+            MptError::Malformed => write!(f, "malformed node"),
+        }
+    }
+}
+
+impl std::error::Error for MptError {}
+
+/// An account of the state trie. Balances are held as `u128`, which exceeds the supply of ether.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Account {
+    pub nonce: u64,
+    pub balance: u128,
+    /// The root of the storage trie of the account.
+    pub storage_root: Hash<32>,
+    /// The hash of the code of the account, being the hash of no bytes for accounts without code.
+    pub code_hash: Hash<32>,
+}
+
+impl Account {
+    /// The RLP encoding of the account, as held by the state trie.
+    pub fn rlp(&self) -> Vec<u8> {
+        list(&[
+            string(trim(&self.nonce.to_be_bytes())),
+            string(trim(&self.balance.to_be_bytes())),
+            string(&self.storage_root),
+            string(&self.code_hash),
+        ])
+    }
+
+    pub fn decode(rlp: &Bytes) -> Result<Self, MptError> {
+        let fields = items(rlp)?;
+        let [nonce, balance, storage_root, code_hash] = fields[..] else {
+            return Err(MptError::Malformed);
+        };
+        Ok(Self {
+            nonce: u64::from_be_bytes(integer(bytes(nonce)?)?),
+            balance: u128::from_be_bytes(integer(bytes(balance)?)?),
+            storage_root: bytes(storage_root)?
+                .try_into()
+                .map_err(|_| MptError::Malformed)?,
+            code_hash: bytes(code_hash)?
+                .try_into()
+                .map_err(|_| MptError::Malformed)?,
+        })
+    }
+}
+
+/// A node of the trie, its paths being nibbles.
+#[derive(Clone, Debug, Default)]
+enum Node {
+    #[default]
+    Empty,
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: Box<Node>,
+    },
+    Branch {
+        children: Box<[Node; 16]>,
+        value: Option<Vec<u8>>,
+    },
+}
+
+/// A Merkle Patricia Trie held in memory.
+///
+/// Keys are used as given, whereas the state and storage tries of Ethereum key entries by the
+/// hash of the address or slot, as `insert_account` and `insert_storage` do.
+#[derive(Clone, Debug, Default)]
+pub struct MerklePatriciaTrie {
+    root: Node,
+}
+
+impl MerklePatriciaTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a value, replacing any value held for the key. As in Ethereum, inserting an empty
+    /// value removes the key.
+    pub fn insert(&mut self, key: &Bytes, value: &Bytes) {
+        let root = std::mem::take(&mut self.root);
+        self.root = match value.is_empty() {
+            true => remove(root, &nibbles(key)),
+            false => insert(root, &nibbles(key), value.to_vec()),
+        };
+    }
+
+    /// Removes the value held for the key, if any.
+    pub fn remove(&mut self, key: &Bytes) {
+        self.insert(key, &[])
+    }
+
+    pub fn get(&self, key: &Bytes) -> Option<&[u8]> {
+        let path = nibbles(key);
+        let mut path = &path[..];
+        let mut node = &self.root;
+        loop {
+            match node {
+                Node::Empty => return None,
+                Node::Leaf { path: leaf, value } => {
+                    return (leaf[..] == *path).then_some(&value[..]);
+                }
+                Node::Extension {
+                    path: extension,
+                    child,
+                } => {
+                    path = path.strip_prefix(&extension[..])?;
+                    node = child;
+                }
+                Node::Branch { children, value } => match path.split_first() {
+                    Some((nibble, rest)) => {
+                        node = &children[*nibble as usize];
+                        path = rest;
+                    }
+                    None => return value.as_deref(),
+                },
+            }
+        }
+    }
+
+    pub fn root(&self) -> Hash<32> {
+        Keccak256::hash(&self.root.encode())
+    }
+
+    /// The RLP encoded nodes on the path to the key, starting at the root, as returned by
+    /// `eth_getProof`. Nodes small enough to be embedded within their parent are not included.
+    /// The proof shows the key is absent if the trie holds no value for it.
+    pub fn prove(&self, key: &Bytes) -> Vec<Vec<u8>> {
+        let path = nibbles(key);
+        let mut path = &path[..];
+        let mut node = &self.root;
+        let mut proof = vec![];
+        loop {
+            let encoded = node.encode();
+            if proof.is_empty() || encoded.len() >= 32 {
+                proof.push(encoded);
+            }
+            match node {
+                Node::Extension {
+                    path: extension,
+                    child,
+                } if path.starts_with(extension) => {
+                    path = &path[extension.len()..];
+                    node = child;
+                }
+                Node::Branch { children, .. } if !path.is_empty() => {
+                    node = &children[path[0] as usize];
+                    path = &path[1..];
+                }
+                _ => return proof,
+            }
+        }
+    }
+
+    /// Inserts an account of a state trie, keyed by the hash of its address.
+    pub fn insert_account(&mut self, address: &[u8; 20], account: &Account) {
+        self.insert(&Keccak256::hash(address), &account.rlp())
+    }
+
+    /// Inserts a value of a storage trie, keyed by the hash of its slot. Zero values are removed.
+    pub fn insert_storage(&mut self, slot: &Hash<32>, value: &Hash<32>) {
+        let value = match trim(value) {
+            [] => vec![],
+            value => string(value),
+        };
+        self.insert(&Keccak256::hash(slot), &value)
+    }
+}
+
+/// Verifies a proof of the value held for a key by the trie with the given root, returning the
+/// value, or `None` if the proof shows the key is absent.
+pub fn verify<P: AsRef<[u8]>>(
+    root: &Hash<32>,
+    key: &Bytes,
+    proof: &[P],
+) -> Result<Option<Vec<u8>>, MptError> {
+    if *root == EMPTY_ROOT {
+        return Ok(None);
+    }
+    let path = nibbles(key);
+    let mut path = &path[..];
+    let mut proof = proof.iter().map(|node| node.as_ref());
+    let mut reference = Reference::Hash(*root);
+    loop {
+        let node = match reference {
+            Reference::Hash(hash) => {
+                let node = proof.next().ok_or(MptError::MissingNode)?;
+                if Keccak256::hash(node) != hash {
+                    return Err(MptError::HashMismatch);
+                }
+                node
+            }
+            Reference::Embedded(node) => node,
+        };
+        let node = items(node)?;
+        match node[..] {
+            [leaf, value] if is_leaf(leaf)? => {
+                let leaf = hex_prefix_decode(bytes(leaf)?)?;
+                return (leaf == path)
+                    .then(|| bytes(value).map(|v| v.to_vec()))
+                    .transpose();
+            }
+            [extension, child] => {
+                let extension = hex_prefix_decode(bytes(extension)?)?;
+                match path.strip_prefix(&extension[..]) {
+                    Some(rest) => path = rest,
+                    None => return Ok(None),
+                }
+                reference = Reference::of(child)?;
+            }
+            _ if node.len() == 17 => {
+                let (child, rest) = match path.split_first() {
+                    Some((nibble, rest)) => (node[*nibble as usize], rest),
+                    None => {
+                        let value = bytes(node[16])?;
+                        return Ok((!value.is_empty()).then(|| value.to_vec()));
+                    }
+                };
+                if child == [EMPTY_STRING] {
+                    return Ok(None);
+                }
+                path = rest;
+                reference = Reference::of(child)?;
+            }
+            _ => return Err(MptError::Malformed),
+        }
+    }
+}
+
+/// Verifies a proof of an account against a state root, as returned by `eth_getProof` as its
+/// `accountProof`.
+pub fn verify_account<P: AsRef<[u8]>>(
+    state_root: &Hash<32>,
+    address: &[u8; 20],
+    proof: &[P],
+) -> Result<Option<Account>, MptError> {
+    verify(state_root, &Keccak256::hash(address), proof)?
+        .map(|account| Account::decode(&account))
+        .transpose()
+}
+
+/// Verifies a proof of a storage slot against the storage root of an account, as returned by
+/// `eth_getProof` as the `proof` of each of its `storageProof`. Absent slots hold zero.
+pub fn verify_storage<P: AsRef<[u8]>>(
+    storage_root: &Hash<32>,
+    slot: &Hash<32>,
+    proof: &[P],
+) -> Result<Hash<32>, MptError> {
+    match verify(storage_root, &Keccak256::hash(slot), proof)? {
+        Some(value) => integer(bytes(&value)?),
+        None => Ok([0; 32]),
+    }
+}
+
+/// How a node refers to a child, being embedded if its encoding is shorter than a hash.
+enum Reference<'a> {
+    Hash(Hash<32>),
+    Embedded(&'a [u8]),
+}
+
+impl<'a> Reference<'a> {
+    fn of(item: &'a [u8]) -> Result<Self, MptError> {
+        match header(item)? {
+            (true, _, _) => Ok(Reference::Embedded(item)),
+            (false, hash, _) => hash
+                .try_into()
+                .map(Reference::Hash)
+                .map_err(|_| MptError::Malformed),
+        }
+    }
+}
+
+impl Node {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Node::Empty => vec![EMPTY_STRING],
+            Node::Leaf { path, value } => list(&[string(&hex_prefix(path, true)), string(value)]),
+            Node::Extension { path, child } => {
+                list(&[string(&hex_prefix(path, false)), child.reference()])
+            }
+            Node::Branch { children, value } => {
+                let mut items: Vec<Vec<u8>> = children.iter().map(Node::reference).collect();
+                items.push(string(value.as_deref().unwrap_or_default()));
+                list(&items)
+            }
+        }
+    }
+
+    /// The encoding of the node as held by its parent.
+    fn reference(&self) -> Vec<u8> {
+        let encoded = self.encode();
+        match encoded.len() < 32 {
+            true => encoded,
+            false => string(&Keccak256::hash(&encoded)),
+        }
+    }
+}
+
+fn insert(node: Node, path: &[u8], value: Vec<u8>) -> Node {
+    match node {
+        Node::Empty => Node::Leaf {
+            path: path.to_vec(),
+            value,
+        },
+        Node::Leaf { path: leaf, .. } if leaf == path => Node::Leaf { path: leaf, value },
+        Node::Leaf {
+            path: leaf,
+            value: existing,
+        } => {
+            // The leaves diverge at a branch, beneath an extension holding any shared prefix
+            let shared = shared(&leaf, path);
+            let mut branch = branch();
+            if let Node::Branch { children, value } = &mut branch {
+                match leaf.get(shared) {
+                    Some(nibble) => {
+                        children[*nibble as usize] = Node::Leaf {
+                            path: leaf[shared + 1..].to_vec(),
+                            value: existing,
+                        }
+                    }
+                    None => *value = Some(existing),
+                }
+            }
+            extend(&path[..shared], insert(branch, &path[shared..], value))
+        }
+        Node::Extension {
+            path: extension,
+            child,
+        } => {
+            let shared = shared(&extension, path);
+            if shared == extension.len() {
+                return Node::Extension {
+                    child: Box::new(insert(*child, &path[shared..], value)),
+                    path: extension,
+                };
+            }
+            let mut branch = branch();
+            if let Node::Branch { children, .. } = &mut branch {
+                children[extension[shared] as usize] = extend(&extension[shared + 1..], *child);
+            }
+            extend(&path[..shared], insert(branch, &path[shared..], value))
+        }
+        Node::Branch {
+            mut children,
+            value: existing,
+        } => match path.split_first() {
+            Some((nibble, rest)) => {
+                let child = std::mem::take(&mut children[*nibble as usize]);
+                children[*nibble as usize] = insert(child, rest, value);
+                Node::Branch {
+                    children,
+                    value: existing,
+                }
+            }
+            None => Node::Branch {
+                children,
+                value: Some(value),
+            },
+        },
+    }
+}
+
+fn remove(node: Node, path: &[u8]) -> Node {
+    match node {
+        Node::Leaf { path: leaf, .. } if leaf == path => Node::Empty,
+        Node::Extension {
+            path: extension,
+            child,
+        } if path.starts_with(&extension) => {
+            extend(&extension, remove(*child, &path[extension.len()..]))
+        }
+        Node::Branch {
+            mut children,
+            mut value,
+        } => {
+            match path.split_first() {
+                Some((nibble, rest)) => {
+                    let child = std::mem::take(&mut children[*nibble as usize]);
+                    children[*nibble as usize] = remove(child, rest);
+                }
+                None => value = None,
+            }
+
+            // Branches left with a single child or value collapse into it
+            let mut remaining = children
+                .iter()
+                .enumerate()
+                .filter(|(_, child)| !matches!(child, Node::Empty));
+            match (remaining.next(), remaining.next(), &value) {
+                (None, _, Some(_)) => Node::Leaf {
+                    path: vec![],
+                    value: value.unwrap_or_default(),
+                },
+                (Some((nibble, _)), None, None) => {
+                    let child = std::mem::take(&mut children[nibble]);
+                    extend(&[nibble as u8], child)
+                }
+                _ => Node::Branch { children, value },
+            }
+        }
+        node => node,
+    }
+}
+
+/// Prefixes a node with a path, merging the path into the node where it has one of its own.
+fn extend(path: &[u8], node: Node) -> Node {
+    if path.is_empty() {
+        return node;
+    }
+    match node {
+        Node::Empty => Node::Empty,
+        Node::Leaf { path: rest, value } => Node::Leaf {
+            path: [path, &rest].concat(),
+            value,
+        },
+        Node::Extension { path: rest, child } => Node::Extension {
+            path: [path, &rest].concat(),
+            child,
+        },
+        branch => Node::Extension {
+            path: path.to_vec(),
+            child: Box::new(branch),
+        },
+    }
+}
+
+fn branch() -> Node {
+    Node::Branch {
+        children: Box::new(std::array::from_fn(|_| Node::Empty)),
+        value: None,
+    }
+}
+
+fn shared(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+fn nibbles(key: &Bytes) -> Vec<u8> {
+    key.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+/// Packs a path of nibbles, flagging whether it is the path of a leaf and whether it has an odd
+/// number of nibbles.
+fn hex_prefix(path: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = (leaf as u8) << 1 | (path.len() % 2) as u8;
+    let (first, rest) = match path.len() % 2 {
+        1 => (flag << 4 | path[0], &path[1..]),
+        _ => (flag << 4, path),
+    };
+    let mut packed = vec![first];
+    packed.extend(rest.chunks(2).map(|pair| pair[0] << 4 | pair[1]));
+    packed
+}
+
+fn hex_prefix_decode(packed: &[u8]) -> Result<Vec<u8>, MptError> {
+    let (first, rest) = packed.split_first().ok_or(MptError::Malformed)?;
+    let mut path = match first >> 4 {
+        0 | 2 => vec![],
+        1 | 3 => vec![first & 0x0f],
+        _ => return Err(MptError::Malformed),
+    };
+    path.extend(rest.iter().flat_map(|b| [b >> 4, b & 0x0f]));
+    Ok(path)
+}
+
+fn is_leaf(path: &[u8]) -> Result<bool, MptError> {
+    let packed = bytes(path)?;
+    Ok(packed.first().is_some_and(|first| first >> 4 >= 2))
+}
+
+fn trim(integer: &[u8]) -> &[u8] {
+    let zeros = integer.iter().take_while(|b| **b == 0).count();
+    &integer[zeros..]
+}
+
+/// Left pads a big-endian integer to a fixed width.
+fn integer<const N: usize>(bytes: &[u8]) -> Result<[u8; N], MptError> {
+    if bytes.len() > N {
+        return Err(MptError::Malformed);
+    }
+    let mut integer = [0; N];
+    integer[N - bytes.len()..].copy_from_slice(bytes);
+    Ok(integer)
+}
+
+fn length(offset: u8, len: usize) -> Vec<u8> {
+    match len {
+        0..=55 => vec![offset + len as u8],
+        _ => {
+            let len = len.to_be_bytes();
+            let len = trim(&len);
+            let mut header = vec![offset + 55 + len.len() as u8];
+            header.extend_from_slice(len);
+            header
+        }
+    }
+}
+
+fn string(bytes: &[u8]) -> Vec<u8> {
+    match bytes {
+        [byte] if *byte < EMPTY_STRING => vec![*byte],
+        _ => [&length(EMPTY_STRING, bytes.len())[..], bytes].concat(),
+    }
+}
+
+/// Encodes a list of items which are already encoded.
+fn list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload = items.concat();
+    [length(0xc0, payload.len()), payload].concat()
+}
+
+/// Reads the header of an item, returning whether it is a list, its payload and the items
+/// following it.
+fn header(rlp: &[u8]) -> Result<(bool, &[u8], &[u8]), MptError> {
+    let (prefix, rest) = rlp.split_first().ok_or(MptError::Malformed)?;
+    let (list, offset, len) = match *prefix {
+        0..=0x7f => return Ok((false, &rlp[..1], rest)),
+        0x80..=0xb7 => (false, 0, (prefix - 0x80) as usize),
+        0xc0..=0xf7 => (true, 0, (prefix - 0xc0) as usize),
+        _ => {
+            let digits = (prefix - if *prefix >= 0xf8 { 0xf7 } else { 0xb7 }) as usize;
+            let digits = rest.get(..digits).ok_or(MptError::Malformed)?;
+            let len = usize::from_be_bytes(integer(digits)?);
+            (*prefix >= 0xf8, digits.len(), len)
+        }
+    };
+    let end = offset.checked_add(len).ok_or(MptError::Malformed)?;
+    let payload = rest.get(offset..end).ok_or(MptError::Malformed)?;
+    Ok((list, payload, &rest[end..]))
+}
+
+/// The payload of a string.
+fn bytes(rlp: &[u8]) -> Result<&[u8], MptError> {
+    match header(rlp)? {
+        (false, payload, []) => Ok(payload),
+        _ => Err(MptError::Malformed),
+    }
+}
+
+/// The encoded items of a list.
+fn items(rlp: &[u8]) -> Result<Vec<&[u8]>, MptError> {
+    let mut payload = match header(rlp)? {
+        (true, payload, []) => payload,
+        _ => return Err(MptError::Malformed),
+    };
+    let mut items = vec![];
+    while !payload.is_empty() {
+        let (_, _, rest) = header(payload)?;
+        items.push(&payload[..payload.len() - rest.len()]);
+        payload = rest;
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EMPTY_ROOT;
+    use super::{verify, verify_account, verify_storage, Account, MerklePatriciaTrie, MptError};
+    use crate::{HashFunction, Keccak256};
+
+    #[test]
+    fn proves_entries() {
+        let mut trie = MerklePatriciaTrie::new();
+        assert_eq!(trie.root(), EMPTY_ROOT);
+        let entries = [
+            ("do", "verb"),
+            ("dog", "puppy"),
+            ("doge", "coin"),
+            ("horse", "stallion"),
+        ];
+        for (key, value) in entries {
+            trie.insert(key.as_bytes(), value.as_bytes());
+        }
+        let root = trie.root();
+        assert_eq!(
+            hex::encode(root),
+            "5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"
+        );
+        for (key, value) in entries {
+            assert_eq!(trie.get(key.as_bytes()), Some(value.as_bytes()));
+            let proof = trie.prove(key.as_bytes());
+            assert_eq!(
+                verify(&root, key.as_bytes(), &proof),
+                Ok(Some(value.as_bytes().to_vec()))
+            );
+        }
+
+        // Absent keys are proven absent, and proofs do not verify against other roots
+        for key in ["d", "dogs", "cat", "horses"] {
+            assert_eq!(trie.get(key.as_bytes()), None);
+            assert_eq!(
+                verify(&root, key.as_bytes(), &trie.prove(key.as_bytes())),
+                Ok(None)
+            );
+        }
+        let proof = trie.prove(b"dog");
+        assert_eq!(
+            verify(&[0; 32], b"dog", &proof),
+            Err(MptError::HashMismatch)
+        );
+        assert_eq!(
+            verify(&root, b"dog", &proof[..1]),
+            Err(MptError::MissingNode)
+        );
+
+        // Removing entries restores the roots from before they were inserted
+        trie.remove(b"horse");
+        trie.remove(b"doge");
+        let mut expected = MerklePatriciaTrie::new();
+        expected.insert(b"do", b"verb");
+        expected.insert(b"dog", b"puppy");
+        assert_eq!(trie.root(), expected.root());
+        trie.remove(b"do");
+        trie.remove(b"dog");
+        assert_eq!(trie.root(), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn proves_accounts_and_storage() {
+        let mut storage = MerklePatriciaTrie::new();
+        let mut value = [0; 32];
+        value[31] = 42;
+        for slot in 0..20u8 {
+            let mut key = [0; 32];
+            key[31] = slot;
+            storage.insert_storage(&key, &value);
+        }
+        let account = Account {
+            nonce: 1,
+            balance: 10u128.pow(18),
+            storage_root: storage.root(),
+            code_hash: Keccak256::hash(&[]),
+        };
+        let address = [7; 20];
+        let mut state = MerklePatriciaTrie::new();
+        for i in 0..20 {
+            state.insert_account(
+                &[i; 20],
+                &Account {
+                    nonce: i as u64,
+                    ..account
+                },
+            );
+        }
+        state.insert_account(&address, &account);
+
+        let proof = state.prove(&Keccak256::hash(&address));
+        assert_eq!(
+            verify_account(&state.root(), &address, &proof),
+            Ok(Some(account))
+        );
+        let slot = [0; 32];
+        let proof = storage.prove(&Keccak256::hash(&slot));
+        assert_eq!(
+            verify_storage(&account.storage_root, &slot, &proof),
+            Ok(value)
+        );
+        let slot = [1; 32];
+        let proof = storage.prove(&Keccak256::hash(&slot));
+        assert_eq!(
+            verify_storage(&account.storage_root, &slot, &proof),
+            Ok([0; 32])
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use crate::{HashFunction, MerkleTree, OddStrategy};
+use std::collections::{BTreeSet, VecDeque};
+
+/// A proof for several leaves at once, laid out as expected by OpenZeppelin's
+/// `MerkleProof.multiProofVerify`: the leaves in processing order, the sibling hashes which
+/// cannot be computed from them and one flag per hashing step, indicating whether its second
+/// operand is taken from the leaves/computed hashes (`true`) or from the proof (`false`).
+pub struct MerkleMultiProof<T: HashFunction> {
+    leaves: Vec<T::Hash>,
+    proof: Vec<T::Hash>,
+    proof_flags: Vec<bool>,
+}
+
+impl<T: HashFunction> MerkleMultiProof<T> {
+    pub(crate) fn new(leaves: Vec<T::Hash>, proof: Vec<T::Hash>, proof_flags: Vec<bool>) -> Self {
+        Self {
+            leaves,
+            proof,
+            proof_flags,
+        }
+    }
+
+    pub fn leaves(&self) -> &[T::Hash] {
+        &self.leaves
+    }
+
+    pub fn proof(&self) -> &[T::Hash] {
+        &self.proof
+    }
+
+    pub fn proof_flags(&self) -> &[bool] {
+        &self.proof_flags
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty() && self.proof.is_empty()
+    }
+}
+
+impl<T: HashFunction> Clone for MerkleMultiProof<T> {
+    fn clone(&self) -> Self {
+        Self {
+            leaves: self.leaves.clone(),
+            proof: self.proof.clone(),
+            proof_flags: self.proof_flags.clone(),
+        }
+    }
+}
+
+impl<T: HashFunction> std::fmt::Debug for MerkleMultiProof<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let encode = |hashes: &[T::Hash]| {
+            hashes
+                .iter()
+                .map(|hash| format!("0x{}", hex::encode(hash)))
+                .collect::<Vec<String>>()
+        };
+        f.debug_struct("MerkleMultiProof")
+            .field("leaves", &encode(&self.leaves))
+            .field("proof", &encode(&self.proof))
+            .field("proof_flags", &self.proof_flags)
+            .finish()
+    }
+}
+
+impl<T: HashFunction> Default for MerkleMultiProof<T> {
+    fn default() -> Self {
+        Self {
+            leaves: vec![],
+            proof: vec![],
+            proof_flags: vec![],
+        }
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Generates a single proof for all of the supplied leaves, compatible with OpenZeppelin's
+    /// `multiProofVerify`.
+    ///
+    /// An empty proof is returned if any leaf is not found, if the tree is not a binary tree with
+    /// sorted pairs and no node prefix (as required by OpenZeppelin) or if the shape of the tree
+    /// cannot be expressed as a multiproof, which can happen when a leaf is promoted across
+    /// several layers alongside deeper leaves.
+    pub fn multi_proof<H: Copy + Into<T::Hash>>(&self, leaves: &[H]) -> MerkleMultiProof<T> {
+        let spec = &self.spec;
+        if !spec.sort_pairs || spec.arity != 2 || !spec.node_prefix.is_empty() || leaves.is_empty()
+        {
+            return Default::default();
+        }
+
+        // Locate leaf indices, processed in ascending order
+        let mut indices = BTreeSet::new();
+        for leaf in leaves {
+            match self.index_of(*leaf) {
+                Some(index) => indices.insert(index),
+                None => return Default::default(),
+            };
+        }
+
+        // Determine which nodes of each layer can be computed from the supplied leaves
+        let mut known = vec![indices.clone()];
+        for _ in 1..self.layers.len() {
+            let parents = known[known.len() - 1].iter().map(|i| i / 2).collect();
+            known.push(parents);
+        }
+
+        // Simulate the queue used by the verifier, recording which operand each step requires
+        let root_layer = self.layers.len() - 1;
+        let mut queue: VecDeque<(usize, usize)> =
+            indices.iter().map(|i| self.promoted(0, *i)).collect();
+        let mut proof = vec![];
+        let mut proof_flags = vec![];
+        while let Some((layer, index)) = queue.pop_front() {
+            if layer == root_layer {
+                break;
+            }
+
+            let sibling = index ^ 1;
+            if sibling == self.layers[layer].len() {
+                // Odd node paired with a copy of itself or a zero hash
+                proof.push(match spec.odd_strategy {
+                    OddStrategy::ZeroPad => T::Hash::default(),
+                    _ => self.layers[layer][index],
+                });
+                proof_flags.push(false);
+            } else if known[layer].contains(&sibling) {
+                // Sibling must be the next node processed by the verifier
+                if queue.pop_front() != Some((layer, sibling)) {
+                    return Default::default();
+                }
+                proof_flags.push(true);
+            } else {
+                proof.push(self.layers[layer][sibling]);
+                proof_flags.push(false);
+            }
+
+            queue.push_back(self.promoted(layer + 1, index / 2));
+        }
+
+        MerkleMultiProof::new(
+            indices.iter().map(|i| self.leaves()[*i]).collect(),
+            proof,
+            proof_flags,
+        )
+    }
+
+    /// Verifies a multiproof against the root, following OpenZeppelin's `processMultiProof`.
+    pub fn verify_multi_proof(
+        &self,
+        proof: &MerkleMultiProof<T>,
+        root: impl Into<T::Hash>,
+    ) -> bool {
+        let root = root.into();
+        let leaves = &proof.leaves;
+        let total = proof.proof_flags.len();
+        if leaves.len() + proof.proof.len() != total + 1 {
+            return false;
+        }
+
+        let mut hashes = Vec::with_capacity(total);
+        let (mut leaf_position, mut hash_position, mut proof_position) = (0, 0, 0);
+        for flag in proof.proof_flags.iter() {
+            // Take the next leaf, falling back to previously computed hashes
+            let mut next = || {
+                if leaf_position < leaves.len() {
+                    leaf_position += 1;
+                    leaves.get(leaf_position - 1).copied()
+                } else {
+                    hash_position += 1;
+                    hashes.get(hash_position - 1).copied()
+                }
+            };
+
+            let a = next();
+            let b = if *flag {
+                next()
+            } else {
+                proof_position += 1;
+                proof.proof.get(proof_position - 1).copied()
+            };
+            match (a, b) {
+                (Some(a), Some(b)) => hashes.push(MerkleTree::<T>::hash_sorted_pair(a, b)),
+                _ => return false,
+            }
+        }
+
+        let computed = if total > 0 {
+            if proof_position != proof.proof.len() {
+                return false;
+            }
+            hashes[total - 1]
+        } else if !leaves.is_empty() {
+            leaves[0]
+        } else {
+            proof.proof[0]
+        };
+
+        computed == root
+    }
+
+    /// Follows a node up through any layers to which it is promoted unchanged, as the last node of
+    /// a layer with an odd number of nodes.
+    fn promoted(&self, mut layer: usize, mut index: usize) -> (usize, usize) {
+        while self.spec.odd_strategy == OddStrategy::Promote
+            && layer + 1 < self.layers.len()
+            && self.layers[layer].len() == index + 1
+            && index.is_multiple_of(2)
+        {
+            layer += 1;
+            index /= 2;
+        }
+        (layer, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleTree, MerkleTreeBuilder, OddStrategy};
+
+    fn tree(count: u8) -> MerkleTree<Keccak256> {
+        let values: Vec<[u8; 1]> = (0..count).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        MerkleTree::<Keccak256>::new(&leaves)
+    }
+
+    #[test]
+    fn verifies_all_subsets() {
+        for count in 1..=8u8 {
+            let tree = tree(count);
+            let root = tree.root();
+            for subset in 1..(1u32 << count) {
+                let leaves: Vec<[u8; 32]> = (0..count as usize)
+                    .filter(|i| subset & (1 << i) > 0)
+                    .map(|i| tree.leaves()[i])
+                    .collect();
+
+                let proof = tree.multi_proof(&leaves);
+                if proof.is_empty() {
+                    // Only unbalanced trees may be inexpressible
+                    assert!(!count.is_power_of_two(), "{} {:b}", count, subset);
+                    continue;
+                }
+                assert!(
+                    tree.verify_multi_proof(&proof, root),
+                    "{} {:b}",
+                    count,
+                    subset
+                );
+                assert_eq!(proof.leaves().len(), leaves.len());
+            }
+        }
+    }
+
+    #[test]
+    fn completed_odd_nodes() {
+        for (count, strategy) in
+            (1..=8u8).flat_map(|c| [(c, OddStrategy::Duplicate), (c, OddStrategy::ZeroPad)])
+        {
+            let values: Vec<[u8; 1]> = (0..count).map(|i| [i]).collect();
+            let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+            let tree = MerkleTreeBuilder::<Keccak256>::new()
+                .odd_strategy(strategy)
+                .build(&leaves);
+            for subset in 1..(1u32 << count) {
+                let leaves: Vec<[u8; 32]> = (0..count as usize)
+                    .filter(|i| subset & (1 << i) > 0)
+                    .map(|i| tree.leaves()[i])
+                    .collect();
+
+                // Every subset can be expressed when no nodes are promoted
+                let proof = tree.multi_proof(&leaves);
+                assert!(tree.verify_multi_proof(&proof, tree.root()));
+            }
+        }
+    }
+
+    #[test]
+    fn matches_single_proof() {
+        let tree = tree(5);
+        let leaf = tree.leaves()[1];
+        let proof = tree.multi_proof(&[leaf]);
+        assert_eq!(proof.proof(), tree.proof(leaf).hashes());
+        assert!(proof.proof_flags().iter().all(|flag| !flag));
+    }
+
+    #[test]
+    fn rejects_invalid_proofs() {
+        let tree = tree(8);
+        let root = tree.root();
+        let leaves = [tree.leaves()[0], tree.leaves()[3], tree.leaves()[6]];
+        let proof = tree.multi_proof(&leaves);
+        assert!(tree.verify_multi_proof(&proof, root));
+
+        // Tampered leaf
+        let mut tampered = proof.clone();
+        tampered.leaves[1] = tree.leaves()[2];
+        assert!(!tree.verify_multi_proof(&tampered, root));
+
+        // Tampered flags
+        let mut tampered = proof.clone();
+        tampered.proof_flags[0] = !tampered.proof_flags[0];
+        assert!(!tree.verify_multi_proof(&tampered, root));
+
+        // Truncated proof
+        let mut tampered = proof;
+        tampered.proof.pop();
+        assert!(!tree.verify_multi_proof(&tampered, root));
+
+        // Unknown leaf
+        assert!(tree.multi_proof(&[[0u8; 32]]).is_empty());
+    }
+}
@@ -0,0 +1,683 @@
+use crate::compat::heap;
+use crate::{HashFunction, Keccak256, MerkleProof, MerkleTree, MerkleTreeBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+
+const FORMAT: &str = "standard-v1";
+
+type Hash = [u8; 32];
+
+/// A tree compatible with `StandardMerkleTree` from `@openzeppelin/merkle-tree`, which can be
+/// read from and written to the JSON dump format shared with the JS tooling.
+///
+/// Leaves are ABI encoded according to the leaf encoding and hashed twice with keccak256, pairs
+/// are sorted before hashing and the nodes are stored as a flat binary heap with the root first.
+/// For counts of leaves which are not a power of two this places leaves at different depths to
+/// `MerkleTree`, so the roots of the two only agree for balanced trees.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StandardMerkleTree {
+    tree: Vec<Hash>,
+    values: Vec<StandardValue>,
+    leaf_encoding: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StandardValue {
+    value: Vec<Value>,
+    tree_index: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StandardDump {
+    format: String,
+    tree: Vec<String>,
+    values: Vec<StandardValue>,
+    leaf_encoding: Vec<String>,
+}
+
+impl StandardMerkleTree {
+    /// Creates a tree from the supplied values, each encoded according to `leaf_encoding`, such
+    /// as `["address", "uint256"]`. Leaves are sorted by hash, as is the default in OpenZeppelin.
+    /// The elementary static types, `bytes`, `string` and arrays of any of them are supported.
+    pub fn of(values: Vec<Vec<Value>>, leaf_encoding: &[&str]) -> Result<Self, StandardTreeError> {
+        if values.is_empty() {
+            return Err(StandardTreeError::Empty);
+        }
+        let leaf_encoding: Vec<String> = leaf_encoding.iter().map(|t| t.to_string()).collect();
+
+        let hashes = values
+            .iter()
+            .map(|value| leaf_hash(&leaf_encoding, value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (tree, indices) = heap(&hashes);
+
+        let values = values
+            .into_iter()
+            .zip(indices)
+            .map(|(value, tree_index)| StandardValue { value, tree_index })
+            .collect();
+        Ok(Self {
+            tree,
+            values,
+            leaf_encoding,
+        })
+    }
+
+    /// Loads a tree from the JSON produced by `StandardMerkleTree.dump()`, checking that every
+    /// node and leaf is consistent with the values.
+    pub fn from_standard_json(json: &str) -> Result<Self, StandardTreeError> {
+        let dump: StandardDump =
+            serde_json::from_str(json).map_err(|e| StandardTreeError::Json(e.to_string()))?;
+        if dump.format != FORMAT {
+            return Err(StandardTreeError::UnsupportedFormat(dump.format));
+        }
+
+        let tree = dump
+            .tree
+            .iter()
+            .map(|node| {
+                hex::decode(node.trim_start_matches("0x"))
+                    .ok()
+                    .and_then(|bytes| Hash::try_from(bytes).ok())
+                    .ok_or_else(|| StandardTreeError::Json(format!("invalid node '{}'", node)))
+            })
+            .collect::<Result<Vec<Hash>, _>>()?;
+        if tree.is_empty() || dump.values.is_empty() {
+            return Err(StandardTreeError::Empty);
+        }
+
+        // Check internal nodes and that each value is stored at a leaf with its hash
+        for i in 0..tree.len() {
+            if 2 * i + 2 < tree.len() {
+                let expected =
+                    MerkleTree::<Keccak256>::hash_sorted_pair(tree[2 * i + 1], tree[2 * i + 2]);
+                if tree[i] != expected {
+                    return Err(StandardTreeError::InvalidTree(i));
+                }
+            }
+        }
+        for value in &dump.values {
+            let index = value.tree_index;
+            if index >= tree.len()
+                || 2 * index + 1 < tree.len()
+                || leaf_hash(&dump.leaf_encoding, &value.value)? != tree[index]
+            {
+                return Err(StandardTreeError::InvalidTree(index));
+            }
+        }
+
+        Ok(Self {
+            tree,
+            values: dump.values,
+            leaf_encoding: dump.leaf_encoding,
+        })
+    }
+
+    /// Writes the tree in the `standard-v1` JSON format read by `StandardMerkleTree.load()`.
+    pub fn to_standard_json(&self) -> String {
+        let dump = StandardDump {
+            format: FORMAT.to_string(),
+            tree: self
+                .tree
+                .iter()
+                .map(|node| format!("0x{}", hex::encode(node)))
+                .collect(),
+            values: self.values.clone(),
+            leaf_encoding: self.leaf_encoding.clone(),
+        };
+        serde_json::to_string(&dump).expect("dump contains only strings and numbers")
+    }
+
+    pub fn root(&self) -> Hash {
+        self.tree[0]
+    }
+
+    pub fn leaf_encoding(&self) -> &[String] {
+        &self.leaf_encoding
+    }
+
+    /// The values of the tree, in the order they were supplied.
+    pub fn values(&self) -> impl Iterator<Item = &[Value]> {
+        self.values.iter().map(|v| &v.value[..])
+    }
+
+    /// The leaf hash of the value at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn leaf(&self, index: usize) -> Hash {
+        self.tree[self.values[index].tree_index]
+    }
+
+    /// Generates a proof for the value at `index`, which can be checked with `verify` or with
+    /// OpenZeppelin's `MerkleProof.verify`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn proof(&self, index: usize) -> MerkleProof<Keccak256> {
+        let mut index = self.values[index].tree_index;
+        let mut proof = vec![];
+        while index > 0 {
+            let sibling = if index % 2 == 1 { index + 1 } else { index - 1 };
+            proof.push(self.tree[sibling]);
+            index = (index - 1) / 2;
+        }
+        MerkleProof::new(proof, None, None)
+    }
+
+    pub fn verify(&self, proof: &MerkleProof<Keccak256>, leaf: impl Into<Hash>) -> bool {
+        let computed = proof.iter().fold(leaf.into(), |hash, sibling| {
+            MerkleTree::<Keccak256>::hash_sorted_pair(hash, *sibling)
+        });
+        computed == self.root()
+    }
+}
+
+impl MerkleTree<Keccak256> {
+    /// Loads a tree from the JSON produced by `StandardMerkleTree.dump()`, checked as by
+    /// `StandardMerkleTree::from_standard_json`, whose leaves are the leaf hashes of the values.
+    /// The leaves are pre-hashed, so leaves added later must also be leaf hashes.
+    ///
+    /// OpenZeppelin arranges leaves as a heap, which for some counts of leaves which are not a
+    /// power of two gives another root than a `MerkleTree` of the same leaves. Such trees fail to
+    /// load with `StandardTreeError::RootMismatch`, and are used through `StandardMerkleTree`.
+    pub fn from_standard_json(json: &str) -> Result<Self, StandardTreeError> {
+        StandardMerkleTree::from_standard_json(json)?.to_merkle_tree()
+    }
+
+    /// Writes the tree in the `standard-v1` JSON format read by `StandardMerkleTree.load()`,
+    /// along with the values of its leaves encoded according to `leaf_encoding`. Fails with
+    /// `StandardTreeError::RootMismatch` unless the OpenZeppelin tree of the values has the root
+    /// of this tree, as when the values are not those of the leaves or are arranged differently.
+    pub fn to_standard_json(
+        &self,
+        values: Vec<Vec<Value>>,
+        leaf_encoding: &[&str],
+    ) -> Result<String, StandardTreeError> {
+        let standard = StandardMerkleTree::of(values, leaf_encoding)?;
+        match standard.root() == self.root() {
+            true => Ok(standard.to_standard_json()),
+            false => Err(StandardTreeError::RootMismatch),
+        }
+    }
+}
+
+impl StandardMerkleTree {
+    /// The `MerkleTree` of the leaf hashes, if it has the same root.
+    fn to_merkle_tree(&self) -> Result<MerkleTree<Keccak256>, StandardTreeError> {
+        let leaves = (0..self.values.len()).map(|i| self.leaf(i)).collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .hash_leaves(false)
+            .build_from_hashes(leaves);
+        match tree.root() == self.root() {
+            true => Ok(tree),
+            false => Err(StandardTreeError::RootMismatch),
+        }
+    }
+}
+
+/// Hashes a value as OpenZeppelin does, as `keccak256(keccak256(abi.encode(value)))`.
+fn leaf_hash(types: &[String], value: &[Value]) -> Result<Hash, StandardTreeError> {
+    let types: Vec<&str> = types.iter().map(|t| t.as_str()).collect();
+    let encoded = encode_tuple(&types, value)?;
+    Ok(Keccak256::hash(&Keccak256::hash(&encoded)))
+}
+
+/// ABI encodes values as a tuple of the supplied types, with the head of each value in turn
+/// followed by the tails of the dynamic values, which their heads hold the offsets of.
+fn encode_tuple(types: &[&str], values: &[Value]) -> Result<Vec<u8>, StandardTreeError> {
+    if types.len() != values.len() {
+        return Err(StandardTreeError::InvalidValue(format!(
+            "expected {} values but found {}",
+            types.len(),
+            values.len()
+        )));
+    }
+
+    let mut heads = Vec::with_capacity(32 * types.len());
+    let mut tails = vec![];
+    let mut encoded = Vec::with_capacity(types.len());
+    for (kind, value) in types.iter().zip(values) {
+        let dynamic = is_dynamic(kind)?;
+        encoded.push((dynamic, encode(kind, value)?));
+    }
+    let head_len: usize = encoded
+        .iter()
+        .map(|(dynamic, value)| if *dynamic { 32 } else { value.len() })
+        .sum();
+    for (dynamic, value) in encoded {
+        match dynamic {
+            true => {
+                heads.extend_from_slice(&to_word((head_len + tails.len()) as u64));
+                tails.extend_from_slice(&value);
+            }
+            false => heads.extend_from_slice(&value),
+        }
+    }
+    heads.extend_from_slice(&tails);
+    Ok(heads)
+}
+
+/// Whether values of the type are encoded in the tail of a tuple, being `bytes`, `string`,
+/// arrays of dynamic length and fixed length arrays of dynamic types.
+fn is_dynamic(kind: &str) -> Result<bool, StandardTreeError> {
+    if let Some((element, length)) = array(kind) {
+        let dynamic = is_dynamic(element)?;
+        return Ok(length.is_none() || dynamic);
+    }
+    match kind {
+        "bytes" | "string" => Ok(true),
+        _ => encode_word(kind, None).map(|_| false),
+    }
+}
+
+/// Splits an array type such as `uint8[]` or `address[2]` into its element type and length,
+/// which is `None` for an array of dynamic length.
+fn array(kind: &str) -> Option<(&str, Option<usize>)> {
+    let (element, length) = kind.strip_suffix(']')?.rsplit_once('[')?;
+    match length {
+        "" => Some((element, None)),
+        _ => Some((element, Some(length.parse().ok()?))),
+    }
+}
+
+/// ABI encodes a single value of a type, without the offset of a dynamic value.
+fn encode(kind: &str, value: &Value) -> Result<Vec<u8>, StandardTreeError> {
+    let invalid = || StandardTreeError::InvalidValue(format!("invalid {} '{}'", kind, value));
+    if let Some((element, length)) = array(kind) {
+        let values = value.as_array().ok_or_else(invalid)?;
+        if length.is_some_and(|length| length != values.len()) {
+            return Err(invalid());
+        }
+        let mut encoded = match length {
+            Some(_) => vec![],
+            None => to_word(values.len() as u64).to_vec(),
+        };
+        encoded.extend_from_slice(&encode_tuple(&vec![element; values.len()], values)?);
+        return Ok(encoded);
+    }
+
+    let bytes = match kind {
+        "bytes" => value.as_str().and_then(decode_hex).ok_or_else(invalid)?,
+        "string" => value.as_str().ok_or_else(invalid)?.as_bytes().to_vec(),
+        _ => return Ok(encode_word(kind, Some(value))?.to_vec()),
+    };
+    let mut encoded = to_word(bytes.len() as u64).to_vec();
+    encoded.extend_from_slice(&bytes);
+    encoded.resize(32 + bytes.len().div_ceil(32) * 32, 0);
+    Ok(encoded)
+}
+
+/// ABI encodes a single value of a static elementary type as a 32 byte word. Without a value the
+/// type is only checked to be supported.
+fn encode_word(kind: &str, value: Option<&Value>) -> Result<Hash, StandardTreeError> {
+    let supported = match kind {
+        "address" | "bool" => true,
+        _ if kind.starts_with("uint") => bits(kind, "uint").is_ok(),
+        _ if kind.starts_with("int") => bits(kind, "int").is_ok(),
+        _ if kind.starts_with("bytes") => kind["bytes".len()..]
+            .parse::<usize>()
+            .is_ok_and(|size| (1..=32).contains(&size)),
+        _ => false,
+    };
+    let value = match (supported, value) {
+        (false, _) => return Err(StandardTreeError::UnsupportedType(kind.to_string())),
+        (true, None) => return Ok(Hash::default()),
+        (true, Some(value)) => value,
+    };
+
+    let invalid = || StandardTreeError::InvalidValue(format!("invalid {} '{}'", kind, value));
+    let mut word = Hash::default();
+    match kind {
+        "address" => {
+            let address = value.as_str().ok_or_else(invalid)?;
+            let bytes = decode_hex(address).filter(|b| b.len() == 20);
+            word[12..].copy_from_slice(&bytes.ok_or_else(invalid)?);
+        }
+        "bool" => word[31] = value.as_bool().ok_or_else(invalid)? as u8,
+        _ if kind.starts_with("uint") => {
+            let bits = bits(kind, "uint")?;
+            word = parse_integer(value).ok_or_else(invalid)?;
+            if !fits(&word, bits) {
+                return Err(invalid());
+            }
+        }
+        _ if kind.starts_with("int") => {
+            let bits = bits(kind, "int")?;
+            let (negative, magnitude) = match value {
+                Value::String(s) if s.starts_with('-') => {
+                    (true, parse_integer(&Value::String(s[1..].to_string())))
+                }
+                Value::Number(n) if n.as_i64().is_some_and(|n| n < 0) => {
+                    (true, Some(to_word(n.as_i64().unwrap().unsigned_abs())))
+                }
+                _ => (false, parse_integer(value)),
+            };
+            let magnitude = magnitude.ok_or_else(invalid)?;
+
+            // The range of a signed integer extends one further below zero than above
+            let limit = magnitude == power_of_two(bits - 1);
+            if !(fits(&magnitude, bits - 1) || negative && limit) {
+                return Err(invalid());
+            }
+            word = if negative {
+                negate(magnitude)
+            } else {
+                magnitude
+            };
+        }
+        _ => {
+            let size = kind["bytes".len()..].parse::<usize>().unwrap_or_default();
+            let bytes = value.as_str().and_then(decode_hex);
+            let bytes = bytes.filter(|b| b.len() == size).ok_or_else(invalid)?;
+            word[..size].copy_from_slice(&bytes);
+        }
+    }
+    Ok(word)
+}
+
+/// Parses the size of an integer type such as `uint64`, where no size means 256 bits.
+fn bits(kind: &str, prefix: &str) -> Result<usize, StandardTreeError> {
+    let size = &kind[prefix.len()..];
+    if size.is_empty() {
+        return Ok(256);
+    }
+    size.parse::<usize>()
+        .ok()
+        .filter(|bits| (8..=256).contains(bits) && bits.is_multiple_of(8))
+        .ok_or_else(|| StandardTreeError::UnsupportedType(kind.to_string()))
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    value.strip_prefix("0x").and_then(|v| hex::decode(v).ok())
+}
+
+/// Parses a non-negative integer given as a JSON number, a decimal string or a `0x` prefixed
+/// hex string into a big endian word.
+fn parse_integer(value: &Value) -> Option<Hash> {
+    match value {
+        Value::Number(n) => n.as_u64().map(to_word),
+        Value::String(s) if s.starts_with("0x") => {
+            let digits = s[2..].trim_start_matches('0');
+            let bytes = hex::decode(format!("{:0>64}", digits)).ok()?;
+            Hash::try_from(bytes).ok()
+        }
+        Value::String(s) if !s.is_empty() => {
+            let mut word = Hash::default();
+            for digit in s.chars() {
+                let mut carry = digit.to_digit(10)?;
+                for byte in word.iter_mut().rev() {
+                    let product = *byte as u32 * 10 + carry;
+                    *byte = product as u8;
+                    carry = product >> 8;
+                }
+                if carry > 0 {
+                    return None;
+                }
+            }
+            Some(word)
+        }
+        _ => None,
+    }
+}
+
+fn to_word(value: u64) -> Hash {
+    let mut word = Hash::default();
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Whether the word is less than 2^bits.
+fn fits(word: &Hash, bits: usize) -> bool {
+    word < &power_of_two(bits) || bits == 256
+}
+
+fn power_of_two(bits: usize) -> Hash {
+    let mut word = Hash::default();
+    if bits < 256 {
+        word[31 - bits / 8] = 1 << (bits % 8);
+    }
+    word
+}
+
+/// Computes the two's complement of a word.
+fn negate(mut word: Hash) -> Hash {
+    let mut carry = 1;
+    for byte in word.iter_mut().rev() {
+        let sum = (!*byte) as u16 + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+    word
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StandardTreeError {
+    /// The tree has no values.
+    Empty,
+    /// The JSON could not be parsed.
+    Json(String),
+    /// The dump is not in the `standard-v1` format.
+    UnsupportedFormat(String),
+    /// The leaf encoding contains a type which is not supported.
+    UnsupportedType(String),
+    /// A value could not be encoded with its type.
+    InvalidValue(String),
+    /// The node at the index does not match the values of the tree.
+    InvalidTree(usize),
+    /// The OpenZeppelin tree of the values and the `MerkleTree` of their leaf hashes have
+    /// different roots.
+    RootMismatch,
+}
+
+impl Display for StandardTreeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StandardTreeError::Empty => write!(f, "tree must contain at least one value"),
+            StandardTreeError::Json(e) => write!(f, "invalid tree json: {}", e),
+            StandardTreeError::UnsupportedFormat(format) => {
+                write!(f, "unsupported tree format '{}'", format)
+            }
+            StandardTreeError::UnsupportedType(kind) => {
+                write!(f, "unsupported leaf encoding type '{}'", kind)
+            }
+            StandardTreeError::InvalidValue(e) => write!(f, "{}", e),
+            StandardTreeError::InvalidTree(index) => {
+                write!(f, "tree node {} does not match the tree values", index)
+            }
+            StandardTreeError::RootMismatch => {
+                write!(
+                    f,
+                    "openzeppelin tree does not have the root of the merkle tree"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for StandardTreeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::encode_tuple;
+    use crate::{Keccak256, MerkleTree, StandardMerkleTree, StandardTreeError};
+    use serde_json::{json, Value};
+
+    fn tree() -> StandardMerkleTree {
+        StandardMerkleTree::of(
+            vec![
+                vec![
+                    json!("0x1111111111111111111111111111111111111111"),
+                    json!("5000000000000000000"),
+                ],
+                vec![
+                    json!("0x2222222222222222222222222222222222222222"),
+                    json!("2500000000000000000"),
+                ],
+            ],
+            &["address", "uint256"],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn matches_openzeppelin() {
+        // Root of the example in the @openzeppelin/merkle-tree readme
+        let tree = tree();
+        assert_eq!(
+            hex::encode(tree.root()),
+            "d4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77"
+        );
+        for i in 0..2 {
+            assert!(tree.verify(&tree.proof(i), tree.leaf(i)));
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let values = (0..5)
+            .map(|i| vec![json!(i), json!(format!("-{}", i)), json!(i % 2 == 0)])
+            .collect();
+        let tree = StandardMerkleTree::of(values, &["uint8", "int16", "bool"]).unwrap();
+
+        let json = tree.to_standard_json();
+        assert!(json.starts_with("{\"format\":\"standard-v1\",\"tree\":[\"0x"));
+        let loaded = StandardMerkleTree::from_standard_json(&json).unwrap();
+        assert_eq!(loaded, tree);
+        for i in 0..5 {
+            assert!(loaded.verify(&loaded.proof(i), loaded.leaf(i)));
+            assert!(!loaded.verify(&loaded.proof(i), loaded.leaf((i + 1) % 5)));
+        }
+
+        // Tampered nodes are detected
+        let mut dump: serde_json::Value = serde_json::from_str(&json).unwrap();
+        dump["tree"][5] = json!(format!("0x{}", hex::encode([0u8; 32])));
+        assert!(matches!(
+            StandardMerkleTree::from_standard_json(&dump.to_string()),
+            Err(StandardTreeError::InvalidTree(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        for (kind, value) in [
+            ("uint8", json!(256)),
+            ("int8", json!("-129")),
+            ("int8", json!(128)),
+            ("address", json!("0x11")),
+            ("bytes2", json!("0x112233")),
+        ] {
+            assert!(matches!(
+                StandardMerkleTree::of(vec![vec![value]], &[kind]),
+                Err(StandardTreeError::InvalidValue(_))
+            ));
+        }
+        assert!(StandardMerkleTree::of(vec![vec![json!("-128")]], &["int8"]).is_ok());
+        for (kind, value, unsupported) in [
+            ("function", json!("a"), "function"),
+            ("uint7[]", json!([]), "uint7"),
+            ("string[x]", json!(["a"]), "string[x]"),
+        ] {
+            assert_eq!(
+                StandardMerkleTree::of(vec![vec![value]], &[kind]),
+                Err(StandardTreeError::UnsupportedType(unsupported.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn encodes_dynamic_types() {
+        let word = |n: u8| format!("{:0>64}", format!("{:x}", n));
+        let encoded = encode_tuple(
+            &["uint256", "string", "uint8[]"],
+            &[json!(1), json!("abc"), json!([1, 2])],
+        )
+        .unwrap();
+        let expected = [
+            word(1),
+            word(0x60),
+            word(0xa0),
+            word(3),
+            format!("{:0<64}", "616263"),
+            word(2),
+            word(1),
+            word(2),
+        ];
+        assert_eq!(hex::encode(encoded), expected.concat());
+
+        // Arrays of dynamic types hold the offsets of their elements, relative to the elements
+        let encoded = encode_tuple(&["bytes[2]"], &[json!(["0x12", "0x"])]).unwrap();
+        let expected = [
+            word(0x20),
+            word(0x40),
+            word(0x80),
+            word(1),
+            format!("{:0<64}", "12"),
+            word(0),
+        ];
+        assert_eq!(hex::encode(encoded), expected.concat());
+        assert!(matches!(
+            encode_tuple(&["address[2]"], &[json!(["0x11", "0x22"])]),
+            Err(StandardTreeError::InvalidValue(_))
+        ));
+
+        // Trees of dynamic values round trip through the dump format
+        let values = vec![
+            vec![
+                json!("alice"),
+                json!(["0x1111111111111111111111111111111111111111"]),
+            ],
+            vec![json!("bob"), json!([])],
+        ];
+        let tree = StandardMerkleTree::of(values, &["string", "address[]"]).unwrap();
+        let loaded = StandardMerkleTree::from_standard_json(&tree.to_standard_json()).unwrap();
+        assert_eq!(loaded, tree);
+        assert!(StandardMerkleTree::of(vec![vec![json!([1])]], &["uint8[2]"]).is_err());
+    }
+
+    #[test]
+    fn converts_merkle_trees() {
+        let standard = tree();
+        let tree =
+            MerkleTree::<Keccak256>::from_standard_json(&standard.to_standard_json()).unwrap();
+        assert_eq!(tree.root(), standard.root());
+        for i in 0..2 {
+            let leaf = standard.leaf(i);
+            assert!(standard.verify(&tree.proof(leaf), leaf));
+        }
+
+        let values: Vec<Vec<Value>> = standard.values().map(|v| v.to_vec()).collect();
+        let json = tree
+            .to_standard_json(values.clone(), &["address", "uint256"])
+            .unwrap();
+        assert_eq!(
+            StandardMerkleTree::from_standard_json(&json).unwrap(),
+            standard
+        );
+        assert_eq!(
+            tree.to_standard_json(values[..1].to_vec(), &["address", "uint256"]),
+            Err(StandardTreeError::RootMismatch)
+        );
+
+        // Five leaves are arranged differently by OpenZeppelin, while three are not
+        let values = |count: u64| (0..count).map(|i| vec![json!(i)]).collect::<Vec<_>>();
+        let five = StandardMerkleTree::of(values(5), &["uint256"]).unwrap();
+        assert_eq!(
+            MerkleTree::<Keccak256>::from_standard_json(&five.to_standard_json()).err(),
+            Some(StandardTreeError::RootMismatch)
+        );
+        let three = StandardMerkleTree::of(values(3), &["uint256"]).unwrap();
+        let json = three.to_standard_json();
+        assert_eq!(
+            MerkleTree::<Keccak256>::from_standard_json(&json)
+                .unwrap()
+                .root(),
+            three.root()
+        );
+    }
+}
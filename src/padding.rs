@@ -0,0 +1,122 @@
+use crate::{HashFunction, MerkleTree, PaddingLeaf, TreeSpec};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// The leaf padding a tree with the supplied spec, if padded.
+    pub(crate) fn padding_leaf(spec: &TreeSpec) -> Option<T::Hash> {
+        match &spec.padding {
+            Some(PaddingLeaf::Zero) => Some(T::Hash::default()),
+            Some(PaddingLeaf::Custom(leaf)) => T::Hash::try_from(leaf.clone()).ok(),
+            None => None,
+        }
+    }
+
+    /// The number of leaves a tree with `len` leaves is padded to.
+    pub(crate) fn padded_len(spec: &TreeSpec, len: usize) -> usize {
+        if spec.padding.is_none() || len == 0 {
+            return len;
+        }
+        let mut padded = 1;
+        while padded < len {
+            padded *= spec.arity;
+        }
+        padded
+    }
+
+    /// Appends padding leaves as required by the spec.
+    pub(crate) fn pad_leaves(spec: &TreeSpec, leaves: &mut Vec<T::Hash>) {
+        if let Some(padding) = MerkleTree::<T>::padding_leaf(spec) {
+            leaves.resize(MerkleTree::<T>::padded_len(spec, leaves.len()), padding);
+        }
+    }
+
+    /// Removes the padding leaves from the end of the leaf layer, so leaves can be added or
+    /// removed before padding again. A leaf equal to the padding leaf at the end of the leaf
+    /// layer is indistinguishable from padding, so is also removed.
+    pub(crate) fn unpad(&mut self) {
+        let len = self.unpadded_len();
+        self.layers[0].truncate(len);
+    }
+
+    /// The number of leaves before any padding.
+    pub(crate) fn unpadded_len(&self) -> usize {
+        let leaves = &self.layers[0];
+        match MerkleTree::<T>::padding_leaf(&self.spec) {
+            Some(padding) => {
+                leaves.len() - leaves.iter().rev().take_while(|l| **l == padding).count()
+            }
+            None => leaves.len(),
+        }
+    }
+
+    /// Pads the leaf layer again after `unpad`.
+    pub(crate) fn pad(&mut self) {
+        MerkleTree::<T>::pad_leaves(&self.spec, &mut self.layers[0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Bytes, HashFunction, Keccak256, MerkleTree, MerkleTreeBuilder, PaddingLeaf, SpecError,
+        TreeSpec,
+    };
+
+    #[test]
+    fn pads_to_complete_trees() {
+        let values: Vec<[u8; 1]> = (0..5).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let builder = MerkleTreeBuilder::<Keccak256>::new().pad_to_pow2(PaddingLeaf::Zero);
+        let tree = builder.build(&leaves);
+        assert_eq!(tree.leaves().len(), 8);
+        assert_eq!(tree.leaves()[5..], [[0u8; 32]; 3]);
+        assert_eq!(
+            tree.spec().to_string(),
+            "keccak256/sorted/promote-odd/arity2/pad-zero"
+        );
+
+        // Every proof has the same length
+        for leaf in tree.leaves().iter().take(5) {
+            let proof = tree.proof(*leaf);
+            assert_eq!(proof.len(), 3);
+            assert!(tree.verify(proof, *leaf, tree.root()));
+        }
+
+        // Mutations keep the tree padded
+        let mut tree = tree;
+        tree.push(&[5]);
+        tree.push(&[6]);
+        tree.push(&[7]);
+        tree.push(&[8]);
+        assert_eq!(tree.leaves().len(), 16);
+        let values: Vec<[u8; 1]> = (0..9).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        assert_eq!(tree.root(), builder.build(&leaves).root());
+        tree.remove(tree.index_of(Keccak256::hash(&[0])).unwrap());
+        assert_eq!(tree.leaves().len(), 8);
+        assert_eq!(tree.root(), builder.build(&leaves[1..]).root());
+        tree.update(tree.index_of(Keccak256::hash(&[1])).unwrap(), &[9]);
+        let mut updated = leaves[2..].to_vec();
+        updated.push(&[9]);
+        assert_eq!(tree.root(), builder.build(&updated).root());
+
+        // Custom padding with a higher arity
+        let custom = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(false)
+            .arity(3)
+            .pad_to_pow2(PaddingLeaf::Custom(vec![1; 32]))
+            .build(&leaves[..4]);
+        assert_eq!(custom.leaves().len(), 9);
+        assert_eq!(custom.leaves()[8], [1; 32]);
+        assert_eq!(MerkleTree::<Keccak256>::new(&[]).leaves().len(), 0);
+        assert_eq!(builder.build(&[]).leaves().len(), 0);
+        assert_eq!(builder.build(&leaves[..1]).leaves().len(), 1);
+
+        let spec = TreeSpec::parse("keccak256/pad-leaf00").unwrap();
+        assert_eq!(
+            MerkleTreeBuilder::<Keccak256>::from_spec(&spec).err(),
+            Some(SpecError::InvalidPadding("00".to_string()))
+        );
+    }
+}
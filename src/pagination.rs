@@ -0,0 +1,154 @@
+use crate::{HashFunction, MerkleTree};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Length of the root prefix identifying which version of a tree a cursor was issued for.
+const ROOT_ID: usize = 8;
+
+/// A page of consecutive leaves, borrowed from the tree rather than copied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeafPage<'a, H> {
+    pub leaves: &'a [H],
+    /// The index of the first leaf of the page.
+    pub offset: usize,
+    /// The number of leaves in the tree.
+    pub total: usize,
+    /// The cursor of the following page, if there are more leaves.
+    pub next: Option<LeafCursor>,
+}
+
+/// An opaque position within the leaves of a particular version of a tree, formatted as a string
+/// for use as the cursor of a REST or GraphQL API. Cursors are rejected once the tree changes, as
+/// leaves may have moved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LeafCursor {
+    offset: usize,
+    root_id: [u8; ROOT_ID],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PageError {
+    /// The cursor is not one formatted by `LeafCursor`.
+    MalformedCursor,
+    /// The cursor was issued for another version of the tree.
+    StaleCursor,
+}
+
+impl Display for PageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageError::MalformedCursor => write!(f, "malformed cursor"),
+            PageError::StaleCursor => write!(f, "cursor was issued before the tree changed"),
+        }
+    }
+}
+
+impl std::error::Error for PageError {}
+
+impl Display for LeafCursor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.offset, hex::encode(self.root_id))
+    }
+}
+
+impl FromStr for LeafCursor {
+    type Err = PageError;
+
+    fn from_str(cursor: &str) -> Result<Self, Self::Err> {
+        let (offset, root_id) = cursor.split_once('-').ok_or(PageError::MalformedCursor)?;
+        let offset = offset.parse().map_err(|_| PageError::MalformedCursor)?;
+        let root_id = hex::decode(root_id)
+            .ok()
+            .and_then(|id| id.try_into().ok())
+            .ok_or(PageError::MalformedCursor)?;
+        Ok(Self { offset, root_id })
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// The page of at most `limit` leaves starting at `offset`, which is empty beyond the last
+    /// leaf. Leaves are in the order of the tree, which is stable until the tree changes.
+    pub fn leaves_page(&self, offset: usize, limit: usize) -> LeafPage<'_, T::Hash> {
+        let leaves = self.leaves();
+        let start = offset.min(leaves.len());
+        let end = start.saturating_add(limit).min(leaves.len());
+        LeafPage {
+            leaves: &leaves[start..end],
+            offset: start,
+            total: leaves.len(),
+            next: (end < leaves.len() && end > start).then(|| LeafCursor {
+                offset: end,
+                root_id: self.root_id(),
+            }),
+        }
+    }
+
+    /// The cursor of the first page of leaves.
+    pub fn leaves_cursor(&self) -> LeafCursor {
+        LeafCursor {
+            offset: 0,
+            root_id: self.root_id(),
+        }
+    }
+
+    /// The page of at most `limit` leaves at `cursor`, failing if the tree has changed since the
+    /// cursor was issued.
+    pub fn leaves_after(
+        &self,
+        cursor: &LeafCursor,
+        limit: usize,
+    ) -> Result<LeafPage<'_, T::Hash>, PageError> {
+        match cursor.root_id == self.root_id() {
+            true => Ok(self.leaves_page(cursor.offset, limit)),
+            false => Err(PageError::StaleCursor),
+        }
+    }
+
+    fn root_id(&self) -> [u8; ROOT_ID] {
+        let root = self.root();
+        let mut id = [0; ROOT_ID];
+        let length = root.as_ref().len().min(ROOT_ID);
+        id[..length].copy_from_slice(&root.as_ref()[..length]);
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, LeafCursor, MerkleTree, PageError};
+
+    #[test]
+    fn paginates_leaves() {
+        let values: Vec<[u8; 1]> = (0..10).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let mut tree = MerkleTree::<Keccak256>::new(&leaves);
+
+        let page = tree.leaves_page(8, 5);
+        assert_eq!(
+            (page.leaves, page.offset, page.total),
+            (&tree.leaves()[8..], 8, 10)
+        );
+        assert_eq!(page.next, None);
+        assert!(tree.leaves_page(20, 5).leaves.is_empty());
+
+        // Following cursors visits every leaf once, in order
+        let mut cursor = Some(tree.leaves_cursor());
+        let mut visited = vec![];
+        while let Some(current) = cursor {
+            let parsed: LeafCursor = current.to_string().parse().unwrap();
+            let page = tree.leaves_after(&parsed, 3).unwrap();
+            visited.extend_from_slice(page.leaves);
+            cursor = page.next;
+        }
+        assert_eq!(&visited, tree.leaves());
+
+        let cursor = tree.leaves_page(0, 3).next.unwrap();
+        tree.push(b"k");
+        assert_eq!(tree.leaves_after(&cursor, 3), Err(PageError::StaleCursor));
+        assert_eq!("3".parse::<LeafCursor>(), Err(PageError::MalformedCursor));
+        assert_eq!(
+            "3-00".parse::<LeafCursor>(),
+            Err(PageError::MalformedCursor)
+        );
+    }
+}
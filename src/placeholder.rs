@@ -0,0 +1,228 @@
+use crate::{verify_proof, Bytes, HashFunction, MerkleError, MerkleProof, MerkleTree};
+use crate::{MerkleTreeBuilder, TreeSpec};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// The placeholder leaves reserved by `MerkleTreeBuilder::reserve_slots`.
+pub enum PlaceholderPolicy<H> {
+    /// Placeholders commit to nothing, each being a distinct hash of its slot number, so slots
+    /// may be filled with any value.
+    Blank,
+    /// Each placeholder is a commitment to the value which will fill it, as computed by
+    /// `MerkleTree::commitment`, so the slot can only be revealed with that value and its nonce.
+    /// Sealed bids are committed this way, and revealed once bidding closes.
+    Committed(Vec<H>),
+}
+
+/// A placeholder reserved by `reserve_slots`, along with how it may be revealed.
+#[derive(Clone, Copy)]
+pub(crate) struct Placeholder<H> {
+    pub(crate) hash: H,
+    /// The slot number of a blank placeholder, or `None` for a commitment.
+    pub(crate) blank: Option<usize>,
+}
+
+/// The replacement of a placeholder by a value, linking the root before the reveal to the root
+/// after it. Anyone holding the old root can check that the placeholder was committed to by it,
+/// that the value opens the placeholder if it was committed, and that the new root includes the
+/// value.
+pub struct Reveal<T: HashFunction> {
+    pub placeholder: T::Hash,
+    /// The slot number of a blank placeholder, which is checked to be the placeholder of that slot,
+    /// or `None` if the placeholder is a commitment which must be opened.
+    pub slot: Option<usize>,
+    /// The leaf hash of the revealed value.
+    pub leaf: T::Hash,
+    pub old_root: T::Hash,
+    pub new_root: T::Hash,
+    /// The proof of the placeholder against the old root.
+    pub old_proof: MerkleProof<T>,
+    /// The proof of the revealed leaf against the new root.
+    pub new_proof: MerkleProof<T>,
+}
+
+impl<T: HashFunction> Reveal<T> {
+    /// Verifies the reveal of `value` for a tree with the supplied spec. A committed placeholder
+    /// must be opened by the value under `nonce`, and a blank placeholder must be that of its slot.
+    ///
+    /// Leaves of trees with unsorted leaves are replaced in place, so the new root must also be
+    /// recomputed by the value with the siblings of the placeholder, linking the new root to the
+    /// old. Trees with sorted leaves move the value to its sorted position, so only the inclusion
+    /// of the value in the new root is checked.
+    pub fn verify(&self, spec: &TreeSpec, value: &Bytes, nonce: Option<&[u8]>) -> bool {
+        let opened = match self.slot {
+            Some(slot) => blank::<T>(slot) == self.placeholder,
+            None => nonce
+                .is_some_and(|nonce| MerkleTree::<T>::commitment(value, nonce) == self.placeholder),
+        };
+        let linked =
+            spec.sort_leaves || self.old_proof.compute_root(spec, self.leaf) == Some(self.new_root);
+        opened
+            && linked
+            && MerkleTree::<T>::try_hash_leaf(spec, value) == Ok(self.leaf)
+            && verify_proof(spec, &self.old_proof, self.placeholder, self.old_root)
+            && verify_proof(spec, &self.new_proof, self.leaf, self.new_root)
+    }
+}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Reserves `slots` placeholder leaves, appended to the leaves of each tree built, which are
+    /// later filled by `MerkleTree::reveal`. Placeholders are appended by `build`,
+    /// `build_from_hashes` and `build_from_iter`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the policy commits to a number of values other than `slots`.
+    pub fn reserve_slots(mut self, slots: usize, policy: PlaceholderPolicy<T::Hash>) -> Self {
+        match policy {
+            PlaceholderPolicy::Blank => {
+                let start = self.placeholders.len();
+                self.placeholders
+                    .extend((start..start + slots).map(|slot| Placeholder {
+                        hash: blank::<T>(slot),
+                        blank: Some(slot),
+                    }));
+            }
+            PlaceholderPolicy::Committed(commitments) => {
+                assert_eq!(
+                    commitments.len(),
+                    slots,
+                    "one commitment is required per slot"
+                );
+                self.placeholders.extend(
+                    commitments
+                        .into_iter()
+                        .map(|hash| Placeholder { hash, blank: None }),
+                );
+            }
+        }
+        self
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// The commitment to a value under a random nonce, for reserving a slot to be revealed later
+    /// with `PlaceholderPolicy::Committed`. The nonce must be kept secret until the reveal, so the
+    /// value cannot be guessed from the commitment.
+    pub fn commitment(value: &Bytes, nonce: &[u8]) -> T::Hash {
+        T::hash_keyed(nonce, value)
+    }
+
+    /// Replaces the placeholder at `index` with `value`, returning the reveal linking the previous
+    /// root to the new one. The leaf at `index` must be a placeholder yet to be revealed, and a
+    /// committed placeholder must be the commitment to the value under `nonce`. The nonce of a
+    /// blank placeholder is ignored.
+    pub fn reveal(
+        &mut self,
+        index: usize,
+        value: &Bytes,
+        nonce: Option<&[u8]>,
+    ) -> Result<Reveal<T>, MerkleError> {
+        let placeholder = *self.leaves().get(index).ok_or(MerkleError::LeafNotFound)?;
+        let position = self
+            .placeholders
+            .iter()
+            .position(|p| p.hash == placeholder)
+            .ok_or(MerkleError::NotPlaceholder)?;
+        let slot = self.placeholders[position].blank;
+        let opened = |nonce| MerkleTree::<T>::commitment(value, nonce) == placeholder;
+        if slot.is_none() && !nonce.is_some_and(opened) {
+            return Err(MerkleError::CommitmentMismatch);
+        }
+        let leaf = MerkleTree::<T>::try_hash_leaf(&self.spec, value)?;
+
+        let (old_root, old_proof) = (self.root(), self.proof_by_index(index));
+        self.update_hash(index, leaf);
+        self.placeholders.remove(position);
+        let index = self.index_of(leaf).ok_or(MerkleError::LeafNotFound)?;
+        Ok(Reveal {
+            placeholder,
+            slot,
+            leaf,
+            old_root,
+            new_root: self.root(),
+            old_proof,
+            new_proof: self.proof_by_index(index),
+        })
+    }
+}
+
+/// The placeholder of a blank slot.
+fn blank<T: HashFunction>(slot: usize) -> T::Hash {
+    T::hash_keyed(b"placeholder", &(slot as u64).to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Bytes, HashFunction, Keccak256, MerkleError, MerkleTree, MerkleTreeBuilder,
+        PlaceholderPolicy,
+    };
+
+    #[test]
+    fn reveals_placeholders() {
+        let leaves: Vec<&Bytes> = ["a", "b"].iter().map(|x| x.as_bytes()).collect();
+        let bid = b"bid 100";
+        let commitment = MerkleTree::<Keccak256>::commitment(bid, b"nonce");
+        for sorted in [true, false] {
+            let mut tree = MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(sorted)
+                .sort_pairs(sorted)
+                .reserve_slots(2, PlaceholderPolicy::Blank)
+                .reserve_slots(1, PlaceholderPolicy::Committed(vec![commitment]))
+                .build(&leaves);
+            assert_eq!(tree.leaves().len(), 5);
+            let spec = tree.spec().clone();
+
+            // Committed slots only open with the committed value and nonce
+            let index = tree.index_of(commitment).unwrap();
+            assert_eq!(
+                tree.reveal(index, b"bid 200", Some(b"nonce")).err(),
+                Some(MerkleError::CommitmentMismatch)
+            );
+            assert_eq!(
+                tree.reveal(index, bid, None).err(),
+                Some(MerkleError::CommitmentMismatch)
+            );
+            let mut reveal = tree.reveal(index, bid, Some(b"nonce")).unwrap();
+            assert!(reveal.verify(&spec, bid, Some(b"nonce")));
+            assert!(!reveal.verify(&spec, bid, Some(b"other")));
+            assert!(!reveal.verify(&spec, bid, None));
+            assert_eq!(reveal.new_root, tree.root());
+            reveal.slot = Some(0);
+            assert!(!reveal.verify(&spec, bid, None));
+
+            // Slots are revealed once, and leaves which are not placeholders never
+            let index = tree.index_of(Keccak256::hash(bid)).unwrap();
+            assert_eq!(
+                tree.reveal(index, bid, Some(b"nonce")).err(),
+                Some(MerkleError::NotPlaceholder)
+            );
+            let index = tree.index_of(Keccak256::hash(b"a")).unwrap();
+            assert_eq!(
+                tree.reveal(index, b"c", None).err(),
+                Some(MerkleError::NotPlaceholder)
+            );
+
+            // Blank slots may be filled with anything
+            let index = (0..tree.leaves().len())
+                .find(|i| {
+                    let leaf = tree.leaves()[*i];
+                    !["a", "b"]
+                        .iter()
+                        .any(|v| leaf == Keccak256::hash(v.as_bytes()))
+                        && leaf != Keccak256::hash(bid)
+                })
+                .unwrap();
+            let mut reveal = tree.reveal(index, b"c", None).unwrap();
+            assert!(reveal.verify(&spec, b"c", None));
+            assert!(!reveal.verify(&spec, b"d", None));
+
+            // The new root of a tree with unsorted leaves must follow from the old one
+            let unrelated = MerkleTree::<Keccak256>::new(&[b"c"]);
+            reveal.new_root = unrelated.root();
+            reveal.new_proof = unrelated.proof(reveal.leaf);
+            assert_eq!(reveal.verify(&spec, b"c", None), sorted);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::HashFunction;
+use ark_crypto_primitives::sponge::poseidon::{
+    find_poseidon_ark_and_mds, PoseidonConfig, PoseidonSponge,
+};
+use ark_crypto_primitives::sponge::{Absorb, CryptographicSponge};
+use ark_ff::{BigInteger, PrimeField};
+use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+/// Bytes per absorbed element, so that every element is less than the modulus of either field.
+pub(crate) const CHUNK: usize = 31;
+
+/// A scalar field Poseidon is defined over, along with its round parameters.
+pub trait PoseidonField: PrimeField + Absorb {
+    /// Name of the hash function over the field, as given by `HashFunction::NAME`.
+    const NAME: &'static str;
+
+    fn config() -> &'static PoseidonConfig<Self>;
+}
+
+impl PoseidonField for ark_bn254::Fr {
+    const NAME: &'static str = "poseidon-bn254";
+
+    fn config() -> &'static PoseidonConfig<Self> {
+        static CONFIG: OnceLock<PoseidonConfig<ark_bn254::Fr>> = OnceLock::new();
+        CONFIG.get_or_init(|| config(254))
+    }
+}
+
+impl PoseidonField for ark_bls12_381::Fr {
+    const NAME: &'static str = "poseidon-bls12-381";
+
+    fn config() -> &'static PoseidonConfig<Self> {
+        static CONFIG: OnceLock<PoseidonConfig<ark_bls12_381::Fr>> = OnceLock::new();
+        CONFIG.get_or_init(|| config(255))
+    }
+}
+
+/// Parameters for a width of three with the S-box `x^5`, as used by circomlib, with 8 full and
+/// 57 partial rounds.
+fn config<F: PrimeField>(bits: u64) -> PoseidonConfig<F> {
+    let (ark, mds) = find_poseidon_ark_and_mds::<F>(bits, 2, 8, 57, 0);
+    PoseidonConfig::new(8, 57, 5, mds, ark, 2, 1)
+}
+
+/// An element of the field `F`, held as its 32 byte little-endian encoding so that it can be used
+/// as a node of a tree. Elements compare as their encodings, as circuit gadgets sorting pairs do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FieldElement<F> {
+    bytes: [u8; 32],
+    field: PhantomData<F>,
+}
+
+impl<F: PrimeField> FieldElement<F> {
+    pub fn new(element: F) -> Self {
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(&element.into_bigint().to_bytes_le());
+        Self {
+            bytes,
+            field: PhantomData,
+        }
+    }
+
+    pub fn element(&self) -> F {
+        F::from_le_bytes_mod_order(&self.bytes)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.bytes
+    }
+}
+
+impl<F> From<FieldElement<F>> for Vec<u8> {
+    fn from(element: FieldElement<F>) -> Self {
+        element.bytes.to_vec()
+    }
+}
+
+/// Reads the canonical encoding of an element, failing for encodings of the wrong length or of
+/// integers not less than the modulus.
+impl<F: PrimeField> TryFrom<Vec<u8>> for FieldElement<F> {
+    type Error = Vec<u8>;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        let element = match <[u8; 32]>::try_from(bytes.as_slice()) {
+            Ok(encoding) => FieldElement::new(F::from_le_bytes_mod_order(&encoding)),
+            Err(_) => return Err(bytes),
+        };
+        match element.bytes[..] == bytes[..] {
+            true => Ok(element),
+            false => Err(bytes),
+        }
+    }
+}
+
+impl<F> AsRef<[u8]> for FieldElement<F> {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Poseidon over the scalar field of BN254 or BLS12-381, for trees whose proofs are verified
+/// within SNARKs where Keccak would cost far more constraints. Values are absorbed as their length
+/// followed by 31 byte little-endian chunks, and the hash is the squeezed element.
+pub struct Poseidon<F: PoseidonField = ark_bn254::Fr> {
+    field: PhantomData<F>,
+}
+
+impl<F: PoseidonField> Default for Poseidon<F> {
+    fn default() -> Self {
+        Self { field: PhantomData }
+    }
+}
+
+impl<F: PoseidonField> HashFunction for Poseidon<F> {
+    type Hash = FieldElement<F>;
+
+    const NAME: &'static str = F::NAME;
+
+    fn hash(value: &[u8]) -> Self::Hash {
+        let mut sponge = PoseidonSponge::new(F::config());
+        sponge.absorb(&elements::<F>(value));
+        FieldElement::new(sponge.squeeze_field_elements::<F>(1)[0])
+    }
+}
+
+/// The elements a value is absorbed as.
+pub(crate) fn elements<F: PrimeField>(value: &[u8]) -> Vec<F> {
+    std::iter::once(F::from(value.len() as u64))
+        .chain(value.chunks(CHUNK).map(F::from_le_bytes_mod_order))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FieldElement;
+    use crate::{Bytes, HashFunction, MerkleTree, Poseidon};
+    use ark_ff::{BigInteger, PrimeField};
+
+    #[test]
+    fn hashes_to_field_elements() {
+        type Bls = Poseidon<ark_bls12_381::Fr>;
+        assert_eq!(
+            Poseidon::<ark_bn254::Fr>::hash(b"a"),
+            <Poseidon>::hash(b"a")
+        );
+        assert_ne!(<Poseidon>::hash(b"a"), <Poseidon>::hash(b"a\0"));
+        assert_ne!(
+            <Poseidon>::hash(b"a").to_bytes(),
+            Bls::hash(b"a").to_bytes()
+        );
+
+        // Only canonical encodings are elements
+        let hash = <Poseidon>::hash(b"a");
+        assert_eq!(FieldElement::try_from(Vec::from(hash)), Ok(hash));
+        assert_eq!(FieldElement::new(hash.element()), hash);
+        assert!(FieldElement::<ark_bn254::Fr>::try_from(vec![0xff; 32]).is_err());
+        assert!(FieldElement::<ark_bn254::Fr>::try_from(vec![0; 31]).is_err());
+        let modulus = ark_bn254::Fr::MODULUS.to_bytes_le();
+        assert!(FieldElement::<ark_bn254::Fr>::try_from(modulus).is_err());
+
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Bls>::new(&leaves);
+        assert_eq!(tree.spec().hash, "poseidon-bls12-381");
+        let leaf = tree.leaves()[1];
+        assert!(tree.verify(tree.proof(leaf), leaf, tree.root()));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{HashFunction, MerkleProof, MerkleTree, Store};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+impl<T: HashFunction, S: Store<T::Hash> + Sync> MerkleTree<T, S> {
+    /// Hints to the store that the proofs of the leaves at `indices` will be generated soon, so
+    /// the nodes on their paths can be loaded before they are read. Each layer is hinted from its
+    /// own thread, and stores which load in the background return before the nodes are resident,
+    /// hiding the latency of slow storage from bulk proof exports.
+    pub fn prefetch(&self, indices: &[usize]) -> std::io::Result<()> {
+        let (store, layers) = (&self.layers, self.path_nodes(indices));
+        std::thread::scope(|scope| {
+            let hints: Vec<_> = layers
+                .iter()
+                .enumerate()
+                .map(|(layer, nodes)| scope.spawn(move || store.prefetch(layer, nodes)))
+                .collect();
+            hints.into_iter().try_for_each(|hint| {
+                hint.join()
+                    .unwrap_or_else(|_| Err(std::io::Error::other("prefetch panicked")))
+            })
+        })
+    }
+
+    /// Hints to the store as `prefetch` does without waiting for the hints to be given, returning
+    /// a handle to the thread giving them, so that proofs can be prepared for meanwhile.
+    pub fn prefetch_in_background(
+        tree: &Arc<Self>,
+        indices: &[usize],
+    ) -> JoinHandle<std::io::Result<()>>
+    where
+        Self: Send + Sync + 'static,
+    {
+        let (tree, indices) = (Arc::clone(tree), indices.to_vec());
+        std::thread::spawn(move || tree.prefetch(&indices))
+    }
+
+    /// Generates the proofs of the leaves at `indices`, prefetching the nodes on their paths first.
+    pub fn proofs_by_index(&self, indices: &[usize]) -> std::io::Result<Vec<MerkleProof<T>>> {
+        self.prefetch(indices)?;
+        Ok(indices.iter().map(|i| self.proof_by_index(*i)).collect())
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// The sorted indices of the nodes within each layer below the root which are read when
+    /// generating proofs for the leaves at `indices`.
+    fn path_nodes(&self, indices: &[usize]) -> Vec<Vec<usize>> {
+        let arity = self.spec.arity;
+        let layers = self.layers.layers().saturating_sub(1);
+        let mut groups: BTreeSet<usize> = indices
+            .iter()
+            .filter(|i| **i < self.layers.layer_len(0))
+            .map(|i| i / arity)
+            .collect();
+
+        let mut nodes = Vec::with_capacity(layers);
+        for layer in 0..layers {
+            let len = self.layers.layer_len(layer);
+            nodes.push(
+                groups
+                    .iter()
+                    .flat_map(|group| group * arity..len.min((group + 1) * arity))
+                    .collect(),
+            );
+            groups = groups.iter().map(|group| group / arity).collect();
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Keccak256, MerkleTree, MerkleTreeBuilder};
+    use std::sync::Arc;
+
+    #[test]
+    fn prefetches_path_nodes() {
+        let values: Vec<[u8; 1]> = (0..10).map(|i| [i]).collect();
+        let leaves: Vec<&[u8]> = values.iter().map(|v| &v[..]).collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(false)
+            .arity(3)
+            .build(&leaves);
+
+        assert_eq!(
+            tree.path_nodes(&[1, 9, 20]),
+            vec![vec![0, 1, 2, 9], vec![0, 1, 2, 3], vec![0, 1]]
+        );
+        assert_eq!(tree.path_nodes(&[]), vec![Vec::<usize>::new(); 3]);
+        let tree = Arc::new(tree);
+        let hint = MerkleTree::prefetch_in_background(&tree, &[1, 9]);
+        assert!(hint.join().unwrap().is_ok());
+
+        let indices = [7, 2, 2];
+        let proofs = tree.proofs_by_index(&indices).unwrap();
+        for (index, proof) in indices.iter().zip(proofs) {
+            assert_eq!(proof, tree.proof_by_index(*index));
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn prefetches_memory_mapped_store() {
+        use crate::{MmapStore, Store};
+
+        let directory =
+            std::env::temp_dir().join(format!("merkle-tree-prefetch-{}", std::process::id()));
+        let values: Vec<[u8; 4]> = (0..3000u32).map(|i| i.to_be_bytes()).collect();
+        let builder = MerkleTreeBuilder::<Keccak256>::new().sort_leaves(false);
+        let tree = builder
+            .build_in(MmapStore::create(&directory).unwrap(), &values)
+            .unwrap();
+
+        let indices: Vec<usize> = (0..3000).step_by(7).collect();
+        let proofs = tree.proofs_by_index(&indices).unwrap();
+        for (index, proof) in indices.iter().zip(proofs) {
+            let leaf = tree.store().get(0, *index).unwrap();
+            assert!(tree.verify(proof, leaf, tree.root()));
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
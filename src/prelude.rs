@@ -0,0 +1,17 @@
+//! The types used by most programs building or verifying trees, for importing together with
+//! `use merkle_tree::prelude::*`.
+
+#[cfg(feature = "ripemd160")]
+pub use crate::hash_functions::Ripemd160;
+#[cfg(feature = "sha1")]
+pub use crate::hash_functions::Sha1;
+#[cfg(feature = "sha256")]
+pub use crate::hash_functions::{DoubleSha256, Sha256};
+pub use crate::hash_functions::{Hash, HashFunction, Keccak256, Salted};
+pub use crate::proof::{Direction, MerkleProof};
+pub use crate::spec::{OddStrategy, SpecError, TreeSpec};
+#[cfg(feature = "std")]
+pub use crate::store::{MemoryStore, Store};
+pub use crate::verify::{verify_fixed, verify_proof};
+#[cfg(feature = "std")]
+pub use crate::{MerkleError, MerkleTree, MerkleTreeBuilder};
@@ -0,0 +1,328 @@
+use crate::HashFunction;
+#[cfg(feature = "std")]
+use crate::{MerkleError, MerkleTree};
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+/// The side on which a proof node sits relative to the node being proven.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+/// The sibling hashes required to recompute a root from a leaf, ordered from the leaf layer
+/// upwards. Proofs from trees with positional (unsorted) pairs also record the direction of each
+/// sibling. Proofs generated by a tree also record the index of the leaf they prove.
+pub struct MerkleProof<T: HashFunction> {
+    hashes: Vec<T::Hash>,
+    directions: Option<Vec<Direction>>,
+    sibling_counts: Option<Vec<usize>>,
+    index: Option<usize>,
+}
+
+impl<T: HashFunction> MerkleProof<T> {
+    /// Creates a proof from its sibling hashes, along with the direction of each sibling for trees
+    /// with positional pairs and the number of siblings per step for trees of higher arity.
+    pub fn new(
+        hashes: Vec<T::Hash>,
+        directions: Option<Vec<Direction>>,
+        sibling_counts: Option<Vec<usize>>,
+    ) -> Self {
+        Self {
+            hashes,
+            directions,
+            sibling_counts,
+            index: None,
+        }
+    }
+
+    /// Records the index of the leaf the proof is for.
+    pub fn with_index(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    pub fn hashes(&self) -> &[T::Hash] {
+        &self.hashes
+    }
+
+    pub fn directions(&self) -> Option<&[Direction]> {
+        self.directions.as_deref()
+    }
+
+    /// The number of sibling hashes at each step of the proof, for trees with an arity above two.
+    /// Binary proofs have a single sibling per step.
+    pub fn sibling_counts(&self) -> Option<&[usize]> {
+        self.sibling_counts.as_deref()
+    }
+
+    /// The index of the leaf the proof is for, if known.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, T::Hash> {
+        self.hashes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// The number of steps from the leaf to the root, which is the number of hashes for a binary
+    /// tree.
+    pub fn depth(&self) -> usize {
+        match &self.sibling_counts {
+            Some(counts) => counts.len(),
+            None => self.hashes.len(),
+        }
+    }
+
+    /// The number of bytes of sibling hashes, which dominate the size of the proof however it is
+    /// encoded.
+    pub fn byte_len(&self) -> usize {
+        self.hashes.iter().map(|h| h.as_ref().len()).sum()
+    }
+
+    /// Whether the sibling counts and directions of the proof are consistent with its hashes.
+    pub fn is_well_formed(&self) -> bool {
+        let siblings = match &self.sibling_counts {
+            Some(counts) => counts.iter().sum(),
+            None => self.hashes.len(),
+        };
+        let directions = match &self.directions {
+            Some(directions) => directions.len(),
+            None => self.hashes.len(),
+        };
+        siblings == self.hashes.len() && directions == self.hashes.len()
+    }
+}
+
+impl<T: HashFunction> Clone for MerkleProof<T> {
+    fn clone(&self) -> Self {
+        Self {
+            hashes: self.hashes.clone(),
+            directions: self.directions.clone(),
+            sibling_counts: self.sibling_counts.clone(),
+            index: self.index,
+        }
+    }
+}
+
+impl<T: HashFunction> core::fmt::Debug for MerkleProof<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MerkleProof")
+            .field(
+                "hashes",
+                &self
+                    .hashes
+                    .iter()
+                    .map(|hash| format!("0x{}", hex::encode(hash)))
+                    .collect::<Vec<String>>(),
+            )
+            .field("directions", &self.directions)
+            .field("sibling_counts", &self.sibling_counts)
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl<T: HashFunction> Default for MerkleProof<T> {
+    fn default() -> Self {
+        Self::new(vec![], None, None)
+    }
+}
+
+/// Collects sibling hashes into a proof for a binary tree with sorted pairs, accepting any type
+/// convertible to a hash such as the `digest::Output` of a RustCrypto hasher.
+impl<T: HashFunction, H: Into<T::Hash>> FromIterator<H> for MerkleProof<T> {
+    fn from_iter<I: IntoIterator<Item = H>>(hashes: I) -> Self {
+        Self::new(hashes.into_iter().map(Into::into).collect(), None, None)
+    }
+}
+
+impl<T: HashFunction> PartialEq for MerkleProof<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hashes == other.hashes
+            && self.directions == other.directions
+            && self.sibling_counts == other.sibling_counts
+            && self.index == other.index
+    }
+}
+
+impl<'a, T: HashFunction> IntoIterator for &'a MerkleProof<T> {
+    type Item = &'a T::Hash;
+    type IntoIter = core::slice::Iter<'a, T::Hash>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: HashFunction> IntoIterator for MerkleProof<T> {
+    type Item = T::Hash;
+    type IntoIter = alloc::vec::IntoIter<T::Hash>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.hashes.into_iter()
+    }
+}
+
+/// Formats the proof as comma separated `0x` prefixed hashes, followed by any directions as `L`
+/// and `R`, sibling counts and leaf index as space separated fields, such as
+/// `0xab..,0xcd.. directions=LR index=2`.
+#[cfg(feature = "std")]
+impl<T: HashFunction> Display for MerkleProof<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut parts = vec![];
+        if !self.hashes.is_empty() {
+            let hashes: Vec<String> = self
+                .hashes
+                .iter()
+                .map(|hash| format!("0x{}", hex::encode(hash)))
+                .collect();
+            parts.push(hashes.join(","));
+        }
+        if let Some(directions) = &self.directions {
+            let directions: String = directions
+                .iter()
+                .map(|d| match d {
+                    Direction::Left => 'L',
+                    Direction::Right => 'R',
+                })
+                .collect();
+            parts.push(format!("directions={}", directions));
+        }
+        if let Some(counts) = &self.sibling_counts {
+            let counts: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+            parts.push(format!("counts={}", counts.join(",")));
+        }
+        if let Some(index) = self.index {
+            parts.push(format!("index={}", index));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Parses a proof formatted by `Display`.
+#[cfg(feature = "std")]
+impl<T: HashFunction> FromStr for MerkleProof<T> {
+    type Err = MerkleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut proof = MerkleProof::default();
+        for (position, part) in s.split_whitespace().enumerate() {
+            let list = |value: &str| match value {
+                "" => vec![],
+                _ => value
+                    .split(',')
+                    .map(str::to_string)
+                    .collect::<Vec<String>>(),
+            };
+            match part.split_once('=') {
+                None if position == 0 => {
+                    proof.hashes = list(part)
+                        .iter()
+                        .map(|hash| {
+                            let bytes = hash
+                                .strip_prefix("0x")
+                                .and_then(|h| hex::decode(h).ok())
+                                .ok_or(MerkleError::MalformedProof)?;
+                            MerkleTree::<T>::hash_from_slice(&bytes)
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                Some(("directions", value)) => {
+                    proof.directions = Some(
+                        value
+                            .chars()
+                            .map(|c| match c {
+                                'L' => Ok(Direction::Left),
+                                'R' => Ok(Direction::Right),
+                                _ => Err(MerkleError::MalformedProof),
+                            })
+                            .collect::<Result<_, _>>()?,
+                    );
+                }
+                Some(("counts", value)) => {
+                    proof.sibling_counts = Some(
+                        list(value)
+                            .iter()
+                            .map(|c| c.parse().map_err(|_| MerkleError::MalformedProof))
+                            .collect::<Result<_, _>>()?,
+                    );
+                }
+                Some(("index", value)) => {
+                    proof.index = Some(value.parse().map_err(|_| MerkleError::MalformedProof)?);
+                }
+                _ => return Err(MerkleError::MalformedProof),
+            }
+        }
+        Ok(proof)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleError, MerkleProof, MerkleTree, MerkleTreeBuilder};
+
+    #[test]
+    fn round_trips_strings() {
+        let values: Vec<[u8; 1]> = (0..7).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for tree in [
+            MerkleTree::<Keccak256>::new(&leaves),
+            MerkleTree::<Keccak256>::new_ordered(&leaves),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .arity(3)
+                .build(&leaves),
+        ] {
+            let proof = tree.proof(tree.leaves()[4]);
+            assert_eq!(proof.index(), Some(4));
+            let formatted = proof.to_string();
+            assert!(formatted.starts_with("0x") && formatted.ends_with(" index=4"));
+            assert_eq!(
+                formatted.parse::<MerkleProof<Keccak256>>(),
+                Ok(proof.clone())
+            );
+            assert_eq!((&proof).into_iter().count(), proof.len());
+            assert_eq!(
+                proof.clone().into_iter().collect::<Vec<_>>(),
+                proof.hashes()
+            );
+        }
+
+        let empty = MerkleProof::<Keccak256>::default();
+        assert_eq!(empty.to_string(), "");
+        assert_eq!("".parse::<MerkleProof<Keccak256>>(), Ok(empty));
+        assert_eq!(
+            "0x00".parse::<MerkleProof<Keccak256>>(),
+            Err(MerkleError::InvalidHash {
+                expected: 32,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            "00".parse::<MerkleProof<Keccak256>>(),
+            Err(MerkleError::MalformedProof)
+        );
+        assert_eq!(
+            "directions=LX".parse::<MerkleProof<Keccak256>>(),
+            Err(MerkleError::MalformedProof)
+        );
+    }
+}
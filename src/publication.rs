@@ -0,0 +1,202 @@
+use crate::{Bytes, Direction, HashFunction, MerkleProof, MerkleTree, MerkleTreeBuilder};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Whether the pre-image of a leaf may be published alongside its proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    /// Only the leaf hash is published, which is still required to recompute the root.
+    Private,
+}
+
+/// A tree of mixed visibility leaves, such as an allowlist where some entries are published and
+/// others are only disclosed to their owners.
+///
+/// Only the pre-images of public leaves are retained, so private pre-images cannot be exported.
+pub struct Publication<T: HashFunction> {
+    tree: MerkleTree<T>,
+    values: BTreeMap<T::Hash, Vec<u8>>,
+}
+
+/// A leaf as exported, in tree order.
+pub struct PublishedLeaf<T: HashFunction> {
+    pub leaf: T::Hash,
+    /// The pre-image, unless the leaf is private.
+    pub value: Option<Vec<u8>>,
+    /// The proof, unless the leaf is private.
+    pub proof: Option<MerkleProof<T>>,
+}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Builds a tree from leaves tagged with their visibility, for partial publication.
+    pub fn build_tagged(&self, leaves: &[(&Bytes, Visibility)]) -> Publication<T> {
+        let values: Vec<&Bytes> = leaves.iter().map(|(value, _)| *value).collect();
+        let tree = self.build(&values);
+        let values = leaves
+            .iter()
+            .filter(|(_, visibility)| *visibility == Visibility::Public)
+            .map(|(value, _)| (self.hash_leaf(value), value.to_vec()))
+            .collect();
+        Publication { tree, values }
+    }
+}
+
+impl<T: HashFunction> Publication<T> {
+    pub fn tree(&self) -> &MerkleTree<T> {
+        &self.tree
+    }
+
+    pub fn root(&self) -> T::Hash {
+        self.tree.root()
+    }
+
+    pub fn is_public(&self, leaf: impl Into<T::Hash>) -> bool {
+        self.values.contains_key(&leaf.into())
+    }
+
+    /// Every leaf of the tree, with the pre-image and proof of each public leaf.
+    pub fn entries(&self) -> Vec<PublishedLeaf<T>> {
+        self.tree
+            .leaves()
+            .iter()
+            .map(|leaf| {
+                let value = self.values.get(leaf).cloned();
+                PublishedLeaf {
+                    leaf: *leaf,
+                    proof: value.as_ref().map(|_| self.tree.proof(*leaf)),
+                    value,
+                }
+            })
+            .collect()
+    }
+
+    /// The proof of each public leaf, keyed by its pre-image.
+    pub fn claims(&self) -> BTreeMap<Vec<u8>, MerkleProof<T>> {
+        self.values
+            .iter()
+            .map(|(leaf, value)| (value.clone(), self.tree.proof(*leaf)))
+            .collect()
+    }
+
+    /// Exports the root and every leaf as a JSON document, redacting private pre-images.
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self.entries().iter().map(entry_json).collect();
+        format!(
+            "{{\"root\":\"{}\",\"leaves\":[{}]}}",
+            encode(self.root()),
+            entries.join(",")
+        )
+    }
+
+    /// Exports every leaf as newline delimited JSON, redacting private pre-images.
+    pub fn write_ndjson<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for entry in self.entries() {
+            writeln!(writer, "{}", entry_json(&entry))?;
+        }
+        writer.flush()
+    }
+}
+
+fn entry_json<T: HashFunction>(entry: &PublishedLeaf<T>) -> String {
+    let (value, proof) = match (&entry.value, &entry.proof) {
+        (Some(value), Some(proof)) => (value, proof),
+        _ => return format!("{{\"leaf\":\"{}\",\"private\":true}}", encode(entry.leaf)),
+    };
+
+    let hashes: Vec<String> = proof
+        .iter()
+        .map(|h| format!("\"{}\"", encode(*h)))
+        .collect();
+    let mut json = format!(
+        "{{\"leaf\":\"{}\",\"value\":\"{}\",\"proof\":[{}]",
+        encode(entry.leaf),
+        encode(value),
+        hashes.join(",")
+    );
+    if let Some(directions) = proof.directions() {
+        let directions: Vec<&str> = directions
+            .iter()
+            .map(|d| match d {
+                Direction::Left => "\"left\"",
+                Direction::Right => "\"right\"",
+            })
+            .collect();
+        json.push_str(&format!(",\"directions\":[{}]", directions.join(",")));
+    }
+    if let Some(counts) = proof.sibling_counts() {
+        let counts: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+        json.push_str(&format!(",\"sibling_counts\":[{}]", counts.join(",")));
+    }
+    json.push('}');
+    json
+}
+
+fn encode(bytes: impl AsRef<[u8]>) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleTree, MerkleTreeBuilder, Visibility};
+
+    #[test]
+    fn redacts_private_leaves() {
+        let leaves: Vec<(&Bytes, Visibility)> = vec![
+            (b"alice", Visibility::Public),
+            (b"bob", Visibility::Private),
+            (b"carol", Visibility::Public),
+        ];
+        let builder = MerkleTreeBuilder::<Keccak256>::new();
+        let publication = builder.build_tagged(&leaves);
+
+        // The root is that of the full tree
+        let values: Vec<&Bytes> = leaves.iter().map(|(v, _)| *v).collect();
+        assert_eq!(
+            publication.root(),
+            MerkleTree::<Keccak256>::new(&values).root()
+        );
+        assert!(!publication.is_public(builder.hash_leaf(b"bob")));
+
+        let entries = publication.entries();
+        assert_eq!(entries.len(), 3);
+        for entry in &entries {
+            match &entry.value {
+                Some(value) => {
+                    let proof = entry.proof.clone().unwrap();
+                    assert!(publication
+                        .tree()
+                        .verify_leaf_value(proof, value, publication.root()));
+                }
+                None => assert!(entry.proof.is_none()),
+            }
+        }
+        let claims = publication.claims();
+        assert_eq!(claims.len(), 2);
+        assert!(claims.contains_key(&b"carol"[..]));
+
+        let json = publication.to_json();
+        assert!(json.contains(&hex::encode(b"alice")));
+        assert!(!json.contains(&hex::encode(b"bob")));
+        assert!(json.contains(&hex::encode(builder.hash_leaf(b"bob"))));
+
+        let mut ndjson = vec![];
+        publication.write_ndjson(&mut ndjson).unwrap();
+        let ndjson = String::from_utf8(ndjson).unwrap();
+        assert_eq!(ndjson.lines().count(), 3);
+        assert_eq!(ndjson.matches("\"private\":true").count(), 1);
+    }
+
+    #[test]
+    fn exports_valid_json() {
+        let leaves: Vec<(&Bytes, Visibility)> =
+            vec![(b"a", Visibility::Private), (b"b", Visibility::Public)];
+        let publication = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(false)
+            .sort_pairs(false)
+            .build_tagged(&leaves);
+        let json: serde_json::Value = serde_json::from_str(&publication.to_json()).unwrap();
+        assert_eq!(json["leaves"].as_array().unwrap().len(), 2);
+        assert_eq!(json["leaves"][1]["directions"][0], "left");
+    }
+}
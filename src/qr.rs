@@ -0,0 +1,322 @@
+use crate::{Direction, HashFunction, MerkleProof, MerkleTree, Store};
+use std::fmt::{Display, Formatter};
+
+/// The base45 alphabet of RFC 9285, which is the character set of the QR alphanumeric mode.
+const ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+/// The number of alphanumeric characters a QR code can hold at the lowest error correction level.
+pub const QR_CAPACITY: usize = 4296;
+/// Length of the root prefix identifying which root a proof was generated for.
+const ROOT_ID: usize = 8;
+const VERSION: u8 = 1;
+const DIRECTIONS: u8 = 1;
+const SIBLING_COUNTS: u8 = 2;
+
+/// A proof along with the index of its leaf and an identifier of the root it was generated for,
+/// encodable as a short base45 string for QR codes on offline tickets and credentials.
+///
+/// The root is identified by its first eight bytes, so a verifier holding several roots can pick
+/// the one to verify against.
+pub struct CompactProof<T: HashFunction> {
+    pub index: usize,
+    pub root_id: [u8; ROOT_ID],
+    pub proof: MerkleProof<T>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompactError {
+    /// The encoded proof has `length` characters, more than a QR code can hold.
+    TooLarge { length: usize },
+    /// The character is not part of the base45 alphabet.
+    InvalidCharacter(char),
+    /// The decoded bytes are not a valid compact proof.
+    Malformed,
+}
+
+impl Display for CompactError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompactError::TooLarge { length } => write!(
+                f,
+                "encoded proof of {} characters exceeds the QR capacity of {}",
+                length, QR_CAPACITY
+            ),
+            CompactError::InvalidCharacter(c) => write!(f, "invalid base45 character '{}'", c),
+            CompactError::Malformed => write!(f, "malformed compact proof"),
+        }
+    }
+}
+
+impl std::error::Error for CompactError {}
+
+impl<T: HashFunction> CompactProof<T> {
+    pub fn new(index: usize, root: T::Hash, proof: MerkleProof<T>) -> Self {
+        let mut root_id = [0; ROOT_ID];
+        let length = root.as_ref().len().min(ROOT_ID);
+        root_id[..length].copy_from_slice(&root.as_ref()[..length]);
+        Self {
+            index,
+            root_id,
+            proof,
+        }
+    }
+
+    /// Whether the proof identifies the supplied root.
+    pub fn matches_root(&self, root: impl Into<T::Hash>) -> bool {
+        Self::new(0, root.into(), Default::default()).root_id == self.root_id
+    }
+
+    /// Encodes the proof as base45, failing if the result would not fit within a QR code.
+    pub fn to_base45(&self) -> Result<String, CompactError> {
+        let proof = &self.proof;
+        let flags = match proof.directions() {
+            Some(_) => DIRECTIONS,
+            None => 0,
+        } | match proof.sibling_counts() {
+            Some(_) => SIBLING_COUNTS,
+            None => 0,
+        };
+
+        let mut bytes = vec![VERSION, flags];
+        write_varint(&mut bytes, self.index);
+        bytes.extend_from_slice(&self.root_id);
+        write_varint(&mut bytes, proof.len());
+        for hash in proof.iter() {
+            bytes.extend_from_slice(hash.as_ref());
+        }
+        if let Some(directions) = proof.directions() {
+            // Pack directions as bits, set for siblings on the left
+            for chunk in directions.chunks(8) {
+                let byte = chunk
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, d)| **d == Direction::Left)
+                    .fold(0u8, |byte, (i, _)| byte | 1 << i);
+                bytes.push(byte);
+            }
+        }
+        if let Some(counts) = proof.sibling_counts() {
+            write_varint(&mut bytes, counts.len());
+            for count in counts {
+                write_varint(&mut bytes, *count);
+            }
+        }
+
+        let encoded = encode(&bytes);
+        match encoded.len() {
+            length if length > QR_CAPACITY => Err(CompactError::TooLarge { length }),
+            _ => Ok(encoded),
+        }
+    }
+
+    pub fn from_base45(encoded: &str) -> Result<Self, CompactError> {
+        let bytes = decode(encoded)?;
+        let mut reader = Reader(&bytes);
+        if reader.byte()? != VERSION {
+            return Err(CompactError::Malformed);
+        }
+        let flags = reader.byte()?;
+        let index = reader.varint()?;
+        let root_id = reader
+            .take(ROOT_ID)?
+            .try_into()
+            .map_err(|_| CompactError::Malformed)?;
+
+        let size = T::Hash::default().as_ref().len();
+        let length = reader.varint()?;
+        let hashes = (0..length)
+            .map(|_| {
+                T::Hash::try_from(reader.take(size)?.to_vec()).map_err(|_| CompactError::Malformed)
+            })
+            .collect::<Result<Vec<T::Hash>, CompactError>>()?;
+        let directions = match flags & DIRECTIONS {
+            0 => None,
+            _ => {
+                let packed = reader.take(length.div_ceil(8))?;
+                Some(
+                    (0..length)
+                        .map(|i| match packed[i / 8] >> (i % 8) & 1 {
+                            1 => Direction::Left,
+                            _ => Direction::Right,
+                        })
+                        .collect(),
+                )
+            }
+        };
+        let sibling_counts = match flags & SIBLING_COUNTS {
+            0 => None,
+            _ => {
+                let steps = reader.varint()?;
+                Some(
+                    (0..steps)
+                        .map(|_| reader.varint())
+                        .collect::<Result<Vec<usize>, _>>()?,
+                )
+            }
+        };
+        if !reader.0.is_empty() {
+            return Err(CompactError::Malformed);
+        }
+
+        Ok(Self {
+            index,
+            root_id,
+            proof: MerkleProof::new(hashes, directions, sibling_counts).with_index(index),
+        })
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// Generates a compact proof for the leaf at `index`, if there is one.
+    pub fn compact_proof(&self, index: usize) -> Option<CompactProof<T>> {
+        if index >= self.layers.layer_len(0) {
+            return None;
+        }
+        Some(CompactProof::new(
+            index,
+            self.root(),
+            self.proof_by_index(index),
+        ))
+    }
+}
+
+/// Encodes bytes as base45, as specified by RFC 9285.
+fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(2) * 3);
+    for chunk in bytes.chunks(2) {
+        let (mut value, digits) = match chunk {
+            [a, b] => ((*a as usize) << 8 | *b as usize, 3),
+            _ => (chunk[0] as usize, 2),
+        };
+        for _ in 0..digits {
+            encoded.push(ALPHABET[value % 45] as char);
+            value /= 45;
+        }
+    }
+    encoded
+}
+
+/// Decodes base45, as specified by RFC 9285.
+fn decode(encoded: &str) -> Result<Vec<u8>, CompactError> {
+    let digits = encoded
+        .chars()
+        .map(|c| match ALPHABET.iter().position(|a| *a as char == c) {
+            Some(digit) => Ok(digit),
+            None => Err(CompactError::InvalidCharacter(c)),
+        })
+        .collect::<Result<Vec<usize>, CompactError>>()?;
+
+    let mut bytes = Vec::with_capacity(digits.len() / 3 * 2 + 1);
+    for chunk in digits.chunks(3) {
+        let value = chunk
+            .iter()
+            .rev()
+            .fold(0, |value, digit| value * 45 + digit);
+        match chunk.len() {
+            3 if value <= 0xffff => bytes.extend_from_slice(&(value as u16).to_be_bytes()),
+            2 if value <= 0xff => bytes.push(value as u8),
+            _ => return Err(CompactError::Malformed),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Writes an unsigned LEB128 integer.
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], CompactError> {
+        if length > self.0.len() {
+            return Err(CompactError::Malformed);
+        }
+        let (taken, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, CompactError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<usize, CompactError> {
+        let mut value = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(CompactError::Malformed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::qr::{decode, encode};
+    use crate::{Bytes, CompactError, CompactProof, Keccak256, MerkleTreeBuilder, QR_CAPACITY};
+
+    #[test]
+    fn base45() {
+        // Examples from RFC 9285
+        for (decoded, encoded) in [
+            ("AB", "BB8"),
+            ("Hello!!", "%69 VD92EX0"),
+            ("base-45", "UJCLQE7W581"),
+            ("ietf!", "QED8WEX0"),
+        ] {
+            assert_eq!(encode(decoded.as_bytes()), encoded);
+            assert_eq!(decode(encoded).unwrap(), decoded.as_bytes());
+        }
+        assert_eq!(decode("GGW"), Err(CompactError::Malformed));
+        assert_eq!(decode("a"), Err(CompactError::InvalidCharacter('a')));
+    }
+
+    #[test]
+    fn round_trips_proofs() {
+        let values: Vec<[u8; 2]> = (0..1000u16).map(|i| i.to_be_bytes()).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for builder in [
+            MerkleTreeBuilder::<Keccak256>::new(),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .sort_pairs(false)
+                .arity(3),
+        ] {
+            let tree = builder.build(&leaves);
+            for index in [0, 500, 999] {
+                let compact = tree.compact_proof(index).unwrap();
+                let encoded = compact.to_base45().unwrap();
+                assert!(encoded.len() < QR_CAPACITY);
+
+                let decoded = CompactProof::<Keccak256>::from_base45(&encoded).unwrap();
+                assert_eq!(decoded.index, index);
+                assert!(decoded.matches_root(tree.root()));
+                assert!(!decoded.matches_root([0u8; 32]));
+                assert_eq!(decoded.proof.hashes(), compact.proof.hashes());
+                assert_eq!(decoded.proof.directions(), compact.proof.directions());
+                assert_eq!(
+                    decoded.proof.sibling_counts(),
+                    compact.proof.sibling_counts()
+                );
+                let leaf = tree.leaves()[index];
+                assert!(tree.verify(decoded.proof, leaf, tree.root()));
+
+                // Truncated codes are rejected
+                let truncated = &encoded[..encoded.len() - 3];
+                assert!(CompactProof::<Keccak256>::from_base45(truncated).is_err());
+            }
+        }
+        assert!(MerkleTreeBuilder::<Keccak256>::new()
+            .build(&leaves)
+            .compact_proof(1000)
+            .is_none());
+    }
+}
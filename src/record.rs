@@ -0,0 +1,256 @@
+use crate::store::Store;
+use crate::{Clock, HashFunction, MerkleProof, MerkleTree, SystemClock};
+use std::fmt::{Display, Formatter};
+use std::fs::OpenOptions;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Destination for recorded proof requests, holding the first write error encountered so proof
+/// generation itself never fails.
+pub(crate) struct Recorder {
+    writer: Box<dyn Write + Send>,
+    clock: Box<dyn Clock>,
+    error: Option<std::io::Error>,
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// Records every subsequent proof request to `writer`, one line per request holding the time
+    /// in milliseconds since the Unix epoch, the leaf index (or `-` if the leaf was not found), the
+    /// leaf and a digest of the proof served.
+    pub fn record_proofs(&mut self, writer: impl Write + Send + 'static) {
+        self.record_proofs_with_clock(writer, SystemClock)
+    }
+
+    /// Records every subsequent proof request to `writer`, timestamped by `clock` rather than the
+    /// system clock.
+    pub fn record_proofs_with_clock(
+        &mut self,
+        writer: impl Write + Send + 'static,
+        clock: impl Clock + 'static,
+    ) {
+        self.recorder = Some(Mutex::new(Recorder {
+            writer: Box::new(writer),
+            clock: Box::new(clock),
+            error: None,
+        }));
+    }
+
+    /// Records every subsequent proof request to the file at `path`, which is appended to.
+    pub fn record_proofs_to(&mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.record_proofs(file);
+        Ok(())
+    }
+
+    /// Stops recording proof requests, returning the first error encountered while recording.
+    pub fn stop_recording(&mut self) -> std::io::Result<()> {
+        match self.recorder.take().map(|r| r.into_inner()) {
+            Some(Ok(mut recorder)) => match recorder.error {
+                Some(error) => Err(error),
+                None => recorder.writer.flush(),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a proof request, if recording.
+    pub(crate) fn record_proof(&self, leaf: T::Hash, proof: &MerkleProof<T>) {
+        let mut recorder = match self.recorder.as_ref().and_then(|r| r.lock().ok()) {
+            Some(recorder) => recorder,
+            None => return,
+        };
+        if recorder.error.is_some() {
+            return;
+        }
+
+        let timestamp = recorder.clock.now_millis();
+        let index = match self.index_of(leaf) {
+            Some(index) => index.to_string(),
+            None => "-".to_string(),
+        };
+        let line = format!(
+            "{} {} 0x{} 0x{}\n",
+            timestamp,
+            index,
+            hex::encode(leaf),
+            hex::encode(digest(proof))
+        );
+        if let Err(error) = recorder.writer.write_all(line.as_bytes()) {
+            recorder.error = Some(error);
+        }
+    }
+
+    /// Re-issues recorded proof requests against this tree, reporting each request for which the
+    /// tree serves a different proof or finds the leaf at a different index than when recorded.
+    ///
+    /// This verifies that a rebuilt tree still serves identical proofs before it replaces the
+    /// tree it was rebuilt from.
+    pub fn replay(&self, recording: impl BufRead) -> Result<Replay, ReplayError> {
+        let mut replay = Replay {
+            requests: 0,
+            mismatches: vec![],
+        };
+        for (number, line) in recording.lines().enumerate() {
+            let line = line.map_err(ReplayError::Io)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let malformed = || ReplayError::Malformed { line: number + 1 };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [_, index, leaf, proof] = fields[..] else {
+                return Err(malformed());
+            };
+            let leaf = decode(leaf)
+                .and_then(|l| T::Hash::try_from(l).ok())
+                .ok_or_else(malformed)?;
+            let proof = decode(proof).ok_or_else(malformed)?;
+
+            // Compare the request as it would be served now
+            replay.requests += 1;
+            let served = match self.index_of(leaf) {
+                Some(index) => index.to_string(),
+                None => "-".to_string(),
+            };
+            if served != index || digest(&self.proof(leaf)).as_ref() != proof {
+                replay.mismatches.push(number + 1);
+            }
+        }
+        Ok(replay)
+    }
+}
+
+/// The outcome of replaying recorded proof requests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    /// The number of requests replayed.
+    pub requests: usize,
+    /// Line numbers of the requests which were served differently.
+    pub mismatches: Vec<usize>,
+}
+
+impl Replay {
+    pub fn is_identical(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    /// The recording could not be parsed at the line number.
+    Malformed {
+        line: usize,
+    },
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "could not read recording: {}", e),
+            ReplayError::Malformed { line } => write!(f, "malformed recording at line {}", line),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// A digest of everything a proof consists of, so proofs can be compared without being stored.
+fn digest<T: HashFunction>(proof: &MerkleProof<T>) -> T::Hash {
+    let mut bytes: Vec<u8> = proof.iter().flat_map(|h| h.as_ref().to_vec()).collect();
+    if let Some(directions) = proof.directions() {
+        bytes.extend(directions.iter().map(|d| *d as u8));
+    }
+    if let Some(counts) = proof.sibling_counts() {
+        bytes.extend(counts.iter().flat_map(|c| (*c as u64).to_le_bytes()));
+    }
+    T::hash(&bytes)
+}
+
+fn decode(value: &str) -> Option<Vec<u8>> {
+    value.strip_prefix("0x").and_then(|v| hex::decode(v).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, FixedClock, Keccak256, MerkleTree, MerkleTreeBuilder, ReplayError};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// Writer which can be inspected after being handed to a tree.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn records_and_replays() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c", "d"].iter().map(|x| x.as_bytes()).collect();
+        let mut tree = MerkleTree::<Keccak256>::new(&leaves);
+        let recording = Shared::default();
+        tree.record_proofs(recording.clone());
+        for leaf in tree.leaves().clone() {
+            tree.proof(leaf);
+        }
+        tree.proof([0u8; 32]);
+        tree.stop_recording().unwrap();
+        tree.proof(tree.leaves()[0]);
+
+        let recording = recording.0.lock().unwrap().clone();
+        let lines: Vec<&str> = std::str::from_utf8(&recording).unwrap().lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[4].contains(" - 0x0000"));
+
+        // A rebuild from the same leaves serves identical proofs
+        let rebuilt = MerkleTree::<Keccak256>::new(&leaves);
+        let replay = rebuilt.replay(&recording[..]).unwrap();
+        assert_eq!(replay.requests, 5);
+        assert!(replay.is_identical());
+
+        // A rebuild with different settings does not
+        let rebuilt = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_pairs(false)
+            .build(&leaves);
+        assert_eq!(
+            rebuilt.replay(&recording[..]).unwrap().mismatches,
+            vec![1, 2, 3, 4]
+        );
+        let rebuilt = MerkleTree::<Keccak256>::new(&leaves[..3]);
+        assert!(!rebuilt.replay(&recording[..]).unwrap().is_identical());
+
+        assert!(matches!(
+            rebuilt.replay(&b"1 0 0x00\n"[..]),
+            Err(ReplayError::Malformed { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn records_with_clock() {
+        let leaves: Vec<&Bytes> = ["a", "b"].iter().map(|x| x.as_bytes()).collect();
+        let mut tree = MerkleTree::<Keccak256>::new(&leaves);
+        let recording = Shared::default();
+        tree.record_proofs_with_clock(
+            recording.clone(),
+            FixedClock::from_millis(1_700_000_000_000),
+        );
+        tree.proof(tree.leaves()[0]);
+        tree.proof(tree.leaves()[1]);
+        tree.stop_recording().unwrap();
+
+        // Recordings of the same requests are identical, whenever they were made
+        let recording = recording.0.lock().unwrap().clone();
+        let recording = std::str::from_utf8(&recording).unwrap();
+        assert!(recording
+            .lines()
+            .all(|line| line.starts_with("1700000000000 ")));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{verify_proof, HashFunction, MerkleError, MerkleProof, MerkleTree, Store, TreeSpec};
+
+/// A proof for a tree with sorted pairs, which carries no directions as each group of siblings is
+/// sorted before hashing. Verification therefore needs neither the index of the leaf nor the side
+/// of each sibling.
+pub struct SortedProof<T: HashFunction>(MerkleProof<T>);
+
+/// A proof for a tree with positional pairs, carrying the side of each sibling.
+pub struct PositionalProof<T: HashFunction>(MerkleProof<T>);
+
+impl<T: HashFunction> SortedProof<T> {
+    pub fn proof(&self) -> &MerkleProof<T> {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> MerkleProof<T> {
+        self.0
+    }
+
+    /// Verifies the proof without a tree, failing if the spec describes positional pairs.
+    pub fn verify(
+        &self,
+        spec: &TreeSpec,
+        leaf: impl Into<T::Hash>,
+        root: impl Into<T::Hash>,
+    ) -> Result<bool, MerkleError> {
+        if !spec.sort_pairs {
+            return Err(MerkleError::SchemeMismatch);
+        }
+        Ok(verify_proof(spec, &self.0, leaf.into(), root.into()))
+    }
+}
+
+impl<T: HashFunction> PositionalProof<T> {
+    pub fn proof(&self) -> &MerkleProof<T> {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> MerkleProof<T> {
+        self.0
+    }
+
+    /// Verifies the proof without a tree, failing if the spec describes sorted pairs.
+    pub fn verify(
+        &self,
+        spec: &TreeSpec,
+        leaf: impl Into<T::Hash>,
+        root: impl Into<T::Hash>,
+    ) -> Result<bool, MerkleError> {
+        if spec.sort_pairs {
+            return Err(MerkleError::SchemeMismatch);
+        }
+        Ok(verify_proof(spec, &self.0, leaf.into(), root.into()))
+    }
+}
+
+/// Accepts proofs without directions.
+impl<T: HashFunction> TryFrom<MerkleProof<T>> for SortedProof<T> {
+    type Error = MerkleError;
+
+    fn try_from(proof: MerkleProof<T>) -> Result<Self, Self::Error> {
+        match proof.directions() {
+            None => Ok(Self(proof)),
+            Some(_) => Err(MerkleError::SchemeMismatch),
+        }
+    }
+}
+
+/// Accepts proofs with directions.
+impl<T: HashFunction> TryFrom<MerkleProof<T>> for PositionalProof<T> {
+    type Error = MerkleError;
+
+    fn try_from(proof: MerkleProof<T>) -> Result<Self, Self::Error> {
+        match proof.directions() {
+            Some(_) => Ok(Self(proof)),
+            None => Err(MerkleError::SchemeMismatch),
+        }
+    }
+}
+
+impl<T: HashFunction> Clone for SortedProof<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: HashFunction> Clone for PositionalProof<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// Generates a proof for a leaf of a tree with sorted pairs.
+    pub fn sorted_proof(&self, leaf: impl Into<T::Hash>) -> Result<SortedProof<T>, MerkleError> {
+        if !self.spec.sort_pairs {
+            return Err(MerkleError::SchemeMismatch);
+        }
+        self.try_proof(leaf).map(SortedProof)
+    }
+
+    /// Generates a proof for a leaf of a tree with positional pairs.
+    pub fn positional_proof(
+        &self,
+        leaf: impl Into<T::Hash>,
+    ) -> Result<PositionalProof<T>, MerkleError> {
+        if self.spec.sort_pairs {
+            return Err(MerkleError::SchemeMismatch);
+        }
+        self.try_proof(leaf).map(PositionalProof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleError, MerkleTree, PositionalProof, SortedProof};
+
+    #[test]
+    fn separates_schemes() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let sorted = MerkleTree::<Keccak256>::new(&leaves);
+        let ordered = MerkleTree::<Keccak256>::new_ordered(&leaves);
+
+        let leaf = sorted.leaves()[1];
+        let proof = sorted.sorted_proof(leaf).unwrap();
+        assert_eq!(proof.verify(sorted.spec(), leaf, sorted.root()), Ok(true));
+        assert_eq!(
+            proof.verify(ordered.spec(), leaf, ordered.root()),
+            Err(MerkleError::SchemeMismatch)
+        );
+        assert_eq!(
+            sorted.positional_proof(leaf).err(),
+            Some(MerkleError::SchemeMismatch)
+        );
+
+        let leaf = ordered.leaves()[1];
+        let proof = ordered.positional_proof(leaf).unwrap();
+        assert_eq!(proof.verify(ordered.spec(), leaf, ordered.root()), Ok(true));
+        assert_eq!(
+            proof.verify(sorted.spec(), leaf, sorted.root()),
+            Err(MerkleError::SchemeMismatch)
+        );
+        assert_eq!(
+            ordered.sorted_proof(leaf).err(),
+            Some(MerkleError::SchemeMismatch)
+        );
+
+        // Untyped proofs are classified by whether they carry directions
+        assert!(SortedProof::try_from(ordered.proof(leaf)).is_err());
+        assert!(PositionalProof::try_from(ordered.proof(leaf)).is_ok());
+        assert!(PositionalProof::try_from(sorted.proof(sorted.leaves()[0])).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{HashFunction, MerkleTree, MerkleTreeBuilder};
+use std::fmt::{Debug, Formatter};
+use zeroize::Zeroize;
+
+/// A confidential leaf value, such as a hashed email address or national ID, which is cleared
+/// from memory when dropped and never printed.
+///
+/// The value is deliberately neither `Copy` nor `Clone`, so it is not duplicated implicitly.
+pub struct SecretLeaf(Vec<u8>);
+
+impl SecretLeaf {
+    pub fn new(value: impl Into<Vec<u8>>) -> Self {
+        Self(value.into())
+    }
+
+    /// Exposes the raw value, which should not be retained beyond immediate use.
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Debug for SecretLeaf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretLeaf([REDACTED])")
+    }
+}
+
+impl Drop for SecretLeaf {
+    fn drop(&mut self) {
+        self.0.zeroize()
+    }
+}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Builds a tree from confidential leaves, which are only ever borrowed while being hashed.
+    pub fn build_secret(&self, leaves: &[SecretLeaf]) -> MerkleTree<T> {
+        let leaves: Vec<&[u8]> = leaves.iter().map(|l| l.expose_secret()).collect();
+        self.build(&leaves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleTreeBuilder, SecretLeaf};
+
+    #[test]
+    fn redacts_debug() {
+        let leaf = SecretLeaf::new("alice@example.com");
+        assert_eq!(format!("{:?}", leaf), "SecretLeaf([REDACTED])");
+        assert_eq!(leaf.expose_secret(), b"alice@example.com");
+    }
+
+    #[test]
+    fn builds_tree() {
+        let values = ["alice@example.com", "bob@example.com", "carol@example.com"];
+        let secrets: Vec<SecretLeaf> = values.iter().map(|v| SecretLeaf::new(*v)).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| v.as_bytes()).collect();
+
+        let builder = MerkleTreeBuilder::<Keccak256>::new().leaf_prefix(&[0]);
+        let tree = builder.build_secret(&secrets);
+        assert_eq!(tree.root(), builder.build(&leaves).root());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{Bytes, HashFunction, MerkleTree, MerkleTreeBuilder};
+use std::fmt::{Display, Formatter};
+
+/// Known answers for each built in hash function: the hash of `abc`, followed by the roots of
+/// the sorted and ordered trees of `VALUES`.
+const VECTORS: &[(&str, [&str; 3])] = &[(
+    "keccak256",
+    [
+        "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+        "3cb8ebf3ea51dca2c1dbf070aea7b5c035ecaaea486bc68f4819b59360c5a2f8",
+        "1dd0d2a6ae466d665cb26e1a31f07c57ae5df7d2bc559cd5826d417be9141a5d",
+    ],
+)];
+const VALUES: [&str; 5] = ["a", "b", "c", "d", "e"];
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Builds small known trees, generating and verifying a proof of every leaf and comparing each
+    /// root against known answers for the hash function.
+    ///
+    /// Services can run this at startup to detect a miscompiled or misconfigured hash function
+    /// before serving any proofs. Hash functions without known answers are only checked for
+    /// consistency between proof generation and verification.
+    pub fn self_test() -> Result<(), SelfTestError> {
+        let expected = VECTORS
+            .iter()
+            .find(|(name, _)| *name == T::NAME)
+            .map(|(_, vector)| vector);
+        let check = |index: usize, actual: &[u8]| match expected {
+            Some(vector) if hex::encode(actual) != vector[index] => Err(SelfTestError::Mismatch {
+                vector: ["hash", "sorted root", "ordered root"][index],
+                expected: vector[index].to_string(),
+                actual: hex::encode(actual),
+            }),
+            _ => Ok(()),
+        };
+        check(0, T::hash(b"abc").as_ref())?;
+
+        let leaves: Vec<&Bytes> = VALUES.iter().map(|v| v.as_bytes()).collect();
+        for (index, sorted) in [(1, true), (2, false)] {
+            let tree = MerkleTreeBuilder::<T>::new()
+                .sort_leaves(sorted)
+                .sort_pairs(sorted)
+                .build(&leaves);
+            check(index, tree.root().as_ref())?;
+            for leaf in tree.leaves() {
+                if !tree.verify(tree.proof(*leaf), *leaf, tree.root()) {
+                    return Err(SelfTestError::ProofRejected);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SelfTestError {
+    /// The hash function computed a different answer to the known answer.
+    Mismatch {
+        vector: &'static str,
+        expected: String,
+        actual: String,
+    },
+    /// A generated proof was rejected on verification.
+    ProofRejected,
+}
+
+impl Display for SelfTestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelfTestError::Mismatch {
+                vector,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "self test {} was 0x{}, expected 0x{}",
+                vector, actual, expected
+            ),
+            SelfTestError::ProofRejected => write!(f, "self test proof was rejected"),
+        }
+    }
+}
+
+impl std::error::Error for SelfTestError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HashFunction, Keccak256, MerkleTree, SelfTestError};
+
+    #[test]
+    fn passes() {
+        MerkleTree::<Keccak256>::self_test().unwrap();
+    }
+
+    /// A hash function behaving like a miscompiled keccak256.
+    #[derive(Default)]
+    struct Broken {}
+
+    impl HashFunction for Broken {
+        type Hash = [u8; 32];
+
+        const NAME: &'static str = "keccak256";
+
+        fn hash(value: &[u8]) -> Self::Hash {
+            let mut hash = Keccak256::hash(value);
+            hash[31] ^= 1;
+            hash
+        }
+    }
+
+    #[test]
+    fn detects_broken_hash_functions() {
+        assert!(matches!(
+            MerkleTree::<Broken>::self_test(),
+            Err(SelfTestError::Mismatch { vector: "hash", .. })
+        ));
+    }
+}
@@ -0,0 +1,367 @@
+use crate::{
+    Direction, HashFunction, LeafKey, MerkleMultiProof, MerkleProof, MerkleTree, TreeSpec,
+};
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::marker::PhantomData;
+
+/// Serializes a list of hashes as `0x` prefixed hex strings for human readable formats, or as a
+/// single contiguous byte string for binary formats.
+pub(crate) mod hashes {
+    use super::*;
+
+    pub fn serialize<H: AsRef<[u8]>, S: Serializer>(
+        hashes: &[H],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let mut seq = serializer.serialize_seq(Some(hashes.len()))?;
+            for hash in hashes {
+                seq.serialize_element(&format!("0x{}", hex::encode(hash)))?;
+            }
+            seq.end()
+        } else {
+            let bytes: Vec<u8> = hashes.iter().flat_map(|h| h.as_ref().to_vec()).collect();
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+
+    pub fn deserialize<'de, H, D>(deserializer: D) -> Result<Vec<H>, D::Error>
+    where
+        H: Default + AsRef<[u8]> + TryFrom<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_seq(HashesVisitor(PhantomData))
+        } else {
+            deserializer.deserialize_bytes(HashesVisitor(PhantomData))
+        }
+    }
+
+    struct HashesVisitor<H>(PhantomData<H>);
+
+    impl<'de, H: Default + AsRef<[u8]> + TryFrom<Vec<u8>>> Visitor<'de> for HashesVisitor<H> {
+        type Value = Vec<H>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(formatter, "a list of hex encoded hashes or a byte string")
+        }
+
+        fn visit_bytes<E: Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+            let size = H::default().as_ref().len();
+            if !bytes.len().is_multiple_of(size) {
+                return Err(E::custom(format!(
+                    "byte length {} is not a multiple of the hash size {}",
+                    bytes.len(),
+                    size
+                )));
+            }
+            bytes
+                .chunks(size)
+                .map(|chunk| H::try_from(chunk.to_vec()).map_err(|_| E::custom("invalid hash")))
+                .collect()
+        }
+
+        fn visit_byte_buf<E: Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+            self.visit_bytes(&bytes)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut hashes = vec![];
+            while let Some(hash) = seq.next_element::<String>()? {
+                let bytes = hex::decode(hash.trim_start_matches("0x"))
+                    .map_err(|_| A::Error::custom(format!("invalid hex hash '{}'", hash)))?;
+                if bytes.len() != H::default().as_ref().len() {
+                    return Err(A::Error::custom(format!("invalid hash length '{}'", hash)));
+                }
+                let hash = H::try_from(bytes).map_err(|_| A::Error::custom("invalid hash"))?;
+                hashes.push(hash);
+            }
+            Ok(hashes)
+        }
+    }
+}
+
+/// A single layer of a tree, serialized as a list of hashes.
+struct Layer<H>(Vec<H>);
+
+struct LayerRef<'a, H>(&'a [H]);
+
+impl<H: AsRef<[u8]>> Serialize for LayerRef<'_, H> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hashes::serialize(self.0, serializer)
+    }
+}
+
+impl<'de, H: Default + AsRef<[u8]> + TryFrom<Vec<u8>>> Deserialize<'de> for Layer<H> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        hashes::deserialize(deserializer).map(Layer)
+    }
+}
+
+impl Serialize for TreeSpec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for TreeSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let spec = String::deserialize(deserializer)?;
+        TreeSpec::parse(&spec).map_err(D::Error::custom)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(bound(serialize = "H: AsRef<[u8]>"))]
+struct TreeRef<'a, H> {
+    spec: &'a TreeSpec,
+    layers: Vec<LayerRef<'a, H>>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "H: Default + AsRef<[u8]> + TryFrom<Vec<u8>>"))]
+struct TreeRepr<H> {
+    // Parsed once the key of a keyed spec is known
+    spec: String,
+    layers: Vec<Layer<H>>,
+}
+
+impl<T: HashFunction> Serialize for MerkleTree<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TreeRef {
+            spec: &self.spec,
+            layers: self.layers.iter().map(|l| LayerRef(l)).collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: HashFunction> Deserialize<'de> for MerkleTree<T> {
+    /// Deserializes a tree, failing if its spec is keyed, as the key is not serialized. Trees with
+    /// a keyed spec are deserialized with `deserialize_keyed`.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        MerkleTree::deserialize_with_key(deserializer, None)
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Deserializes a tree whose spec is keyed, supplying the key which is not serialized. Trees
+    /// whose spec is not keyed fail to deserialize, rather than ignoring the key.
+    pub fn deserialize_keyed<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        key: LeafKey,
+    ) -> Result<Self, D::Error> {
+        MerkleTree::deserialize_with_key(deserializer, Some(key))
+    }
+
+    fn deserialize_with_key<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        key: Option<LeafKey>,
+    ) -> Result<Self, D::Error> {
+        let repr = TreeRepr::<T::Hash>::deserialize(deserializer)?;
+        let spec = TreeSpec::parse_with_key(&repr.spec, key).map_err(D::Error::custom)?;
+        if spec.hash != T::NAME {
+            return Err(D::Error::custom(format!(
+                "tree was built with hash function '{}' rather than '{}'",
+                spec.hash,
+                T::NAME
+            )));
+        }
+
+        // Check the layers have the shape implied by the number of leaves
+        let layers: Vec<Vec<T::Hash>> = repr.layers.into_iter().map(|l| l.0).collect();
+        if layers.is_empty() {
+            return Err(D::Error::custom("tree has no layers"));
+        }
+        for i in 1..layers.len() {
+            let expected = layers[i - 1].len().div_ceil(spec.arity);
+            if layers[i - 1].len() <= 1 || layers[i].len() != expected {
+                return Err(D::Error::custom(format!(
+                    "layer {} has an invalid length",
+                    i
+                )));
+            }
+        }
+        if layers[layers.len() - 1].len() > 1 {
+            return Err(D::Error::custom("tree is missing layers"));
+        }
+
+        Ok(MerkleTree::from_layers(spec, layers))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "H: AsRef<[u8]>",
+    deserialize = "H: Default + AsRef<[u8]> + TryFrom<Vec<u8>>"
+))]
+struct ProofRepr<H> {
+    #[serde(with = "hashes")]
+    hashes: Vec<H>,
+    // Absent fields are accepted but always written, as binary formats cannot skip fields
+    #[serde(default)]
+    directions: Option<Vec<Direction>>,
+    #[serde(default)]
+    sibling_counts: Option<Vec<usize>>,
+    #[serde(default)]
+    index: Option<usize>,
+}
+
+impl<T: HashFunction> Serialize for MerkleProof<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ProofRepr {
+            hashes: self.hashes().to_vec(),
+            directions: self.directions().map(|d| d.to_vec()),
+            sibling_counts: self.sibling_counts().map(|c| c.to_vec()),
+            index: self.index(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: HashFunction> Deserialize<'de> for MerkleProof<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = ProofRepr::<T::Hash>::deserialize(deserializer)?;
+        let proof = MerkleProof::new(repr.hashes, repr.directions, repr.sibling_counts);
+        Ok(match repr.index {
+            Some(index) => proof.with_index(index),
+            None => proof,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "H: AsRef<[u8]>",
+    deserialize = "H: Default + AsRef<[u8]> + TryFrom<Vec<u8>>"
+))]
+struct MultiProofRepr<H> {
+    #[serde(with = "hashes")]
+    leaves: Vec<H>,
+    #[serde(with = "hashes")]
+    proof: Vec<H>,
+    proof_flags: Vec<bool>,
+}
+
+impl<T: HashFunction> Serialize for MerkleMultiProof<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MultiProofRepr {
+            leaves: self.leaves().to_vec(),
+            proof: self.proof().to_vec(),
+            proof_flags: self.proof_flags().to_vec(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: HashFunction> Deserialize<'de> for MerkleMultiProof<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MultiProofRepr::<T::Hash>::deserialize(deserializer)?;
+        Ok(MerkleMultiProof::new(
+            repr.leaves,
+            repr.proof,
+            repr.proof_flags,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Bytes, Keccak256, LeafKey, MerkleMultiProof, MerkleProof, MerkleTree, MerkleTreeBuilder,
+    };
+
+    fn tree() -> MerkleTree<Keccak256> {
+        let leaves: Vec<&Bytes> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|x| x.as_bytes())
+            .collect();
+        MerkleTreeBuilder::<Keccak256>::new()
+            .sort_pairs(false)
+            .build(&leaves)
+    }
+
+    #[test]
+    fn tree_round_trip() {
+        let tree = tree();
+
+        let json = serde_json::to_string(&tree).unwrap();
+        assert!(json.contains(&format!("0x{}", hex::encode(tree.root()))));
+        let decoded: MerkleTree<Keccak256> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.layers, tree.layers);
+        assert_eq!(decoded.spec(), tree.spec());
+
+        // Binary formats store each layer as a single byte string
+        let bytes = bincode::serialize(&tree).unwrap();
+        let hashes: usize = tree.layers.iter().map(|l| l.len()).sum();
+        assert!(bytes.len() < hashes * 32 + 100);
+        let decoded: MerkleTree<Keccak256> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.layers, tree.layers);
+    }
+
+    #[test]
+    fn rejects_malformed_tree() {
+        let tree = tree();
+        let mut value = serde_json::to_value(&tree).unwrap();
+        value["layers"][1].as_array_mut().unwrap().pop();
+        assert!(serde_json::from_value::<MerkleTree<Keccak256>>(value).is_err());
+
+        let mut value = serde_json::to_value(&tree).unwrap();
+        value["spec"] = "sha256/sorted".into();
+        assert!(serde_json::from_value::<MerkleTree<Keccak256>>(value).is_err());
+    }
+
+    #[test]
+    fn requires_leaf_key() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .salt(b"salt")
+            .build(&leaves);
+        let json = serde_json::to_string(&tree).unwrap();
+        assert!(json.contains("/keyed"));
+        assert!(!json.contains("73616c74"));
+        assert!(serde_json::from_str::<MerkleTree<Keccak256>>(&json).is_err());
+
+        let key = || LeafKey::new(b"salt".to_vec());
+        let mut deserializer = serde_json::Deserializer::from_str(&json);
+        let decoded = MerkleTree::<Keccak256>::deserialize_keyed(&mut deserializer, key()).unwrap();
+        assert_eq!(decoded.spec(), tree.spec());
+        assert_eq!(decoded.proof_for_value(b"b"), tree.proof_for_value(b"b"));
+
+        let json = serde_json::to_string(&MerkleTree::<Keccak256>::new(&leaves)).unwrap();
+        let mut deserializer = serde_json::Deserializer::from_str(&json);
+        assert!(MerkleTree::<Keccak256>::deserialize_keyed(&mut deserializer, key()).is_err());
+    }
+
+    #[test]
+    fn proof_round_trip() {
+        let tree = tree();
+        let leaf = tree.leaves()[2];
+        let proof = tree.proof(leaf);
+
+        let json = serde_json::to_string(&proof).unwrap();
+        assert!(json.contains("\"directions\":[\"right\",\"left\",\"right\"]"));
+        let decoded: MerkleProof<Keccak256> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(tree.verify(decoded, leaf, tree.root()));
+
+        let bytes = bincode::serialize(&proof).unwrap();
+        assert_eq!(
+            bincode::deserialize::<MerkleProof<Keccak256>>(&bytes).unwrap(),
+            proof
+        );
+    }
+
+    #[test]
+    fn multi_proof_round_trip() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c", "d"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Keccak256>::new(&leaves);
+        let proof = tree.multi_proof(&[tree.leaves()[0], tree.leaves()[3]]);
+
+        let json = serde_json::to_string(&proof).unwrap();
+        let decoded: MerkleMultiProof<Keccak256> = serde_json::from_str(&json).unwrap();
+        assert!(tree.verify_multi_proof(&decoded, tree.root()));
+    }
+}
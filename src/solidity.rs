@@ -0,0 +1,70 @@
+use crate::{Hash, HashFunction, Keccak256, MerkleProof};
+
+/// Signature of OpenZeppelin's `MerkleProof.verify`.
+const VERIFY: &str = "verify(bytes32[],bytes32,bytes32)";
+
+/// Conversions for passing proofs to OpenZeppelin's `MerkleProof` library. The library hashes
+/// sorted pairs, so only proofs from binary trees with sorted pairs verify on chain.
+impl<T: HashFunction<Hash = Hash<32>>> MerkleProof<T> {
+    /// The proof as a `bytes32[]`.
+    pub fn to_solidity(&self) -> Vec<[u8; 32]> {
+        self.hashes().to_vec()
+    }
+
+    /// The proof as `0x` prefixed hex strings, as accepted for a `bytes32[]` by ethers-rs and
+    /// foundry scripts.
+    pub fn to_solidity_hex(&self) -> Vec<String> {
+        self.iter()
+            .map(|hash| format!("0x{}", hex::encode(hash)))
+            .collect()
+    }
+
+    /// The ABI encoded calldata of a call to `verify(bytes32[] proof, bytes32 root, bytes32 leaf)`,
+    /// including the function selector.
+    pub fn to_calldata(&self, root: [u8; 32], leaf: [u8; 32]) -> Vec<u8> {
+        let mut calldata = Keccak256::hash(VERIFY.as_bytes())[..4].to_vec();
+        // The proof is dynamic, so the head holds its offset and the tail its length and hashes
+        calldata.extend_from_slice(&word(3 * 32));
+        calldata.extend_from_slice(&root);
+        calldata.extend_from_slice(&leaf);
+        calldata.extend_from_slice(&word(self.len()));
+        for hash in self.iter() {
+            calldata.extend_from_slice(hash);
+        }
+        calldata
+    }
+}
+
+fn word(value: usize) -> [u8; 32] {
+    let mut word = [0; 32];
+    word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleTree};
+
+    #[test]
+    fn encodes_calldata() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTree::<Keccak256>::new(&leaves);
+        let leaf = tree.leaves()[0];
+        let proof = tree.proof(leaf);
+        assert_eq!(proof.to_solidity(), proof.hashes());
+        assert_eq!(
+            proof.to_solidity_hex()[0],
+            format!("0x{}", hex::encode(proof.hashes()[0]))
+        );
+
+        let calldata = proof.to_calldata(tree.root(), leaf);
+        assert_eq!(hex::encode(&calldata[..4]), "5a9a49c7");
+        let words: Vec<&[u8]> = calldata[4..].chunks(32).collect();
+        assert_eq!(words.len(), 4 + proof.len());
+        assert_eq!(words[0][31], 0x60);
+        assert_eq!(words[1], tree.root());
+        assert_eq!(words[2], leaf);
+        assert_eq!(words[3][31] as usize, proof.len());
+        assert_eq!(words[4], proof.hashes()[0]);
+    }
+}
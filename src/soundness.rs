@@ -0,0 +1,191 @@
+//! Exhaustive checks that verification accepts no proof other than the one generated for a leaf,
+//! across every tree of up to eight leaves for a range of specs. Each proof is mutated by flipping
+//! single bits, swapping siblings, flipping directions, regrouping siblings and truncating or
+//! extending it, and every mutation must fail to verify.
+
+use crate::{Bytes, Direction, Keccak256, MerkleProof, MerkleTree, MerkleTreeBuilder, TreeSpec};
+
+const SPECS: [&str; 9] = [
+    "keccak256/sorted/promote-odd/arity2",
+    "keccak256/ordered/promote-odd/arity2",
+    "keccak256/sorted/dup-odd/arity2",
+    "keccak256/ordered/dup-odd/arity2",
+    "keccak256/sorted-leaves/promote-odd/arity3",
+    "keccak256/ordered/dup-odd/arity3",
+    "keccak256/sorted/zero-odd/arity2",
+    "keccak256/ordered/zero-odd/arity3",
+    "keccak256/ordered/promote-odd/arity2/leaf-prefix00/node-prefix01",
+];
+
+type Proof = MerkleProof<Keccak256>;
+
+/// The parts of a proof, to be mutated and reassembled.
+#[derive(Clone)]
+struct Parts {
+    hashes: Vec<[u8; 32]>,
+    directions: Option<Vec<Direction>>,
+    counts: Option<Vec<usize>>,
+}
+
+impl Parts {
+    fn of(proof: &Proof) -> Self {
+        Self {
+            hashes: proof.hashes().to_vec(),
+            directions: proof.directions().map(|d| d.to_vec()),
+            counts: proof.sibling_counts().map(|c| c.to_vec()),
+        }
+    }
+
+    fn proof(self) -> Proof {
+        MerkleProof::new(self.hashes, self.directions, self.counts)
+    }
+
+    /// The step of the proof each hash belongs to.
+    fn steps(&self) -> Vec<usize> {
+        match &self.counts {
+            Some(counts) => (0..)
+                .zip(counts)
+                .flat_map(|(step, count)| vec![step; *count])
+                .collect(),
+            None => (0..self.hashes.len()).collect(),
+        }
+    }
+}
+
+/// Every mutation of a proof, each of which proves nothing about the leaf.
+fn mutations(tree: &MerkleTree<Keccak256>, leaf: usize, proof: &Proof) -> Vec<(String, Proof)> {
+    let parts = Parts::of(proof);
+    let steps = parts.steps();
+    let mut mutations = vec![];
+
+    // Single bit flips of every sibling
+    for (i, bit) in (0..parts.hashes.len()).flat_map(|i| (0..256).map(move |bit| (i, bit))) {
+        let mut mutated = parts.clone();
+        mutated.hashes[i][bit / 8] ^= 1 << (bit % 8);
+        mutations.push((
+            format!("bit {} of sibling {} flipped", bit, i),
+            mutated.proof(),
+        ));
+    }
+
+    // Siblings swapped between steps, as siblings of the same sorted group hash identically
+    for i in 0..parts.hashes.len() {
+        for j in i + 1..parts.hashes.len() {
+            if steps[i] != steps[j] && parts.hashes[i] != parts.hashes[j] {
+                let mut mutated = parts.clone();
+                mutated.hashes.swap(i, j);
+                mutations.push((format!("siblings {} and {} swapped", i, j), mutated.proof()));
+            }
+        }
+    }
+
+    // Directions flipped, except for a duplicate of the node itself which hashes identically on
+    // either side
+    for i in 0..parts.directions.as_ref().map_or(0, |d| d.len()) {
+        let node = tree.layers[steps[i]][leaf / tree.spec.arity.pow(steps[i] as u32)];
+        if tree.spec.odd_strategy == crate::OddStrategy::Duplicate && parts.hashes[i] == node {
+            continue;
+        }
+        let mut mutated = parts.clone();
+        let directions = mutated.directions.as_mut().unwrap();
+        directions[i] = match directions[i] {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        };
+        mutations.push((format!("direction {} flipped", i), mutated.proof()));
+    }
+
+    // A sibling moved into the neighbouring step
+    for i in 1..parts.counts.as_ref().map_or(0, |c| c.len()) {
+        for (from, to) in [(i - 1, i), (i, i - 1)] {
+            let mut mutated = parts.clone();
+            let counts = mutated.counts.as_mut().unwrap();
+            if counts[from] > 0 {
+                counts[from] -= 1;
+                counts[to] += 1;
+                mutations.push((format!("sibling moved from step {}", from), mutated.proof()));
+            }
+        }
+    }
+
+    // Truncated by a whole step or by a single sibling
+    if let Some(last) = steps.last() {
+        let len = steps.iter().position(|step| step == last).unwrap();
+        let mut mutated = parts.clone();
+        mutated.hashes.truncate(len);
+        mutated.directions.iter_mut().for_each(|d| d.truncate(len));
+        mutated.counts.iter_mut().for_each(|c| {
+            c.pop();
+        });
+        mutations.push(("last step removed".to_string(), mutated.proof()));
+
+        let mut mutated = parts.clone();
+        mutated.hashes.pop();
+        mutations.push(("last sibling removed".to_string(), mutated.proof()));
+    }
+
+    // Extended by the leaf, the root or a step made of either
+    for (name, hash) in [("leaf", tree.layers[0][leaf]), ("root", tree.root())] {
+        let mut mutated = parts.clone();
+        mutated.hashes.push(hash);
+        mutated
+            .directions
+            .iter_mut()
+            .for_each(|d| d.push(Direction::Right));
+        mutated.counts.iter_mut().for_each(|c| c.push(1));
+        mutations.push((format!("extended by the {}", name), mutated.proof()));
+
+        let mut mutated = parts.clone();
+        mutated.hashes.insert(0, hash);
+        mutations.push((format!("prefixed by the {}", name), mutated.proof()));
+    }
+
+    mutations
+}
+
+#[test]
+fn rejects_mutated_proofs() {
+    // Each spec is checked on its own thread, as there are over a hundred thousand mutations in all
+    std::thread::scope(|scope| {
+        for spec in SPECS {
+            scope.spawn(move || check(spec));
+        }
+    });
+}
+
+fn check(spec: &str) {
+    let values: Vec<[u8; 1]> = (0..8).map(|i| [i]).collect();
+    let builder =
+        MerkleTreeBuilder::<Keccak256>::from_spec(&TreeSpec::parse(spec).unwrap()).unwrap();
+    for count in 1..=values.len() {
+        let leaves: Vec<&Bytes> = values[..count].iter().map(|v| &v[..]).collect();
+        let tree = builder.build(&leaves);
+        let root = tree.root();
+
+        for index in 0..count {
+            let leaf = tree.leaves()[index];
+            let proof = tree.proof_by_index(index);
+            let context = format!("leaf {} of {} with {}", index, count, spec);
+            assert!(tree.verify(proof.clone(), leaf, root), "{}", context);
+
+            // The proof holds for no other leaf or root, the root of a single leaf being the
+            // leaf itself
+            for other in tree.leaves().iter().filter(|other| **other != leaf) {
+                assert!(!tree.verify(proof.clone(), *other, root), "{}", context);
+            }
+            if count > 1 {
+                assert!(!tree.verify(proof.clone(), leaf, leaf), "{}", context);
+            }
+            assert!(!tree.verify(proof.clone(), leaf, [0; 32]), "{}", context);
+
+            for (mutation, mutated) in mutations(&tree, index, &proof) {
+                assert!(
+                    !tree.verify(mutated, leaf, root),
+                    "{} accepted with {}",
+                    context,
+                    mutation
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use crate::{Bytes, HashFunction, MerkleTree};
+use std::collections::BTreeMap;
+
+/// A sparse Merkle tree mapping hash sized keys to values, as used for account and state maps.
+///
+/// The tree has one leaf for every possible key, so its depth is the number of bits in a hash.
+/// Absent keys hold an empty leaf, and only the nodes which differ from the root of an empty
+/// subtree of the same height are stored. Proofs can therefore show that a key is absent as well
+/// as that it holds a value.
+pub struct SparseMerkleTree<T: HashFunction> {
+    depth: usize,
+    /// Root of an empty subtree of each height, from the empty leaf upwards.
+    defaults: Vec<T::Hash>,
+    /// Nodes which differ from their default, keyed by height and the key with the bits below
+    /// that height cleared.
+    nodes: BTreeMap<(usize, T::Hash), T::Hash>,
+    values: BTreeMap<T::Hash, Vec<u8>>,
+}
+
+impl<T: HashFunction> SparseMerkleTree<T> {
+    pub fn new() -> Self {
+        let depth = T::Hash::default().as_ref().len() * 8;
+        let mut defaults = vec![T::Hash::default()];
+        for height in 0..depth {
+            let empty = defaults[height];
+            defaults.push(MerkleTree::<T>::hash_nodes(&[empty, empty]));
+        }
+
+        Self {
+            depth,
+            defaults,
+            nodes: BTreeMap::new(),
+            values: BTreeMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> T::Hash {
+        self.node(self.depth, T::Hash::default())
+    }
+
+    pub fn get(&self, key: T::Hash) -> Option<&[u8]> {
+        self.values.get(&key).map(|v| &v[..])
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Sets the value for a key, returning the previous value if there was one.
+    pub fn insert(&mut self, key: T::Hash, value: &Bytes) -> Option<Vec<u8>> {
+        let previous = self.values.insert(key, value.to_vec());
+        self.rehash_path(key, T::hash(value));
+        previous
+    }
+
+    /// Removes the value for a key, returning it if there was one.
+    pub fn delete(&mut self, key: T::Hash) -> Option<Vec<u8>> {
+        let previous = self.values.remove(&key);
+        if previous.is_some() {
+            self.rehash_path(key, self.defaults[0]);
+        }
+        previous
+    }
+
+    /// Generates a proof for a key, which shows either the value it holds or that it is absent.
+    pub fn proof(&self, key: T::Hash) -> SparseMerkleProof<T> {
+        let mut siblings = vec![];
+        let mut defaults = vec![];
+        for height in 0..self.depth {
+            let sibling = self.node(height, flip(key, self.depth - 1 - height));
+            let default = sibling == self.defaults[height];
+            if !default {
+                siblings.push(sibling);
+            }
+            defaults.push(default);
+        }
+
+        SparseMerkleProof { siblings, defaults }
+    }
+
+    /// Verifies a proof that `key` holds `value` within the tree with the supplied root, or that
+    /// it is absent if `value` is `None`.
+    pub fn verify(
+        &self,
+        proof: &SparseMerkleProof<T>,
+        key: T::Hash,
+        value: Option<&Bytes>,
+        root: T::Hash,
+    ) -> bool {
+        if proof.defaults.len() != self.depth {
+            return false;
+        }
+
+        let mut hash = match value {
+            Some(value) => T::hash(value),
+            None => self.defaults[0],
+        };
+        let mut siblings = proof.siblings.iter();
+        for (height, default) in proof.defaults.iter().enumerate() {
+            let sibling = match default {
+                true => self.defaults[height],
+                false => match siblings.next() {
+                    Some(sibling) => *sibling,
+                    None => return false,
+                },
+            };
+            hash = match bit(key, self.depth - 1 - height) {
+                false => MerkleTree::<T>::hash_nodes(&[hash, sibling]),
+                true => MerkleTree::<T>::hash_nodes(&[sibling, hash]),
+            };
+        }
+
+        siblings.next().is_none() && hash == root
+    }
+
+    /// The node of the given height on the path to `key`.
+    fn node(&self, height: usize, key: T::Hash) -> T::Hash {
+        match self.nodes.get(&(height, clear(key, height))) {
+            Some(node) => *node,
+            None => self.defaults[height],
+        }
+    }
+
+    /// Recomputes the nodes on the path from the leaf for `key` to the root.
+    fn rehash_path(&mut self, key: T::Hash, leaf: T::Hash) {
+        let mut hash = leaf;
+        for height in 0..=self.depth {
+            // Only store nodes which differ from those of an empty tree
+            let position = (height, clear(key, height));
+            if hash == self.defaults[height] {
+                self.nodes.remove(&position);
+            } else {
+                self.nodes.insert(position, hash);
+            }
+            if height == self.depth {
+                break;
+            }
+
+            let sibling = self.node(height, flip(key, self.depth - 1 - height));
+            hash = match bit(key, self.depth - 1 - height) {
+                false => MerkleTree::<T>::hash_nodes(&[hash, sibling]),
+                true => MerkleTree::<T>::hash_nodes(&[sibling, hash]),
+            };
+        }
+    }
+}
+
+impl<T: HashFunction> Default for SparseMerkleTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The sibling hashes on the path from a key to the root of a sparse tree, from the leaf upwards.
+/// Siblings which are the root of an empty subtree are omitted and flagged instead, so proofs
+/// only grow with the number of populated neighbours rather than with the depth of the tree.
+pub struct SparseMerkleProof<T: HashFunction> {
+    siblings: Vec<T::Hash>,
+    defaults: Vec<bool>,
+}
+
+impl<T: HashFunction> SparseMerkleProof<T> {
+    /// The sibling hashes which are not the root of an empty subtree.
+    pub fn siblings(&self) -> &[T::Hash] {
+        &self.siblings
+    }
+
+    /// Whether the sibling at each height is the root of an empty subtree.
+    pub fn defaults(&self) -> &[bool] {
+        &self.defaults
+    }
+}
+
+impl<T: HashFunction> Clone for SparseMerkleProof<T> {
+    fn clone(&self) -> Self {
+        Self {
+            siblings: self.siblings.clone(),
+            defaults: self.defaults.clone(),
+        }
+    }
+}
+
+impl<T: HashFunction> std::fmt::Debug for SparseMerkleProof<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SparseMerkleProof")
+            .field(
+                "siblings",
+                &self
+                    .siblings
+                    .iter()
+                    .map(|hash| format!("0x{}", hex::encode(hash)))
+                    .collect::<Vec<String>>(),
+            )
+            .field("defaults", &self.defaults)
+            .finish()
+    }
+}
+
+/// The bit of a key at `index`, counted from the most significant bit.
+fn bit<H: AsRef<[u8]>>(key: H, index: usize) -> bool {
+    key.as_ref()[index / 8] & (0x80 >> (index % 8)) != 0
+}
+
+/// Flips the bit of a key at `index`, giving the path to the sibling at that point.
+fn flip<H: AsRef<[u8]> + TryFrom<Vec<u8>> + Default>(key: H, index: usize) -> H {
+    let mut bytes = key.as_ref().to_vec();
+    bytes[index / 8] ^= 0x80 >> (index % 8);
+    H::try_from(bytes).unwrap_or_default()
+}
+
+/// Clears the lowest `bits` bits of a key, identifying the subtree of that height containing it.
+fn clear<H: AsRef<[u8]> + TryFrom<Vec<u8>> + Default>(key: H, bits: usize) -> H {
+    let mut bytes = key.as_ref().to_vec();
+    let length = bytes.len();
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        let low = (length - i) * 8;
+        if low <= bits {
+            *byte = 0;
+        } else if low - 8 < bits {
+            *byte &= 0xff << (bits - (low - 8));
+        }
+    }
+    H::try_from(bytes).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HashFunction, Keccak256, SparseMerkleTree};
+
+    fn key(i: u8) -> [u8; 32] {
+        Keccak256::hash(&[i])
+    }
+
+    #[test]
+    fn inserts_and_deletes() {
+        let mut tree = SparseMerkleTree::<Keccak256>::new();
+        let empty = tree.root();
+        assert!(tree.is_empty());
+        assert!(tree.nodes.is_empty());
+
+        for i in 0..10 {
+            assert_eq!(tree.insert(key(i), &[i, i]), None);
+        }
+        assert_eq!(tree.len(), 10);
+        assert_eq!(tree.get(key(3)), Some(&[3, 3][..]));
+        assert_eq!(tree.get(key(42)), None);
+        assert_eq!(tree.insert(key(3), b"three"), Some(vec![3, 3]));
+
+        // The root depends only on the contents, not the order of insertion
+        let mut other = SparseMerkleTree::<Keccak256>::new();
+        other.insert(key(3), b"three");
+        for i in (0..10).rev().filter(|i| *i != 3) {
+            other.insert(key(i), &[i, i]);
+        }
+        assert_eq!(other.root(), tree.root());
+
+        // Deleting every key restores the empty tree
+        for i in 0..10 {
+            assert!(tree.delete(key(i)).is_some());
+        }
+        assert_eq!(tree.delete(key(0)), None);
+        assert_eq!(tree.root(), empty);
+        assert!(tree.nodes.is_empty());
+    }
+
+    #[test]
+    fn proves_inclusion_and_non_inclusion() {
+        let mut tree = SparseMerkleTree::<Keccak256>::new();
+        for i in 0..10 {
+            tree.insert(key(i), &[i]);
+        }
+        let root = tree.root();
+
+        for i in 0..10 {
+            let proof = tree.proof(key(i));
+            assert!(tree.verify(&proof, key(i), Some(&[i]), root));
+            assert!(!tree.verify(&proof, key(i), Some(&[i + 1]), root));
+            assert!(!tree.verify(&proof, key(i), None, root));
+
+            // Only populated neighbours are included
+            assert_eq!(proof.defaults().len(), 256);
+            assert!(proof.siblings().len() < 16);
+        }
+
+        let absent = key(42);
+        let proof = tree.proof(absent);
+        assert!(tree.verify(&proof, absent, None, root));
+        assert!(!tree.verify(&proof, absent, Some(&[42]), root));
+        assert!(!tree.verify(&proof, key(0), None, root));
+
+        // Tampered proofs are rejected
+        let mut tampered = tree.proof(key(1));
+        tampered.siblings.pop();
+        assert!(!tree.verify(&tampered, key(1), Some(&[1]), root));
+    }
+}
@@ -0,0 +1,512 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::{Debug, Display, Formatter};
+use core::str::FromStr;
+use zeroize::Zeroize;
+
+/// How a node left without a full group of siblings at the end of a layer is handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OddStrategy {
+    /// A lone node is promoted unchanged to the next layer, as in merkletreejs by default and
+    /// RFC 6962. Incomplete groups of several nodes are hashed as they are.
+    Promote,
+    /// The last node is repeated to complete its group before hashing, as in Bitcoin.
+    Duplicate,
+    /// The group is completed with zero hashes before hashing.
+    ZeroPad,
+}
+
+/// The leaf appended to pad the leaves of a tree to a power of two.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PaddingLeaf {
+    /// A hash of zero bytes.
+    Zero,
+    /// The supplied hash, which must be the length of a hash.
+    Custom(Vec<u8>),
+}
+
+/// A step normalizing leaf values before they are hashed, so values differing only in
+/// presentation, such as the casing of an address, are the same leaf. Steps apply only to values
+/// of the form they describe and leave other values unchanged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Canonicalization {
+    /// Removes leading and trailing whitespace from UTF-8 values.
+    Trim,
+    /// Normalizes UTF-8 values to Unicode normalization form C, with the `canonicalize-nfc`
+    /// feature.
+    #[cfg(feature = "canonicalize-nfc")]
+    Nfc,
+    /// Lowercases hex values, with or without a `0x` prefix.
+    LowercaseHex,
+    /// Rewrites 20 byte hex addresses in the mixed case checksum of EIP-55, with a `0x` prefix.
+    Eip55,
+}
+
+impl Canonicalization {
+    /// The name of the step in the string form of a spec.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Canonicalization::Trim => "trim",
+            #[cfg(feature = "canonicalize-nfc")]
+            Canonicalization::Nfc => "nfc",
+            Canonicalization::LowercaseHex => "lowercase",
+            Canonicalization::Eip55 => "eip55",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            Canonicalization::Trim,
+            #[cfg(feature = "canonicalize-nfc")]
+            Canonicalization::Nfc,
+            Canonicalization::LowercaseHex,
+            Canonicalization::Eip55,
+        ]
+        .into_iter()
+        .find(|step| step.name() == name)
+    }
+}
+
+/// A secret key under which leaves are hashed, which is cleared from memory when dropped and never
+/// printed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LeafKey(Vec<u8>);
+
+impl LeafKey {
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self(key.into())
+    }
+
+    /// Exposes the raw key, which should not be retained beyond immediate use.
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Debug for LeafKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "LeafKey([REDACTED])")
+    }
+}
+
+impl Drop for LeafKey {
+    fn drop(&mut self) {
+        self.0.zeroize()
+    }
+}
+
+/// The largest number of children per node, bounding the nodes hashed together.
+pub const MAX_ARITY: usize = 256;
+
+/// A complete description of how a tree is constructed, with a compact string form such as
+/// `keccak256/sorted/dup-odd/arity2/leaf-prefix00/node-prefix01/pad-zero/canon-trim+eip55`
+/// suitable for configs, CLIs and serialized files.
+///
+/// Segments after the hash function may appear in any order, and omitted segments take their
+/// default values: `sorted`, `promote-odd`, `arity2`, no leaf or node prefix and no padding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeSpec {
+    /// Name of the hash function, as given by `HashFunction::NAME`.
+    pub hash: String,
+    /// Whether leaf hashes are sorted before the tree is constructed.
+    pub sort_leaves: bool,
+    /// Whether each group of nodes is sorted before being hashed, making proofs position independent.
+    pub sort_pairs: bool,
+    pub odd_strategy: OddStrategy,
+    /// Number of children per node.
+    pub arity: usize,
+    /// Bytes prepended to each leaf value before it is hashed.
+    pub leaf_prefix: Vec<u8>,
+    /// Bytes prepended to each group of child nodes before it is hashed.
+    pub node_prefix: Vec<u8>,
+    /// The leaf padding the leaves to the next power of the arity, if padded.
+    pub padding: Option<PaddingLeaf>,
+    /// Whether duplicate leaves are removed, keeping the first occurrence of each.
+    pub deduplicate: bool,
+    /// Whether leaf values are hashed, rather than being supplied already hashed.
+    pub hash_leaves: bool,
+    /// The steps normalizing each leaf value before it is prefixed and hashed, in the order they
+    /// are applied.
+    pub canonicalize: Vec<Canonicalization>,
+    /// The key leaves are hashed under with `HashFunction::hash_keyed`, if keyed. The string form
+    /// holds only a `keyed` marker in place of the key, so the key must be supplied separately
+    /// with `parse_keyed` to parse it again.
+    pub leaf_key: Option<LeafKey>,
+}
+
+impl TreeSpec {
+    /// Creates a spec with the default settings for the supplied hash function name.
+    pub fn new(hash: &str) -> Self {
+        Self {
+            hash: hash.to_string(),
+            sort_leaves: true,
+            sort_pairs: true,
+            odd_strategy: OddStrategy::Promote,
+            arity: 2,
+            leaf_prefix: vec![],
+            node_prefix: vec![],
+            padding: None,
+            deduplicate: false,
+            hash_leaves: true,
+            canonicalize: vec![],
+            leaf_key: None,
+        }
+    }
+
+    /// Parses the string form of a spec, failing with `SpecError::MissingKey` if the spec is keyed.
+    pub fn parse(spec: &str) -> Result<Self, SpecError> {
+        TreeSpec::parse_with_key(spec, None)
+    }
+
+    /// Parses the string form of a keyed spec, supplying the key the string form omits. Specs which
+    /// are not keyed fail with `SpecError::UnexpectedKey`, rather than ignoring the key.
+    pub fn parse_keyed(spec: &str, key: LeafKey) -> Result<Self, SpecError> {
+        TreeSpec::parse_with_key(spec, Some(key))
+    }
+
+    /// Parses a spec, requiring a key exactly when the spec is keyed.
+    pub(crate) fn parse_with_key(spec: &str, key: Option<LeafKey>) -> Result<Self, SpecError> {
+        let mut segments = spec.split('/');
+        let hash = match segments.next() {
+            Some(hash) if !hash.is_empty() => hash,
+            _ => return Err(SpecError::MissingHash),
+        };
+
+        let mut result = TreeSpec::new(hash);
+        let mut seen = vec![];
+        for segment in segments {
+            // Determine the kind of segment so duplicates can be rejected
+            let kind = if segment.starts_with("arity") {
+                "arity"
+            } else if segment.starts_with("leaf-prefix") {
+                "leaf-prefix"
+            } else if segment.starts_with("node-prefix") {
+                "node-prefix"
+            } else if segment.starts_with("pad-") {
+                "pad"
+            } else if segment.starts_with("canon-") {
+                "canon"
+            } else if segment == "dedup" || segment == "prehashed" || segment == "keyed" {
+                segment
+            } else if segment.ends_with("-odd") {
+                "odd"
+            } else {
+                "sort"
+            };
+            if seen.contains(&kind) {
+                return Err(SpecError::DuplicateSegment(segment.to_string()));
+            }
+            seen.push(kind);
+
+            match segment {
+                "sorted" => (result.sort_leaves, result.sort_pairs) = (true, true),
+                "sorted-leaves" => (result.sort_leaves, result.sort_pairs) = (true, false),
+                "sorted-pairs" => (result.sort_leaves, result.sort_pairs) = (false, true),
+                "ordered" => (result.sort_leaves, result.sort_pairs) = (false, false),
+                "promote-odd" => result.odd_strategy = OddStrategy::Promote,
+                "dup-odd" => result.odd_strategy = OddStrategy::Duplicate,
+                "zero-odd" => result.odd_strategy = OddStrategy::ZeroPad,
+                _ if kind == "arity" => {
+                    result.arity = match segment["arity".len()..].parse() {
+                        Ok(arity) if (2..=MAX_ARITY).contains(&arity) => arity,
+                        _ => return Err(SpecError::InvalidArity(segment.to_string())),
+                    }
+                }
+                _ if kind == "leaf-prefix" => {
+                    result.leaf_prefix = hex::decode(&segment["leaf-prefix".len()..])
+                        .map_err(|_| SpecError::InvalidPrefix(segment.to_string()))?
+                }
+                _ if kind == "node-prefix" => {
+                    result.node_prefix = hex::decode(&segment["node-prefix".len()..])
+                        .map_err(|_| SpecError::InvalidPrefix(segment.to_string()))?
+                }
+                "pad-zero" => result.padding = Some(PaddingLeaf::Zero),
+                _ if segment.starts_with("pad-leaf") => {
+                    let leaf = hex::decode(&segment["pad-leaf".len()..])
+                        .map_err(|_| SpecError::InvalidPadding(segment.to_string()))?;
+                    result.padding = Some(PaddingLeaf::Custom(leaf))
+                }
+                "dedup" => result.deduplicate = true,
+                "prehashed" => result.hash_leaves = false,
+                "keyed" => match &key {
+                    Some(_) => result.leaf_key = key.clone(),
+                    None => return Err(SpecError::MissingKey),
+                },
+                _ if kind == "canon" => {
+                    result.canonicalize = segment["canon-".len()..]
+                        .split('+')
+                        .map(Canonicalization::from_name)
+                        .collect::<Option<_>>()
+                        .ok_or_else(|| SpecError::UnknownSegment(segment.to_string()))?
+                }
+                _ => return Err(SpecError::UnknownSegment(segment.to_string())),
+            }
+        }
+        if key.is_some() && result.leaf_key.is_none() {
+            return Err(SpecError::UnexpectedKey);
+        }
+
+        Ok(result)
+    }
+}
+
+impl Display for TreeSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let sort = match (self.sort_leaves, self.sort_pairs) {
+            (true, true) => "sorted",
+            (true, false) => "sorted-leaves",
+            (false, true) => "sorted-pairs",
+            (false, false) => "ordered",
+        };
+        let odd = match self.odd_strategy {
+            OddStrategy::Promote => "promote-odd",
+            OddStrategy::Duplicate => "dup-odd",
+            OddStrategy::ZeroPad => "zero-odd",
+        };
+        write!(f, "{}/{}/{}/arity{}", self.hash, sort, odd, self.arity)?;
+        if !self.leaf_prefix.is_empty() {
+            write!(f, "/leaf-prefix{}", hex::encode(&self.leaf_prefix))?;
+        }
+        if !self.node_prefix.is_empty() {
+            write!(f, "/node-prefix{}", hex::encode(&self.node_prefix))?;
+        }
+        match &self.padding {
+            Some(PaddingLeaf::Zero) => write!(f, "/pad-zero")?,
+            Some(PaddingLeaf::Custom(leaf)) => write!(f, "/pad-leaf{}", hex::encode(leaf))?,
+            None => {}
+        }
+        if self.deduplicate {
+            write!(f, "/dedup")?;
+        }
+        if !self.hash_leaves {
+            write!(f, "/prehashed")?;
+        }
+        if self.leaf_key.is_some() {
+            write!(f, "/keyed")?;
+        }
+        for (i, step) in self.canonicalize.iter().enumerate() {
+            let separator = if i == 0 { "/canon-" } else { "+" };
+            write!(f, "{}{}", separator, step.name())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TreeSpec {
+    type Err = SpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TreeSpec::parse(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpecError {
+    MissingHash,
+    /// The spec names a hash function the crate does not provide, or whose feature is disabled.
+    UnknownHash(String),
+    UnknownSegment(String),
+    DuplicateSegment(String),
+    InvalidArity(String),
+    InvalidPrefix(String),
+    /// The padding leaf is not valid hex or is not the length of a hash.
+    InvalidPadding(String),
+    /// The spec is keyed, but its key was not supplied.
+    MissingKey,
+    /// A key was supplied for a spec which is not keyed.
+    UnexpectedKey,
+    /// The spec names a different hash function to the one the tree is being built with.
+    HashMismatch {
+        spec: String,
+        supplied: String,
+    },
+}
+
+impl Display for SpecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SpecError::MissingHash => write!(f, "tree spec does not name a hash function"),
+            SpecError::UnknownHash(s) => write!(f, "unknown hash function '{}'", s),
+            SpecError::UnknownSegment(s) => write!(f, "unknown tree spec segment '{}'", s),
+            SpecError::DuplicateSegment(s) => write!(f, "duplicate tree spec segment '{}'", s),
+            SpecError::InvalidArity(s) => {
+                write!(f, "invalid arity '{}', must be from 2 to {}", s, MAX_ARITY)
+            }
+            SpecError::InvalidPrefix(s) => write!(f, "invalid hex prefix '{}'", s),
+            SpecError::InvalidPadding(s) => write!(f, "invalid padding leaf '{}'", s),
+            SpecError::MissingKey => write!(f, "tree spec is keyed but no key was supplied"),
+            SpecError::UnexpectedKey => {
+                write!(f, "key supplied for a tree spec which is not keyed")
+            }
+            SpecError::HashMismatch { spec, supplied } => write!(
+                f,
+                "tree spec requires hash function '{}' but '{}' was supplied",
+                spec, supplied
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SpecError {}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Canonicalization, LeafKey, OddStrategy, PaddingLeaf, SpecError, TreeSpec};
+
+    #[test]
+    fn parses() {
+        let spec = TreeSpec::parse("keccak256/sorted/dup-odd/arity2/leaf-prefix00").unwrap();
+        assert_eq!(spec.hash, "keccak256");
+        assert!(spec.sort_leaves && spec.sort_pairs);
+        assert_eq!(spec.odd_strategy, OddStrategy::Duplicate);
+        assert_eq!(spec.arity, 2);
+        assert_eq!(spec.leaf_prefix, vec![0]);
+        assert_eq!(
+            spec.to_string(),
+            "keccak256/sorted/dup-odd/arity2/leaf-prefix00"
+        );
+    }
+
+    #[test]
+    fn parses_node_prefix() {
+        let spec = TreeSpec::parse("keccak256/node-prefix01/leaf-prefix00").unwrap();
+        assert_eq!(
+            (spec.leaf_prefix.clone(), spec.node_prefix.clone()),
+            (vec![0], vec![1])
+        );
+        assert_eq!(
+            spec.to_string(),
+            "keccak256/sorted/promote-odd/arity2/leaf-prefix00/node-prefix01"
+        );
+        assert_eq!(
+            TreeSpec::parse("keccak256/node-prefixz"),
+            Err(SpecError::InvalidPrefix("node-prefixz".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_padding() {
+        let spec = TreeSpec::parse("keccak256/pad-zero").unwrap();
+        assert_eq!(spec.padding, Some(PaddingLeaf::Zero));
+        assert_eq!(
+            spec.to_string(),
+            "keccak256/sorted/promote-odd/arity2/pad-zero"
+        );
+        let spec = TreeSpec::parse("keccak256/pad-leafff00").unwrap();
+        assert_eq!(spec.padding, Some(PaddingLeaf::Custom(vec![0xff, 0])));
+        assert_eq!(TreeSpec::parse(&spec.to_string()).unwrap(), spec);
+        assert_eq!(
+            TreeSpec::parse("keccak256/pad-leafz"),
+            Err(SpecError::InvalidPadding("pad-leafz".to_string()))
+        );
+        assert_eq!(
+            TreeSpec::parse("keccak256/pad-zero/pad-leaf00"),
+            Err(SpecError::DuplicateSegment("pad-leaf00".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_leaf_handling() {
+        let spec = TreeSpec::parse("keccak256/prehashed/dedup").unwrap();
+        assert!(spec.deduplicate && !spec.hash_leaves);
+        assert_eq!(
+            spec.to_string(),
+            "keccak256/sorted/promote-odd/arity2/dedup/prehashed"
+        );
+        assert_eq!(TreeSpec::parse(&spec.to_string()).unwrap(), spec);
+        assert_eq!(
+            TreeSpec::parse("keccak256/dedup/sorted/dedup"),
+            Err(SpecError::DuplicateSegment("dedup".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_keyed() {
+        let mut spec = TreeSpec::new("keccak256");
+        spec.leaf_key = Some(LeafKey::new(b"salt".to_vec()));
+        assert_eq!(
+            spec.to_string(),
+            "keccak256/sorted/promote-odd/arity2/keyed"
+        );
+        assert_eq!(
+            TreeSpec::parse(&spec.to_string()),
+            Err(SpecError::MissingKey)
+        );
+        assert_eq!(
+            TreeSpec::parse_keyed(&spec.to_string(), LeafKey::new(b"salt".to_vec())),
+            Ok(spec)
+        );
+        assert_eq!(
+            TreeSpec::parse_keyed("keccak256/sorted", LeafKey::new(b"salt".to_vec())),
+            Err(SpecError::UnexpectedKey)
+        );
+    }
+
+    #[test]
+    fn parses_canonicalization() {
+        let spec = TreeSpec::parse("keccak256/canon-trim+eip55").unwrap();
+        assert_eq!(
+            spec.canonicalize,
+            [Canonicalization::Trim, Canonicalization::Eip55]
+        );
+        assert_eq!(
+            spec.to_string(),
+            "keccak256/sorted/promote-odd/arity2/canon-trim+eip55"
+        );
+        assert_eq!(
+            TreeSpec::parse("keccak256/canon-trim+upper"),
+            Err(SpecError::UnknownSegment("canon-trim+upper".to_string()))
+        );
+    }
+
+    #[test]
+    fn defaults_omitted_segments() {
+        let spec: TreeSpec = "keccak256/arity4/ordered".parse().unwrap();
+        assert_eq!(spec.arity, 4);
+        assert!(!spec.sort_leaves && !spec.sort_pairs);
+        assert_eq!(spec.odd_strategy, OddStrategy::Promote);
+        assert!(spec.leaf_prefix.is_empty());
+        assert_eq!(spec.to_string(), "keccak256/ordered/promote-odd/arity4");
+        assert_eq!(TreeSpec::parse(&spec.to_string()).unwrap(), spec);
+
+        let spec = TreeSpec::parse("keccak256/zero-odd").unwrap();
+        assert_eq!(spec.odd_strategy, OddStrategy::ZeroPad);
+        assert_eq!(spec.to_string(), "keccak256/sorted/zero-odd/arity2");
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        assert_eq!(TreeSpec::parse(""), Err(SpecError::MissingHash));
+        assert_eq!(
+            TreeSpec::parse("keccak256/shuffled"),
+            Err(SpecError::UnknownSegment("shuffled".to_string()))
+        );
+        assert_eq!(
+            TreeSpec::parse("keccak256/sorted/ordered"),
+            Err(SpecError::DuplicateSegment("ordered".to_string()))
+        );
+        assert_eq!(
+            TreeSpec::parse("keccak256/arity1"),
+            Err(SpecError::InvalidArity("arity1".to_string()))
+        );
+        assert_eq!(
+            TreeSpec::parse("keccak256/arity257"),
+            Err(SpecError::InvalidArity("arity257".to_string()))
+        );
+        assert_eq!(
+            TreeSpec::parse("keccak256/arity18446744073709551615"),
+            Err(SpecError::InvalidArity(
+                "arity18446744073709551615".to_string()
+            ))
+        );
+        assert_eq!(
+            TreeSpec::parse("keccak256/leaf-prefixzz"),
+            Err(SpecError::InvalidPrefix("leaf-prefixzz".to_string()))
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{HashFunction, MerkleProof, MerkleTree, Store};
+
+/// The sizes of a batch of proofs, for alerting when proofs grow after a tree is rebuilt, as when
+/// far more leaves were added than expected or the tree is no longer built with the same spec.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProofStats {
+    /// The number of proofs.
+    pub count: usize,
+    /// The fewest bytes of sibling hashes in a proof, as given by `MerkleProof::byte_len`.
+    pub min_bytes: usize,
+    pub max_bytes: usize,
+    pub mean_bytes: f64,
+    /// The fewest steps from a leaf to the root, as given by `MerkleProof::depth`.
+    pub min_depth: usize,
+    pub max_depth: usize,
+    pub mean_depth: f64,
+}
+
+impl ProofStats {
+    /// Aggregates the sizes of a batch of proofs, all of which are zero for an empty batch.
+    pub fn from_proofs<'a, T: HashFunction + 'a>(
+        proofs: impl IntoIterator<Item = &'a MerkleProof<T>>,
+    ) -> Self {
+        let mut stats = ProofStats {
+            min_bytes: usize::MAX,
+            min_depth: usize::MAX,
+            ..Default::default()
+        };
+        let (mut bytes, mut depth) = (0, 0);
+        for proof in proofs {
+            stats.add(proof.byte_len(), proof.depth());
+            bytes += proof.byte_len();
+            depth += proof.depth();
+        }
+        if stats.count == 0 {
+            return Self::default();
+        }
+        stats.mean_bytes = bytes as f64 / stats.count as f64;
+        stats.mean_depth = depth as f64 / stats.count as f64;
+        stats
+    }
+
+    fn add(&mut self, bytes: usize, depth: usize) {
+        self.count += 1;
+        self.min_bytes = self.min_bytes.min(bytes);
+        self.max_bytes = self.max_bytes.max(bytes);
+        self.min_depth = self.min_depth.min(depth);
+        self.max_depth = self.max_depth.max(depth);
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// The sizes of the proofs of every leaf.
+    pub fn proof_stats(&self) -> ProofStats {
+        let proofs: Vec<MerkleProof<T>> = self.proofs().map(|(_, proof)| proof).collect();
+        ProofStats::from_proofs(&proofs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleProof, MerkleTree, ProofStats};
+
+    #[test]
+    fn aggregates_proof_sizes() {
+        let values: Vec<[u8; 1]> = (0..5).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let tree = MerkleTree::<Keccak256>::new(&leaves);
+        let stats = tree.proof_stats();
+
+        // Four leaves have three siblings, while the promoted fifth has one
+        assert_eq!((stats.count, stats.min_depth, stats.max_depth), (5, 1, 3));
+        assert_eq!((stats.min_bytes, stats.max_bytes), (32, 96));
+        assert_eq!(stats.mean_depth, 13.0 / 5.0);
+        assert_eq!(stats.mean_bytes, 32.0 * 13.0 / 5.0);
+        assert_eq!(
+            ProofStats::from_proofs::<Keccak256>(&Vec::<MerkleProof<Keccak256>>::new()),
+            ProofStats::default()
+        );
+    }
+}
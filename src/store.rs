@@ -0,0 +1,846 @@
+#[cfg(feature = "std")]
+use crate::{Bytes, MerkleTreeBuilder};
+use crate::{HashFunction, MerkleTree, TreeSpec};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+#[cfg(feature = "std")]
+use std::io::{Error, ErrorKind};
+
+/// The error of a write to a store, which is an I/O error where the standard library is
+/// available.
+#[cfg(feature = "std")]
+pub type StoreError = std::io::Error;
+
+/// The error of a write to a store. Without the standard library nodes are only held in memory,
+/// so a write can only fail by being beyond the end of a layer.
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoreError {
+    pub layer: usize,
+    pub index: usize,
+}
+
+/// Storage for the nodes of a tree, addressed by layer and by index within the layer, so trees
+/// too large to hold in memory can be kept elsewhere.
+pub trait Store<H> {
+    /// The number of layers, including the leaves.
+    fn layers(&self) -> usize;
+
+    /// The number of nodes in a layer, which is zero for layers beyond the last.
+    fn layer_len(&self, layer: usize) -> usize;
+
+    /// The node at `index` of a layer, or `None` if there is none. Stores which can fail to read
+    /// a node, such as `SledStore`, also return `None` on failure and report the error otherwise.
+    fn get(&self, layer: usize, index: usize) -> Option<H>;
+
+    /// Sets the node at `index`, which may be the length of the layer to append to it. Likewise
+    /// `layer` may be the number of layers to add a layer.
+    fn put(&mut self, layer: usize, index: usize, hash: H) -> Result<(), StoreError>;
+
+    /// Hints that the nodes at `indices` of a layer, which are sorted, will be read soon, so a
+    /// store backed by slow storage can start loading them. The default does nothing.
+    fn prefetch(&self, layer: usize, indices: &[usize]) -> Result<(), StoreError> {
+        let _ = (layer, indices);
+        Ok(())
+    }
+}
+
+/// The default store, holding each layer in memory.
+pub type MemoryStore<H> = Vec<Vec<H>>;
+
+impl<H: Copy> Store<H> for Vec<Vec<H>> {
+    fn layers(&self) -> usize {
+        self.len()
+    }
+
+    fn layer_len(&self, layer: usize) -> usize {
+        self.as_slice().get(layer).map_or(0, |l| l.len())
+    }
+
+    fn get(&self, layer: usize, index: usize) -> Option<H> {
+        self.as_slice().get(layer)?.get(index).copied()
+    }
+
+    fn put(&mut self, layer: usize, index: usize, hash: H) -> Result<(), StoreError> {
+        if layer == self.len() {
+            self.push(vec![]);
+        }
+        match self.get_mut(layer) {
+            Some(nodes) if index < nodes.len() => nodes[index] = hash,
+            Some(nodes) if index == nodes.len() => nodes.push(hash),
+            _ => return Err(out_of_bounds(layer, index)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn out_of_bounds(layer: usize, index: usize) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("node {} of layer {} is out of bounds", index, layer),
+    )
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn out_of_bounds(layer: usize, index: usize) -> StoreError {
+    StoreError { layer, index }
+}
+
+/// The index of the last occurrence of a leaf within the store.
+pub(crate) fn position<H: PartialEq, S: Store<H>>(store: &S, leaf: H) -> Option<usize> {
+    (0..store.layer_len(0))
+        .rev()
+        .find(|i| store.get(0, *i).as_ref() == Some(&leaf))
+}
+
+#[cfg(feature = "std")]
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Builds a tree into an empty store, hashing leaves as they are read from `leaves` and then
+    /// computing each layer from the one below it within the store.
+    ///
+    /// Leaves are held in memory only if they must be sorted or deduplicated, so trees too large
+    /// for memory should be built with `sort_leaves(false)` from leaves which are already in the
+    /// required order.
+    pub fn build_in<S: Store<T::Hash>, L: AsRef<[u8]>>(
+        &self,
+        mut store: S,
+        leaves: impl IntoIterator<Item = L>,
+    ) -> std::io::Result<MerkleTree<T, S>> {
+        if store.layers() > 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "store is not empty"));
+        }
+
+        // Add leaves, sorting and deduplicating them if required
+        let hashes = MerkleTree::<T>::hash_batches(self.spec(), leaves);
+        if self.spec().sort_leaves {
+            let mut sorted: Vec<T::Hash> = hashes.collect();
+            sorted.sort();
+            if self.spec().deduplicate {
+                sorted.dedup();
+            }
+            for (index, hash) in sorted.into_iter().enumerate() {
+                store.put(0, index, hash)?;
+            }
+        } else {
+            let mut seen = BTreeSet::new();
+            let hashes = hashes.filter(|hash| !self.spec().deduplicate || seen.insert(*hash));
+            for (index, hash) in hashes.enumerate() {
+                store.put(0, index, hash)?;
+            }
+        }
+
+        let (spec, arity) = (self.spec(), self.spec().arity);
+        if let Some(padding) = MerkleTree::<T>::padding_leaf(spec) {
+            let len = store.layer_len(0);
+            for index in len..MerkleTree::<T>::padded_len(spec, len) {
+                store.put(0, index, padding)?;
+            }
+        }
+
+        // Build remaining layers
+        let mut layer = 0;
+        let mut group = Vec::with_capacity(arity);
+        while store.layer_len(layer) > 1 {
+            let len = store.layer_len(layer);
+            for (parent, start) in (0..len).step_by(arity).enumerate() {
+                group.clear();
+                group.extend(
+                    (start..len.min(start + arity))
+                        .map(|i| store.get(layer, i).unwrap_or_default()),
+                );
+                store.put(layer + 1, parent, MerkleTree::<T>::parent(spec, &group))?;
+            }
+            layer += 1;
+        }
+
+        Ok(MerkleTree::from_store(spec.clone(), store))
+    }
+}
+
+impl<T: HashFunction, S: Store<T::Hash>> MerkleTree<T, S> {
+    /// Creates a tree from a store which already holds its nodes, such as one reopened from disk.
+    pub fn from_store(spec: TreeSpec, store: S) -> Self {
+        Self {
+            layers: store,
+            spec,
+            #[cfg(feature = "std")]
+            subscribers: Default::default(),
+            #[cfg(feature = "std")]
+            history: None,
+            index: None,
+            inputs: None,
+            placeholders: vec![],
+            #[cfg(feature = "record")]
+            recorder: None,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.layers
+    }
+
+    pub fn into_store(self) -> S {
+        self.layers
+    }
+
+    /// The number of leaves in the tree, including any padding leaves.
+    pub fn leaf_count(&self) -> usize {
+        self.layers.layer_len(0)
+    }
+
+    /// The number of leaves in the tree, as `leaf_count`.
+    pub fn len(&self) -> usize {
+        self.leaf_count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaf_count() == 0
+    }
+
+    /// The number of layers above the leaves, being the length of a proof in a complete binary
+    /// tree.
+    pub fn depth(&self) -> usize {
+        self.layers.layers().saturating_sub(1)
+    }
+
+    /// The node at `index` within `layer`, where layer zero holds the leaves and the last layer
+    /// the root.
+    pub fn node(&self, layer: usize, index: usize) -> Option<T::Hash> {
+        self.layers.get(layer, index)
+    }
+
+    /// Appends a leaf within the store, writing only the path from the new leaf to the root. Trees
+    /// held in memory should use `push` instead, which also supports sorted and padded leaves.
+    ///
+    /// Appending to a tree with sorted or padded leaves fails, as would appending a duplicate leaf
+    /// to a deduplicated tree.
+    #[cfg(feature = "std")]
+    pub fn append(&mut self, leaf: &Bytes) -> std::io::Result<()> {
+        if self.spec.sort_leaves || self.spec.padding.is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "leaves can only be appended to trees with unsorted, unpadded leaves",
+            ));
+        }
+        let hash = MerkleTree::<T>::try_hash_leaf(&self.spec, leaf)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        if self.spec.deduplicate && position(&self.layers, hash).is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "leaf is already in the tree",
+            ));
+        }
+
+        let arity = self.spec.arity;
+        let mut index = self.layers.layer_len(0);
+        self.layers.put(0, index, hash)?;
+        if let Some(leaves) = &mut self.index {
+            leaves.insert(hash, index);
+        }
+        let mut layer = 0;
+        let mut group = Vec::with_capacity(arity);
+        while self.layers.layer_len(layer) > 1 {
+            let start = index - index % arity;
+            let end = self.layers.layer_len(layer).min(start + arity);
+            group.clear();
+            group.extend((start..end).map(|i| self.layers.get(layer, i).unwrap_or_default()));
+            index /= arity;
+            let parent = MerkleTree::<T>::parent(&self.spec, &group);
+            self.layers.put(layer + 1, index, parent)?;
+            layer += 1;
+        }
+        Ok(())
+    }
+}
+
+/// A store holding each layer in a memory-mapped file within a directory, so only the pages of
+/// the layers being read or written need to be resident.
+#[cfg(feature = "mmap")]
+mod mmap {
+    use super::{out_of_bounds, Store};
+    #[cfg(unix)]
+    use memmap2::Advice;
+    use memmap2::MmapMut;
+    use std::fs::{File, OpenOptions};
+    use std::io::{Error, ErrorKind};
+    use std::marker::PhantomData;
+    use std::path::{Path, PathBuf};
+
+    /// Each layer file starts with the number of nodes it holds, followed by the nodes themselves
+    /// and any capacity reserved for further nodes.
+    const HEADER: usize = 8;
+    /// The number of nodes reserved when a layer is created.
+    const CAPACITY: usize = 1024;
+
+    pub struct MmapStore<H> {
+        directory: PathBuf,
+        layers: Vec<Layer>,
+        size: usize,
+        phantom: PhantomData<H>,
+    }
+
+    struct Layer {
+        file: File,
+        map: MmapMut,
+        len: usize,
+    }
+
+    impl<H: Default + AsRef<[u8]>> MmapStore<H> {
+        /// Creates an empty store within `directory`, replacing any store it already holds.
+        pub fn create(directory: impl AsRef<Path>) -> std::io::Result<Self> {
+            let directory = directory.as_ref().to_path_buf();
+            std::fs::create_dir_all(&directory)?;
+            let store = Self::empty(directory);
+            for layer in 0.. {
+                match std::fs::remove_file(store.path(layer)) {
+                    Err(e) if e.kind() == ErrorKind::NotFound => break,
+                    result => result?,
+                }
+            }
+            Ok(store)
+        }
+
+        /// Opens the store previously created within `directory`.
+        pub fn open(directory: impl AsRef<Path>) -> std::io::Result<Self> {
+            let mut store = Self::empty(directory.as_ref().to_path_buf());
+            loop {
+                let file = match OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(store.path(store.layers.len()))
+                {
+                    Ok(file) => file,
+                    Err(e) if e.kind() == ErrorKind::NotFound => break,
+                    Err(e) => return Err(e),
+                };
+
+                let map = map(&file)?;
+                let len = match map.get(..HEADER) {
+                    Some(header) => {
+                        u64::from_le_bytes(header.try_into().unwrap_or_default()) as usize
+                    }
+                    None => 0,
+                };
+                if map.len() < HEADER + len * store.size {
+                    return Err(Error::new(ErrorKind::InvalidData, "truncated layer file"));
+                }
+                store.layers.push(Layer { file, map, len });
+            }
+            Ok(store)
+        }
+
+        /// Flushes outstanding changes to disk.
+        pub fn flush(&self) -> std::io::Result<()> {
+            for layer in &self.layers {
+                layer.map.flush()?;
+            }
+            Ok(())
+        }
+
+        fn empty(directory: PathBuf) -> Self {
+            Self {
+                directory,
+                layers: vec![],
+                size: H::default().as_ref().len(),
+                phantom: PhantomData,
+            }
+        }
+
+        fn path(&self, layer: usize) -> PathBuf {
+            self.directory.join(format!("layer{}", layer))
+        }
+
+        fn add_layer(&mut self) -> std::io::Result<()> {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(self.path(self.layers.len()))?;
+            file.set_len((HEADER + CAPACITY * self.size) as u64)?;
+            let map = map(&file)?;
+            self.layers.push(Layer { file, map, len: 0 });
+            Ok(())
+        }
+    }
+
+    impl<H> Store<H> for MmapStore<H>
+    where
+        H: Default + AsRef<[u8]> + TryFrom<Vec<u8>>,
+    {
+        fn layers(&self) -> usize {
+            self.layers.len()
+        }
+
+        fn layer_len(&self, layer: usize) -> usize {
+            self.layers.get(layer).map_or(0, |l| l.len)
+        }
+
+        fn get(&self, layer: usize, index: usize) -> Option<H> {
+            let layer = self.layers.get(layer)?;
+            if index >= layer.len {
+                return None;
+            }
+            let offset = HEADER + index * self.size;
+            H::try_from(layer.map[offset..offset + self.size].to_vec()).ok()
+        }
+
+        fn put(&mut self, layer: usize, index: usize, hash: H) -> std::io::Result<()> {
+            if layer == self.layers.len() {
+                self.add_layer()?;
+            }
+            let size = self.size;
+            let nodes = match self.layers.get_mut(layer) {
+                Some(nodes) if index <= nodes.len => nodes,
+                _ => return Err(out_of_bounds(layer, index)),
+            };
+
+            if index == nodes.len {
+                // Double the capacity of a full layer, remapping the enlarged file
+                if HEADER + (index + 1) * size > nodes.map.len() {
+                    nodes.map.flush()?;
+                    nodes.file.set_len((HEADER + 2 * index * size) as u64)?;
+                    nodes.map = map(&nodes.file)?;
+                }
+                nodes.len += 1;
+                nodes.map[..HEADER].copy_from_slice(&(nodes.len as u64).to_le_bytes());
+            }
+
+            let offset = HEADER + index * size;
+            nodes.map[offset..offset + size].copy_from_slice(hash.as_ref());
+            Ok(())
+        }
+
+        /// Advises the kernel to read ahead the pages holding each run of consecutive nodes,
+        /// returning without waiting for them to be read.
+        #[cfg(unix)]
+        fn prefetch(&self, layer: usize, indices: &[usize]) -> std::io::Result<()> {
+            let layer = match self.layers.get(layer) {
+                Some(layer) => layer,
+                None => return Ok(()),
+            };
+            let indices: Vec<usize> = indices.iter().copied().filter(|i| *i < layer.len).collect();
+            for run in indices.chunk_by(|a, b| *b <= a + 1) {
+                let offset = HEADER + run[0] * self.size;
+                let len = (run[run.len() - 1] + 1 - run[0]) * self.size;
+                layer.map.advise_range(Advice::WillNeed, offset, len)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn map(file: &File) -> std::io::Result<MmapMut> {
+        // Safety: layer files are private to the store, which holds the only mapping of each, so
+        // they are not modified while mapped other than through the mapping itself.
+        unsafe { MmapMut::map_mut(file) }
+    }
+}
+
+#[cfg(feature = "mmap")]
+pub use mmap::MmapStore;
+
+/// A store holding nodes within a sled database, so a tree survives restarts of the process
+/// appending to it. Writes are batched in memory until flushed, and each flush is applied
+/// atomically, so a tree recovered after a crash is the tree as of its last flush.
+#[cfg(feature = "sled")]
+mod sled {
+    use super::{out_of_bounds, Store};
+    use crate::{HashFunction, LeafKey, MerkleTree, MerkleTreeBuilder, TreeSpec};
+    use std::collections::BTreeMap;
+    use std::io::{Error, ErrorKind};
+    use std::marker::PhantomData;
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    /// The key holding the spec of the tree within the database.
+    const SPEC: &[u8] = b"spec";
+
+    /// Reads of a node which fail, whether by an I/O error or a malformed node, are returned by
+    /// `get` as a missing node, as `Store` requires. The first such error is kept until taken by
+    /// `take_error`, and fails the next flush, so nodes computed from an unreadable node are never
+    /// made durable.
+    pub struct SledStore<H> {
+        db: ::sled::Db,
+        lengths: Vec<usize>,
+        /// Nodes written since the last flush.
+        pending: BTreeMap<(usize, usize), H>,
+        /// The first error reading a node since it was last taken.
+        error: Mutex<Option<Error>>,
+        phantom: PhantomData<H>,
+    }
+
+    impl<H: Copy + AsRef<[u8]>> SledStore<H> {
+        /// Opens the store within the database at `path`, creating it if it does not exist.
+        pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+            let db = ::sled::open(path)?;
+            let mut lengths = vec![];
+            while let Some(len) = db.get(length_key(lengths.len()))? {
+                let len = len
+                    .as_ref()
+                    .try_into()
+                    .map_err(|_| Error::new(ErrorKind::InvalidData, "malformed layer length"))?;
+                lengths.push(u64::from_be_bytes(len) as usize);
+            }
+            Ok(Self {
+                db,
+                lengths,
+                pending: BTreeMap::new(),
+                error: Mutex::new(None),
+                phantom: PhantomData,
+            })
+        }
+
+        /// Writes the nodes put since the last flush to disk as a single batch, returning once
+        /// they are durable.
+        pub fn flush(&mut self) -> std::io::Result<()> {
+            self.apply()?;
+            self.db.flush()?;
+            Ok(())
+        }
+
+        /// Writes the nodes put since the last flush as `flush` does, without blocking while they
+        /// are made durable.
+        pub async fn flush_async(&mut self) -> std::io::Result<()> {
+            self.apply()?;
+            self.db.flush_async().await?;
+            Ok(())
+        }
+
+        /// The number of nodes put since the last flush.
+        pub fn pending(&self) -> usize {
+            self.pending.len()
+        }
+
+        /// Takes the first error reading a node since the error was last taken, if any.
+        pub fn take_error(&self) -> Option<Error> {
+            self.error.lock().map_or(None, |mut error| error.take())
+        }
+
+        /// Keeps the error of a failed read, unless an earlier error is yet to be taken.
+        fn record_error(&self, error: Error) {
+            if let Ok(mut kept) = self.error.lock() {
+                kept.get_or_insert(error);
+            }
+        }
+
+        fn apply(&mut self) -> std::io::Result<()> {
+            if let Some(error) = self.take_error() {
+                return Err(error);
+            }
+            if self.pending.is_empty() {
+                return Ok(());
+            }
+            let mut batch = ::sled::Batch::default();
+            for ((layer, index), hash) in std::mem::take(&mut self.pending) {
+                batch.insert(&node_key(layer, index)[..], hash.as_ref());
+            }
+            for (layer, len) in self.lengths.iter().enumerate() {
+                batch.insert(&length_key(layer)[..], &(*len as u64).to_be_bytes()[..]);
+            }
+            self.db.apply_batch(batch)?;
+            Ok(())
+        }
+
+        /// The spec the tree was persisted with, supplying the key of a keyed spec, which is not
+        /// persisted.
+        fn spec(&self, key: Option<LeafKey>) -> std::io::Result<Option<TreeSpec>> {
+            match self.db.get(SPEC)? {
+                Some(spec) => std::str::from_utf8(&spec)
+                    .map_err(|_| Error::new(ErrorKind::InvalidData, "malformed tree spec"))
+                    .and_then(|spec| {
+                        TreeSpec::parse_with_key(spec, key)
+                            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+                    })
+                    .map(Some),
+                None => Ok(None),
+            }
+        }
+    }
+
+    impl<H> Store<H> for SledStore<H>
+    where
+        H: Copy + AsRef<[u8]> + TryFrom<Vec<u8>>,
+    {
+        fn layers(&self) -> usize {
+            self.lengths.len()
+        }
+
+        fn layer_len(&self, layer: usize) -> usize {
+            self.lengths.get(layer).copied().unwrap_or_default()
+        }
+
+        fn get(&self, layer: usize, index: usize) -> Option<H> {
+            if index >= self.layer_len(layer) {
+                return None;
+            }
+            if let Some(hash) = self.pending.get(&(layer, index)) {
+                return Some(*hash);
+            }
+            let read = match self.db.get(node_key(layer, index)) {
+                Ok(Some(hash)) => H::try_from(hash.to_vec())
+                    .map_err(|_| Error::new(ErrorKind::InvalidData, "malformed node")),
+                Ok(None) => Err(Error::new(ErrorKind::NotFound, "node is missing")),
+                Err(e) => Err(e.into()),
+            };
+            read.map_err(|e| self.record_error(e)).ok()
+        }
+
+        fn put(&mut self, layer: usize, index: usize, hash: H) -> std::io::Result<()> {
+            if layer == self.lengths.len() {
+                self.lengths.push(0);
+            }
+            match self.lengths.get_mut(layer) {
+                Some(len) if index < *len => {}
+                Some(len) if index == *len => *len += 1,
+                _ => return Err(out_of_bounds(layer, index)),
+            }
+            self.pending.insert((layer, index), hash);
+            Ok(())
+        }
+    }
+
+    impl<T: HashFunction> MerkleTreeBuilder<T> {
+        /// Opens the tree persisted in the database at `path`, or creates an empty tree as
+        /// configured if there is none. Trees persisted with another spec fail to open, as do
+        /// trees persisted with a keyed spec unless the builder has a key, which is not persisted.
+        pub fn open(
+            &self,
+            path: impl AsRef<Path>,
+        ) -> std::io::Result<MerkleTree<T, SledStore<T::Hash>>> {
+            let store = SledStore::open(path)?;
+            match store.spec(self.spec().leaf_key.clone())? {
+                Some(spec) if spec != *self.spec() => Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("tree was persisted with spec {}", spec),
+                )),
+                Some(_) => Ok(MerkleTree::from_store(self.spec().clone(), store)),
+                None => {
+                    store.db.insert(SPEC, self.spec().to_string().as_bytes())?;
+                    Ok(MerkleTree::from_store(self.spec().clone(), store))
+                }
+            }
+        }
+    }
+
+    impl<T: HashFunction> MerkleTree<T, SledStore<T::Hash>> {
+        /// Opens the tree persisted in the database at `path` with the spec it was persisted
+        /// with, or creates an empty tree with the default spec if there is none. Trees persisted
+        /// with a keyed spec fail to open, and are opened by a builder with the key instead.
+        pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+            let store = SledStore::open(path)?;
+            let builder = match store.spec(None)? {
+                Some(spec) => MerkleTreeBuilder::<T>::from_spec(&spec)
+                    .map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+                None => MerkleTreeBuilder::<T>::new(),
+            };
+            store
+                .db
+                .insert(SPEC, builder.spec().to_string().as_bytes())?;
+            Ok(MerkleTree::from_store(builder.spec().clone(), store))
+        }
+
+        /// Writes the nodes changed since the last flush to disk, returning once they are durable.
+        pub fn flush(&mut self) -> std::io::Result<()> {
+            self.layers.flush()
+        }
+    }
+
+    fn node_key(layer: usize, index: usize) -> [u8; 13] {
+        let mut key = [b'n'; 13];
+        key[1..5].copy_from_slice(&(layer as u32).to_be_bytes());
+        key[5..].copy_from_slice(&(index as u64).to_be_bytes());
+        key
+    }
+
+    fn length_key(layer: usize) -> [u8; 5] {
+        let mut key = [b'l'; 5];
+        key[1..].copy_from_slice(&(layer as u32).to_be_bytes());
+        key
+    }
+}
+
+#[cfg(feature = "sled")]
+pub use self::sled::SledStore;
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Keccak256, MemoryStore, MerkleTreeBuilder, OddStrategy, Store};
+
+    fn values(count: u32) -> Vec<[u8; 4]> {
+        (0..count).map(|i| i.to_be_bytes()).collect()
+    }
+
+    /// Opens a sled database until it fails other than by its lock, which the threads of a
+    /// dropped handle may hold for a while.
+    #[cfg(feature = "sled")]
+    fn reopen<T>(open: impl Fn() -> std::io::Result<T>) -> std::io::Result<T> {
+        for _ in 0..100 {
+            match open() {
+                Err(e) if e.kind() == std::io::ErrorKind::Other => {
+                    std::thread::sleep(std::time::Duration::from_millis(10))
+                }
+                result => return result,
+            }
+        }
+        open()
+    }
+
+    #[test]
+    fn builds_in_memory_store() {
+        for builder in [
+            MerkleTreeBuilder::<Keccak256>::new(),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .sort_pairs(false)
+                .odd_strategy(OddStrategy::Duplicate)
+                .arity(3),
+        ] {
+            for count in [0, 1, 2, 7, 16] {
+                let values = values(count);
+                let leaves: Vec<&[u8]> = values.iter().map(|v| &v[..]).collect();
+                let expected = builder.build(&leaves);
+                let tree = builder.build_in(MemoryStore::new(), &values).unwrap();
+
+                assert_eq!(tree.root(), expected.root());
+                assert_eq!(tree.leaf_count(), count as usize);
+                for leaf in expected.leaves() {
+                    assert_eq!(tree.proof(*leaf).hashes(), expected.proof(*leaf).hashes());
+                }
+            }
+        }
+
+        let mut store = MemoryStore::<[u8; 32]>::new();
+        store.put(0, 0, [0; 32]).unwrap();
+        assert!(store.put(0, 2, [0; 32]).is_err());
+        assert!(store.put(2, 0, [0; 32]).is_err());
+        assert!(MerkleTreeBuilder::<Keccak256>::new()
+            .build_in(store, values(2))
+            .is_err());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn builds_memory_mapped_store() {
+        use crate::{MerkleTree, MmapStore};
+
+        let directory = std::env::temp_dir().join(format!("merkle-tree-{}", std::process::id()));
+        let values = values(5000);
+        let builder = MerkleTreeBuilder::<Keccak256>::new().sort_leaves(false);
+        let expected = builder.build(&values.iter().map(|v| &v[..]).collect::<Vec<_>>());
+
+        let store = MmapStore::create(&directory).unwrap();
+        let tree = builder.build_in(store, &values).unwrap();
+        assert_eq!(tree.root(), expected.root());
+        tree.store().flush().unwrap();
+        drop(tree);
+
+        // Reopened stores can be queried without rebuilding
+        let store = MmapStore::open(&directory).unwrap();
+        let tree = MerkleTree::<Keccak256, _>::from_store(builder.spec().clone(), store);
+        assert_eq!(tree.leaf_count(), 5000);
+        assert_eq!(tree.root(), expected.root());
+        let leaf = expected.leaves()[1234];
+        assert!(tree.verify(tree.proof(leaf), leaf, tree.root()));
+
+        // Creating a store replaces the previous one
+        let store = MmapStore::<[u8; 32]>::create(&directory).unwrap();
+        assert_eq!(store.layers(), 0);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn recovers_sled_store() {
+        use crate::{Bytes, MerkleTree, SledStore};
+
+        let path = std::env::temp_dir().join(format!("merkle-tree-sled-{}", std::process::id()));
+        let values = values(300);
+        for builder in [
+            MerkleTreeBuilder::<Keccak256>::new().sort_leaves(false),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .sort_pairs(false)
+                .odd_strategy(OddStrategy::Duplicate)
+                .arity(3),
+        ] {
+            let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+            let expected = builder.build(&leaves);
+            let mut tree = builder.open(&path).unwrap();
+            for leaf in &leaves[..200] {
+                tree.append(leaf).unwrap();
+            }
+            tree.flush().unwrap();
+
+            // Nodes appended after the last flush are lost, as if the process crashed
+            for leaf in &leaves[200..250] {
+                tree.append(leaf).unwrap();
+            }
+            assert!(tree.store().pending() > 0);
+            drop(tree);
+
+            let mut tree = reopen(|| MerkleTree::<Keccak256, SledStore<_>>::open(&path)).unwrap();
+            assert_eq!(tree.spec(), builder.spec());
+            assert_eq!(tree.leaf_count(), 200);
+            for leaf in &leaves[200..] {
+                tree.append(leaf).unwrap();
+            }
+            assert_eq!(tree.root(), expected.root());
+            let leaf = expected.leaves()[123];
+            assert!(tree.verify(tree.proof(leaf), leaf, tree.root()));
+            drop(tree);
+
+            assert!(reopen(|| MerkleTreeBuilder::<Keccak256>::new().open(&path)).is_err());
+            std::fs::remove_dir_all(&path).unwrap();
+        }
+
+        // Keyed trees reopen only with their key
+        let builder = MerkleTreeBuilder::<Keccak256>::new()
+            .sort_leaves(false)
+            .salt(b"salt");
+        let mut tree = builder.open(&path).unwrap();
+        tree.append(b"a").unwrap();
+        tree.flush().unwrap();
+        let root = tree.root();
+        drop(tree);
+        assert!(reopen(|| MerkleTree::<Keccak256, SledStore<_>>::open(&path)).is_err());
+        assert_eq!(reopen(|| builder.open(&path)).unwrap().root(), root);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn reports_sled_read_errors() {
+        use crate::{MerkleTree, SledStore, Store};
+
+        let path =
+            std::env::temp_dir().join(format!("merkle-tree-sled-errors-{}", std::process::id()));
+        let builder = MerkleTreeBuilder::<Keccak256>::new().sort_leaves(false);
+        let mut tree = builder.open(&path).unwrap();
+        tree.append(b"a").unwrap();
+        tree.append(b"b").unwrap();
+        tree.flush().unwrap();
+        drop(tree);
+
+        // Corrupt the first leaf, which is read as missing with the error kept
+        let db = reopen(|| Ok(::sled::open(&path)?)).unwrap();
+        let mut key = [b'n'; 13];
+        key[1..].fill(0);
+        db.insert(key, &[0u8; 3][..]).unwrap();
+        db.flush().unwrap();
+        drop(db);
+
+        let mut tree = reopen(|| MerkleTree::<Keccak256, SledStore<_>>::open(&path)).unwrap();
+        assert_eq!(tree.store().get(0, 0), None);
+        assert!(tree.store().get(0, 1).is_some());
+        tree.append(b"c").unwrap();
+        let error = tree.flush().unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(tree.store().take_error().is_none());
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
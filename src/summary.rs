@@ -0,0 +1,126 @@
+use crate::{Bytes, HashFunction, MerkleTree, MerkleTreeBuilder, OddStrategy, TreeSpec};
+use std::time::{Duration, Instant};
+
+/// The cost of building a tree, for tracking build regressions as datasets grow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildSummary {
+    /// Total time taken to build the tree.
+    pub wall_time: Duration,
+    /// Time taken to compute each layer, starting with hashing and sorting the leaves.
+    pub layer_timings: Vec<Duration>,
+    /// Number of bytes passed to the hash function.
+    pub bytes_hashed: usize,
+    /// Estimate of the bytes held by the nodes and leaf index of the tree once built, which is
+    /// when memory use peaks.
+    pub peak_memory: usize,
+    /// Number of leaves which duplicate an earlier leaf.
+    pub duplicates: usize,
+}
+
+impl<T: HashFunction> MerkleTreeBuilder<T> {
+    /// Builds a tree as `build` does, summarising the cost of doing so.
+    pub fn build_with_summary(&self, leaves: &[&Bytes]) -> (MerkleTree<T>, BuildSummary) {
+        let spec = self.spec();
+        let started = Instant::now();
+        let mut hashes: Vec<T::Hash> = leaves.iter().map(|l| self.hash_leaf(l)).collect();
+        let inputs = spec.deduplicate.then(|| hashes.clone());
+        if spec.sort_leaves {
+            hashes.sort();
+        }
+        if spec.deduplicate {
+            MerkleTree::<T>::deduplicate(spec, &mut hashes);
+        }
+        MerkleTree::<T>::pad_leaves(spec, &mut hashes);
+        let mut bytes_hashed = leaves
+            .iter()
+            .filter(|_| spec.hash_leaves)
+            .map(|l| spec.leaf_prefix.len() + l.len())
+            .sum();
+        let mut layer_timings = vec![started.elapsed()];
+
+        // Build each layer as the tree would, timing each in turn
+        let size = T::Hash::default().as_ref().len();
+        let mut layers = vec![hashes];
+        while layers[layers.len() - 1].len() > 1 {
+            let layer_started = Instant::now();
+            let parents = layers[layers.len() - 1]
+                .chunks(spec.arity)
+                .map(|group| {
+                    bytes_hashed += hashed(spec, group.len(), size);
+                    MerkleTree::<T>::parent(spec, group)
+                })
+                .collect();
+            layers.push(parents);
+            layer_timings.push(layer_started.elapsed());
+        }
+
+        let mut sorted = layers[0].clone();
+        if !spec.sort_leaves {
+            sorted.sort();
+        }
+        let duplicates = sorted.windows(2).filter(|w| w[0] == w[1]).count();
+
+        let nodes: usize = layers.iter().map(|l| l.len()).sum();
+        let peak_memory = nodes * size + layers[0].len() * std::mem::size_of::<(T::Hash, usize)>();
+
+        // Adopt the computed layers rather than building them again
+        let mut tree = MerkleTree::from_layers(spec.clone(), layers);
+        tree.inputs = inputs;
+
+        let summary = BuildSummary {
+            wall_time: started.elapsed(),
+            layer_timings,
+            bytes_hashed,
+            peak_memory,
+            duplicates,
+        };
+        (tree, summary)
+    }
+}
+
+/// The number of bytes hashed to compute the parent of a group of `length` siblings.
+fn hashed(spec: &TreeSpec, length: usize, size: usize) -> usize {
+    match spec.odd_strategy {
+        OddStrategy::Promote if length == 1 => 0,
+        OddStrategy::Duplicate | OddStrategy::ZeroPad => spec.node_prefix.len() + spec.arity * size,
+        _ => spec.node_prefix.len() + length * size,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleTreeBuilder, OddStrategy};
+
+    #[test]
+    fn summarises_builds() {
+        let values: Vec<[u8; 1]> = [0, 1, 2, 3, 0].iter().map(|i| [*i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for builder in [
+            MerkleTreeBuilder::<Keccak256>::new(),
+            MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(false)
+                .sort_pairs(false),
+        ] {
+            let (tree, summary) = builder.build_with_summary(&leaves);
+            let expected = builder.build(&leaves);
+            assert_eq!(tree.root(), expected.root());
+            assert_eq!(tree.leaves(), expected.leaves());
+            for leaf in tree.leaves() {
+                assert_eq!(tree.index_of(*leaf), expected.index_of(*leaf));
+            }
+
+            // Five leaves of one byte, then pairs of 32 byte hashes with odd nodes promoted
+            assert_eq!(summary.bytes_hashed, 5 + 4 * 64);
+            assert_eq!(summary.layer_timings.len(), 4);
+            assert!(summary.wall_time >= summary.layer_timings.iter().sum());
+            assert_eq!(summary.duplicates, 1);
+            assert!(summary.peak_memory >= 11 * 32);
+        }
+
+        let (_, summary) = MerkleTreeBuilder::<Keccak256>::new()
+            .odd_strategy(OddStrategy::Duplicate)
+            .node_prefix(&[1])
+            .build_with_summary(&leaves);
+        assert_eq!(summary.bytes_hashed, 5 + 6 * 65);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{HashFunction, MerkleTree};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A tree frozen for sharing between threads, such as by a service which builds a tree once and
+/// serves proofs to many concurrent requests. Clones share the nodes of the tree through an `Arc`,
+/// so each request may hold its own handle without copying the tree.
+///
+/// The tree can no longer change, so reads take no locks: the leaves are indexed before the tree
+/// is frozen, and subscribers and any recorder, which would otherwise be shared between readers,
+/// are dropped. The read API of `MerkleTree` is available through `Deref`.
+pub struct SyncMerkleTree<T: HashFunction> {
+    tree: Arc<MerkleTree<T>>,
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Freezes the tree for sharing between threads.
+    pub fn freeze(mut self) -> SyncMerkleTree<T> {
+        if self.index.is_none() {
+            self.index_leaves();
+        }
+        self.subscribers = Default::default();
+        #[cfg(feature = "record")]
+        {
+            self.recorder = None;
+        }
+        SyncMerkleTree {
+            tree: Arc::new(self),
+        }
+    }
+}
+
+impl<T: HashFunction> SyncMerkleTree<T> {
+    /// The tree, if this is the only handle to it, so that it may be changed again. Otherwise the
+    /// handle is returned.
+    pub fn thaw(self) -> Result<MerkleTree<T>, Self> {
+        Arc::try_unwrap(self.tree).map_err(|tree| Self { tree })
+    }
+}
+
+impl<T: HashFunction> Clone for SyncMerkleTree<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tree: Arc::clone(&self.tree),
+        }
+    }
+}
+
+impl<T: HashFunction> Deref for SyncMerkleTree<T> {
+    type Target = MerkleTree<T>;
+
+    fn deref(&self) -> &MerkleTree<T> {
+        &self.tree
+    }
+}
+
+impl<T: HashFunction> From<MerkleTree<T>> for SyncMerkleTree<T> {
+    fn from(tree: MerkleTree<T>) -> Self {
+        tree.freeze()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, HashFunction, Keccak256, MerkleTree, SyncMerkleTree};
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn serves_proofs_across_threads() {
+        assert_send_sync::<MerkleTree<Keccak256>>();
+        assert_send_sync::<SyncMerkleTree<Keccak256>>();
+
+        let values: Vec<[u8; 2]> = (0..1000u16).map(|i| i.to_be_bytes()).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        let mut tree = MerkleTree::<Keccak256>::new(&leaves);
+        let _updates = tree.subscribe();
+        let shared = tree.freeze();
+        let root = shared.root();
+
+        let handles: Vec<_> = (0..8u16)
+            .map(|worker| {
+                let tree = shared.clone();
+                thread::spawn(move || {
+                    (worker..1000).step_by(8).all(|i| {
+                        let leaf = Keccak256::hash(&i.to_be_bytes());
+                        tree.verify(tree.proof(leaf), leaf, root)
+                    })
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap());
+        }
+
+        // The tree may be changed again once no other handle remains
+        let held = shared.clone();
+        let shared = shared.thaw().err().unwrap();
+        drop(held);
+        let mut tree = shared.thaw().ok().unwrap();
+        tree.push(b"a");
+        assert_ne!(tree.root(), root);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{Direction, Hash, HashFunction, MerkleProof, TreeSpec};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+/// The longest node prefix supported by `verify_fixed`.
+pub const MAX_FIXED_PREFIX: usize = 32;
+
+/// Verifies that a proof recomputes the root from the leaf for a tree with the supplied spec,
+/// without the tree itself. This is available without `std`, for verification within runtimes
+/// such as Substrate's.
+pub fn verify_proof<T: HashFunction>(
+    spec: &TreeSpec,
+    proof: &MerkleProof<T>,
+    leaf: T::Hash,
+    root: T::Hash,
+) -> bool {
+    proof.compute_root(spec, leaf) == Some(root)
+}
+
+impl<T: HashFunction> MerkleProof<T> {
+    /// The root recomputed from the leaf by the proof for a tree with the supplied spec, or `None`
+    /// if the proof is not well formed. Comparing the root with a trusted root verifies the proof.
+    ///
+    /// The proof must carry directions exactly when the spec has positional pairs, and each step
+    /// must have at least one sibling and fewer than the arity of the spec, so a proof of another
+    /// scheme is rejected rather than folded into a root.
+    pub fn compute_root(&self, spec: &TreeSpec, leaf: T::Hash) -> Option<T::Hash> {
+        // Each step of a proof contains a single sibling, unless the tree has a higher arity
+        if !self.is_well_formed() || self.directions().is_some() == spec.sort_pairs {
+            return None;
+        }
+        let counts = match self.sibling_counts() {
+            Some(counts) => counts.to_vec(),
+            None => vec![1; self.len()],
+        };
+        if counts
+            .iter()
+            .any(|count| *count == 0 || *count >= spec.arity)
+        {
+            return None;
+        }
+
+        let mut hash = leaf;
+        let mut offset = 0;
+        for count in counts {
+            let siblings = &self.hashes()[offset..offset + count];
+            let mut group = Vec::with_capacity(count + 1);
+
+            match self.directions() {
+                // Positional proof, so combine each node on the side it was recorded
+                Some(directions) => {
+                    let directions = &directions[offset..offset + count];
+                    let left = directions.iter().filter(|d| **d == Direction::Left).count();
+                    group.extend_from_slice(&siblings[..left]);
+                    group.push(hash);
+                    group.extend_from_slice(&siblings[left..]);
+                }
+                None => {
+                    group.extend_from_slice(siblings);
+                    group.push(hash);
+                    group.sort();
+                }
+            }
+
+            hash = hash_children::<T>(spec, &group);
+            offset += count;
+        }
+
+        Some(hash)
+    }
+}
+
+/// Verifies a binary proof of exactly `DEPTH` siblings of `N` bytes without allocating, for
+/// devices without a heap. For positional pairs, bit `i` of `left` is set when the `i`th sibling
+/// is on the left; `left` is ignored for sorted pairs.
+///
+/// Proofs with more than 64 positional siblings or node prefixes longer than `MAX_FIXED_PREFIX`
+/// are not supported, and fail verification. Proofs, leaves and roots must all be hashes of `N`
+/// bytes, so those of another hash function fail to compile.
+pub fn verify_fixed<T: HashFunction<Hash = Hash<N>>, const N: usize, const DEPTH: usize>(
+    node_prefix: &[u8],
+    sort_pairs: bool,
+    proof: &[Hash<N>; DEPTH],
+    left: u64,
+    leaf: Hash<N>,
+    root: Hash<N>,
+) -> bool {
+    if node_prefix.len() > MAX_FIXED_PREFIX || (!sort_pairs && DEPTH > 64) {
+        return false;
+    }
+
+    // Hashes are at most 32 bytes, as longer arrays are not `Default`
+    let mut buffer = [0u8; MAX_FIXED_PREFIX + 64];
+    let (start, length) = (node_prefix.len(), node_prefix.len() + 2 * N);
+    buffer[..start].copy_from_slice(node_prefix);
+    let mut hash = leaf;
+    for (i, sibling) in proof.iter().enumerate() {
+        let sibling_left = match sort_pairs {
+            true => *sibling < hash,
+            false => (left >> i) & 1 == 1,
+        };
+        let (a, b) = match sibling_left {
+            true => (sibling, &hash),
+            false => (&hash, sibling),
+        };
+        buffer[start..start + N].copy_from_slice(a);
+        buffer[start + N..length].copy_from_slice(b);
+        hash = T::hash(&buffer[..length]);
+    }
+    hash == root
+}
+
+/// Nodes are concatenated within a buffer of this many bytes on the stack where they fit, so
+/// hashing a group of children does not allocate.
+const STACK_BUFFER: usize = 256;
+
+/// Hashes a group of child nodes into their parent, prefixed as the spec requires.
+pub(crate) fn hash_children<T: HashFunction>(spec: &TreeSpec, nodes: &[T::Hash]) -> T::Hash {
+    hash_concatenated::<T>(&spec.node_prefix, nodes)
+}
+
+/// Hashes a group of nodes without the node prefix of a spec.
+#[cfg(any(feature = "std", test))]
+pub(crate) fn hash_nodes<T: HashFunction>(nodes: &[T::Hash]) -> T::Hash {
+    hash_concatenated::<T>(&[], nodes)
+}
+
+fn hash_concatenated<T: HashFunction>(prefix: &[u8], nodes: &[T::Hash]) -> T::Hash {
+    let length = prefix.len() + nodes.iter().map(|n| n.as_ref().len()).sum::<usize>();
+    if length > STACK_BUFFER {
+        let mut combined = Vec::with_capacity(length);
+        combined.extend_from_slice(prefix);
+        for node in nodes {
+            combined.extend_from_slice(node.as_ref());
+        }
+        return T::hash(&combined);
+    }
+
+    let mut buffer = [0u8; STACK_BUFFER];
+    buffer[..prefix.len()].copy_from_slice(prefix);
+    let mut offset = prefix.len();
+    for node in nodes {
+        let node = node.as_ref();
+        buffer[offset..offset + node.len()].copy_from_slice(node);
+        offset += node.len();
+    }
+    T::hash(&buffer[..length])
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::verify::{verify_fixed, verify_proof};
+    use crate::{Bytes, Direction, Keccak256, MerkleTreeBuilder};
+
+    #[test]
+    fn verifies_without_trees() {
+        let values: Vec<[u8; 1]> = (0..8).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for sorted in [true, false] {
+            let tree = MerkleTreeBuilder::<Keccak256>::new()
+                .sort_leaves(sorted)
+                .sort_pairs(sorted)
+                .domain_separation(true)
+                .build(&leaves);
+            let spec = tree.spec();
+            for leaf in tree.leaves() {
+                let proof = tree.proof(*leaf);
+                assert!(verify_proof(spec, &proof, *leaf, tree.root()));
+                assert!(!verify_proof(spec, &proof, [0; 32], tree.root()));
+
+                let left = proof
+                    .directions()
+                    .unwrap_or_default()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, d)| **d == Direction::Left)
+                    .fold(0, |left, (i, _)| left | 1 << i);
+                let hashes: &[[u8; 32]; 3] = proof.hashes().try_into().unwrap();
+                let verify = |left, leaf| {
+                    verify_fixed::<Keccak256, 32, 3>(&[1], sorted, hashes, left, leaf, tree.root())
+                };
+                assert!(verify(left, *leaf));
+                assert!(!verify(left, [0; 32]));
+                assert_eq!(verify(!left, *leaf), sorted);
+            }
+        }
+        assert!(!verify_fixed::<Keccak256, 32, 0>(
+            &[0; 33],
+            true,
+            &[],
+            0,
+            [0; 32],
+            [0; 32]
+        ));
+    }
+
+    #[cfg(feature = "ripemd160")]
+    #[test]
+    fn verifies_shorter_hashes_without_trees() {
+        use crate::Ripemd160;
+
+        let leaves: Vec<&Bytes> = ["a", "b", "c", "d"].iter().map(|x| x.as_bytes()).collect();
+        let tree = MerkleTreeBuilder::<Ripemd160>::new().build(&leaves);
+        let leaf = tree.leaves()[2];
+        let proof = tree.proof(leaf);
+        let hashes: &[[u8; 20]; 2] = proof.hashes().try_into().unwrap();
+        assert!(verify_fixed::<Ripemd160, 20, 2>(
+            &[],
+            true,
+            hashes,
+            0,
+            leaf,
+            tree.root()
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{Bytes, HashFunction, MerkleProof, MerkleTree};
+
+/// A tree recording a version on every append, update or removal, for audit logs whose clients
+/// may still hold proofs against older roots. Versions share their unchanged nodes, as with
+/// `MerkleTree::keep_history`, and old versions may be pruned once no longer needed.
+pub struct VersionedMerkleTree<T: HashFunction> {
+    tree: MerkleTree<T>,
+}
+
+impl<T: HashFunction> VersionedMerkleTree<T> {
+    /// Starts versioning a tree, its current state being the first version.
+    pub fn new(mut tree: MerkleTree<T>) -> Self {
+        tree.keep_history();
+        Self { tree }
+    }
+
+    /// The current version.
+    pub fn version(&self) -> u64 {
+        self.tree.generation()
+    }
+
+    /// The versions which have not been pruned, in ascending order.
+    pub fn versions(&self) -> Vec<u64> {
+        self.tree.versions()
+    }
+
+    /// The tree as of the current version.
+    pub fn tree(&self) -> &MerkleTree<T> {
+        &self.tree
+    }
+
+    pub fn into_inner(self) -> MerkleTree<T> {
+        self.tree
+    }
+
+    /// Appends a leaf, returning the new version.
+    pub fn push(&mut self, leaf: &Bytes) -> u64 {
+        self.tree.push(leaf);
+        self.version()
+    }
+
+    /// Appends several leaves as a single version, returning the new version.
+    pub fn extend(&mut self, leaves: &[&Bytes]) -> u64 {
+        self.tree.extend(leaves);
+        self.version()
+    }
+
+    /// Replaces the leaf at `index`, returning the new version.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn update(&mut self, index: usize, leaf: &Bytes) -> u64 {
+        self.tree.update(index, leaf);
+        self.version()
+    }
+
+    /// Removes the leaf at `index`, returning the new version.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> u64 {
+        self.tree.remove(index);
+        self.version()
+    }
+
+    /// The root as of `version`, if that version has not been pruned.
+    pub fn root_at(&self, version: u64) -> Option<T::Hash> {
+        self.tree.root_at_version(version)
+    }
+
+    /// The proof of a leaf as of `version`, if that version has not been pruned and the leaf was
+    /// then in the tree.
+    pub fn proof_at(&self, version: u64, leaf: impl Into<T::Hash>) -> Option<MerkleProof<T>> {
+        let leaf = leaf.into();
+        let proof = self.tree.proof_at_version(version, leaf);
+        match proof.is_empty() && self.root_at(version) != Some(leaf) {
+            true => None,
+            false => Some(proof),
+        }
+    }
+
+    /// Discards every version before `version`, keeping at least the current version.
+    pub fn prune_before(&mut self, version: u64) {
+        self.tree
+            .forget_versions_before(version.min(self.version()))
+    }
+}
+
+impl<T: HashFunction> From<MerkleTree<T>> for VersionedMerkleTree<T> {
+    fn from(tree: MerkleTree<T>) -> Self {
+        Self::new(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, HashFunction, Keccak256, MerkleTree, VersionedMerkleTree};
+
+    #[test]
+    fn queries_versions() {
+        let leaves: Vec<&Bytes> = ["a", "b"].iter().map(|x| x.as_bytes()).collect();
+        let mut tree = VersionedMerkleTree::from(MerkleTree::<Keccak256>::new(&leaves));
+        let first = tree.tree().root();
+        assert_eq!(tree.push(b"c"), 1);
+        let a = tree.tree().index_of(Keccak256::hash(b"a")).unwrap();
+        assert_eq!(tree.update(a, b"d"), 2);
+        assert_eq!(tree.remove(0), 3);
+        assert_eq!(tree.versions(), [0, 1, 2, 3]);
+
+        // Proofs of any version verify against the root of that version
+        let leaf = Keccak256::hash(b"a");
+        let proof = tree.proof_at(0, leaf).unwrap();
+        assert!(tree.tree().verify(proof, leaf, first));
+        assert!(tree.proof_at(2, leaf).is_none());
+        assert!(tree.proof_at(1, Keccak256::hash(b"c")).is_some());
+        assert_eq!(tree.root_at(3), Some(tree.tree().root()));
+
+        tree.prune_before(2);
+        assert_eq!(tree.versions(), [2, 3]);
+        assert_eq!(tree.root_at(0), None);
+        assert!(tree.proof_at(0, leaf).is_none());
+        tree.prune_before(10);
+        assert_eq!(tree.versions(), [3]);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{Keccak256, MerkleProof, MerkleTree, MerkleTreeBuilder, TreeSpec};
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+/// A keccak256 tree for JavaScript, built exactly as the equivalent tree in Rust so that roots
+/// and proofs computed in a browser match those of a backend using this crate.
+///
+/// The crate is built as an rlib only, so packaging it for JavaScript requires a cdylib from a
+/// wrapper crate declaring `crate-type = ["cdylib"]`, or from `cargo rustc --crate-type cdylib`.
+#[wasm_bindgen]
+pub struct JsMerkleTree {
+    tree: MerkleTree<Keccak256>,
+}
+
+/// A proof generated by a `JsMerkleTree`.
+#[wasm_bindgen]
+pub struct JsMerkleProof {
+    proof: MerkleProof<Keccak256>,
+}
+
+#[wasm_bindgen]
+impl JsMerkleTree {
+    /// Creates a tree as `MerkleTree::new` does, hashing each leaf and sorting leaves and pairs.
+    #[wasm_bindgen(constructor)]
+    pub fn new(leaves: Vec<Uint8Array>) -> JsMerkleTree {
+        let values: Vec<Vec<u8>> = leaves.iter().map(|l| l.to_vec()).collect();
+        JsMerkleTree::build(MerkleTreeBuilder::new(), &values)
+    }
+
+    /// Creates a tree described by a spec string such as `keccak256/ordered/duplicate-odd`.
+    #[wasm_bindgen(js_name = withSpec)]
+    pub fn with_spec(leaves: Vec<Uint8Array>, spec: &str) -> Result<JsMerkleTree, JsError> {
+        let builder = MerkleTreeBuilder::from_spec(&TreeSpec::parse(spec)?)?;
+        let values: Vec<Vec<u8>> = leaves.iter().map(|l| l.to_vec()).collect();
+        Ok(JsMerkleTree::build(builder, &values))
+    }
+
+    pub fn root(&self) -> Vec<u8> {
+        self.tree.root().to_vec()
+    }
+
+    /// The hashed leaves, in tree order.
+    pub fn leaves(&self) -> Vec<Uint8Array> {
+        self.tree.leaves().iter().map(|l| l[..].into()).collect()
+    }
+
+    pub fn spec(&self) -> String {
+        self.tree.spec().to_string()
+    }
+
+    /// Generates a proof for a hashed leaf, failing if the leaf is not in the tree.
+    pub fn proof(&self, leaf: &[u8]) -> Result<JsMerkleProof, JsError> {
+        let leaf = MerkleTree::<Keccak256>::hash_from_slice(leaf)?;
+        Ok(JsMerkleProof {
+            proof: self.tree.try_proof(leaf)?,
+        })
+    }
+
+    pub fn verify(&self, proof: &JsMerkleProof, leaf: &[u8], root: &[u8]) -> Result<bool, JsError> {
+        let leaf = MerkleTree::<Keccak256>::hash_from_slice(leaf)?;
+        let root = MerkleTree::<Keccak256>::hash_from_slice(root)?;
+        Ok(self.tree.try_verify(proof.proof.clone(), leaf, root)?)
+    }
+
+    fn build(builder: MerkleTreeBuilder<Keccak256>, values: &[Vec<u8>]) -> JsMerkleTree {
+        let leaves: Vec<&[u8]> = values.iter().map(|v| v.as_slice()).collect();
+        JsMerkleTree {
+            tree: builder.build(&leaves),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl JsMerkleProof {
+    /// The sibling hashes, from the leaf towards the root.
+    pub fn hashes(&self) -> Vec<Uint8Array> {
+        self.proof.iter().map(|h| h[..].into()).collect()
+    }
+
+    /// The proof in the text form accepted by `MerkleProof::from_str`.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.proof.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsMerkleTree;
+    use crate::{Bytes, Keccak256, MerkleTree, MerkleTreeBuilder};
+
+    #[test]
+    fn matches_rust_trees() {
+        let values: Vec<Vec<u8>> = ["a", "b", "c"]
+            .iter()
+            .map(|x| x.as_bytes().to_vec())
+            .collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| v.as_slice()).collect();
+        let expected = MerkleTree::<Keccak256>::new(&leaves);
+
+        let tree = JsMerkleTree::build(MerkleTreeBuilder::new(), &values);
+        assert_eq!(tree.root(), expected.root());
+        assert_eq!(tree.spec(), expected.spec().to_string());
+        let leaf = expected.leaves()[1];
+        let proof = tree.proof(&leaf).unwrap();
+        assert_eq!(proof.proof, expected.proof(leaf));
+        assert!(tree.verify(&proof, &leaf, &tree.root()).unwrap());
+        assert!(!tree.verify(&proof, &leaf, &[0; 32]).unwrap());
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{HashFunction, MerkleTree};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Subscribers notified of the new root after each mutation of a tree.
+pub(crate) struct Subscribers<H> {
+    generation: u64,
+    senders: Vec<Sender<(u64, H)>>,
+}
+
+impl<H> Default for Subscribers<H> {
+    fn default() -> Self {
+        Self {
+            generation: 0,
+            senders: vec![],
+        }
+    }
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// The number of mutations applied to the tree since it was constructed.
+    pub fn generation(&self) -> u64 {
+        self.subscribers.generation
+    }
+
+    /// Subscribes to root updates, so that publishers can react to changes without polling.
+    ///
+    /// After each `push`, `extend`, `update` or `remove` the new generation and root are sent to
+    /// every subscriber. Dropping the receiver ends the subscription.
+    pub fn subscribe(&mut self) -> Receiver<(u64, T::Hash)> {
+        let (sender, receiver) = channel();
+        self.subscribers.senders.push(sender);
+        receiver
+    }
+
+    /// Advances the generation, recording the new version if history is being kept, and notifies
+    /// subscribers of the new root.
+    pub(crate) fn publish(&mut self) {
+        self.subscribers.generation += 1;
+        self.record_version();
+        let update = (self.subscribers.generation, self.root());
+        self.subscribers
+            .senders
+            .retain(|sender| sender.send(update).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Keccak256, MerkleTree};
+
+    #[test]
+    fn publishes_updates() {
+        let leaves: Vec<&Bytes> = ["a", "b", "c"].iter().map(|x| x.as_bytes()).collect();
+        let mut tree = MerkleTree::<Keccak256>::new(&leaves);
+        let receiver = tree.subscribe();
+        assert!(receiver.try_recv().is_err());
+
+        tree.push(b"d");
+        assert_eq!(receiver.try_recv(), Ok((1, tree.root())));
+        tree.extend(&[b"e", b"f"]);
+        assert_eq!(receiver.try_recv(), Ok((2, tree.root())));
+        tree.update(0, b"g");
+        assert_eq!(receiver.try_recv(), Ok((3, tree.root())));
+        tree.remove(0);
+        assert_eq!(receiver.try_recv(), Ok((4, tree.root())));
+
+        // Empty batches do not modify the tree
+        tree.extend(&[]);
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(tree.generation(), 4);
+
+        // Dropped receivers are discarded
+        let other = tree.subscribe();
+        drop(receiver);
+        tree.push(b"h");
+        assert_eq!(other.try_recv(), Ok((5, tree.root())));
+        assert_eq!(tree.subscribers.senders.len(), 1);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{Direction, HashFunction, MerkleError, MerkleTree};
+
+/// A proof laid out as the private inputs of the binary Merkle inclusion gadgets of circom and
+/// arkworks, with each hash as a field element and a bit per step giving the side of the node.
+///
+/// Hashes are read as big-endian integers, so must be less than the modulus of the circuit's
+/// field, as with trees hashed by Poseidon or MiMC through a custom `HashFunction`. Steps at which
+/// a node was promoted are omitted, so gadgets of fixed depth require complete trees.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZkWitness {
+    pub leaf: String,
+    /// The sibling at each step, from the leaf towards the root, in decimal.
+    pub path_elements: Vec<String>,
+    /// Whether the node is the right child at each step, as bit `i` of the leaf index is within a
+    /// complete tree.
+    pub path_indices: Vec<u8>,
+}
+
+impl<T: HashFunction> MerkleTree<T> {
+    /// Exports the proof of a leaf as a witness for a membership circuit. Trees with sorted pairs
+    /// record the side each pair sorted to, so the circuit need not sort.
+    pub fn zk_witness(&self, leaf: impl Into<T::Hash>) -> Result<ZkWitness, MerkleError> {
+        if self.spec.arity != 2 {
+            return Err(MerkleError::ArityUnsupported);
+        }
+        let leaf = leaf.into();
+        let proof = self.try_proof(leaf)?;
+
+        let mut node = leaf;
+        let mut path_indices = vec![];
+        for (i, sibling) in proof.iter().enumerate() {
+            let right = match proof.directions() {
+                Some(directions) => directions[i] == Direction::Left,
+                None => *sibling < node,
+            };
+            let pair = match right {
+                true => [*sibling, node],
+                false => [node, *sibling],
+            };
+            path_indices.push(right as u8);
+            node = MerkleTree::<T>::hash_children(&self.spec, &pair);
+        }
+
+        Ok(ZkWitness {
+            leaf: field_element(leaf.as_ref()),
+            path_elements: proof.iter().map(|h| field_element(h.as_ref())).collect(),
+            path_indices,
+        })
+    }
+}
+
+impl ZkWitness {
+    /// The index of the leaf as used by arkworks paths, being the path indices as bits.
+    pub fn leaf_index(&self) -> u128 {
+        self.path_indices
+            .iter()
+            .rev()
+            .fold(0, |index, bit| index << 1 | *bit as u128)
+    }
+
+    /// The witness as a circom input file, with the `leaf`, `pathElements` and `pathIndices`
+    /// signals of the common inclusion templates.
+    pub fn to_circom_input(&self) -> String {
+        let quoted: Vec<String> = self
+            .path_elements
+            .iter()
+            .map(|e| format!("\"{}\"", e))
+            .collect();
+        let indices: Vec<String> = self.path_indices.iter().map(|i| i.to_string()).collect();
+        format!(
+            "{{\"leaf\":\"{}\",\"pathElements\":[{}],\"pathIndices\":[{}]}}",
+            self.leaf,
+            quoted.join(","),
+            indices.join(",")
+        )
+    }
+}
+
+/// Formats big-endian bytes as a decimal integer.
+fn field_element(bytes: &[u8]) -> String {
+    let mut value = bytes.to_vec();
+    let mut digits = vec![];
+    while value.iter().any(|b| *b != 0) {
+        // Divide by ten in place, keeping the remainder as the next digit
+        let mut remainder = 0u16;
+        for byte in value.iter_mut() {
+            let current = remainder << 8 | *byte as u16;
+            *byte = (current / 10) as u8;
+            remainder = current % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::field_element;
+    use crate::{Bytes, Keccak256, MerkleError, MerkleTree, MerkleTreeBuilder};
+
+    #[test]
+    fn formats_field_elements() {
+        assert_eq!(field_element(&[0; 32]), "0");
+        assert_eq!(field_element(&[1, 0]), "256");
+        assert_eq!(
+            field_element(&[0xff; 32]),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+    }
+
+    #[test]
+    fn exports_witnesses() {
+        let values: Vec<[u8; 1]> = (0..8).map(|i| [i]).collect();
+        let leaves: Vec<&Bytes> = values.iter().map(|v| &v[..]).collect();
+        for tree in [
+            MerkleTree::<Keccak256>::new(&leaves),
+            MerkleTree::<Keccak256>::new_ordered(&leaves),
+        ] {
+            for (index, leaf) in tree.leaves().iter().enumerate() {
+                let witness = tree.zk_witness(*leaf).unwrap();
+                assert_eq!(witness.path_elements.len(), 3);
+                assert_eq!(witness.leaf, field_element(leaf));
+
+                // Hashing each pair in the order given by the path indices recomputes the root
+                let proof = tree.proof(*leaf);
+                let root = proof.iter().zip(&witness.path_indices).fold(
+                    *leaf,
+                    |node, (sibling, index)| match index {
+                        1 => MerkleTree::<Keccak256>::hash_pair(*sibling, node),
+                        _ => MerkleTree::<Keccak256>::hash_pair(node, *sibling),
+                    },
+                );
+                assert_eq!(root, tree.root());
+                if !tree.spec().sort_pairs {
+                    assert_eq!(witness.leaf_index(), index as u128);
+                }
+            }
+        }
+
+        let tree = MerkleTree::<Keccak256>::new_ordered(&leaves);
+        let witness = tree.zk_witness(tree.leaves()[5]).unwrap();
+        assert_eq!(witness.path_indices, [1, 0, 1]);
+        assert!(witness
+            .to_circom_input()
+            .ends_with(",\"pathIndices\":[1,0,1]}"));
+
+        let tree = MerkleTreeBuilder::<Keccak256>::new()
+            .arity(3)
+            .build(&leaves);
+        assert_eq!(
+            tree.zk_witness(tree.leaves()[0]).err(),
+            Some(MerkleError::ArityUnsupported)
+        );
+    }
+}